@@ -0,0 +1,20 @@
+#!/bin/sh
+# Light the number-row key of the active virtual desktop.
+#
+# Reference client for `logi-led workspaces`: it watches the EWMH
+# _NET_CURRENT_DESKTOP property, which KWin, Mutter (GNOME) and most other
+# X11 window managers keep up to date. The property counts from 0; the
+# protocol counts from 1. Any other source works the same way, as long as
+# it prints one `workspace <n>` line per switch, e.g. a KWin script or a
+# GNOME Shell extension writing to a pipe.
+#
+# Usage: workspace-leds.sh [logi-led workspaces options, e.g. --color cyan]
+
+set -eu
+
+xprop -root -spy _NET_CURRENT_DESKTOP |
+    while read -r line; do
+        desktop=${line##* }
+        echo "workspace $((desktop + 1))"
+    done |
+    logi-led workspaces "$@"
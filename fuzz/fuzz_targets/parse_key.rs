@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logi_led::keyboard::parser::parse_key;
+
+fuzz_target!(|input: &str| {
+    let _ = parse_key(input);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logi_led::keyboard::api::NullKeyboard;
+use logi_led::profile::parse_profile;
+
+fuzz_target!(|input: &[u8]| {
+    let _ = parse_profile(&mut NullKeyboard, input, false);
+    let _ = parse_profile(&mut NullKeyboard, input, true);
+});
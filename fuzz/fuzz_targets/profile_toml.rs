@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use logi_led::keyboard::api::NullKeyboard;
+use logi_led::profile::load_toml_profile_str;
+
+fuzz_target!(|input: &str| {
+    let _ = load_toml_profile_str(&mut NullKeyboard, input, "fuzz");
+});
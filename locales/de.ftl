@@ -0,0 +1,52 @@
+## help-keys
+
+keys-title = Tasten
+keys-group-list = Gruppenliste
+keys-group = Gruppe { $label } ({ $name }):
+
+group-logo = Logo
+group-indicators = Anzeigen
+group-multimedia = Multimedia
+group-gkeys = G-Tasten
+group-fkeys = Funktionstasten
+group-modifiers = Modifikatortasten
+group-functions = Navigations- und Systemtasten
+group-arrows = Pfeiltasten
+group-numeric = Ziffernblock
+group-keys = Haupttasten
+
+## help-effects
+
+effects-title = Effekte
+effects-usage = Verwendung:
+effects-examples = Beispiele:
+effects-parts = Bereiche
+effects-name = Effekt
+effects-options = Optionen
+effects-logo-fallback = { $part } (statisch { $color })
+effects-storage = Speicher
+effects-none = Keine eingebauten Effekte
+
+## help-samples
+
+samples-title = Beispiele
+sample-load-profile = Ein Profil aus einer Datei laden
+sample-set-logo = Die Logo-Taste rot färben
+sample-set-all = Alle Tasten grün färben
+
+## help-colors
+
+colors-title = Farben
+
+## help-regions
+
+regions-title = Regionen
+regions-none = Keine Regionen
+
+## errors
+
+error-no-device = Kein passendes Gerät gefunden
+error-invalid-color = ungültige Farbe: { $value }
+error-invalid-key = ungültige Taste: { $value }
+error-did-you-mean = meinten Sie `{ $suggestion }`?
+error-invalid-group = ungültige Tastengruppe: { $value }
@@ -0,0 +1,52 @@
+## help-keys
+
+keys-title = Keys
+keys-group-list = Group List
+keys-group = Group { $label } ({ $name }):
+
+group-logo = Logo
+group-indicators = Indicators
+group-multimedia = Multimedia
+group-gkeys = G-keys
+group-fkeys = Function keys
+group-modifiers = Modifiers
+group-functions = Navigation and system keys
+group-arrows = Arrows
+group-numeric = Numeric keypad
+group-keys = Main keys
+
+## help-effects
+
+effects-title = Effects
+effects-usage = Usage:
+effects-examples = Examples:
+effects-parts = Parts
+effects-name = Effect
+effects-options = Options
+effects-logo-fallback = { $part } (static { $color })
+effects-storage = Storage
+effects-none = No built-in effects
+
+## help-samples
+
+samples-title = Samples
+sample-load-profile = Load a profile from a file
+sample-set-logo = Set the logo key red
+sample-set-all = Set all keys green
+
+## help-colors
+
+colors-title = Colors
+
+## help-regions
+
+regions-title = Regions
+regions-none = No regions
+
+## errors
+
+error-no-device = No matching device
+error-invalid-color = invalid color: { $value }
+error-invalid-key = invalid key: { $value }
+error-did-you-mean = did you mean `{ $suggestion }`?
+error-invalid-group = invalid key group: { $value }
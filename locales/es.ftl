@@ -0,0 +1,52 @@
+## help-keys
+
+keys-title = Teclas
+keys-group-list = Lista de grupos
+keys-group = Grupo { $label } ({ $name }):
+
+group-logo = Logotipo
+group-indicators = Indicadores
+group-multimedia = Multimedia
+group-gkeys = Teclas G
+group-fkeys = Teclas de función
+group-modifiers = Modificadores
+group-functions = Teclas de navegación y sistema
+group-arrows = Flechas
+group-numeric = Teclado numérico
+group-keys = Teclas principales
+
+## help-effects
+
+effects-title = Efectos
+effects-usage = Uso:
+effects-examples = Ejemplos:
+effects-parts = Zonas
+effects-name = Efecto
+effects-options = Opciones
+effects-logo-fallback = { $part } (fijo { $color })
+effects-storage = Almacenamiento
+effects-none = Sin efectos integrados
+
+## help-samples
+
+samples-title = Ejemplos
+sample-load-profile = Cargar un perfil desde un archivo
+sample-set-logo = Poner la tecla del logotipo en rojo
+sample-set-all = Poner todas las teclas en verde
+
+## help-colors
+
+colors-title = Colores
+
+## help-regions
+
+regions-title = Regiones
+regions-none = Sin regiones
+
+## errors
+
+error-no-device = No se encontró ningún dispositivo compatible
+error-invalid-color = color no válido: { $value }
+error-invalid-key = tecla no válida: { $value }
+error-did-you-mean = ¿quiso decir `{ $suggestion }`?
+error-invalid-group = grupo de teclas no válido: { $value }
@@ -0,0 +1,52 @@
+## help-keys
+
+keys-title = Touches
+keys-group-list = Liste des groupes
+keys-group = Groupe { $label } ({ $name }) :
+
+group-logo = Logo
+group-indicators = Voyants
+group-multimedia = Multimédia
+group-gkeys = Touches G
+group-fkeys = Touches de fonction
+group-modifiers = Touches de modification
+group-functions = Touches de navigation et système
+group-arrows = Flèches
+group-numeric = Pavé numérique
+group-keys = Touches principales
+
+## help-effects
+
+effects-title = Effets
+effects-usage = Utilisation :
+effects-examples = Exemples :
+effects-parts = Zones
+effects-name = Effet
+effects-options = Options
+effects-logo-fallback = { $part } (fixe { $color })
+effects-storage = Stockage
+effects-none = Aucun effet intégré
+
+## help-samples
+
+samples-title = Exemples
+sample-load-profile = Charger un profil depuis un fichier
+sample-set-logo = Mettre la touche logo en rouge
+sample-set-all = Mettre toutes les touches en vert
+
+## help-colors
+
+colors-title = Couleurs
+
+## help-regions
+
+regions-title = Régions
+regions-none = Aucune région
+
+## errors
+
+error-no-device = Aucun périphérique correspondant
+error-invalid-color = couleur invalide : { $value }
+error-invalid-key = touche invalide : { $value }
+error-did-you-mean = vouliez-vous dire `{ $suggestion }` ?
+error-invalid-group = groupe de touches invalide : { $value }
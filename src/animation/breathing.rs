@@ -0,0 +1,51 @@
+use anyhow::Result;
+use core::time::Duration;
+use std::f64::consts::TAU;
+
+use super::{Effect, mix, phase};
+use crate::keyboard::{Color, api::KeyboardApi};
+
+/// Fade smoothly from one color to another and back over `period`.
+///
+/// Native breathing only fades a single color to black.
+pub struct BreathingDuo {
+    pub first: Color,
+    pub second: Color,
+    pub period: Duration,
+}
+
+impl BreathingDuo {
+    /// The color `elapsed` into the animation.
+    pub fn color_at(&self, elapsed: Duration) -> Color {
+        // A raised cosine eases in and out of each color like hardware breathing.
+        let t = (1.0 - (TAU * phase(elapsed, self.period)).cos()) / 2.0;
+        mix(self.first, self.second, t)
+    }
+}
+
+impl Effect for BreathingDuo {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        kbd.set_all_keys(self.color_at(elapsed))?;
+        kbd.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duo_visits_both_colors() {
+        let duo = BreathingDuo {
+            first: Color::new(255, 0, 0),
+            second: Color::new(0, 0, 255),
+            period: Duration::from_secs(4),
+        };
+        assert_eq!(duo.color_at(Duration::ZERO), duo.first);
+        assert_eq!(duo.color_at(Duration::from_secs(2)), duo.second);
+        assert_eq!(duo.color_at(Duration::from_secs(4)), duo.first);
+        // Halfway lands on 127.5, so either neighbour is fine.
+        let middle = duo.color_at(Duration::from_secs(1));
+        assert!((127..=128).contains(&middle.red) && (127..=128).contains(&middle.blue));
+    }
+}
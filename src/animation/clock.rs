@@ -0,0 +1,211 @@
+use anyhow::Result;
+use chrono::Timelike;
+use core::time::Duration;
+
+use super::Effect;
+use crate::clock::{Clock, SystemClock};
+use crate::keyboard::{Color, Key, KeyValue, api::KeyboardApi};
+
+/// How long each digit is lit, followed by [`DIGIT_GAP`] dark.
+const DIGIT_ON: Duration = Duration::from_millis(550);
+const DIGIT_GAP: Duration = Duration::from_millis(150);
+/// Dark pause before the sequence repeats.
+const CYCLE_PAUSE: Duration = Duration::from_millis(1200);
+/// How long a finished countdown flashes before the effect ends.
+const ALARM: Duration = Duration::from_secs(5);
+
+/// Number-row and numpad key for each digit, so boards without a numpad work too.
+const DIGIT_KEYS: [[Key; 2]; 10] = [
+    [Key::N0, Key::Num0],
+    [Key::N1, Key::Num1],
+    [Key::N2, Key::Num2],
+    [Key::N3, Key::Num3],
+    [Key::N4, Key::Num4],
+    [Key::N5, Key::Num5],
+    [Key::N6, Key::Num6],
+    [Key::N7, Key::Num7],
+    [Key::N8, Key::Num8],
+    [Key::N9, Key::Num9],
+];
+
+/// What the clock shows.
+pub enum ClockMode {
+    /// The local time as `HH MM`, read from `clock`.
+    Time { clock: Box<dyn Clock> },
+    /// Time left as `MM SS` (`H MM` from an hour up), then a flashing alarm.
+    Countdown { total: Duration },
+}
+
+/// Spell a time on the digit keys, one digit at a time.
+pub struct DigitClock {
+    pub mode: ClockMode,
+    pub color: Color,
+    pub background: Color,
+    /// Last frame drawn, to skip redundant writes.
+    last: Option<Frame>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    Dark,
+    Digit(u8),
+    Flash(bool),
+}
+
+impl DigitClock {
+    pub fn time(color: Color, background: Color) -> Self {
+        let clock = Box::new(SystemClock);
+        Self::new(ClockMode::Time { clock }, color, background)
+    }
+
+    pub fn countdown(total: Duration, color: Color, background: Color) -> Self {
+        Self::new(ClockMode::Countdown { total }, color, background)
+    }
+
+    pub fn new(mode: ClockMode, color: Color, background: Color) -> Self {
+        Self {
+            mode,
+            color,
+            background,
+            last: None,
+        }
+    }
+
+    /// Digit groups to show `elapsed` into the effect, or `None` once a countdown hit zero.
+    fn groups(&self, elapsed: Duration) -> Option<[[u8; 2]; 2]> {
+        let pair = |n: u64| {
+            [
+                u8::try_from(n / 10 % 10).unwrap_or(0),
+                u8::try_from(n % 10).unwrap_or(0),
+            ]
+        };
+        match &self.mode {
+            ClockMode::Time { clock } => {
+                let time = clock.local();
+                Some([pair(time.hour().into()), pair(time.minute().into())])
+            }
+            &ClockMode::Countdown { total } => {
+                // Round up so the display reaches 00 00 exactly at the end.
+                let left = total.saturating_sub(elapsed);
+                if left.is_zero() {
+                    return None;
+                }
+                let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+                Some(if secs >= 3600 {
+                    [pair(secs / 3600), pair(secs / 60 % 60)]
+                } else {
+                    [pair(secs / 60), pair(secs % 60)]
+                })
+            }
+        }
+    }
+
+    fn frame_at(&self, elapsed: Duration) -> Frame {
+        let Some(groups) = self.groups(elapsed) else {
+            let over = match &self.mode {
+                &ClockMode::Countdown { total } => elapsed.saturating_sub(total),
+                ClockMode::Time { .. } => Duration::ZERO,
+            };
+            return Frame::Flash(over.as_millis() / 250 % 2 == 0);
+        };
+
+        // One slot per digit plus one between the groups, then a pause.
+        let slot = DIGIT_ON + DIGIT_GAP;
+        let cycle = slot * 5 + CYCLE_PAUSE;
+        let within =
+            Duration::from_nanos(u64::try_from(elapsed.as_nanos() % cycle.as_nanos()).unwrap_or(0));
+        let index = within.as_nanos() / slot.as_nanos();
+        let lit = within.as_nanos() % slot.as_nanos() < DIGIT_ON.as_nanos();
+        match (index, lit) {
+            (0 | 1, true) => Frame::Digit(groups[0][usize::try_from(index).unwrap_or(0)]),
+            (3 | 4, true) => Frame::Digit(groups[1][usize::try_from(index - 3).unwrap_or(0)]),
+            _ => Frame::Dark,
+        }
+    }
+}
+
+impl Effect for DigitClock {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let frame = self.frame_at(elapsed);
+        if self.last == Some(frame) {
+            return Ok(());
+        }
+        self.last = Some(frame);
+
+        match frame {
+            Frame::Flash(on) => {
+                kbd.set_all_keys(if on { self.color } else { self.background })?;
+            }
+            Frame::Dark => kbd.set_all_keys(self.background)?,
+            Frame::Digit(digit) => {
+                kbd.set_all_keys(self.background)?;
+                let keys = DIGIT_KEYS[usize::from(digit)].map(|key| KeyValue {
+                    key,
+                    color: self.color,
+                });
+                kbd.set_keys(&keys)?;
+            }
+        }
+        kbd.commit()
+    }
+
+    fn done(&self, elapsed: Duration) -> bool {
+        match &self.mode {
+            &ClockMode::Countdown { total } => elapsed >= total + ALARM,
+            ClockMode::Time { .. } => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{Local, TimeZone};
+
+    const WHITE: Color = Color::new(255, 255, 255);
+    const BLACK: Color = Color::new(0, 0, 0);
+
+    fn at(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn time_is_spelled_digit_by_digit() {
+        let local = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+        let clock = Box::new(ManualClock::new(local));
+        let clock = DigitClock::new(ClockMode::Time { clock }, WHITE, BLACK);
+        assert_eq!(clock.frame_at(at(0)), Frame::Digit(1));
+        assert_eq!(clock.frame_at(at(600)), Frame::Dark);
+        assert_eq!(clock.frame_at(at(700)), Frame::Digit(4));
+        assert_eq!(clock.frame_at(at(1400)), Frame::Dark);
+        assert_eq!(clock.frame_at(at(2100)), Frame::Digit(3));
+        assert_eq!(clock.frame_at(at(2800)), Frame::Digit(0));
+        assert_eq!(clock.frame_at(at(3600)), Frame::Dark);
+        // The cycle (5 slots + pause) is 4.7 s long.
+        assert_eq!(clock.frame_at(at(4700)), Frame::Digit(1));
+        assert!(!clock.done(Duration::from_hours(24)));
+    }
+
+    #[test]
+    fn countdown_shows_time_left_then_flashes() {
+        let clock = DigitClock::countdown(Duration::from_secs(90), WHITE, BLACK);
+        // 01:30 left at the start.
+        assert_eq!(clock.frame_at(at(0)), Frame::Digit(0));
+        assert_eq!(clock.frame_at(at(700)), Frame::Digit(1));
+        // Minutes then seconds, which have ticked down to 28 by 2.1 s.
+        assert_eq!(clock.frame_at(at(2100)), Frame::Digit(2));
+
+        assert_eq!(clock.frame_at(at(90_000)), Frame::Flash(true));
+        assert_eq!(clock.frame_at(at(90_250)), Frame::Flash(false));
+        assert!(!clock.done(at(94_999)));
+        assert!(clock.done(at(95_000)));
+
+        let long = DigitClock::countdown(
+            Duration::from_hours(2) + Duration::from_mins(5),
+            WHITE,
+            BLACK,
+        );
+        assert_eq!(long.groups(Duration::ZERO), Some([[0, 2], [0, 5]]));
+    }
+}
@@ -0,0 +1,382 @@
+//! Demo animations on the key grid, run with `logi-led animate demo:<name>`.
+
+use anyhow::Result;
+use core::time::Duration;
+
+use super::grid::{COLS, KEY_GRID, ROWS, draw};
+use super::{Direction, Effect, RainbowWave, mix};
+use crate::keyboard::{Color, api::KeyboardApi};
+
+type Cells<T> = [[T; COLS]; ROWS];
+
+const BLACK: Color = Color::new(0, 0, 0);
+
+/// Builds a demo from a random seed.
+pub type NewDemo = fn(u64) -> Box<dyn Effect>;
+
+/// Built-in demos: name, description and constructor.
+pub const DEMOS: &[(&str, &str, NewDemo)] = &[
+    ("life", "Conway's Game of Life on the key grid", |seed| {
+        Box::new(Life::new(seed))
+    }),
+    (
+        "matrix",
+        "Green code rain falling down the columns",
+        |seed| Box::new(MatrixRain::new(seed)),
+    ),
+    (
+        "fireworks",
+        "Bursts of color spreading from random keys",
+        |seed| Box::new(Fireworks::new(seed)),
+    ),
+    ("wave", "A rainbow sweeping across the board", |_| {
+        Box::new(RainbowWave {
+            period: Duration::from_secs(2),
+            direction: Direction::Right,
+        })
+    }),
+];
+
+/// Look up a built-in demo by name.
+pub fn demo(name: &str, seed: u64) -> Option<Box<dyn Effect>> {
+    DEMOS
+        .iter()
+        .find(|(demo, _, _)| *demo == name)
+        .map(|(_, _, new)| new(seed))
+}
+
+/// Tiny xorshift generator; demos only need variety, not quality.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Zero is xorshift's only fixed point.
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform-enough value in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        usize::try_from(self.next() % n as u64).unwrap_or(0)
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+/// Steps an effect at its own rate regardless of the frame tick.
+struct Stepper {
+    interval: Duration,
+    taken: u128,
+}
+
+impl Stepper {
+    const fn new(interval: Duration) -> Self {
+        Self { interval, taken: 0 }
+    }
+
+    /// Number of steps due at `elapsed` that have not been taken yet.
+    fn due(&mut self, elapsed: Duration) -> u128 {
+        let target = elapsed.as_nanos() / self.interval.as_nanos() + 1;
+        let due = target.saturating_sub(self.taken);
+        self.taken = self.taken.max(target);
+        due
+    }
+}
+
+/// Conway's Game of Life on a torus the shape of the key grid.
+pub struct Life {
+    rng: Rng,
+    cells: Cells<bool>,
+    generation: u32,
+    stepper: Stepper,
+}
+
+impl Life {
+    const STEP: Duration = Duration::from_millis(400);
+    /// Reseed after this many generations even if the board never settles.
+    const MAX_GENERATIONS: u32 = 150;
+    const ALIVE: Color = Color::new(0x40, 0xff, 0x60);
+
+    pub fn new(seed: u64) -> Self {
+        let mut life = Self {
+            rng: Rng::new(seed),
+            cells: [[false; COLS]; ROWS],
+            generation: 0,
+            stepper: Stepper::new(Self::STEP),
+        };
+        life.seed();
+        life
+    }
+
+    fn seed(&mut self) {
+        for row in &mut self.cells {
+            for cell in row {
+                *cell = self.rng.chance(35);
+            }
+        }
+        self.generation = 0;
+    }
+
+    fn neighbours(&self, row: usize, col: usize) -> usize {
+        let mut count = 0;
+        for dr in [ROWS - 1, 0, 1] {
+            for dc in [COLS - 1, 0, 1] {
+                if (dr, dc) != (0, 0) && self.cells[(row + dr) % ROWS][(col + dc) % COLS] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Advance one generation; reseed a board that died, froze or ran too long.
+    fn step(&mut self) {
+        let mut next = [[false; COLS]; ROWS];
+        for (row, cells) in next.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                *cell = matches!(
+                    (self.cells[row][col], self.neighbours(row, col)),
+                    (true, 2 | 3) | (false, 3)
+                );
+            }
+        }
+        let settled = next == self.cells || !next.iter().flatten().any(|&alive| alive);
+        self.cells = next;
+        self.generation += 1;
+        if settled || self.generation >= Self::MAX_GENERATIONS {
+            self.seed();
+        }
+    }
+}
+
+impl Effect for Life {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let due = self.stepper.due(elapsed);
+        if due == 0 {
+            return Ok(());
+        }
+        if elapsed >= Self::STEP {
+            for _ in 0..due {
+                self.step();
+            }
+        }
+        draw(kbd, |row, col| {
+            if self.cells[row][col] {
+                Self::ALIVE
+            } else {
+                BLACK
+            }
+        })
+    }
+}
+
+/// Falling streaks with a bright head and a fading green tail.
+pub struct MatrixRain {
+    rng: Rng,
+    /// Head row per column, counted from above the board; `None` while idle.
+    heads: [Option<usize>; COLS],
+    stepper: Stepper,
+}
+
+impl MatrixRain {
+    const STEP: Duration = Duration::from_millis(120);
+    const TAIL: usize = 4;
+    const HEAD: Color = Color::new(0xd0, 0xff, 0xd0);
+    const TRAIL: Color = Color::new(0x00, 0xff, 0x40);
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            heads: [None; COLS],
+            stepper: Stepper::new(Self::STEP),
+        }
+    }
+
+    fn step(&mut self) {
+        for head in &mut self.heads {
+            *head = match *head {
+                Some(row) if row < ROWS + Self::TAIL => Some(row + 1),
+                None if self.rng.chance(8) => Some(0),
+                _ => None,
+            };
+        }
+    }
+
+    fn color(&self, row: usize, col: usize) -> Color {
+        let Some(head) = self.heads[col] else {
+            return BLACK;
+        };
+        match head.checked_sub(row) {
+            Some(0) => Self::HEAD,
+            Some(distance) if distance <= Self::TAIL => {
+                #[allow(clippy::cast_precision_loss)]
+                let fade = distance as f64 / (Self::TAIL + 1) as f64;
+                mix(Self::TRAIL, BLACK, fade)
+            }
+            _ => BLACK,
+        }
+    }
+}
+
+impl Effect for MatrixRain {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let due = self.stepper.due(elapsed);
+        if due == 0 {
+            return Ok(());
+        }
+        for _ in 0..due {
+            self.step();
+        }
+        draw(kbd, |row, col| self.color(row, col))
+    }
+}
+
+/// Rings of color expanding from random keys and fading out.
+pub struct Fireworks {
+    rng: Rng,
+    /// Centre, color and age in steps of each live burst.
+    bursts: Vec<(usize, usize, Color, usize)>,
+    stepper: Stepper,
+}
+
+impl Fireworks {
+    const STEP: Duration = Duration::from_millis(80);
+    const LIFETIME: usize = 8;
+    const PALETTE: [Color; 5] = [
+        Color::new(0xff, 0x30, 0x30),
+        Color::new(0xff, 0xc0, 0x20),
+        Color::new(0x30, 0x80, 0xff),
+        Color::new(0xff, 0x40, 0xff),
+        Color::new(0x40, 0xff, 0x80),
+    ];
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            bursts: Vec::new(),
+            stepper: Stepper::new(Self::STEP),
+        }
+    }
+
+    fn step(&mut self) {
+        for burst in &mut self.bursts {
+            burst.3 += 1;
+        }
+        self.bursts.retain(|burst| burst.3 < Self::LIFETIME);
+        if self.bursts.len() < 3 && self.rng.chance(15) {
+            // Launch from a real key so the centre is visible.
+            let (row, col) = loop {
+                let (row, col) = (self.rng.below(ROWS), self.rng.below(COLS));
+                if KEY_GRID[row][col].is_some() {
+                    break (row, col);
+                }
+            };
+            let color = Self::PALETTE[self.rng.below(Self::PALETTE.len())];
+            self.bursts.push((row, col, color, 0));
+        }
+    }
+
+    fn color(&self, row: usize, col: usize) -> Color {
+        self.bursts
+            .iter()
+            .filter(|&&(r, c, _, age)| {
+                // Columns are narrower than rows are tall, so stretch the ring sideways.
+                let distance = r.abs_diff(row).max(c.abs_diff(col) / 2);
+                distance == age / 2
+            })
+            .map(|&(_, _, color, age)| {
+                #[allow(clippy::cast_precision_loss)]
+                let fade = age as f64 / Self::LIFETIME as f64;
+                mix(color, BLACK, fade)
+            })
+            .next_back()
+            .unwrap_or(BLACK)
+    }
+}
+
+impl Effect for Fireworks {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let due = self.stepper.due(elapsed);
+        if due == 0 {
+            return Ok(());
+        }
+        for _ in 0..due {
+            self.step();
+        }
+        draw(kbd, |row, col| self.color(row, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{DEFAULT_TICK, Pacing, play_with};
+    use crate::clock::ManualClock;
+    use crate::keyboard::api::NullKeyboard;
+
+    #[test]
+    fn blinker_oscillates() {
+        let mut life = Life::new(1);
+        life.cells = [[false; COLS]; ROWS];
+        for col in 4..7 {
+            life.cells[2][col] = true;
+        }
+        life.step();
+        let alive: Vec<(usize, usize)> = (0..ROWS)
+            .flat_map(|row| (0..COLS).map(move |col| (row, col)))
+            .filter(|&(row, col)| life.cells[row][col])
+            .collect();
+        assert_eq!(alive, [(1, 5), (2, 5), (3, 5)]);
+    }
+
+    #[test]
+    fn dead_board_is_reseeded() {
+        let mut life = Life::new(7);
+        life.cells = [[false; COLS]; ROWS];
+        life.step();
+        assert!(life.cells.iter().flatten().any(|&alive| alive));
+        assert_eq!(life.generation, 0);
+    }
+
+    #[test]
+    fn stepper_catches_up() {
+        let mut stepper = Stepper::new(Duration::from_millis(100));
+        assert_eq!(stepper.due(Duration::ZERO), 1);
+        assert_eq!(stepper.due(Duration::from_millis(50)), 0);
+        assert_eq!(stepper.due(Duration::from_millis(350)), 3);
+    }
+
+    #[test]
+    fn rain_has_a_bright_head() {
+        let mut rain = MatrixRain::new(3);
+        rain.heads[2] = Some(3);
+        assert_eq!(rain.color(3, 2), MatrixRain::HEAD);
+        assert_eq!(rain.color(2, 2), mix(MatrixRain::TRAIL, BLACK, 0.2));
+        assert_eq!(rain.color(4, 2), BLACK);
+        assert_eq!(rain.color(3, 3), BLACK);
+    }
+
+    #[test]
+    fn every_demo_runs() {
+        for (name, _, _) in DEMOS {
+            let mut effect = demo(name, 42).unwrap();
+            play_with(
+                &mut NullKeyboard,
+                effect.as_mut(),
+                Pacing::fixed(DEFAULT_TICK),
+                Some(Duration::from_secs(120)),
+                &ManualClock::default(),
+            )
+            .unwrap();
+        }
+        assert!(demo("nope", 1).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+//! Physical key positions on a full-size board, for effects that move across it.
+
+use anyhow::Result;
+
+use crate::keyboard::{Color, Key, KeyValue, api::KeyboardApi};
+
+pub const ROWS: usize = 6;
+pub const COLS: usize = 21;
+
+/// Main block (14 columns), navigation cluster (3) and numpad (4), top to bottom.
+/// Wide keys take a single cell; `None` cells are gaps.
+#[rustfmt::skip]
+#[allow(clippy::enum_glob_use)]
+pub const KEY_GRID: [[Option<Key>; COLS]; ROWS] = {
+    use Key::*;
+    const GAP: Option<Key> = None;
+    [
+        [Some(Esc), Some(F1), Some(F2), Some(F3), Some(F4), Some(F5), Some(F6), Some(F7),
+         Some(F8), Some(F9), Some(F10), Some(F11), Some(F12), GAP,
+         Some(PrintScreen), Some(ScrollLock), Some(PauseBreak), GAP, GAP, GAP, GAP],
+        [Some(Tilde), Some(N1), Some(N2), Some(N3), Some(N4), Some(N5), Some(N6), Some(N7),
+         Some(N8), Some(N9), Some(N0), Some(Minus), Some(Equal), Some(Backspace),
+         Some(Insert), Some(Home), Some(PageUp),
+         Some(NumLock), Some(NumSlash), Some(NumAsterisk), Some(NumMinus)],
+        [Some(Tab), Some(Q), Some(W), Some(E), Some(R), Some(T), Some(Y), Some(U), Some(I),
+         Some(O), Some(P), Some(OpenBracket), Some(CloseBracket), Some(Backslash),
+         Some(Del), Some(End), Some(PageDown),
+         Some(Num7), Some(Num8), Some(Num9), Some(NumPlus)],
+        [Some(CapsLock), Some(A), Some(S), Some(D), Some(F), Some(G), Some(H), Some(J),
+         Some(K), Some(L), Some(Semicolon), Some(Quote), Some(Enter), GAP,
+         GAP, GAP, GAP,
+         Some(Num4), Some(Num5), Some(Num6), GAP],
+        [Some(ShiftLeft), Some(Z), Some(X), Some(C), Some(V), Some(B), Some(N), Some(M),
+         Some(Comma), Some(Period), Some(Slash), Some(ShiftRight), GAP, GAP,
+         GAP, Some(ArrowTop), GAP,
+         Some(Num1), Some(Num2), Some(Num3), Some(NumEnter)],
+        [Some(CtrlLeft), Some(WinLeft), Some(AltLeft), GAP, GAP, Some(Space), GAP, GAP, GAP,
+         Some(AltRight), Some(WinRight), Some(Menu), Some(CtrlRight), GAP,
+         Some(ArrowLeft), Some(ArrowBottom), Some(ArrowRight),
+         Some(Num0), GAP, Some(NumDot), GAP],
+    ]
+};
+
+/// Paint every key on the grid with `color(row, col)` and commit.
+pub fn draw(kbd: &mut dyn KeyboardApi, color: impl Fn(usize, usize) -> Color) -> Result<()> {
+    let keys: Vec<KeyValue> = KEY_GRID
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter_map(move |(col, cell)| cell.map(|key| (row, col, key)))
+        })
+        .map(|(row, col, key)| KeyValue {
+            key,
+            color: color(row, col),
+        })
+        .collect();
+    kbd.set_keys(&keys)?;
+    kbd.commit()
+}
+
+/// `i` out of `n`, as a float.
+pub fn fraction(i: usize, n: usize) -> f64 {
+    // The grid is a few dozen cells, far below where f64 loses precision.
+    #[allow(clippy::cast_precision_loss)]
+    let fraction = i as f64 / n as f64;
+    fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_appears_once() {
+        let keys: Vec<Key> = KEY_GRID.iter().flatten().flatten().copied().collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key), "{key:?} appears twice");
+        }
+        assert_eq!(keys.len(), 104);
+    }
+}
@@ -0,0 +1,169 @@
+//! Software animations: effects the firmware cannot run by itself.
+//!
+//! An [`Effect`] draws one frame for a point in time; [`play`] calls it at the
+//! tick [`Pacing`] sets, in the foreground until a time limit or Ctrl-C. Once
+//! [`shutdown::catch`] is in place, Ctrl-C and SIGTERM end it after the frame
+//! being drawn instead of killing the process mid-write.
+
+mod breathing;
+mod clock;
+pub mod demos;
+pub mod grid;
+mod pacing;
+pub mod preview;
+mod rainbow;
+mod sequence;
+mod trail;
+mod transition;
+
+pub use crate::keyboard::colors::mix;
+pub use breathing::BreathingDuo;
+pub use clock::{ClockMode, DigitClock};
+pub use pacing::{FrameStats, Pacing};
+pub use rainbow::{Direction, RAINBOW_PERIOD, Rainbow, RainbowWave, WAVE_PERIOD};
+pub use sequence::{KeySequence, KeySequences};
+pub use trail::Trail;
+pub use transition::{Transition, TransitionStyle};
+
+use anyhow::Result;
+use core::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::keyboard::{Color, api::KeyboardApi};
+use crate::output;
+use crate::shutdown;
+use pacing::Pacer;
+
+/// Frame interval used unless a command asks for another.
+pub const DEFAULT_TICK: Duration = Duration::from_millis(50);
+
+/// A software animation.
+pub trait Effect {
+    /// Draw and commit the frame `elapsed` after the animation started.
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()>;
+
+    /// Whether the animation has run its course; most run until stopped.
+    fn done(&self, _elapsed: Duration) -> bool {
+        false
+    }
+}
+
+/// Play `effect` as `pacing` sets until it is done or `limit` has passed.
+///
+/// Frames that could not be written in time are reported when it ends.
+pub fn play(
+    kbd: &mut dyn KeyboardApi,
+    effect: &mut dyn Effect,
+    pacing: Pacing,
+    limit: Option<Duration>,
+) -> Result<()> {
+    let stats = play_with(kbd, effect, pacing, limit, &SystemClock)?;
+    if stats.late > 0 {
+        output::note(format_args!(
+            "{} of {} frames were late; the slowest took {} ms to write, running at {:.1} fps{}",
+            stats.late,
+            stats.frames,
+            stats.slowest.as_millis(),
+            stats.fps(),
+            if pacing.adaptive {
+                ""
+            } else {
+                " (adaptive_fps = true lowers the rate to what the keyboard keeps up with)"
+            }
+        ));
+    }
+    Ok(())
+}
+
+/// [`play`] on `clock`, so tests need not wait.
+pub fn play_with(
+    kbd: &mut dyn KeyboardApi,
+    effect: &mut dyn Effect,
+    pacing: Pacing,
+    limit: Option<Duration>,
+    clock: &dyn Clock,
+) -> Result<FrameStats> {
+    let start = clock.now();
+    let elapsed = || clock.now().saturating_sub(start);
+    let mut pacer = Pacer::new(pacing);
+    loop {
+        let now = elapsed();
+        if limit.is_some_and(|limit| now >= limit) || effect.done(now) || shutdown::requested() {
+            return Ok(pacer.stats);
+        }
+        effect.frame(kbd, now)?;
+        clock.sleep(pacer.frame(elapsed().saturating_sub(now)));
+    }
+}
+
+/// Fully saturated color `turn` of the way around the color wheel.
+pub fn hue(turn: f64) -> Color {
+    let sector = turn.rem_euclid(1.0) * 6.0;
+    let rising = 1.0 - (sector % 2.0 - 1.0).abs();
+    let (red, green, blue) = match sector {
+        s if s < 1.0 => (1.0, rising, 0.0),
+        s if s < 2.0 => (rising, 1.0, 0.0),
+        s if s < 3.0 => (0.0, 1.0, rising),
+        s if s < 4.0 => (0.0, rising, 1.0),
+        s if s < 5.0 => (rising, 0.0, 1.0),
+        _ => (1.0, 0.0, rising),
+    };
+    // Each channel is within 0..=1 before scaling, so the casts are lossless.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let channel = |v: f64| (v * 255.0).round() as u8;
+    Color::new(channel(red), channel(green), channel(blue))
+}
+
+/// Position within a repeating `period`, from 0 up to (not including) 1.
+pub fn phase(elapsed: Duration, period: Duration) -> f64 {
+    if period.is_zero() {
+        return 0.0;
+    }
+    (elapsed.as_secs_f64() / period.as_secs_f64()).fract()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::keyboard::api::NullKeyboard;
+
+    struct Counter(Vec<Duration>);
+
+    impl Effect for Counter {
+        fn frame(&mut self, _kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+            self.0.push(elapsed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn play_stops_at_the_limit() {
+        let tick = Duration::from_millis(100);
+        let mut effect = Counter(Vec::new());
+        play_with(
+            &mut NullKeyboard,
+            &mut effect,
+            Pacing::fixed(tick),
+            Some(Duration::from_millis(300)),
+            &ManualClock::default(),
+        )
+        .unwrap();
+        assert_eq!(effect.0, [0, 100, 200].map(Duration::from_millis).to_vec());
+    }
+
+    #[test]
+    fn hue_visits_the_primaries() {
+        assert_eq!(hue(0.0), Color::new(0xff, 0, 0));
+        assert_eq!(hue(1.0 / 3.0), Color::new(0, 0xff, 0));
+        assert_eq!(hue(2.0 / 3.0), Color::new(0, 0, 0xff));
+        assert_eq!(hue(1.0), hue(0.0));
+    }
+
+    #[test]
+    fn phase_wraps_around_the_period() {
+        let period = Duration::from_secs(4);
+        assert!((phase(Duration::from_secs(5), period) - 0.25).abs() < 1e-9);
+        assert!(phase(Duration::from_secs(1), Duration::ZERO).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,160 @@
+//! Frame pacing: how often [`play`](super::play) draws, and what it does
+//! when the keyboard cannot take frames that fast.
+
+use core::time::Duration;
+
+/// Slowest tick the adaptive mode falls back to, 2 frames a second.
+const SLOWEST_TICK: Duration = Duration::from_millis(500);
+
+/// How often to draw frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    /// Interval between frames when the keyboard keeps up.
+    pub tick: Duration,
+    /// Lengthen the interval while frames take longer to write than it allows.
+    pub adaptive: bool,
+}
+
+impl Pacing {
+    /// The interval that draws `fps` frames a second.
+    pub fn tick_for(fps: u32) -> Duration {
+        Duration::from_secs(1) / fps.max(1)
+    }
+
+    /// Draw every `tick`, however long frames take.
+    pub const fn fixed(tick: Duration) -> Self {
+        Self {
+            tick,
+            adaptive: false,
+        }
+    }
+
+    /// Draw every `tick`, or less often if that would exceed `max_fps`.
+    pub fn capped(tick: Duration, max_fps: Option<u32>, adaptive: bool) -> Self {
+        let floor = max_fps.map_or(Duration::ZERO, Self::tick_for);
+        Self {
+            tick: tick.max(floor),
+            adaptive,
+        }
+    }
+}
+
+/// What happened while an animation played.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames drawn.
+    pub frames: u64,
+    /// Frames that took longer to write than the tick allowed, so the next
+    /// one was behind.
+    pub late: u64,
+    /// Longest time a frame took to write.
+    pub slowest: Duration,
+    /// Interval between frames at the end, longer than asked for when the
+    /// adaptive mode lowered the frame rate.
+    pub tick: Duration,
+}
+
+impl FrameStats {
+    /// Frames a second at the final tick.
+    pub fn fps(&self) -> f64 {
+        if self.tick.is_zero() {
+            return 0.0;
+        }
+        1.0 / self.tick.as_secs_f64()
+    }
+}
+
+/// Tracks frame write times and picks the interval before the next frame.
+pub(super) struct Pacer {
+    pacing: Pacing,
+    /// Running average of recent write times.
+    average: Duration,
+    pub(super) stats: FrameStats,
+}
+
+impl Pacer {
+    pub(super) fn new(pacing: Pacing) -> Self {
+        Self {
+            pacing,
+            average: Duration::ZERO,
+            stats: FrameStats {
+                tick: pacing.tick,
+                ..FrameStats::default()
+            },
+        }
+    }
+
+    /// Record a frame that took `cost` to write; returns how long to wait
+    /// before the next one.
+    pub(super) fn frame(&mut self, cost: Duration) -> Duration {
+        let stats = &mut self.stats;
+        stats.frames += 1;
+        stats.slowest = stats.slowest.max(cost);
+        if cost > stats.tick {
+            stats.late += 1;
+        }
+        if self.pacing.adaptive {
+            // Weigh in each frame by an eighth, so a single slow write does
+            // not halve the frame rate but a slow device soon does.
+            self.average = self.average * 7 / 8 + cost / 8;
+            // A base tick already slower than the fallback is never lengthened.
+            let slowest = self.pacing.tick.max(SLOWEST_TICK);
+            stats.tick = (self.average * 5 / 4).clamp(self.pacing.tick, slowest);
+        }
+        // Subtract the time the frame took so slow USB writes do not stretch the animation.
+        stats.tick.saturating_sub(cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn max_fps_only_lengthens_the_tick() {
+        assert_eq!(Pacing::capped(50 * MS, Some(10), false).tick, 100 * MS);
+        assert_eq!(Pacing::capped(50 * MS, Some(60), false).tick, 50 * MS);
+        assert_eq!(Pacing::capped(50 * MS, None, true).tick, 50 * MS);
+    }
+
+    #[test]
+    fn adaptive_pacing_slows_down_and_recovers() {
+        let mut pacer = Pacer::new(Pacing::capped(50 * MS, None, true));
+        for _ in 0..40 {
+            pacer.frame(80 * MS);
+        }
+        // Settled a quarter above the write time, and no longer late.
+        assert!(pacer.stats.tick.abs_diff(100 * MS) < MS);
+        let late = pacer.stats.late;
+        assert!(late > 0);
+        assert!(pacer.frame(80 * MS).abs_diff(20 * MS) < MS);
+        assert_eq!(pacer.stats.late, late);
+
+        for _ in 0..40 {
+            pacer.frame(5 * MS);
+        }
+        assert_eq!(pacer.stats.tick, 50 * MS);
+        assert_eq!(pacer.stats.slowest, 80 * MS);
+    }
+
+    #[test]
+    fn adaptive_pacing_keeps_ticks_slower_than_its_fallback() {
+        let mut pacer = Pacer::new(Pacing::capped(50 * MS, Some(1), true));
+        assert_eq!(pacer.frame(20 * MS), 980 * MS);
+        for _ in 0..40 {
+            pacer.frame(1500 * MS);
+        }
+        assert_eq!(pacer.stats.tick, 1000 * MS);
+    }
+
+    #[test]
+    fn fixed_pacing_counts_late_frames() {
+        let mut pacer = Pacer::new(Pacing::fixed(50 * MS));
+        assert_eq!(pacer.frame(20 * MS), 30 * MS);
+        assert_eq!(pacer.frame(80 * MS), Duration::ZERO);
+        assert_eq!(pacer.stats.late, 1);
+        assert_eq!(pacer.stats.tick, 50 * MS);
+    }
+}
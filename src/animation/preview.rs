@@ -0,0 +1,302 @@
+//! Terminal preview: draw effects on a sketch of the board before any of it
+//! reaches the keyboard.
+//!
+//! [`Simulator`] stands in for a keyboard and redraws the key grid with
+//! 24-bit ANSI colors on every commit. Native effects run in firmware, so
+//! [`NativePreview`] imitates them in software for the preview only.
+
+use anyhow::Result;
+use core::f64::consts::TAU;
+use core::time::Duration;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+use super::grid::{self, COLS, KEY_GRID, ROWS, fraction};
+use super::{DEFAULT_TICK, Effect, Pacing, hue, mix, phase, play};
+use crate::history::Snapshot;
+use crate::keyboard::{
+    Color, Key, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+    NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+
+/// Stand-in for the firmware's own period when none is given.
+const FIRMWARE_PERIOD: Duration = Duration::from_secs(5);
+
+const BLACK: Color = Color::new(0, 0, 0);
+
+/// A keyboard that draws the key grid on a terminal instead.
+///
+/// Regions, modes and native effects have nothing to draw and are accepted
+/// without effect.
+pub struct Simulator<W: Write> {
+    out: W,
+    colors: [[Color; COLS]; ROWS],
+    drawn: bool,
+}
+
+impl<W: Write> Simulator<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            colors: [[BLACK; COLS]; ROWS],
+            drawn: false,
+        }
+    }
+
+    fn set(&mut self, key: Key, color: Color) {
+        for (row, cells) in KEY_GRID.iter().enumerate() {
+            if let Some(col) = cells.iter().position(|&cell| cell == Some(key)) {
+                self.colors[row][col] = color;
+            }
+        }
+    }
+}
+
+impl<W: Write> KeyboardApi for Simulator<W> {
+    fn model(&self) -> KeyboardModel {
+        KeyboardModel::Unknown
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        let mut frame = String::new();
+        if self.drawn {
+            // Draw over the previous frame instead of scrolling.
+            let _ = write!(frame, "\x1b[{ROWS}A");
+        }
+        frame.push_str(&render(|row, col| Some(self.colors[row][col])));
+        self.out.write_all(frame.as_bytes())?;
+        self.out.flush()?;
+        self.drawn = true;
+        Ok(())
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.colors = [[color; COLS]; ROWS];
+        Ok(())
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        for key in group.keys() {
+            self.set(key, color);
+        }
+        Ok(())
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        for kv in keys {
+            self.set(kv.key, kv.color);
+        }
+        Ok(())
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        // Raw keys have no place on the drawing.
+        for kv in keys {
+            if let KeyRef::Known(key) = kv.key {
+                self.set(key, kv.color);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_region(&mut self, _region: u8, _color: Color) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_mr_key(&mut self, _value: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_mn_key(&mut self, _value: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_gkeys_mode(&mut self, _value: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_startup_mode(&mut self, _mode: StartupMode) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_on_board_mode(&mut self, _mode: OnBoardMode) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_fx(
+        &mut self,
+        _effect: NativeEffect,
+        _part: NativeEffectPart,
+        _period: Duration,
+        _color: Color,
+        _storage: NativeEffectStorage,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The key grid with each key in `color(row, col)`; keys without a color
+/// are drawn as faint dots.
+fn render(color: impl Fn(usize, usize) -> Option<Color>) -> String {
+    let mut grid = String::new();
+    for (row, cells) in KEY_GRID.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            match (cell, color(row, col)) {
+                (Some(_), Some(c)) => {
+                    let _ = write!(
+                        grid,
+                        "\x1b[48;2;{};{};{}m  \x1b[0m ",
+                        c.red, c.green, c.blue
+                    );
+                }
+                (Some(_), None) => grid.push_str("\x1b[2m··\x1b[0m "),
+                (None, _) => grid.push_str("   "),
+            }
+        }
+        grid.push('\n');
+    }
+    grid
+}
+
+/// The key grid showing the keys that going from `before` to `after`
+/// recolors, in their new colors.
+pub fn changed_keys(before: &Snapshot, after: &Snapshot) -> Result<String> {
+    let old = grid_colors(before)?;
+    let new = grid_colors(after)?;
+    Ok(render(|row, col| {
+        let color = new[row][col];
+        (color != old[row][col]).then_some(color)
+    }))
+}
+
+/// The color each grid cell shows once `snapshot` is applied to a dark board.
+pub(super) fn grid_colors(snapshot: &Snapshot) -> Result<[[Color; COLS]; ROWS]> {
+    let mut board = Simulator::new(io::sink());
+    snapshot.apply(&mut board)?;
+    Ok(board.colors)
+}
+
+/// A software imitation of a native effect, close enough to judge it by.
+///
+/// Ripple reacts to key presses, which a preview has none of; it shows its
+/// color instead.
+pub struct NativePreview {
+    pub effect: NativeEffect,
+    pub period: Duration,
+    pub color: Color,
+}
+
+impl Effect for NativePreview {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let period = if self.period.is_zero() {
+            FIRMWARE_PERIOD
+        } else {
+            self.period
+        };
+        let t = phase(elapsed, period);
+        let wave = |offset: f64| hue((t - offset).rem_euclid(1.0));
+        match self.effect {
+            NativeEffect::Off => kbd.set_all_keys(BLACK)?,
+            NativeEffect::Color | NativeEffect::Ripple => kbd.set_all_keys(self.color)?,
+            NativeEffect::Breathing => {
+                kbd.set_all_keys(mix(self.color, BLACK, (1.0 - (t * TAU).cos()) / 2.0))?;
+            }
+            NativeEffect::Cycle => kbd.set_all_keys(hue(t))?,
+            NativeEffect::Waves | NativeEffect::HWave => {
+                return grid::draw(kbd, |_, col| wave(fraction(col, COLS)));
+            }
+            NativeEffect::VWave => {
+                return grid::draw(kbd, |row, _| wave(fraction(row, ROWS)));
+            }
+            NativeEffect::CWave => {
+                // Keys are about twice as wide as the rows are tall.
+                return grid::draw(kbd, |row, col| {
+                    let across = fraction(col * 2, COLS) - 1.0;
+                    let down = fraction(row * 2, ROWS) - 1.0;
+                    wave(across.hypot(down / 2.0))
+                });
+            }
+        }
+        kbd.commit()
+    }
+}
+
+/// Play `effect` on the terminal for `length`, then ask whether to send it
+/// to the keyboard.
+pub fn show(effect: &mut dyn Effect, length: Duration) -> Result<bool> {
+    play(
+        &mut Simulator::new(io::stdout().lock()),
+        effect,
+        Pacing::fixed(DEFAULT_TICK),
+        Some(length),
+    )?;
+    confirm()
+}
+
+/// Ask whether to send what was shown to the keyboard.
+pub fn confirm() -> Result<bool> {
+    eprint!("send it to the keyboard? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_redraw_the_grid_in_place() {
+        let mut sim = Simulator::new(Vec::new());
+        sim.set_keys(&[KeyValue {
+            key: Key::Esc,
+            color: Color::new(0xff, 0x80, 0x00),
+        }])
+        .unwrap();
+        sim.commit().unwrap();
+        let first = String::from_utf8(sim.out.clone()).unwrap();
+        assert_eq!(first.lines().count(), ROWS);
+        assert!(first.starts_with("\x1b[48;2;255;128;0m  \x1b[0m \x1b[48;2;0;0;0m"));
+
+        sim.commit().unwrap();
+        let second = String::from_utf8(sim.out).unwrap();
+        assert_eq!(second[first.len()..].strip_prefix("\x1b[6A"), Some(&*first));
+    }
+
+    #[test]
+    fn only_changed_keys_are_colored() {
+        let before = Snapshot::parse("a ff0000\n");
+        let after = Snapshot::parse("a ff0000\nk esc 00ff00\n");
+        let grid = changed_keys(&before, &after).unwrap();
+        assert!(grid.starts_with("\x1b[48;2;0;255;0m  \x1b[0m \x1b[2m··"));
+        assert_eq!(grid.matches("\x1b[48;2").count(), 1);
+    }
+
+    #[test]
+    fn native_effects_are_imitated() {
+        let mut sim = Simulator::new(io::sink());
+        let red = Color::new(0xff, 0, 0);
+        let mut breathing = NativePreview {
+            effect: NativeEffect::Breathing,
+            period: Duration::from_secs(2),
+            color: red,
+        };
+        breathing.frame(&mut sim, Duration::ZERO).unwrap();
+        assert_eq!(sim.colors[0][0], red);
+        breathing.frame(&mut sim, Duration::from_secs(1)).unwrap();
+        assert_eq!(sim.colors[0][0], BLACK);
+
+        let mut wave = NativePreview {
+            effect: NativeEffect::HWave,
+            period: Duration::ZERO,
+            color: red,
+        };
+        wave.frame(&mut sim, Duration::ZERO).unwrap();
+        assert_eq!(sim.colors[0][0], hue(0.0));
+        assert_ne!(sim.colors[0][0], sim.colors[0][10]);
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use core::time::Duration;
+use strum_macros::{Display, EnumString};
+
+use super::grid::{COLS, ROWS, draw, fraction};
+use super::{Effect, hue, phase};
+use crate::keyboard::api::KeyboardApi;
+
+/// Default time for [`Rainbow`] to go through the hues once.
+pub const RAINBOW_PERIOD: Duration = Duration::from_secs(5);
+
+/// Default time for a [`RainbowWave`] color to cross the board.
+pub const WAVE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Cycle the whole board through the hues, one turn per `period`.
+///
+/// Works on every model, region-based ones included, since each frame is a
+/// single all-keys write.
+pub struct Rainbow {
+    pub period: Duration,
+}
+
+impl Effect for Rainbow {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        kbd.set_all_keys(hue(phase(elapsed, self.period)))?;
+        kbd.commit()
+    }
+}
+
+/// Which way a [`RainbowWave`] travels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// The hues sweeping across the key grid, each crossing it once per `period`.
+pub struct RainbowWave {
+    pub period: Duration,
+    pub direction: Direction,
+}
+
+impl RainbowWave {
+    /// How far along the hue circle a key is, before time moves it.
+    fn offset(&self, row: usize, col: usize) -> f64 {
+        // A key takes the color its upstream neighbour had a moment before.
+        match self.direction {
+            Direction::Right => fraction(col, COLS),
+            Direction::Left => -fraction(col, COLS),
+            Direction::Down => fraction(row, ROWS),
+            Direction::Up => -fraction(row, ROWS),
+        }
+    }
+}
+
+impl Effect for RainbowWave {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let turn = phase(elapsed, self.period);
+        draw(kbd, |row, col| hue(turn - self.offset(row, col)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_travel_their_way() {
+        let wave = |direction| RainbowWave {
+            period: Duration::from_secs(2),
+            direction,
+        };
+        // Moving right, a key shows later what its left neighbour shows now.
+        let right = wave(Direction::Right);
+        let step = Duration::from_secs(2).mul_f64(fraction(1, COLS));
+        let now = phase(Duration::ZERO, right.period) - right.offset(0, 3);
+        let later = phase(step, right.period) - right.offset(0, 4);
+        assert!((now - later).abs() < 1e-9);
+
+        let up = wave(Direction::Up);
+        assert!(up.offset(4, 0) < up.offset(1, 0));
+        assert_eq!("LEFT".parse(), Ok(Direction::Left));
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use core::time::Duration;
+
+use super::Effect;
+use crate::keyboard::{Color, Key, KeyValue, api::KeyboardApi};
+
+/// One key stepping through a list of colors, each shown for `step`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence {
+    pub key: Key,
+    pub colors: Vec<Color>,
+    pub step: Duration,
+}
+
+impl KeySequence {
+    /// The color `elapsed` into the animation.
+    pub fn color_at(&self, elapsed: Duration) -> Color {
+        let steps = if self.step.is_zero() {
+            0
+        } else {
+            elapsed.as_nanos() / self.step.as_nanos()
+        };
+        let len = self.colors.len().max(1) as u128;
+        // The remainder is below `colors.len()`, so it fits a usize.
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (steps % len) as usize;
+        self.colors.get(index).copied().unwrap_or_default()
+    }
+}
+
+/// Keys cycling through their own color lists independently.
+///
+/// Frames only reach the keyboard when some key changes color, so slow
+/// sequences do not flood it with identical packets at every tick.
+pub struct KeySequences {
+    sequences: Vec<KeySequence>,
+    shown: Vec<Color>,
+}
+
+impl KeySequences {
+    pub fn new(sequences: Vec<KeySequence>) -> Self {
+        Self {
+            sequences,
+            shown: Vec::new(),
+        }
+    }
+}
+
+impl Effect for KeySequences {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let colors: Vec<Color> = self
+            .sequences
+            .iter()
+            .map(|sequence| sequence.color_at(elapsed))
+            .collect();
+        if colors == self.shown {
+            return Ok(());
+        }
+        let keys: Vec<KeyValue> = self
+            .sequences
+            .iter()
+            .zip(&colors)
+            .map(|(sequence, &color)| KeyValue {
+                key: sequence.key,
+                color,
+            })
+            .collect();
+        kbd.set_keys(&keys)?;
+        kbd.commit()?;
+        self.shown = colors;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardModel;
+    use crate::testing::MockKeyboard;
+
+    fn sequence(key: Key, colors: &[u8], step_ms: u64) -> KeySequence {
+        KeySequence {
+            key,
+            colors: colors.iter().map(|&c| Color::new(c, 0, 0)).collect(),
+            step: Duration::from_millis(step_ms),
+        }
+    }
+
+    #[test]
+    fn keys_step_through_their_colors_independently() {
+        let esc = sequence(Key::Esc, &[1, 2, 3], 500);
+        let f1 = sequence(Key::F1, &[10, 20], 200);
+        let at = |ms| Duration::from_millis(ms);
+        assert_eq!(esc.color_at(at(0)).red, 1);
+        assert_eq!(esc.color_at(at(999)).red, 2);
+        assert_eq!(esc.color_at(at(1500)).red, 1);
+        assert_eq!(f1.color_at(at(500)).red, 10);
+        assert_eq!(f1.color_at(at(600)).red, 20);
+        assert_eq!(sequence(Key::F2, &[7], 0).color_at(at(5)).red, 7);
+    }
+
+    #[test]
+    fn unchanged_frames_are_skipped() {
+        let mut kbd = MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        };
+        let mut effect = KeySequences::new(vec![sequence(Key::Esc, &[1, 2], 500)]);
+        for ms in [0, 50, 100, 500, 550] {
+            effect.frame(&mut kbd, Duration::from_millis(ms)).unwrap();
+        }
+        assert_eq!(
+            kbd.calls,
+            ["key Esc 010000", "commit", "key Esc 020000", "commit"]
+        );
+    }
+}
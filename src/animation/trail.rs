@@ -0,0 +1,158 @@
+//! A trail of pressed keys, each lit in the color of the finger that types it
+//! and fading back to the lighting underneath.
+
+use anyhow::Result;
+use core::time::Duration;
+
+use super::grid::{KEY_GRID, fraction};
+use super::{hue, mix};
+use crate::keyboard::{Color, Key, KeyValue, api::KeyboardApi};
+use crate::state::KeyboardState;
+
+/// Fingers a key can be typed with: four per hand and the thumbs.
+const FINGERS: usize = 9;
+
+/// The touch-typing finger for `key`: 0 to 3 are the left pinky to index,
+/// 4 the thumbs and 5 to 8 the right index to pinky. `None` for keys off
+/// the grid, such as the media keys.
+fn finger(key: Key) -> Option<usize> {
+    if matches!(key, Key::Space | Key::AltLeft | Key::AltRight) {
+        return Some(4);
+    }
+    let col = KEY_GRID
+        .iter()
+        .find_map(|row| row.iter().position(|&cell| cell == Some(key)))?;
+    // The navigation cluster (14 to 16) and the numpad (17 to 20) belong to
+    // the right hand.
+    Some(match col {
+        0 | 1 => 0,
+        2 => 1,
+        3 => 2,
+        4 | 5 => 3,
+        6 | 7 | 14 | 17 => 5,
+        8 | 15 | 18 => 6,
+        9 | 16 | 19 => 7,
+        _ => 8,
+    })
+}
+
+/// Fast at first, then slowly settling, so a key dims quickly after a press.
+fn ease_out(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * (2.0 - t)
+}
+
+/// Keys lit by presses, fading back over `fade` to what `base` gives them.
+pub struct Trail {
+    fade: Duration,
+    base: KeyboardState,
+    /// Keys still fading, each with when it was last pressed.
+    lit: Vec<(Key, Duration)>,
+}
+
+impl Trail {
+    pub fn new(fade: Duration, base: KeyboardState) -> Self {
+        Self {
+            fade,
+            base,
+            lit: Vec::new(),
+        }
+    }
+
+    /// The color `key` is typed in, if it has a finger.
+    pub fn color(key: Key) -> Option<Color> {
+        finger(key).map(|finger| hue(fraction(finger, FINGERS)))
+    }
+
+    /// Light `key`, pressed `now`; pressing a fading key starts it over.
+    pub fn press(&mut self, key: Key, now: Duration) {
+        if finger(key).is_some() {
+            self.lit.retain(|&(lit, _)| lit != key);
+            self.lit.push((key, now));
+        }
+    }
+
+    /// Whether any key is still fading, so frames are needed.
+    pub fn busy(&self) -> bool {
+        !self.lit.is_empty()
+    }
+
+    /// Draw the fading keys as of `now`, putting back those that are done.
+    pub fn frame(&mut self, kbd: &mut dyn KeyboardApi, now: Duration) -> Result<()> {
+        let mut keys = Vec::new();
+        self.lit.retain(|&(key, pressed)| {
+            // A key nothing lit is dark.
+            let base = self.base.key_color(key).unwrap_or(Color::new(0, 0, 0));
+            let t = if self.fade.is_zero() {
+                1.0
+            } else {
+                now.saturating_sub(pressed).as_secs_f64() / self.fade.as_secs_f64()
+            };
+            let lit = Self::color(key).unwrap_or(base);
+            keys.push(KeyValue {
+                key,
+                color: mix(lit, base, ease_out(t)),
+            });
+            t < 1.0
+        });
+        if keys.is_empty() {
+            return Ok(());
+        }
+        kbd.set_keys(&keys)?;
+        kbd.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardModel;
+    use crate::testing::MockKeyboard;
+
+    #[test]
+    fn fingers_follow_the_home_row() {
+        let fingers: Vec<_> = [Key::A, Key::S, Key::D, Key::F, Key::G]
+            .into_iter()
+            .chain([Key::H, Key::J, Key::K, Key::L, Key::Semicolon])
+            .map(finger)
+            .collect();
+        let expected = [0, 1, 2, 3, 3, 5, 5, 6, 7, 8].map(Some);
+        assert_eq!(fingers, expected);
+        assert_eq!(finger(Key::Space), Some(4));
+        assert_eq!(finger(Key::Num5), Some(6));
+        assert_eq!(finger(Key::Play), None);
+    }
+
+    #[test]
+    fn pressed_keys_fade_back_to_the_base() {
+        let mut base = KeyboardState::new();
+        base.set_all_keys(Color::new(0, 0, 0xff)).unwrap();
+        let second = Duration::from_secs(1);
+        let mut trail = Trail::new(second, base);
+        let mut kbd = MockKeyboard::new(KeyboardModel::G810);
+
+        trail.press(Key::Play, Duration::ZERO);
+        assert!(!trail.busy());
+        trail.press(Key::A, Duration::ZERO);
+        trail.frame(&mut kbd, Duration::ZERO).unwrap();
+        trail.frame(&mut kbd, second / 2).unwrap();
+        assert!(trail.busy());
+        trail.frame(&mut kbd, second).unwrap();
+        assert!(!trail.busy());
+        trail.frame(&mut kbd, second * 2).unwrap();
+
+        let red = Trail::color(Key::A).unwrap();
+        let half = mix(red, Color::new(0, 0, 0xff), 0.75);
+        assert_eq!(
+            kbd.calls,
+            [
+                format!("key A {red}"),
+                "commit".into(),
+                format!("key A {half}"),
+                "commit".into(),
+                "key A 0000ff".into(),
+                "commit".into(),
+            ]
+        );
+    }
+}
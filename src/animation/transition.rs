@@ -0,0 +1,107 @@
+use anyhow::Result;
+use core::time::Duration;
+use strum_macros::{Display, EnumString};
+
+use super::grid::{COLS, ROWS, draw, fraction};
+use super::preview::grid_colors;
+use super::{Effect, mix};
+use crate::history::Snapshot;
+use crate::keyboard::{Color, api::KeyboardApi};
+
+/// Columns the soft edge of a [`TransitionStyle::Wipe`] spans.
+const WIPE_EDGE: usize = 3;
+
+/// How a [`Transition`] moves from the old lighting to the new.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum TransitionStyle {
+    /// Every key blends from its old color to its new one.
+    Crossfade,
+    /// The new colors sweep across the board from left to right.
+    Wipe,
+}
+
+/// Animate the key grid from one recorded lighting to another over
+/// `duration`, easing in and out.
+///
+/// Only the grid is drawn: regions, modes and native effects are left for
+/// the caller to apply once it is done.
+pub struct Transition {
+    from: [[Color; COLS]; ROWS],
+    to: [[Color; COLS]; ROWS],
+    style: TransitionStyle,
+    duration: Duration,
+}
+
+impl Transition {
+    pub fn new(
+        from: &Snapshot,
+        to: &Snapshot,
+        style: TransitionStyle,
+        duration: Duration,
+    ) -> Result<Self> {
+        Ok(Self {
+            from: grid_colors(from)?,
+            to: grid_colors(to)?,
+            style,
+            duration,
+        })
+    }
+
+    fn color(&self, row: usize, col: usize, elapsed: Duration) -> Color {
+        let t = if self.duration.is_zero() {
+            1.0
+        } else {
+            ease(elapsed.as_secs_f64() / self.duration.as_secs_f64())
+        };
+        let t = match self.style {
+            TransitionStyle::Crossfade => t,
+            TransitionStyle::Wipe => {
+                // The edge starts left of the board and ends past its right side.
+                let edge = fraction(WIPE_EDGE, COLS);
+                (t * (1.0 + edge) - fraction(col, COLS)) / edge
+            }
+        };
+        mix(self.from[row][col], self.to[row][col], t)
+    }
+}
+
+impl Effect for Transition {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        draw(kbd, |row, col| self.color(row, col, elapsed))
+    }
+
+    fn done(&self, elapsed: Duration) -> bool {
+        elapsed >= self.duration
+    }
+}
+
+/// Smoothstep: slow at both ends, so neither lighting snaps in or out.
+fn ease(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_blend_and_sweep() {
+        let from = Snapshot::parse("a 000000\n");
+        let to = Snapshot::parse("a ffffff\n");
+        let second = Duration::from_secs(1);
+        let half = second / 2;
+
+        let fade = Transition::new(&from, &to, TransitionStyle::Crossfade, second).unwrap();
+        assert_eq!(fade.color(0, 0, Duration::ZERO), Color::new(0, 0, 0));
+        assert_eq!(fade.color(2, 5, half), Color::new(128, 128, 128));
+        assert_eq!(fade.color(5, 20, second), Color::new(255, 255, 255));
+
+        let wipe = Transition::new(&from, &to, TransitionStyle::Wipe, second).unwrap();
+        assert_eq!(wipe.color(0, 0, half), Color::new(255, 255, 255));
+        assert_eq!(wipe.color(0, COLS - 1, half), Color::new(0, 0, 0));
+        assert_eq!(wipe.color(0, COLS - 1, second), Color::new(255, 255, 255));
+        assert!(wipe.done(second) && !wipe.done(half));
+    }
+}
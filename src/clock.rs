@@ -0,0 +1,105 @@
+//! The time timed features run on, so tests can step through it.
+//!
+//! Animations, brightness ramps, the idle animation, schedules, the digit
+//! clock, the mode indicator's debounce, and the packet throttle and pacing
+//! read and wait on a [`Clock`] instead of the system.
+//! [`SystemClock`] is the real one; [`ManualClock`] only moves when slept on
+//! or [advanced](ManualClock::advance).
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use core::cell::Cell;
+use core::time::Duration;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub trait Clock {
+    /// Monotonic time since a starting point of the clock's choosing.
+    fn now(&self) -> Duration;
+
+    /// Local wall-clock time, for schedules and day/night themes.
+    fn local(&self) -> DateTime<Local>;
+
+    /// Wait for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The system's clocks; [`now`](Clock::now) counts from its first use in the
+/// process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// A clock that stands still until slept on or advanced.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+    local: Cell<DateTime<Local>>,
+}
+
+impl ManualClock {
+    /// A clock reading `local`, with [`now`](Clock::now) at zero.
+    pub fn new(local: DateTime<Local>) -> Self {
+        Self {
+            now: Cell::new(Duration::ZERO),
+            local: Cell::new(local),
+        }
+    }
+
+    /// Move both readings on by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+        let by = TimeDelta::from_std(by).unwrap_or(TimeDelta::MAX);
+        self.local.set(self.local.get() + by);
+    }
+}
+
+impl Default for ManualClock {
+    /// Starts at the Unix epoch.
+    fn default() -> Self {
+        Self::new(DateTime::<Utc>::UNIX_EPOCH.with_timezone(&Local))
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        self.local.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_moves_only_when_asked() {
+        let clock = ManualClock::default();
+        let start = clock.local();
+        assert_eq!(clock.now(), Duration::ZERO);
+        clock.sleep(Duration::from_secs(90));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(clock.now(), Duration::from_millis(90_500));
+        assert_eq!(clock.local() - start, TimeDelta::milliseconds(90_500));
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use clap::Args;
+use core::time::Duration;
+
+use super::{Command, Context, parse_duration_arg};
+use crate::animation::{self, DEFAULT_TICK, Effect, phase};
+use crate::help::COLOR_HELP;
+use crate::history::History;
+use crate::keyboard::{Color, Key, KeyValue, api::KeyboardApi};
+use crate::output;
+use crate::shutdown;
+use crate::state::KeyboardState;
+
+/// Time for one on-and-off cycle of `--blink`.
+const BLINK_PERIOD: Duration = Duration::from_secs(1);
+
+const BLACK: Color = Color::new(0, 0, 0);
+
+/// Light a key for a while on top of the current lighting, then put it back
+///
+/// For scripts flagging something, e.g. new mail on `m`:
+/// `logi-led alert m --color orange --for 1m --blink &`. The lighting comes
+/// back from the latest history entry; the alert itself is not recorded.
+#[derive(Args, Debug)]
+pub struct Alert {
+    pub key: Key,
+    #[arg(long, default_value = "red", help = COLOR_HELP)]
+    pub color: Color,
+    /// How long the alert shows, e.g. `30s` or `5m`
+    #[arg(long = "for", default_value = "10s", value_parser = parse_duration_arg)]
+    pub duration: Duration,
+    /// Blink instead of staying lit
+    #[arg(long)]
+    pub blink: bool,
+}
+
+impl Command for Alert {
+    fn name(&self) -> &'static str {
+        "alert"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        // Ctrl-C cuts the alert short but still puts the key back.
+        shutdown::catch()?;
+        let base = ctx.history.map(History::latest).unwrap_or_default();
+        let pacing = ctx.config.pacing(DEFAULT_TICK)?;
+        // Blinking goes back to what the base lighting gives the key.
+        let mut state = KeyboardState::new();
+        base.apply(&mut state)?;
+        let mut flash = Flash {
+            key: self.key,
+            color: self.color,
+            off: state.key_color(self.key).unwrap_or(BLACK),
+            blink: self.blink,
+            lit: None,
+        };
+        ctx.opener.with_keyboard(&mut |kbd| {
+            animation::play(kbd, &mut flash, pacing, Some(self.duration))?;
+            if base.is_empty() {
+                output::note("no recorded lighting to go back to; turning the key off");
+                kbd.set_keys(&[KeyValue {
+                    key: self.key,
+                    color: BLACK,
+                }])?;
+                return kbd.commit();
+            }
+            base.apply(kbd)
+        })
+    }
+}
+
+/// One key lit, or blinking, in the alert color.
+struct Flash {
+    key: Key,
+    color: Color,
+    /// The key's color between blinks.
+    off: Color,
+    blink: bool,
+    /// Whether the key was last drawn lit, to write only when it changes.
+    lit: Option<bool>,
+}
+
+impl Effect for Flash {
+    fn frame(&mut self, kbd: &mut dyn KeyboardApi, elapsed: Duration) -> Result<()> {
+        let lit = !self.blink || phase(elapsed, BLINK_PERIOD) < 0.5;
+        if self.lit == Some(lit) {
+            return Ok(());
+        }
+        self.lit = Some(lit);
+        let color = if lit { self.color } else { self.off };
+        kbd.set_keys(&[KeyValue {
+            key: self.key,
+            color,
+        }])?;
+        kbd.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::MockKeyboard;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn blinking_writes_only_on_changes() {
+        let mut kbd = MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        };
+        let mut flash = Flash {
+            key: Key::M,
+            color: Color::new(0xff, 0x80, 0),
+            off: Color::new(0x10, 0x20, 0x30),
+            blink: true,
+            lit: None,
+        };
+        for ms in [0, 100, 600, 700, 1000] {
+            flash.frame(&mut kbd, Duration::from_millis(ms)).unwrap();
+        }
+        assert_eq!(
+            kbd.calls,
+            [
+                "key M ff8000",
+                "commit",
+                "key M 102030",
+                "commit",
+                "key M ff8000",
+                "commit"
+            ]
+        );
+    }
+}
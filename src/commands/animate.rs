@@ -0,0 +1,176 @@
+use anyhow::{Result, anyhow, bail};
+use clap::Args;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Command, Context, parse_duration_arg, parse_preview_arg};
+use crate::animation::{
+    self, DEFAULT_TICK, Direction, Effect, Pacing, RAINBOW_PERIOD, Rainbow, RainbowWave,
+    WAVE_PERIOD, demos, preview,
+};
+use crate::diagnostics::closest;
+use crate::output;
+
+/// Run an animation on the key grid, e.g. `demo:life` or `wave`
+///
+/// Runs in the foreground until `--for` elapses or it is interrupted.
+/// `rainbow` and `wave` are the `fx2` effects of the same name.
+#[derive(Args, Debug)]
+pub struct Animate {
+    /// Animation to run: `rainbow`, `wave` or `demo:<name>`
+    #[arg(required_unless_present = "list")]
+    pub effect: Option<String>,
+    /// Which way `wave` travels: left, right, up or down (default right)
+    #[arg(long)]
+    pub direction: Option<Direction>,
+    /// Time for one turn of `rainbow` (default 5s) or for a `wave` color to
+    /// cross the board (default 2s)
+    #[arg(long, value_parser = parse_duration_arg)]
+    pub period: Option<Duration>,
+    /// List the available animations
+    #[arg(long)]
+    pub list: bool,
+    /// Stop after this long, e.g. `30s` or `1h`
+    #[arg(long = "for", value_parser = parse_duration_arg)]
+    pub limit: Option<Duration>,
+    /// Frames a second, 1 to 100 (default 20); `max_fps` in the config still caps it
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub fps: Option<u32>,
+    /// Show the animation in the terminal first and ask before sending it;
+    /// `--preview=10s` or `--preview=200` (frames) sets how long
+    #[arg(
+        long,
+        value_name = "LENGTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5s",
+        value_parser = parse_preview_arg
+    )]
+    pub preview: Option<Duration>,
+}
+
+/// The `fx2` effects that also run by name, and what they do.
+const SOFTWARE_EFFECTS: [(&str, &str); 2] = [
+    (
+        "rainbow",
+        "The whole board through the colors of the rainbow",
+    ),
+    ("wave", "The colors of the rainbow sweeping across the keys"),
+];
+
+/// Resolve `rainbow`, `wave` or `demo:<name>` to an effect, suggesting a
+/// close match on typos.
+pub(super) fn lookup(spec: &str, seed: u64) -> Result<Box<dyn Effect>> {
+    lookup_with(spec, seed, None, None)
+}
+
+/// [`lookup`] with the `--direction` and `--period` of `rainbow` and `wave`.
+fn lookup_with(
+    spec: &str,
+    seed: u64,
+    direction: Option<Direction>,
+    period: Option<Duration>,
+) -> Result<Box<dyn Effect>> {
+    match spec {
+        "rainbow" if direction.is_none() => {
+            return Ok(Box::new(Rainbow {
+                period: period.unwrap_or(RAINBOW_PERIOD),
+            }));
+        }
+        "rainbow" => bail!("--direction applies to `wave` only"),
+        "wave" => {
+            return Ok(Box::new(RainbowWave {
+                period: period.unwrap_or(WAVE_PERIOD),
+                direction: direction.unwrap_or(Direction::Right),
+            }));
+        }
+        _ if direction.is_some() || period.is_some() => {
+            bail!("--direction and --period apply to `rainbow` and `wave` only");
+        }
+        _ => {}
+    }
+    let names = || demos::DEMOS.iter().map(|(name, _, _)| *name);
+    let Some(name) = spec.strip_prefix("demo:") else {
+        let software = SOFTWARE_EFFECTS.iter().map(|(name, _)| *name);
+        if let Some(hint) = closest(spec, software, 2) {
+            bail!("unknown animation {spec:?}; did you mean `{hint}`?");
+        }
+        bail!("unknown animation {spec:?}; animations are `rainbow`, `wave` or `demo:<name>`");
+    };
+    demos::demo(name, seed).ok_or_else(|| match closest(name, names(), 2) {
+        Some(hint) => anyhow!("unknown demo {name:?}; did you mean `demo:{hint}`?"),
+        None => anyhow!(
+            "unknown demo {name:?}; available: {}",
+            names().collect::<Vec<_>>().join(", ")
+        ),
+    })
+}
+
+impl Command for Animate {
+    fn name(&self) -> &'static str {
+        "animate"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let Some(spec) = self.effect.as_deref().filter(|_| !self.list) else {
+            for (name, description) in SOFTWARE_EFFECTS {
+                println!("{name:<17} {description}");
+            }
+            for (name, description, _) in demos::DEMOS {
+                println!("demo:{name:<12} {description}");
+            }
+            return Ok(());
+        };
+        let pacing = ctx
+            .config
+            .pacing(self.fps.map_or(DEFAULT_TICK, Pacing::tick_for))?;
+        let seed = time_seed();
+        if let Some(length) = self.preview
+            && !preview::show(self.effect(spec, seed)?.as_mut(), length)?
+        {
+            output::status("nothing sent");
+            return Ok(());
+        }
+        // The same seed, so the keyboard plays what was previewed.
+        let mut effect = self.effect(spec, seed)?;
+        ctx.animate(|| {
+            ctx.with_keyboard(|kbd| animation::play(kbd, effect.as_mut(), pacing, self.limit))
+        })
+    }
+}
+
+impl Animate {
+    fn effect(&self, spec: &str, seed: u64) -> Result<Box<dyn Effect>> {
+        lookup_with(spec, seed, self.direction, self.period)
+    }
+}
+
+/// A seed that differs from run to run.
+pub(super) fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |since| since.as_secs() ^ u64::from(since.subsec_nanos()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_resolve_or_suggest() {
+        assert!(lookup("demo:life", 1).is_ok());
+        let err = lookup("demo:matirx", 1).err().unwrap();
+        assert!(err.to_string().contains("did you mean `demo:matrix`"));
+        assert!(lookup("life", 1).is_err());
+    }
+
+    #[test]
+    fn fx2_effects_run_by_name() {
+        assert!(lookup("rainbow", 1).is_ok());
+        assert!(lookup_with("wave", 1, Some(Direction::Left), None).is_ok());
+        assert!(lookup_with("rainbow", 1, Some(Direction::Left), None).is_err());
+        assert!(lookup_with("demo:life", 1, None, Some(Duration::from_secs(1))).is_err());
+        let err = lookup("rainbw", 1).err().unwrap();
+        assert!(err.to_string().contains("did you mean `rainbow`"));
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow, bail};
+use clap::{Args, ValueHint};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use super::{Command, Context};
+use crate::history::Snapshot;
+use crate::output;
+use crate::profile;
+
+/// Save the modes, lighting and stored effects logi-led last applied to a file
+///
+/// The keyboard cannot report its settings, so this is what logi-led sent
+/// while the lighting history was kept; changes made by other tools are not
+/// included. The file is a g810-led profile.
+#[derive(Args, Debug)]
+pub struct Backup {
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: PathBuf,
+}
+
+impl Command for Backup {
+    fn name(&self) -> &'static str {
+        "backup"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let history = ctx.history.ok_or_else(|| {
+            anyhow!("backups come from the lighting history, which is disabled (history_limit = 0)")
+        })?;
+        let stored = history.stored_effects()?;
+        let current = history.entries()?.pop().map(|entry| entry.snapshot);
+        if stored.is_empty() && current.is_none() {
+            bail!("nothing to back up yet; apply a profile, color or mode first");
+        }
+        let created = chrono::Local::now().format("%Y-%m-%d %H:%M");
+        let text = backup_text(&stored, current.as_ref(), created);
+        std::fs::write(&self.file, text).map_err(|e| anyhow!("{}: {e}", self.file.display()))?;
+        output::status(format_args!("wrote {}", self.file.display()));
+        Ok(())
+    }
+}
+
+/// Reapply a file written by `backup`, or the lighting last applied
+///
+/// Without a file, the modes and lighting from the latest history entry are
+/// sent again, e.g. from a udev rule or login script once a replugged or
+/// resumed keyboard has lost them. Stored effects survive in on-board memory
+/// and are left alone.
+#[derive(Args, Debug)]
+pub struct Restore {
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+}
+
+impl Command for Restore {
+    fn name(&self) -> &'static str {
+        "restore"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let Some(file) = &self.file else {
+            let history = ctx.history.ok_or_else(|| {
+                anyhow!("the last state comes from the lighting history, which is disabled (history_limit = 0)")
+            })?;
+            let latest = history.latest();
+            if latest.is_empty() {
+                bail!("nothing to restore yet; apply a profile, color or mode first");
+            }
+            // Already the latest entry; sending it again adds nothing to record.
+            ctx.opener.with_keyboard(&mut |kbd| latest.apply(kbd))?;
+            output::status("restored the last applied lighting");
+            return Ok(());
+        };
+        // Strict: a half-restored keyboard is worse than none.
+        ctx.with_keyboard(|kbd| profile::load_profile(kbd, file, true))?;
+        output::status(format_args!("restored {}", file.display()));
+        Ok(())
+    }
+}
+
+/// Stored effects first, then the modes and lighting on top of them.
+fn backup_text(
+    stored: &[String],
+    current: Option<&Snapshot>,
+    created: impl std::fmt::Display,
+) -> String {
+    let mut out = format!("# logi-led backup, {created}\n");
+    if !stored.is_empty() {
+        out.push_str("\n# stored in on-board memory\n");
+        for line in stored {
+            let _ = writeln!(out, "{line}");
+        }
+    }
+    out.push_str("\n# modes and lighting\n");
+    match current {
+        Some(snapshot) => {
+            let _ = write!(out, "{snapshot}");
+        }
+        None => out.push_str("c\n"),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Target;
+    use super::super::testing::{MockKeyboard, MockOpener, run_with};
+    use super::*;
+    use crate::config::Config;
+    use crate::history::History;
+    use crate::keyboard::KeyboardModel;
+    use std::cell::RefCell;
+
+    #[test]
+    fn backups_restore_stored_effects_then_lighting() {
+        let stored = ["fx cycle all 5000ms ffffff user".to_owned()];
+        let snapshot = Snapshot::parse("sm wave\ngkm 1\na ff0000\n");
+        let text = backup_text(&stored, Some(&snapshot), "2026-10-17 12:00");
+        assert_eq!(
+            text,
+            "# logi-led backup, 2026-10-17 12:00\n\n\
+             # stored in on-board memory\n\
+             fx cycle all 5000ms ffffff user\n\n\
+             # modes and lighting\n\
+             sm wave\ngkm 1\na ff0000\nc\n"
+        );
+
+        let file = std::env::temp_dir().join("logi_led_backup_restore.profile");
+        std::fs::write(&file, text).unwrap();
+        let restore = Restore {
+            file: Some(file.clone()),
+        };
+        let calls = run_with(KeyboardModel::G810, &Config::default(), |ctx| {
+            restore.run(ctx)
+        })
+        .unwrap();
+        let _ = std::fs::remove_file(file);
+        assert_eq!(
+            calls,
+            [
+                "fx cycle all 5000ms ffffff User",
+                "startup wave",
+                "gkm 1",
+                "all ff0000",
+                "commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_without_a_file_replays_the_latest_entry() {
+        let dir = std::env::temp_dir().join("logi_led_restore_latest");
+        let _ = std::fs::remove_dir_all(&dir);
+        let history = History::new(dir.clone(), 5);
+        let opener = MockOpener(RefCell::new(MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        }));
+        let config = Config::default();
+        let ctx = Context {
+            config: &config,
+            serial: None,
+            target: Target::default(),
+            strict: false,
+            opener: &opener,
+            history: Some(&history),
+        };
+        let restore = Restore { file: None };
+        assert!(restore.run(&ctx).is_err());
+
+        history.push(&Snapshot::parse("gkm 1\na ff0000\n")).unwrap();
+        restore.run(&ctx).unwrap();
+        assert_eq!(history.entries().unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+        assert_eq!(
+            opener.0.into_inner().calls,
+            ["gkm 1", "all ff0000", "commit"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{Result, bail};
+use clap::Args;
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+
+use super::{Command, Context};
+use crate::keyboard::{
+    Color, NativeEffect, NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode,
+    api::KeyboardApi,
+};
+use crate::output;
+use crate::profile;
+
+/// What the keyboard should show when no software runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootTarget {
+    Color(Color),
+    Profile(PathBuf),
+}
+
+/// A profile file if one exists at `s`, otherwise a color.
+fn parse_boot_target(s: &str) -> Result<BootTarget, String> {
+    if Path::new(s).is_file() {
+        return Ok(BootTarget::Profile(PathBuf::from(s)));
+    }
+    s.parse()
+        .map(BootTarget::Color)
+        .map_err(|e| format!("{e}; nor is there a profile file by that name"))
+}
+
+/// Make the keyboard come up with a profile or color even when no software runs
+///
+/// Does whatever the model needs for that, in one go: on models with a
+/// startup mode, applies the lighting, stores a color as the user effect and
+/// switches the startup mode to `color`; on the G815, which keeps only a
+/// stored effect across power loss, stores the color and switches to
+/// on-board mode. Each step is listed as it is written.
+#[derive(Args, Debug)]
+pub struct SetDefaultBoot {
+    /// A profile file, or a color
+    #[arg(value_name = "PROFILE|COLOR", value_parser = parse_boot_target)]
+    pub target: BootTarget,
+}
+
+impl Command for SetDefaultBoot {
+    fn name(&self) -> &'static str {
+        "set-default-boot"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| set_default_boot(kbd, &self.target, ctx.strict))
+    }
+}
+
+/// Write what `kbd`'s model needs to show `target` at power-on.
+fn set_default_boot(kbd: &mut dyn KeyboardApi, target: &BootTarget, strict: bool) -> Result<()> {
+    let model = kbd.model();
+    let spec = model.spec();
+    let store = |kbd: &mut dyn KeyboardApi, color: Color| {
+        kbd.set_fx(
+            NativeEffect::Color,
+            NativeEffectPart::All,
+            Duration::ZERO,
+            color,
+            NativeEffectStorage::User,
+        )?;
+        output::status(format_args!(
+            "stored {color} as the user effect (backlight+7 brings it back)"
+        ));
+        anyhow::Ok(())
+    };
+
+    if spec.startup_header.is_some() {
+        match target {
+            BootTarget::Color(color) => {
+                if spec.effect_params.is_some() {
+                    store(kbd, *color)?;
+                }
+                kbd.set_all_keys(*color)?;
+                kbd.commit()?;
+                output::status(format_args!("lit the board {color}"));
+            }
+            BootTarget::Profile(path) => {
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    profile::load_toml_profile(kbd, path)?;
+                } else {
+                    profile::load_profile(kbd, path, strict)?;
+                }
+                kbd.commit()?;
+                output::status(format_args!("applied {}", path.display()));
+            }
+        }
+        kbd.set_startup_mode(StartupMode::Color)?;
+        output::status("set the startup mode to color, so the board starts with this lighting");
+        return Ok(());
+    }
+
+    if spec.onboard_header.is_some() && spec.effect_params.is_some() {
+        let BootTarget::Color(color) = target else {
+            bail!(
+                "the {model:?} keeps only a stored effect across power loss, not per-key lighting; pass a color"
+            );
+        };
+        store(kbd, *color)?;
+        kbd.set_on_board_mode(OnBoardMode::Board)?;
+        output::status("switched to on-board mode, so the board shows the stored effect");
+        output::note("run `logi-led on-board-mode software` to control it from logi-led again");
+        return Ok(());
+    }
+
+    bail!("the {model:?} has no startup or on-board mode that logi-led knows how to set")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::MockKeyboard;
+    use crate::keyboard::KeyboardModel;
+
+    fn boot(model: KeyboardModel, target: &BootTarget) -> Result<Vec<String>> {
+        let mut kbd = MockKeyboard {
+            model,
+            calls: Vec::new(),
+        };
+        set_default_boot(&mut kbd, target, true)?;
+        Ok(kbd.calls)
+    }
+
+    #[test]
+    fn each_model_gets_its_own_steps() {
+        let red = BootTarget::Color(Color::new(0xff, 0, 0));
+        assert_eq!(
+            boot(KeyboardModel::G810, &red).unwrap(),
+            [
+                "fx color all 0ms ff0000 User",
+                "all ff0000",
+                "commit",
+                "startup color"
+            ]
+        );
+        assert_eq!(
+            boot(KeyboardModel::G815, &red).unwrap(),
+            ["fx color all 0ms ff0000 User", "on-board board"]
+        );
+        assert!(boot(KeyboardModel::Unknown, &red).is_err());
+
+        let profile = BootTarget::Profile(PathBuf::from("boot.profile"));
+        assert!(boot(KeyboardModel::G815, &profile).is_err());
+    }
+
+    #[test]
+    fn profiles_load_in_either_format() {
+        let dir = std::env::temp_dir();
+        let text = dir.join("logi_led_boot.profile");
+        std::fs::write(&text, "a 00ff00\n").unwrap();
+        let toml = dir.join("logi_led_boot.toml");
+        std::fs::write(&toml, "all = \"00ff00\"\n").unwrap();
+
+        for path in [text, toml] {
+            let calls = boot(KeyboardModel::G810, &BootTarget::Profile(path)).unwrap();
+            assert_eq!(calls.first().map(String::as_str), Some("all 00ff00"));
+            assert_eq!(calls.last().map(String::as_str), Some("startup color"));
+        }
+    }
+
+    #[test]
+    fn targets_are_files_or_colors() {
+        assert_eq!(
+            parse_boot_target("red"),
+            Ok(BootTarget::Color(Color::new(0xff, 0, 0)))
+        );
+        let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/Cargo.toml";
+        assert_eq!(
+            parse_boot_target(&path),
+            Ok(BootTarget::Profile(PathBuf::from(&path)))
+        );
+        assert!(parse_boot_target("no-such-profile").is_err());
+    }
+}
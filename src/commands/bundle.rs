@@ -0,0 +1,263 @@
+//! `report-bundle`: everything a bug report needs, in one local tarball.
+//!
+//! Nothing leaves the machine. The bundle is written to disk for the user to
+//! look over and attach to an issue themselves.
+
+use anyhow::{Result, anyhow};
+use clap::{Args, ValueHint};
+use flate2::{Compression, write::GzEncoder};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use super::{Command, Context};
+use crate::config::Config;
+use crate::keyboard::device::{BACKEND, DeviceInfo, HidInterface, Keyboard};
+use crate::keyboard::model::LOGITECH_VENDOR_ID;
+use crate::output;
+use crate::packet_log;
+
+/// Top-level directory inside the archive.
+const ROOT: &str = "logi-led-report";
+
+/// Config keys whose values are withheld, matched within the lowercased key.
+const SECRET_KEYS: [&str; 6] = ["token", "secret", "password", "passwd", "api_key", "auth"];
+
+/// Collect diagnostics, device descriptors, the packet log and config into one file
+#[derive(Args, Debug)]
+pub struct ReportBundle {
+    /// Archive to write (default: `logi-led-report-<date>-<time>.tar.gz`)
+    #[arg(long, short, value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+impl Command for ReportBundle {
+    fn name(&self) -> &'static str {
+        "report-bundle"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let now = chrono::Local::now();
+        let path = self
+            .output
+            .clone()
+            .unwrap_or_else(|| format!("{ROOT}-{}.tar.gz", now.format("%Y%m%d-%H%M%S")).into());
+
+        let mut files = vec![
+            ("about.txt", about(now.format("%Y-%m-%d %H:%M:%S %z"))),
+            ("doctor.txt", super::doctor::report(ctx)),
+            (
+                "devices.txt",
+                format_devices(
+                    Keyboard::list_keyboards(),
+                    Keyboard::vendor_interfaces(LOGITECH_VENDOR_ID),
+                ),
+            ),
+        ];
+        if let Some(log) = packet_log::path().and_then(|p| std::fs::read_to_string(p).ok()) {
+            files.push(("packets.log", log));
+        }
+        if let Some(config) = Config::path().and_then(|p| std::fs::read_to_string(p).ok()) {
+            files.push(("config.toml", scrub_config(&config)));
+        }
+
+        let home = ["HOME", "USERPROFILE"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|home| home.len() > 1));
+        let file = std::fs::File::create(&path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        let mtime = u64::try_from(now.timestamp()).unwrap_or_default();
+        write_bundle(file, &files, home.as_deref(), mtime)?;
+
+        output::status(format_args!("wrote {}", path.display()));
+        output::note("nothing was uploaded; look the bundle over, then attach it to your issue");
+        Ok(())
+    }
+}
+
+fn about(created: impl std::fmt::Display) -> String {
+    format!(
+        "logi-led {} ({BACKEND} backend) on {} {}\ncreated {created} by `logi-led report-bundle`\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    )
+}
+
+/// Supported keyboards, then every HID interface of the vendor's devices.
+fn format_devices(
+    keyboards: Result<Vec<DeviceInfo>>,
+    interfaces: Result<Vec<HidInterface>>,
+) -> String {
+    let mut out = String::from("supported keyboards:\n");
+    match keyboards {
+        Ok(keyboards) if keyboards.is_empty() => out.push_str("  none\n"),
+        Ok(keyboards) => {
+            for kbd in keyboards {
+                let _ = writeln!(
+                    out,
+                    "  {:04x}:{:04x} {:?} \"{}\" at {}",
+                    kbd.vendor_id,
+                    kbd.product_id,
+                    kbd.model,
+                    kbd.product.as_deref().unwrap_or_default(),
+                    kbd.path
+                );
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(out, "  error: {e}");
+        }
+    }
+
+    out.push_str("\nHID interfaces:\n");
+    match interfaces {
+        Ok(interfaces) if interfaces.is_empty() => out.push_str("  none\n"),
+        Ok(interfaces) => {
+            for iface in interfaces {
+                let _ = writeln!(
+                    out,
+                    "  {:04x}:{:04x} \"{}\" {iface}",
+                    iface.vendor_id,
+                    iface.product_id,
+                    iface.product.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(out, "  error: {e}");
+        }
+    }
+    out
+}
+
+/// Withhold the values of secret-looking keys, keeping the rest as written.
+///
+/// Works line by line rather than through the TOML parser, so comments
+/// survive and a config that no longer parses can still be shared.
+fn scrub_config(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        match line.split_once('=') {
+            Some((key, _))
+                if SECRET_KEYS
+                    .iter()
+                    .any(|secret| key.to_ascii_lowercase().contains(secret)) =>
+            {
+                let _ = writeln!(out, "{key}= \"<redacted>\"");
+            }
+            _ => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Write `files` as a gzipped tarball under [`ROOT`], with `home` shown as `~`.
+fn write_bundle(
+    writer: impl std::io::Write,
+    files: &[(&str, String)],
+    home: Option<&str>,
+    mtime: u64,
+) -> Result<()> {
+    let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    for (name, text) in files {
+        let text = home.map_or_else(|| text.clone(), |home| text.replace(home, "~"));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(text.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        tar.append_data(&mut header, format!("{ROOT}/{name}"), text.as_bytes())?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardModel;
+    use std::io::Read;
+
+    #[test]
+    fn secrets_are_withheld_and_the_rest_kept() {
+        let config = "# lights\n\
+                      default_color = \"ffb060\"\n\
+                      Api_Key = \"abc123\"\n\
+                      [extra_devices]\n\
+                      \"046d:c343\" = \"gpro\"\n\
+                      webhook_token=\"t0k3n\"\n";
+        assert_eq!(
+            scrub_config(config),
+            "# lights\n\
+             default_color = \"ffb060\"\n\
+             Api_Key = \"<redacted>\"\n\
+             [extra_devices]\n\
+             \"046d:c343\" = \"gpro\"\n\
+             webhook_token= \"<redacted>\"\n"
+        );
+    }
+
+    #[test]
+    fn devices_list_keyboards_then_interfaces() {
+        let keyboard = DeviceInfo {
+            path: "/dev/hidraw3".into(),
+            vendor_id: 0x046d,
+            product_id: 0xc331,
+            manufacturer: None,
+            product: Some("G810 Orion Spectrum".into()),
+            serial_number: Some("0B3D38673932".into()),
+            model: KeyboardModel::G810,
+        };
+        let iface = HidInterface {
+            vendor_id: 0x046d,
+            product_id: 0xc331,
+            product: Some("G810 Orion Spectrum".into()),
+            interface: Some(1),
+            usage_page: Some(0xff43),
+            usage: Some(0x0602),
+        };
+        let text = format_devices(Ok(vec![keyboard]), Ok(vec![iface]));
+        assert_eq!(
+            text,
+            "supported keyboards:\n  \
+             046d:c331 G810 \"G810 Orion Spectrum\" at /dev/hidraw3\n\n\
+             HID interfaces:\n  \
+             046d:c331 \"G810 Orion Spectrum\" interface 1: usage page 0xff43, usage 0x0602\n"
+        );
+        assert!(!text.contains("0B3D38673932"));
+        assert!(format_devices(Ok(vec![]), Err(anyhow!("denied"))).ends_with("  error: denied\n"));
+    }
+
+    #[test]
+    fn bundle_is_a_gzipped_tarball_without_the_home_path() {
+        let files = [
+            ("about.txt", "logi-led\n".to_owned()),
+            ("packets.log", "read /home/me/.config\n".to_owned()),
+        ];
+        let mut bytes = Vec::new();
+        write_bundle(&mut bytes, &files, Some("/home/me"), 0).unwrap();
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&bytes[..]));
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut text = String::new();
+            entry.read_to_string(&mut text).unwrap();
+            entries.push((entry.path().unwrap().display().to_string(), text));
+        }
+        assert_eq!(
+            entries,
+            [
+                (
+                    "logi-led-report/about.txt".to_owned(),
+                    "logi-led\n".to_owned()
+                ),
+                (
+                    "logi-led-report/packets.log".to_owned(),
+                    "read ~/.config\n".to_owned()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Result, anyhow};
+use clap::Args;
+
+use super::{Command, Context};
+use crate::help::COLOR_HELP;
+use crate::keyboard::{Color, Key, KeyGroup, KeyValue, parser::parse_regions};
+use crate::output;
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct ColorTarget {
+    #[arg(short, long)]
+    pub key: Option<Key>,
+    #[arg(short, long)]
+    pub group: Option<KeyGroup>,
+    #[arg(short = 'A', long)]
+    pub all: bool,
+}
+
+/// Set key colors
+///
+/// Without a color, `default_color` from the config is used.
+#[derive(Args, Debug)]
+pub struct SetColor {
+    #[command(flatten)]
+    pub target: ColorTarget,
+    #[arg(help = COLOR_HELP)]
+    pub color: Option<Color>,
+    #[arg(long)]
+    pub no_commit: bool,
+    /// Scale colors to this percent brightness before sending them
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub brightness: Option<u8>,
+}
+
+impl Command for SetColor {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let color = self.color.map_or_else(|| ctx.config.default_color(), Ok)?;
+        let target = &self.target;
+        ctx.with_keyboard_at(self.brightness, |kbd| {
+            if target.all {
+                kbd.set_all_keys(color)?;
+            } else if let Some(group) = target.group {
+                let model = kbd.model();
+                let supported = kbd.supported_keys();
+                if model.spec().regions.is_empty() && !group.keys().any(|k| supported.contains(&k))
+                {
+                    output::warn(format_args!(
+                        "the {model:?} cannot light any key in the {group} group"
+                    ));
+                }
+                kbd.set_group_keys(group, color)?;
+            } else if let Some(key) = target.key {
+                if !kbd.supported_keys().contains(&key) {
+                    output::warn(format_args!(
+                        "the {:?} cannot light {key:?} on its own",
+                        kbd.model()
+                    ));
+                }
+                kbd.set_keys(&[KeyValue { key, color }])?;
+            }
+            if !self.no_commit {
+                kbd.commit()?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Set every key to the configured `default_color`
+#[derive(Args, Debug)]
+pub struct SetDefault;
+
+impl Command for SetDefault {
+    fn name(&self) -> &'static str {
+        "default"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let color = ctx.config.default_color()?;
+        ctx.with_keyboard(|kbd| {
+            kbd.set_all_keys(color)?;
+            kbd.commit()
+        })
+    }
+}
+
+/// Set a region color
+#[derive(Args, Debug)]
+pub struct SetRegion {
+    /// Region index, name, or inclusive range (e.g. `2`, `numpad`, `1-5`)
+    pub region: String,
+    #[arg(help = COLOR_HELP)]
+    pub color: Color,
+}
+
+impl Command for SetRegion {
+    fn name(&self) -> &'static str {
+        "set-region"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| {
+            let regions = parse_regions(&self.region, &kbd.model().spec().region_names())
+                .ok_or_else(|| anyhow!("invalid region: {}", self.region))?;
+            for region in regions {
+                kbd.set_region(region, self.color)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn set_color_falls_back_to_config_default() {
+        let config = Config::from_toml("default_color = \"102030\"\n", "c").unwrap();
+        let cmd = SetColor {
+            target: ColorTarget {
+                key: None,
+                group: Some(KeyGroup::FKeys),
+                all: false,
+            },
+            color: None,
+            no_commit: false,
+            brightness: None,
+        };
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| cmd.run(ctx)).unwrap();
+        assert_eq!(calls, ["group f-keys 102030", "commit"]);
+    }
+
+    #[test]
+    fn brightness_scales_the_color() {
+        let cmd = SetColor {
+            target: ColorTarget {
+                key: None,
+                group: None,
+                all: true,
+            },
+            color: Some(Color::new(200, 100, 0)),
+            no_commit: false,
+            brightness: Some(50),
+        };
+        let calls = run_with(KeyboardModel::G810, &Config::default(), |ctx| cmd.run(ctx)).unwrap();
+        assert_eq!(calls, ["all 643200", "commit"]);
+    }
+
+    #[test]
+    fn region_names_follow_the_model() {
+        let cmd = SetRegion {
+            region: "navigation-numpad".into(),
+            color: Color::new(0xff, 0, 0),
+        };
+        let calls = run_with(KeyboardModel::G213, &Config::default(), |ctx| cmd.run(ctx)).unwrap();
+        assert_eq!(calls, ["region 4 ff0000", "region 5 ff0000"]);
+
+        let err = run_with(KeyboardModel::G810, &Config::default(), |ctx| cmd.run(ctx));
+        assert!(err.is_err());
+    }
+}
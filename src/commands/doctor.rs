@@ -0,0 +1,240 @@
+use anyhow::{Result, anyhow};
+use clap::Args;
+use std::{collections::BTreeSet, fmt, fs::OpenOptions, path::Path};
+
+use super::{Command, Context};
+use crate::keyboard::device::{BACKEND, Keyboard};
+use crate::sandbox::Sandbox;
+
+/// Other LED tools that grab the same device and fight over its state.
+const CONFLICTING_PROCESSES: &[&str] = &[
+    "openrgb", "g810-led", "g213-led", "g410-led", "g413-led", "g512-led", "g513-led", "g610-led",
+    "g815-led", "g910-led", "gpro-led",
+];
+
+const UDEV_RULE: &str = r#"SUBSYSTEM=="hidraw", ATTRS{idVendor}=="046d", TAG+="uaccess""#;
+const DEVD_RULE: &str = r#"notify 100 { match "system" "USB"; match "subsystem" "DEVICE"; match "type" "ATTACH"; match "vendor" "0x046d"; action "chgrp operator /dev/$cdev && chmod 660 /dev/$cdev"; };"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    name: &'static str,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = match self.status {
+            Status::Ok => " ok ",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        writeln!(f, "[{tag}] {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            for line in fix.lines() {
+                writeln!(f, "       {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            status: Status::Ok,
+            name,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warn,
+            name,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            name,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run environment checks and print pass/fail lines with remediation steps.
+pub fn doctor(ctx: &Context<'_>) -> Result<()> {
+    let checks = checks(ctx);
+    for check in &checks {
+        print!("{check}");
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(anyhow!("{failed} check(s) failed"));
+    }
+    Ok(())
+}
+
+/// The `doctor` report as text, for `report-bundle`.
+pub fn report(ctx: &Context<'_>) -> String {
+    checks(ctx).iter().map(ToString::to_string).collect()
+}
+
+fn checks(ctx: &Context<'_>) -> Vec<Check> {
+    let mut checks = vec![Check::ok("backend", BACKEND)];
+
+    if let Some(sandbox) = Sandbox::detect() {
+        checks.push(Check::warn(
+            "sandbox",
+            format!("running inside {sandbox}"),
+            sandbox.access_hint(),
+        ));
+    }
+
+    if cfg!(all(target_os = "linux", not(feature = "libusb"))) {
+        checks.push(check_hidraw_nodes());
+    }
+
+    match Keyboard::list_keyboards() {
+        Err(e) => checks.push(Check::fail(
+            "detection",
+            format!("could not enumerate devices: {e}"),
+            "make sure the USB/HID subsystem is available to this process",
+        )),
+        Ok(devices) if devices.is_empty() => checks.push(Check::fail(
+            "detection",
+            "no supported keyboard found",
+            "check the cable and run `lsusb | grep 046d`;\n\
+             unsupported PIDs can be tried with `--tuk <protocol> -p <pid>`",
+        )),
+        Ok(devices) => {
+            let models: BTreeSet<String> =
+                devices.iter().map(|d| format!("{:?}", d.model)).collect();
+            checks.push(Check::ok(
+                "detection",
+                models.into_iter().collect::<Vec<_>>().join(", "),
+            ));
+
+            let paths: BTreeSet<&str> = devices.iter().map(|d| d.path.as_str()).collect();
+            checks.extend(paths.into_iter().map(check_access));
+        }
+    }
+
+    checks.push(check_open(ctx));
+    checks.extend(check_conflicting_processes());
+    checks
+}
+
+fn check_hidraw_nodes() -> Check {
+    let found = std::fs::read_dir("/dev").is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|e| e.file_name().to_string_lossy().starts_with("hidraw"))
+    });
+    if found {
+        Check::ok("hidraw", "/dev/hidraw* nodes present")
+    } else {
+        Check::fail(
+            "hidraw",
+            "no /dev/hidraw* nodes",
+            "load the hidraw driver with `modprobe hidraw`, or build with `--features libusb`",
+        )
+    }
+}
+
+fn check_access(path: &str) -> Check {
+    if !Path::new(path).exists() {
+        return Check::warn(
+            "permissions",
+            format!("{path} is not a filesystem path"),
+            "nothing to check on this platform",
+        );
+    }
+
+    let fix = if cfg!(feature = "bsd") {
+        format!(
+            "add a devd rule such as /usr/local/etc/devd/logi-led.conf:\n  {DEVD_RULE}\n\
+             then run `service devd restart`, replug the keyboard and join the operator group"
+        )
+    } else {
+        format!(
+            "add a udev rule such as /etc/udev/rules.d/70-logi-led.rules:\n  {UDEV_RULE}\n\
+             then run `udevadm control --reload && udevadm trigger` and replug the keyboard"
+        )
+    };
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(_) => Check::ok("permissions", format!("{path} is writable")),
+        Err(e) => Check::fail("permissions", format!("{path}: {e}"), fix),
+    }
+}
+
+fn check_open(ctx: &Context<'_>) -> Check {
+    match ctx.open_keyboard() {
+        Err(e) => Check::fail(
+            "open",
+            format!("{e:#}"),
+            "see the permission checks above; another program may hold the device",
+        ),
+        #[cfg(feature = "libusb")]
+        Ok(kbd) if kbd.detached_kernel_driver() => Check::warn(
+            "kernel driver",
+            "usbhid was bound to the LED interface and had to be detached",
+            "this is expected with libusb; the driver is reattached on exit",
+        ),
+        Ok(kbd) => Check::ok(
+            "open",
+            kbd.current_device()
+                .map_or_else(String::new, |info| format!("{:?} opened", info.model)),
+        ),
+    }
+}
+
+fn check_conflicting_processes() -> Option<Check> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+    let running: BTreeSet<String> = entries
+        .flatten()
+        .filter_map(|e| std::fs::read_to_string(e.path().join("comm")).ok())
+        .map(|comm| comm.trim().to_owned())
+        .filter(|comm| CONFLICTING_PROCESSES.contains(&comm.as_str()))
+        .collect();
+
+    if running.is_empty() {
+        return Some(Check::ok("conflicts", "no other LED tools running"));
+    }
+    Some(Check::warn(
+        "conflicts",
+        format!(
+            "running: {}",
+            running.into_iter().collect::<Vec<_>>().join(", ")
+        ),
+        "stop the other tool so it does not overwrite the lighting",
+    ))
+}
+
+/// Check permissions, backends and device access, with remediation steps
+#[derive(Args, Debug)]
+pub struct Doctor;
+
+impl Command for Doctor {
+    fn name(&self) -> &'static str {
+        "doctor"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        doctor(ctx)
+    }
+}
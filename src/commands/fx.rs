@@ -0,0 +1,315 @@
+use anyhow::{Result, anyhow, bail};
+use clap::Args;
+use core::time::Duration;
+
+use super::{Command, Context, parse_period_arg, parse_preview_arg};
+use crate::animation::preview::{self, NativePreview};
+use crate::help::COLOR_HELP;
+use crate::keyboard::spec::Capability;
+use crate::keyboard::{
+    Color, EffectSummary, NativeEffect, NativeEffectPart, NativeEffectStorage, OnBoardMode,
+    api::KeyboardApi,
+};
+use crate::output;
+use crate::profile;
+
+/// Apply a lighting effect
+#[derive(Args, Debug)]
+pub struct Fx {
+    pub effect: NativeEffect,
+    pub part: NativeEffectPart,
+    /// Effect period: `500ms`, `2s` or a hex byte; `0` leaves it to the firmware
+    #[arg(long, value_parser = parse_period_arg)]
+    pub period: Option<Duration>,
+    #[arg(long, help = COLOR_HELP)]
+    pub color: Option<Color>,
+    /// Show the effect in the terminal first and ask before sending it;
+    /// `--preview=10s` or `--preview=200` (frames) sets how long
+    #[arg(
+        long,
+        value_name = "LENGTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5s",
+        value_parser = parse_preview_arg
+    )]
+    pub preview: Option<Duration>,
+    /// Scale colors to this percent brightness before sending them
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub brightness: Option<u8>,
+}
+
+impl Command for Fx {
+    fn name(&self) -> &'static str {
+        "fx"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let color = self.color.map_or_else(|| ctx.config.default_color(), Ok)?;
+        if !confirm_preview(self.preview, self.effect, self.part, self.period, color)? {
+            return Ok(());
+        }
+        ctx.with_keyboard_at(self.brightness, |kbd| {
+            apply_fx(
+                kbd,
+                self.effect,
+                self.part,
+                self.period,
+                color,
+                NativeEffectStorage::None,
+            )
+        })
+    }
+}
+
+/// Store a lighting effect in memory
+#[derive(Args, Debug)]
+pub struct FxStore {
+    pub effect: NativeEffect,
+    pub part: NativeEffectPart,
+    /// Effect period: `500ms`, `2s` or a hex byte; `0` leaves it to the firmware
+    #[arg(long, value_parser = parse_period_arg)]
+    pub period: Option<Duration>,
+    #[arg(long, help = COLOR_HELP)]
+    pub color: Option<Color>,
+    pub storage: NativeEffectStorage,
+    /// Show the effect in the terminal first and ask before sending it;
+    /// `--preview=10s` or `--preview=200` (frames) sets how long
+    #[arg(
+        long,
+        value_name = "LENGTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5s",
+        value_parser = parse_preview_arg
+    )]
+    pub preview: Option<Duration>,
+}
+
+impl Command for FxStore {
+    fn name(&self) -> &'static str {
+        "fx-store"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let color = self.color.map_or_else(|| ctx.config.default_color(), Ok)?;
+        if !confirm_preview(self.preview, self.effect, self.part, self.period, color)? {
+            return Ok(());
+        }
+        ctx.with_keyboard(|kbd| {
+            apply_fx(
+                kbd,
+                self.effect,
+                self.part,
+                self.period,
+                color,
+                self.storage,
+            )
+        })
+    }
+}
+
+/// Show an effect stored with `fx-store` again, without repeating its parameters
+///
+/// Boards with an on-board mode (the G815) are told to show what they
+/// store, whoever stored it. The others have no packet for that, so this
+/// replays what `logi-led` last stored in the slot while the lighting
+/// history was kept; on those the keyboard itself recalls the `user` slot
+/// with backlight+7.
+#[derive(Args, Debug)]
+pub struct FxRecall {
+    pub storage: NativeEffectStorage,
+}
+
+impl Command for FxRecall {
+    fn name(&self) -> &'static str {
+        "fx-recall"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        if self.storage == NativeEffectStorage::None {
+            bail!("`none` is not a storage slot");
+        }
+        ctx.with_keyboard(|kbd| {
+            let model = kbd.model();
+            if model.supports(Capability::OnBoardMode) {
+                kbd.set_on_board_mode(OnBoardMode::Board)?;
+                output::status("switched to on-board mode, so the board shows its stored effect");
+                output::note(
+                    "run `logi-led on-board-mode software` to control it from logi-led again",
+                );
+                return Ok(());
+            }
+            let history = ctx.history.ok_or_else(|| {
+                anyhow!(
+                    "the {model:?} cannot recall a stored effect itself, and the lighting history \
+                     logi-led replays instead is disabled (history_limit = 0)"
+                )
+            })?;
+            let profile = recall_profile(&history.stored_effects()?, self.storage)?;
+            profile::parse_profile(kbd, profile.as_bytes(), true)
+        })
+    }
+}
+
+/// The effects stored in `storage`, as a profile that shows them right now.
+fn recall_profile(stored: &[String], storage: NativeEffectStorage) -> Result<String> {
+    let slot = storage.to_string();
+    let mut profile = String::new();
+    for line in stored {
+        // Stored lines end in their slot; without it the effect is shown live.
+        if let Some(live) = line
+            .strip_suffix(slot.as_str())
+            .filter(|live| live.ends_with(' '))
+        {
+            profile.push_str(live.trim_end());
+            profile.push('\n');
+        }
+    }
+    if profile.is_empty() {
+        bail!("nothing stored in the {slot} slot by logi-led yet; store an effect with `fx-store`");
+    }
+    profile.push_str("c\n");
+    Ok(profile)
+}
+
+/// With `--preview`, imitate the effect in the terminal and ask whether to
+/// go ahead; without it, always go ahead.
+fn confirm_preview(
+    length: Option<Duration>,
+    effect: NativeEffect,
+    part: NativeEffectPart,
+    period: Option<Duration>,
+    color: Color,
+) -> Result<bool> {
+    let Some(length) = length else {
+        return Ok(true);
+    };
+    if part == NativeEffectPart::Logo {
+        output::note("the preview shows the keys only, not the logo");
+    }
+    let mut preview = NativePreview {
+        effect,
+        period: period.unwrap_or_default(),
+        color,
+    };
+    let confirmed = preview::show(&mut preview, length)?;
+    if !confirmed {
+        output::status("nothing sent");
+    }
+    Ok(confirmed)
+}
+
+/// Send an effect and echo what the firmware was actually told.
+fn apply_fx(
+    kbd: &mut dyn KeyboardApi,
+    effect: NativeEffect,
+    part: NativeEffectPart,
+    period: Option<Duration>,
+    color: Color,
+    storage: NativeEffectStorage,
+) -> Result<()> {
+    if period.is_none() && effect.uses_period() {
+        output::warn(
+            "no --period given; the firmware default speed is used (e.g. --period 2s sets one)",
+        );
+    }
+    let period = period.unwrap_or_default();
+
+    let model = kbd.model();
+    let Some(summary) = EffectSummary::sent(model, effect, part, period, color, storage) else {
+        bail!("the {model:?} cannot run {effect} on the {part}; nothing sent");
+    };
+    kbd.set_fx(effect, part, period, color, storage)?;
+    output::status(summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn recall_replays_the_slot_live() {
+        let stored = [
+            "fx color keys ff0000 0ms user".to_owned(),
+            "fx cycle logo 2000ms ffffff user".to_owned(),
+        ];
+        let profile = recall_profile(&stored, NativeEffectStorage::User).unwrap();
+        assert_eq!(
+            profile,
+            "fx color keys ff0000 0ms\nfx cycle logo 2000ms ffffff\nc\n"
+        );
+        let calls = run_with(KeyboardModel::G810, &Config::default(), |ctx| {
+            ctx.with_keyboard(|kbd| profile::parse_profile(kbd, profile.as_bytes(), true))
+        })
+        .unwrap();
+        assert_eq!(
+            calls,
+            [
+                "fx color keys 0ms ff0000 None",
+                "fx cycle logo 2000ms ffffff None",
+                "commit"
+            ]
+        );
+        assert!(recall_profile(&[], NativeEffectStorage::User).is_err());
+    }
+
+    #[test]
+    fn on_board_models_recall_from_the_firmware() {
+        let recall = |storage| FxRecall { storage };
+        // No history needed: the board shows whatever it stores.
+        let calls = run_with(KeyboardModel::G815, &Config::default(), |ctx| {
+            recall(NativeEffectStorage::User).run(ctx)
+        })
+        .unwrap();
+        assert_eq!(calls, ["on-board board"]);
+
+        let err = run_with(KeyboardModel::G810, &Config::default(), |ctx| {
+            recall(NativeEffectStorage::User).run(ctx)
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("history_limit = 0"));
+        assert!(
+            run_with(KeyboardModel::G815, &Config::default(), |ctx| {
+                recall(NativeEffectStorage::None).run(ctx)
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn fx_defaults_color_and_period() {
+        let cmd = Fx {
+            effect: NativeEffect::Breathing,
+            part: NativeEffectPart::All,
+            period: None,
+            color: None,
+            preview: None,
+            brightness: None,
+        };
+        let calls = run_with(KeyboardModel::G810, &Config::default(), |ctx| cmd.run(ctx)).unwrap();
+        assert_eq!(calls, ["fx breathing all 0ms ffffff None"]);
+    }
+
+    #[test]
+    fn fx_sends_nothing_the_board_has_no_packet_for() {
+        let cmd = Fx {
+            effect: NativeEffect::Breathing,
+            part: NativeEffectPart::Logo,
+            period: None,
+            color: None,
+            preview: None,
+            brightness: None,
+        };
+        let err =
+            run_with(KeyboardModel::G213, &Config::default(), |ctx| cmd.run(ctx)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the G213 cannot run breathing on the logo; nothing sent"
+        );
+    }
+}
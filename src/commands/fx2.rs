@@ -0,0 +1,128 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use core::time::Duration;
+
+use super::{Command, Context, parse_duration_arg};
+use crate::animation::{
+    self, BreathingDuo, DEFAULT_TICK, DigitClock, Direction, Effect, Pacing, Rainbow, RainbowWave,
+};
+use crate::config::Config;
+use crate::help::COLOR_HELP;
+use crate::keyboard::Color;
+
+/// Run a software effect the firmware cannot do by itself
+///
+/// Runs in the foreground until `--for` elapses or it is interrupted.
+#[derive(Args, Debug)]
+pub struct Fx2 {
+    #[command(subcommand)]
+    pub effect: SoftwareEffect,
+    /// Stop after this long, e.g. `30s` or `1h`
+    #[arg(long = "for", global = true, value_parser = parse_duration_arg)]
+    pub limit: Option<Duration>,
+    /// Frames a second, 1 to 100 (default 20); `max_fps` in the config still caps it
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub fps: Option<u32>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SoftwareEffect {
+    /// Breathe smoothly between two colors
+    BreathingDuo {
+        #[arg(help = COLOR_HELP)]
+        first: Color,
+        #[arg(help = COLOR_HELP)]
+        second: Color,
+        /// Time for a full cycle, e.g. `4s`
+        #[arg(long, default_value = "4s", value_parser = parse_duration_arg)]
+        period: Duration,
+    },
+
+    /// Cycle the whole board through the colors of the rainbow
+    Rainbow {
+        /// Time for a full cycle, e.g. `5s`
+        #[arg(long, default_value = "5s", value_parser = parse_duration_arg)]
+        period: Duration,
+    },
+
+    /// Sweep the colors of the rainbow across the keys
+    Wave {
+        /// Which way the colors travel: left, right, up or down
+        #[arg(long, default_value = "right")]
+        direction: Direction,
+        /// Time for a color to cross the board, e.g. `2s`
+        #[arg(long, default_value = "2s", value_parser = parse_duration_arg)]
+        period: Duration,
+    },
+
+    /// Spell the local time on the digit keys, one digit at a time
+    Clock {
+        /// Color of the lit digit; defaults to `default_color` from the config
+        #[arg(long)]
+        color: Option<Color>,
+        /// Color of every other key
+        #[arg(long, default_value = "black")]
+        background: Color,
+    },
+
+    /// Spell the time left on the digit keys, then flash when it runs out
+    Countdown {
+        /// Length of the countdown, e.g. `5m` or `90s`
+        #[arg(value_parser = parse_duration_arg)]
+        duration: Duration,
+        /// Color of the lit digit; defaults to `default_color` from the config
+        #[arg(long)]
+        color: Option<Color>,
+        /// Color of every other key
+        #[arg(long, default_value = "black")]
+        background: Color,
+    },
+}
+
+impl SoftwareEffect {
+    fn effect(&self, config: &Config) -> Result<Box<dyn Effect>> {
+        Ok(match *self {
+            SoftwareEffect::BreathingDuo {
+                first,
+                second,
+                period,
+            } => Box::new(BreathingDuo {
+                first,
+                second,
+                period,
+            }),
+            SoftwareEffect::Rainbow { period } => Box::new(Rainbow { period }),
+            SoftwareEffect::Wave { direction, period } => {
+                Box::new(RainbowWave { period, direction })
+            }
+            SoftwareEffect::Clock { color, background } => {
+                let color = color.map_or_else(|| config.default_color(), Ok)?;
+                Box::new(DigitClock::time(color, background))
+            }
+            SoftwareEffect::Countdown {
+                duration,
+                color,
+                background,
+            } => {
+                let color = color.map_or_else(|| config.default_color(), Ok)?;
+                Box::new(DigitClock::countdown(duration, color, background))
+            }
+        })
+    }
+}
+
+impl Command for Fx2 {
+    fn name(&self) -> &'static str {
+        "fx2"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let mut effect = self.effect.effect(ctx.config)?;
+        let pacing = ctx
+            .config
+            .pacing(self.fps.map_or(DEFAULT_TICK, Pacing::tick_for))?;
+        ctx.animate(|| {
+            ctx.with_keyboard(|kbd| animation::play(kbd, effect.as_mut(), pacing, self.limit))
+        })
+    }
+}
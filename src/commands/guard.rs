@@ -0,0 +1,194 @@
+use anyhow::{Result, anyhow, bail};
+use clap::Args;
+use core::time::Duration;
+
+use super::{Command, Context, Opener, parse_duration_arg};
+use crate::history::Snapshot;
+use crate::output;
+
+/// Shortest `--every`, so two programs fighting over the keyboard do not
+/// flood it with writes.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rounds in a row that may fail before the keyboard counts as wedged.
+const WEDGED_AFTER: u32 = 3;
+
+/// Time a reset board gets to come back before the lighting is sent again.
+const RESET_SETTLE: Duration = Duration::from_secs(2);
+
+/// Keep re-applying the latest lighting so other software cannot change it for long
+///
+/// For machines where G HUB or another program also drives the keyboard.
+/// The firmware cannot report what it shows, so a change cannot be seen;
+/// instead the lighting of the latest history entry is sent again every
+/// `--every`; its startup mode and stored effects are left alone. The
+/// keyboard is open only while that happens, and lighting applied with
+/// `logi-led` in the meantime is what gets re-applied next. Runs until
+/// interrupted.
+///
+/// A keyboard that fails three rounds in a row is taken to be wedged: it is
+/// reset on the USB bus, as replugging it would (libusb backend only), and
+/// the lighting is sent again straight away.
+#[derive(Args, Debug)]
+pub struct Guard {
+    /// Time between re-applies, e.g. `30s`; at least `1s`
+    #[arg(long, default_value = "30s", value_parser = parse_duration_arg)]
+    pub every: Duration,
+}
+
+impl Command for Guard {
+    fn name(&self) -> &'static str {
+        "guard"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let history = ctx.history.ok_or_else(|| {
+            anyhow!(
+                "guard re-applies lighting from the history, which is disabled (history_limit = 0)"
+            )
+        })?;
+        if self.every < MIN_INTERVAL {
+            bail!("--every must be at least {}s", MIN_INTERVAL.as_secs());
+        }
+        if history.latest().is_empty() {
+            output::note(
+                "no lighting recorded yet; guarding starts with the first profile or color",
+            );
+        }
+        let mut failures = 0;
+        loop {
+            // On-board memory is not worn down with the same writes, and
+            // --no-persist would fail every round on them.
+            round(ctx.opener, &history.latest().volatile(), &mut failures);
+            std::thread::sleep(self.every);
+        }
+    }
+}
+
+/// Re-apply `snapshot`, resetting the keyboard once `failures` rounds in
+/// a row have gone wrong.
+fn round(opener: &dyn Opener, snapshot: &Snapshot, failures: &mut u32) {
+    if reassert(opener, snapshot) {
+        *failures = 0;
+        return;
+    }
+    *failures += 1;
+    if *failures < WEDGED_AFTER {
+        return;
+    }
+    *failures = 0;
+    output::warn(format_args!(
+        "the keyboard failed {WEDGED_AFTER} rounds in a row; resetting it"
+    ));
+    if let Err(e) = opener.reset() {
+        output::warn(format_args!("could not reset the keyboard: {e:#}"));
+        return;
+    }
+    std::thread::sleep(RESET_SETTLE);
+    if reassert(opener, snapshot) {
+        output::status("the keyboard is back after a reset; lighting re-applied");
+    }
+}
+
+/// Send `snapshot` again, unrecorded. A keyboard that is busy or unplugged
+/// is only warned about, as the next round may find it back; returns
+/// whether it went through.
+fn reassert(opener: &dyn Opener, snapshot: &Snapshot) -> bool {
+    if snapshot.is_empty() {
+        return true;
+    }
+    match opener.with_keyboard(&mut |kbd| snapshot.apply(kbd)) {
+        Ok(()) => true,
+        Err(e) => {
+            output::warn(format_args!("could not re-apply the lighting: {e:#}"));
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::{MockKeyboard, MockOpener};
+    use crate::keyboard::KeyboardModel;
+    use crate::keyboard::api::KeyboardApi;
+    use crate::keyboard::persist::NoPersist;
+    use std::cell::{Cell, RefCell};
+
+    #[test]
+    fn rounds_replay_the_snapshot() {
+        let opener = MockOpener(RefCell::new(MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        }));
+        assert!(reassert(&opener, &Snapshot::default()));
+        assert!(reassert(
+            &opener,
+            &Snapshot::parse("a 0000ff\nk esc ff0000\n")
+        ));
+        assert_eq!(
+            opener.0.into_inner().calls,
+            ["all 0000ff", "key Esc ff0000", "commit"]
+        );
+    }
+
+    /// Hands out the mock wrapped in [`NoPersist`], as `--no-persist` does.
+    struct NoPersistMock(RefCell<MockKeyboard>);
+
+    impl Opener for NoPersistMock {
+        fn with_keyboard(
+            &self,
+            f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>,
+        ) -> Result<()> {
+            f(&mut NoPersist::new(&mut *self.0.borrow_mut()))
+        }
+    }
+
+    #[test]
+    fn rounds_leave_on_board_memory_alone() {
+        let opener = || {
+            NoPersistMock(RefCell::new(MockKeyboard {
+                model: KeyboardModel::G810,
+                calls: Vec::new(),
+            }))
+        };
+        let snapshot = Snapshot::parse("a 0000ff\nsm color\nfx cycle logo 2000ms ffffff user\n");
+        assert!(!reassert(&opener(), &snapshot));
+
+        let opener = opener();
+        let mut failures = 0;
+        round(&opener, &snapshot.volatile(), &mut failures);
+        assert_eq!(failures, 0);
+        assert_eq!(opener.0.into_inner().calls, ["all 0000ff", "commit"]);
+    }
+
+    /// Never opens, and counts the resets asked of it.
+    struct Wedged(Cell<u32>);
+
+    impl Opener for Wedged {
+        fn with_keyboard(
+            &self,
+            _f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>,
+        ) -> Result<()> {
+            bail!("timed out")
+        }
+
+        fn reset(&self) -> Result<()> {
+            self.0.set(self.0.get() + 1);
+            bail!("cannot reset")
+        }
+    }
+
+    #[test]
+    fn repeated_failures_reset_the_keyboard() {
+        let opener = Wedged(Cell::new(0));
+        let snapshot = Snapshot::parse("a 0000ff\n");
+        let mut failures = 0;
+        for _ in 0..WEDGED_AFTER - 1 {
+            round(&opener, &snapshot, &mut failures);
+        }
+        assert_eq!(opener.0.get(), 0);
+        round(&opener, &snapshot, &mut failures);
+        assert_eq!((opener.0.get(), failures), (1, 0));
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+use super::{Command, Context};
+use crate::help::{self, HelpFormat};
+use crate::keyboard::KeyboardModel;
+use crate::keyboard::model::SUPPORTED_KEYBOARDS;
+use crate::output;
+
+/// Display help for keys
+#[derive(Args, Debug)]
+pub struct HelpKeys {
+    /// Print Markdown for documentation instead of plain text
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Only list the keys this model has and can light (e.g. `g815`)
+    #[arg(long, value_name = "MODEL")]
+    pub model: Option<KeyboardModel>,
+}
+
+impl Command for HelpKeys {
+    fn name(&self) -> &'static str {
+        "help-keys"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> Result<()> {
+        help::write_keys_help(
+            &mut std::io::stdout().lock(),
+            HelpFormat::new(self.markdown),
+            self.model,
+        )?;
+        Ok(())
+    }
+}
+
+/// Display help for lighting effects
+///
+/// Lists the effects of the connected keyboard's model, or of every
+/// supported model if none can be opened.
+#[derive(Args, Debug)]
+pub struct HelpEffects {
+    /// Print Markdown for documentation instead of plain text
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Only list the effects of this model (e.g. `g213`)
+    #[arg(long, value_name = "MODEL")]
+    pub model: Option<KeyboardModel>,
+}
+
+impl Command for HelpEffects {
+    fn name(&self) -> &'static str {
+        "help-effects"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        help::write_effects_help(
+            &mut std::io::stdout().lock(),
+            HelpFormat::new(self.markdown),
+            &models(ctx, self.model),
+        )?;
+        Ok(())
+    }
+}
+
+/// Inspect the firmware effects each model supports
+#[derive(Args, Debug)]
+pub struct EffectsCommand {
+    #[command(subcommand)]
+    pub action: EffectsAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EffectsAction {
+    /// List the effects, parts, periods and storage a model's firmware takes
+    ///
+    /// Without `--model` this is the connected keyboard's model, or every
+    /// supported model if none can be opened.
+    List {
+        /// Print Markdown for documentation instead of plain text
+        #[arg(long)]
+        markdown: bool,
+
+        /// Only list the effects of this model (e.g. `g213`)
+        #[arg(long, value_name = "MODEL")]
+        model: Option<KeyboardModel>,
+    },
+}
+
+impl Command for EffectsCommand {
+    fn name(&self) -> &'static str {
+        "effects"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        match &self.action {
+            EffectsAction::List { markdown, model } => {
+                help::write_effects_matrix(
+                    &mut std::io::stdout().lock(),
+                    HelpFormat::new(*markdown),
+                    &models(ctx, *model),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Display help for color names
+#[derive(Args, Debug)]
+pub struct HelpColors {
+    /// Print Markdown for documentation instead of plain text
+    #[arg(long)]
+    pub markdown: bool,
+}
+
+impl Command for HelpColors {
+    fn name(&self) -> &'static str {
+        "help-colors"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> Result<()> {
+        help::write_colors_help(
+            &mut std::io::stdout().lock(),
+            HelpFormat::new(self.markdown),
+        )?;
+        Ok(())
+    }
+}
+
+/// List the lighting regions `set-region` takes, per model
+///
+/// Without `--model` this is the connected keyboard's model, or every
+/// supported model if none can be opened.
+#[derive(Args, Debug)]
+pub struct HelpRegions {
+    /// Print Markdown for documentation instead of plain text
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Only list the regions of this model (e.g. `g213`)
+    #[arg(long, value_name = "MODEL")]
+    pub model: Option<KeyboardModel>,
+}
+
+impl Command for HelpRegions {
+    fn name(&self) -> &'static str {
+        "help-regions"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        help::write_regions_help(
+            &mut std::io::stdout().lock(),
+            HelpFormat::new(self.markdown),
+            &models(ctx, self.model),
+        )?;
+        Ok(())
+    }
+}
+
+/// `model` if given, else the connected keyboard's model, else every
+/// supported model.
+fn models(ctx: &Context<'_>, model: Option<KeyboardModel>) -> Vec<KeyboardModel> {
+    let mut models = Vec::from_iter(model);
+    if models.is_empty() {
+        let detected = ctx.opener.with_keyboard(&mut |kbd| {
+            models.push(kbd.model());
+            Ok(())
+        });
+        if detected.is_err() {
+            output::note("no keyboard found; listing every supported model");
+            models = SUPPORTED_KEYBOARDS
+                .iter()
+                .map(|&(model, _)| model)
+                .collect();
+        }
+    }
+    models
+}
+
+/// Show usage samples
+#[derive(Args, Debug)]
+pub struct HelpSamples {
+    /// Print Markdown for documentation instead of plain text
+    #[arg(long)]
+    pub markdown: bool,
+}
+
+impl Command for HelpSamples {
+    fn name(&self) -> &'static str {
+        "help-samples"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> Result<()> {
+        help::write_samples_help(
+            &mut std::io::stdout().lock(),
+            HelpFormat::new(self.markdown),
+        )?;
+        Ok(())
+    }
+}
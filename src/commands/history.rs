@@ -0,0 +1,104 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local};
+use clap::Args;
+
+use super::{Command, Context};
+use crate::history::{History, Snapshot};
+use crate::keyboard::brightness::Dimmed;
+
+/// Lines of a snapshot shown before eliding the rest.
+const SUMMARY_LINES: usize = 3;
+
+/// List recently applied lighting, newest first
+#[derive(Args, Debug)]
+pub struct ShowHistory;
+
+impl Command for ShowHistory {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let entries = enabled(ctx)?.entries()?;
+        if entries.is_empty() {
+            println!("no lighting history yet");
+        }
+        for (back, entry) in entries.iter().rev().enumerate() {
+            let saved = DateTime::<Local>::from(entry.saved).format("%Y-%m-%d %H:%M:%S");
+            let marker = if back == 0 { "  (current)" } else { "" };
+            println!("{back:>3}  {saved}  {}{marker}", summary(&entry.snapshot));
+        }
+        Ok(())
+    }
+}
+
+/// Restore the lighting from before the last command
+#[derive(Args, Debug)]
+pub struct Undo;
+
+impl Command for Undo {
+    fn name(&self) -> &'static str {
+        "undo"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let history = enabled(ctx)?;
+        let mut entries = history.entries()?;
+        let (Some(current), Some(previous)) = (entries.pop(), entries.pop()) else {
+            return Err(anyhow!("nothing to undo"));
+        };
+        // Replay without recording: undoing moves back through the history
+        // rather than adding to it.
+        ctx.opener
+            .with_keyboard(&mut |kbd| previous.snapshot.apply(kbd))?;
+        history.remove(current.id)
+    }
+}
+
+/// Re-apply the last lighting at a brightness
+///
+/// The history keeps colors as they were given, so `brightness 100` restores
+/// them and dimming twice does not compound.
+#[derive(Args, Debug)]
+pub struct Brightness {
+    /// Percent of the applied colors' brightness to show
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub percent: u8,
+}
+
+impl Command for Brightness {
+    fn name(&self) -> &'static str {
+        "brightness"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let latest = enabled(ctx)?.latest();
+        if latest.is_empty() {
+            return Err(anyhow!(
+                "nothing to dim yet; apply a profile or color first"
+            ));
+        }
+        // Not recorded: the history keeps the undimmed colors.
+        ctx.opener
+            .with_keyboard(&mut |kbd| latest.apply(&mut Dimmed::new(kbd, self.percent)))
+    }
+}
+
+fn enabled<'a>(ctx: &Context<'a>) -> Result<&'a History> {
+    ctx.history
+        .ok_or_else(|| anyhow!("lighting history is disabled (history_limit = 0)"))
+}
+
+fn summary(snapshot: &Snapshot) -> String {
+    let lines = snapshot.lines();
+    let shown = lines
+        .iter()
+        .take(SUMMARY_LINES)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("; ");
+    match lines.len().saturating_sub(SUMMARY_LINES) {
+        0 => shown,
+        more => format!("{shown} (+{more} more)"),
+    }
+}
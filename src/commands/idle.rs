@@ -0,0 +1,177 @@
+use anyhow::{Result, anyhow, bail};
+use clap::Args;
+use core::time::Duration;
+
+use super::{Command, Context, animate, parse_duration_arg};
+use crate::animation::{self, BreathingDuo, DEFAULT_TICK, Effect};
+use crate::clock::{Clock, SystemClock};
+use crate::history::Snapshot;
+use crate::idle::Activity;
+use crate::keyboard::{Color, api::KeyboardApi};
+use crate::shutdown;
+
+/// Length of one breath of the default idle animation.
+const BREATHING_PERIOD: Duration = Duration::from_secs(8);
+
+/// How far the default idle animation dims `default_color`.
+const BREATHING_DEPTH: f64 = 0.8;
+
+/// Animate while idle and restore the lighting on the first input
+///
+/// Runs in the foreground until Ctrl-C or SIGTERM, which put the lighting
+/// back if the animation is running. Input is read from
+/// `/dev/input`, which needs membership of the `input` group; the restored
+/// lighting is the latest entry of `logi-led history`.
+#[derive(Args, Debug)]
+pub struct Idle {
+    /// Time without input before the animation starts, e.g. `5m`
+    #[arg(long, default_value = "5m", value_parser = parse_duration_arg)]
+    pub after: Duration,
+    /// Animation to run, as `demo:<name>` (default: slow breathing of `default_color`)
+    #[arg(long)]
+    pub effect: Option<String>,
+}
+
+impl Idle {
+    fn effect(&self, default_color: Color, seed: u64) -> Result<Box<dyn Effect>> {
+        match &self.effect {
+            Some(spec) => animate::lookup(spec, seed),
+            None => Ok(Box::new(BreathingDuo {
+                first: default_color,
+                second: animation::mix(default_color, Color::new(0, 0, 0), BREATHING_DEPTH),
+                period: BREATHING_PERIOD,
+            })),
+        }
+    }
+}
+
+impl Command for Idle {
+    fn name(&self) -> &'static str {
+        "idle"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let history = ctx.history.ok_or_else(|| {
+            anyhow!(
+                "idle restores lighting from the history, which is disabled (history_limit = 0)"
+            )
+        })?;
+        if history.entries()?.is_empty() {
+            bail!("no lighting to restore yet; apply a profile or color first");
+        }
+        // Fail on a bad `--effect` now rather than after the first idle period.
+        self.effect(ctx.config.default_color()?, 1)?;
+        let activity = Activity::watch()?;
+        shutdown::catch()?;
+        // Waiting for input paces these frames, so only the cap applies.
+        let tick = ctx.config.pacing(DEFAULT_TICK)?.tick;
+
+        // Ctrl-C while animating restores the lighting like input does.
+        while activity.wait_idle(self.after) {
+            // The latest entry, so lighting applied while idle is what comes back.
+            let Some(entry) = history.entries()?.pop() else {
+                continue;
+            };
+            let mut effect = self.effect(ctx.config.default_color()?, animate::time_seed())?;
+            // Neither the animation nor the restore is new lighting to record.
+            ctx.opener.with_keyboard(&mut |kbd| {
+                animate_until_input(
+                    kbd,
+                    effect.as_mut(),
+                    tick,
+                    &entry.snapshot,
+                    &SystemClock,
+                    |timeout| activity.wait(timeout) || shutdown::requested(),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Play `effect` until `input` reports activity, then restore `snapshot`.
+///
+/// `input` waits up to the given time and returns whether there was input;
+/// it also paces the frames.
+fn animate_until_input(
+    kbd: &mut dyn KeyboardApi,
+    effect: &mut dyn Effect,
+    tick: Duration,
+    snapshot: &Snapshot,
+    clock: &dyn Clock,
+    mut input: impl FnMut(Duration) -> bool,
+) -> Result<()> {
+    let start = clock.now();
+    let elapsed = || clock.now().saturating_sub(start);
+    loop {
+        let now = elapsed();
+        if effect.done(now) {
+            break;
+        }
+        effect.frame(kbd, now)?;
+        if input(tick.saturating_sub(elapsed().saturating_sub(now))) {
+            break;
+        }
+    }
+    snapshot.apply(kbd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::MockKeyboard;
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn first_input_restores_the_snapshot() {
+        let mut kbd = MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        };
+        let idle = Idle {
+            after: Duration::from_secs(60),
+            effect: None,
+        };
+        let mut effect = idle.effect(Color::new(0xff, 0xb0, 0x60), 1).unwrap();
+        let snapshot = Snapshot::parse("a 00ff00\ng logo ff0000\n");
+        let clock = ManualClock::default();
+        let mut polls = 0;
+        animate_until_input(
+            &mut kbd,
+            effect.as_mut(),
+            DEFAULT_TICK,
+            &snapshot,
+            &clock,
+            |timeout| {
+                clock.advance(timeout);
+                polls += 1;
+                polls == 3
+            },
+        )
+        .unwrap();
+
+        // Three frames, then the restore repaints everything once more.
+        let frames = kbd.calls.iter().filter(|c| c.starts_with("all ")).count();
+        assert_eq!(frames, 4);
+        assert_eq!(
+            &kbd.calls[kbd.calls.len() - 3..],
+            ["all 00ff00", "group logo ff0000", "commit"]
+        );
+    }
+
+    #[test]
+    fn effects_are_demos_or_breathing() {
+        let idle = |effect: Option<&str>| Idle {
+            after: Duration::from_secs(60),
+            effect: effect.map(ToOwned::to_owned),
+        };
+        assert!(idle(None).effect(Color::default(), 1).is_ok());
+        assert!(
+            idle(Some("demo:matrix"))
+                .effect(Color::default(), 1)
+                .is_ok()
+        );
+        assert!(idle(Some("demo:nope")).effect(Color::default(), 1).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! `generate-initramfs-hook`: light the keyboard from the initramfs, seconds
+//! after power-on rather than at login.
+
+use anyhow::{Result, anyhow, bail};
+use clap::{Args, ValueHint};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use strum_macros::{Display, EnumString};
+
+use super::{Command, Context, parse_u16_arg};
+use crate::keyboard::api::NullKeyboard;
+use crate::output;
+use crate::profile;
+
+/// Where the binary and profile end up inside the image.
+const IMAGE_BINARY: &str = "/usr/bin/logi-led";
+const IMAGE_DIR: &str = "/etc/logi-led";
+
+/// How long the hook waits for the keyboard's device node to appear.
+const OPEN_TIMEOUT: &str = "3s";
+
+/// Initramfs generator to write a hook for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum InitramfsTool {
+    Dracut,
+    Mkinitcpio,
+}
+
+/// Write an initramfs hook that applies a profile early in boot
+#[derive(Args, Debug)]
+pub struct GenerateInitramfsHook {
+    /// Initramfs generator: dracut or mkinitcpio
+    pub tool: InitramfsTool,
+
+    /// Profile to apply (g810-led profile, or structured `.toml`)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub profile: PathBuf,
+
+    /// Directory to write the hook files into
+    #[arg(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    pub output: PathBuf,
+
+    /// `logi-led` binary to copy into the image (default: this one)
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub binary: Option<PathBuf>,
+
+    /// Open this product ID directly instead of enumerating devices (hex or decimal)
+    #[arg(long, value_parser = parse_u16_arg)]
+    pub product_id: Option<u16>,
+}
+
+impl Command for GenerateInitramfsHook {
+    fn name(&self) -> &'static str {
+        "generate-initramfs-hook"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let profile = self
+            .profile
+            .canonicalize()
+            .map_err(|e| anyhow!("{}: {e}", self.profile.display()))?;
+        check_profile(&profile)?;
+        let binary = match &self.binary {
+            Some(path) => path.canonicalize(),
+            None => std::env::current_exe(),
+        }?;
+
+        let hook = Hook {
+            binary: binary.to_string_lossy().into_owned(),
+            profile: profile.to_string_lossy().into_owned(),
+            toml: is_toml(&profile),
+            product_id: self.product_id,
+            serial: ctx.serial.map(ToOwned::to_owned),
+        };
+        for file in hook.files(self.tool) {
+            let path = self.output.join(file.path);
+            write_script(&path, &file.contents)?;
+            output::status(format_args!("wrote {}", path.display()));
+        }
+        output::note(next_steps(self.tool, &self.output));
+        Ok(())
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Fail now rather than at boot: the profile must parse, only the file
+/// itself is copied, so it cannot `extends` another, and it must not animate.
+fn check_profile(path: &Path) -> Result<()> {
+    if is_toml(path) {
+        let text = std::fs::read_to_string(path)?;
+        if text.parse::<toml::Table>()?.contains_key("extends") {
+            bail!(
+                "{}: profiles using `extends` cannot be copied into the initramfs; \
+                 merge them into one file first",
+                path.display()
+            );
+        }
+        profile::load_toml_profile(&mut NullKeyboard, path)
+    } else {
+        let sequences = profile::load_profile_with_sequences(&mut NullKeyboard, path, true)?;
+        if !sequences.is_empty() {
+            bail!(
+                "{}: key color sequences run until interrupted and would stall the boot",
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn write_script(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn next_steps(tool: InitramfsTool, output: &Path) -> String {
+    let output = output.display();
+    match tool {
+        InitramfsTool::Dracut => format!(
+            "copy {output}/90logi-led to /usr/lib/dracut/modules.d/ and run `dracut --force`"
+        ),
+        InitramfsTool::Mkinitcpio => format!(
+            "copy {output}/install and {output}/hooks into /etc/initcpio/, add `logi-led` after \
+             `keyboard` in HOOKS in /etc/mkinitcpio.conf and run `mkinitcpio -P`"
+        ),
+    }
+}
+
+/// Quote `s` for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// A generated file, relative to the output directory.
+struct HookFile {
+    path: &'static str,
+    contents: String,
+}
+
+/// What the hook copies into the image and how it runs it.
+struct Hook {
+    binary: String,
+    profile: String,
+    toml: bool,
+    product_id: Option<u16>,
+    serial: Option<String>,
+}
+
+impl Hook {
+    /// The profile's path inside the image.
+    fn image_profile(&self) -> String {
+        let name = if self.toml {
+            "boot.toml"
+        } else {
+            "boot.profile"
+        };
+        format!("{IMAGE_DIR}/{name}")
+    }
+
+    /// The command the hook runs; a missing keyboard must not stop the boot.
+    fn command_line(&self) -> String {
+        let mut line = format!("{IMAGE_BINARY} -q");
+        if let Some(pid) = self.product_id {
+            let _ = write!(line, " -p 0x{pid:04x}");
+        }
+        if let Some(serial) = &self.serial {
+            let _ = write!(line, " --serial {}", shell_quote(serial));
+        }
+        let load = if self.toml {
+            "load-config"
+        } else {
+            "load-profile"
+        };
+        let _ = write!(
+            line,
+            " --open-timeout {OPEN_TIMEOUT} {load} {} || :",
+            self.image_profile()
+        );
+        line
+    }
+
+    fn files(&self, tool: InitramfsTool) -> Vec<HookFile> {
+        let header = format!("# Generated by `logi-led generate-initramfs-hook {tool}`.");
+        let binary = shell_quote(&self.binary);
+        let profile = shell_quote(&self.profile);
+        let image_profile = self.image_profile();
+        let command = self.command_line();
+        match tool {
+            InitramfsTool::Dracut => vec![
+                HookFile {
+                    path: "90logi-led/module-setup.sh",
+                    contents: format!(
+                        "#!/bin/bash\n{header}\n\n\
+                         check() {{\n    return 0\n}}\n\n\
+                         depends() {{\n    return 0\n}}\n\n\
+                         install() {{\n    \
+                         inst_binary {binary} {IMAGE_BINARY}\n    \
+                         inst_simple {profile} {image_profile}\n    \
+                         inst_hook pre-mount 90 \"$moddir/logi-led-apply.sh\"\n}}\n"
+                    ),
+                },
+                HookFile {
+                    path: "90logi-led/logi-led-apply.sh",
+                    contents: format!("#!/bin/sh\n{header}\n\n{command}\n"),
+                },
+            ],
+            InitramfsTool::Mkinitcpio => vec![
+                HookFile {
+                    path: "install/logi-led",
+                    contents: format!(
+                        "#!/bin/bash\n{header}\n\n\
+                         build() {{\n    \
+                         add_binary {binary} {IMAGE_BINARY}\n    \
+                         add_file {profile} {image_profile}\n    \
+                         add_runscript\n}}\n\n\
+                         help() {{\n    cat <<HELPEOF\n\
+                         Applies a logi-led lighting profile early in boot. Place it after the\n\
+                         udev and keyboard hooks.\n\
+                         HELPEOF\n}}\n"
+                    ),
+                },
+                HookFile {
+                    path: "hooks/logi-led",
+                    contents: format!(
+                        "#!/usr/bin/ash\n{header}\n\nrun_hook() {{\n    {command}\n}}\n"
+                    ),
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(toml: bool) -> Hook {
+        Hook {
+            binary: "/usr/local/bin/logi-led".into(),
+            profile: "/home/me/boot lights.profile".into(),
+            toml,
+            product_id: Some(0xc331),
+            serial: None,
+        }
+    }
+
+    #[test]
+    fn command_opens_directly_and_never_fails_the_boot() {
+        assert_eq!(
+            hook(false).command_line(),
+            "/usr/bin/logi-led -q -p 0xc331 --open-timeout 3s \
+             load-profile /etc/logi-led/boot.profile || :"
+        );
+        let mut toml = hook(true);
+        toml.product_id = None;
+        toml.serial = Some("it's".into());
+        assert_eq!(
+            toml.command_line(),
+            "/usr/bin/logi-led -q --serial 'it'\\''s' --open-timeout 3s \
+             load-config /etc/logi-led/boot.toml || :"
+        );
+    }
+
+    #[test]
+    fn dracut_module_installs_binary_profile_and_hook() {
+        let files = hook(false).files(InitramfsTool::Dracut);
+        let paths: Vec<&str> = files.iter().map(|f| f.path).collect();
+        assert_eq!(
+            paths,
+            ["90logi-led/module-setup.sh", "90logi-led/logi-led-apply.sh"]
+        );
+        let setup = &files[0].contents;
+        assert!(setup.contains("inst_binary '/usr/local/bin/logi-led' /usr/bin/logi-led\n"));
+        assert!(
+            setup.contains(
+                "inst_simple '/home/me/boot lights.profile' /etc/logi-led/boot.profile\n"
+            )
+        );
+        assert!(files[1].contents.ends_with(" || :\n"));
+    }
+
+    #[test]
+    fn mkinitcpio_hook_runs_after_udev() {
+        let files = hook(false).files(InitramfsTool::Mkinitcpio);
+        assert_eq!(files[0].path, "install/logi-led");
+        assert!(files[0].contents.contains("    add_runscript\n"));
+        assert_eq!(files[1].path, "hooks/logi-led");
+        assert!(
+            files[1]
+                .contents
+                .contains("run_hook() {\n    /usr/bin/logi-led -q")
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
+use clap::Args;
 use std::collections::HashSet;
 
+use super::{Command, Context};
 use crate::keyboard::device::Keyboard;
 
 /// List all supported Logitech keyboards, once each.
@@ -29,3 +31,17 @@ pub fn list_keyboards() -> Result<()> {
 
     Ok(())
 }
+
+/// List all connected Logitech HID devices
+#[derive(Args, Debug)]
+pub struct ListKeyboards;
+
+impl Command for ListKeyboards {
+    fn name(&self) -> &'static str {
+        "list-keyboards"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> Result<()> {
+        list_keyboards()
+    }
+}
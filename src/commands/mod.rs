@@ -1,5 +1,256 @@
+//! Subcommand handlers.
+//!
+//! Each subcommand is a clap `Args` struct implementing [`Command`]. Handlers
+//! reach the keyboard only through [`Context`], so tests can run them against
+//! a mock [`KeyboardApi`] instead of hardware.
+
+mod alert;
+mod animate;
+mod backup;
+mod boot;
+mod bundle;
+mod color;
+mod doctor;
+mod fx;
+mod fx2;
+mod guard;
+mod help;
+mod history;
+mod idle;
+mod initramfs;
 mod list;
+mod mode_indicator;
+mod modes;
 mod open;
+mod packet_log;
+mod profile;
+mod ramp;
+mod scene;
+mod schedule;
+mod serve;
+mod start;
+mod stats;
+mod theme;
+mod trail;
+mod visual_bell;
+mod workspaces;
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveTime;
+use core::time::Duration;
+
+use crate::config::{Config, OnInterrupt};
+use crate::history::History;
+use crate::keyboard::api::KeyboardApi;
+use crate::keyboard::brightness::Dimmed;
+use crate::keyboard::device::{Keyboard, explain_open_error};
+use crate::keyboard::model::{self, KeyboardModel};
+use crate::keyboard::parser::{parse_duration, parse_period, parse_u8, parse_u16};
+use crate::keyboard::spec::Capability;
+use crate::shutdown;
+
+pub use alert::Alert;
+pub use animate::Animate;
+pub use backup::{Backup, Restore};
+pub use boot::{BootTarget, SetDefaultBoot};
+pub use bundle::ReportBundle;
+pub use color::{ColorTarget, SetColor, SetDefault, SetRegion};
+pub use doctor::{Doctor, doctor};
+pub use fx::{Fx, FxRecall, FxStore};
+pub use fx2::{Fx2, SoftwareEffect};
+pub use guard::Guard;
+pub use help::{
+    EffectsAction, EffectsCommand, HelpColors, HelpEffects, HelpKeys, HelpRegions, HelpSamples,
+};
+pub use history::{Brightness, ShowHistory, Undo};
+pub use idle::Idle;
+pub use initramfs::{GenerateInitramfsHook, InitramfsTool};
+pub use list::{ListKeyboards, list_keyboards};
+pub use mode_indicator::{ModeIndicator, ModeSource};
+pub use modes::{Commit, SetGKeysMode, SetMn, SetMr, SetOnBoardMode, SetStartupMode};
+pub use open::{Info, PrintDevice, print_device};
+pub use packet_log::PacketLog;
+pub use profile::{DiffLayout, LoadConfig, LoadProfile, PipeProfile, SaveProfile};
+pub use ramp::Ramp;
+pub use scene::{SceneAction, SceneCommand};
+pub use schedule::{ScheduleAction, ScheduleCommand};
+pub use serve::Serve;
+pub use start::Start;
+pub use stats::{StatsAction, StatsCommand, StatsFormat};
+pub use theme::{ThemeAction, ThemeCommand};
+pub use trail::TypingTrail;
+pub use visual_bell::VisualBell;
+pub use workspaces::Workspaces;
+
+/// A subcommand handler.
+pub trait Command {
+    /// Name as typed on the command line.
+    fn name(&self) -> &'static str;
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()>;
+}
+
+/// What the keyboard needs for the command named `command` to do anything,
+/// or `None` if every model can run it.
+pub fn required_capability(command: &str) -> Option<Capability> {
+    Some(match command {
+        "set-region" => Capability::Regions,
+        "set-mr" => Capability::MrKey,
+        "set-mn" => Capability::MnKey,
+        "g-keys-mode" => Capability::GKeysMode,
+        "startup-mode" => Capability::StartupMode,
+        "on-board-mode" => Capability::OnBoardMode,
+        "fx" | "fx-store" | "fx-recall" => Capability::NativeEffects,
+        _ => return None,
+    })
+}
+
+/// Opens the keyboard a command talks to.
+pub trait Opener {
+    fn with_keyboard(&self, f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>) -> Result<()>;
+
+    /// Reset a keyboard that stopped answering, as replugging it would.
+    fn reset(&self) -> Result<()> {
+        Err(anyhow!("this connection cannot reset the keyboard"))
+    }
+}
+
+/// `--vendor-id`, `--product-id` and `--tuk`: which keyboard to open and the
+/// model to drive it as.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Target {
+    /// 0 matches any vendor.
+    pub vendor_id: u16,
+    /// 0 matches any product.
+    pub product_id: u16,
+    /// Protocol forced with `--tuk`, for boards the device table lacks.
+    pub model: Option<KeyboardModel>,
+}
+
+impl Target {
+    /// Make the `--tuk` model the only one detection knows, so its IDs open.
+    pub fn install_override(&self) {
+        if let Some(model) = self.model {
+            model::set_supported_override(vec![(self.vendor_id, self.product_id, model)]);
+        }
+    }
+}
+
+/// What a command may use besides its own arguments.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    /// `--serial`, for commands that enumerate or open devices themselves.
+    pub serial: Option<&'a str>,
+    /// The keyboard the global options pick, for the same commands.
+    pub target: Target,
+    /// `--strict`: fail on unknown profile commands.
+    pub strict: bool,
+    pub opener: &'a dyn Opener,
+    /// Where applied lighting is recorded; `None` when history is disabled.
+    pub history: Option<&'a History>,
+}
+
+impl Context<'_> {
+    /// Open the keyboard [`target`](Self::target) and `--serial` pick
+    /// directly, for commands that talk to the device rather than set its
+    /// lighting.
+    pub fn open_keyboard(&self) -> Result<Keyboard> {
+        let Target {
+            vendor_id,
+            product_id,
+            ..
+        } = self.target;
+        self.target.install_override();
+        Keyboard::open(vendor_id, product_id, self.serial).map_err(|e| {
+            model::clear_supported_override();
+            explain_open_error(e, vendor_id, product_id, self.serial)
+        })
+    }
+
+    /// Open the keyboard and run `f` against it, recording the result in
+    /// the history.
+    pub fn with_keyboard(
+        &self,
+        mut f: impl FnMut(&mut dyn KeyboardApi) -> Result<()>,
+    ) -> Result<()> {
+        match self.history {
+            Some(history) => self
+                .opener
+                .with_keyboard(&mut |kbd| history.record(kbd, |rec| f(rec))),
+            None => self.opener.with_keyboard(&mut f),
+        }
+    }
+
+    /// [`with_keyboard`](Self::with_keyboard) with colors scaled to
+    /// `brightness` percent on their way to the keyboard. The history keeps
+    /// them as given, so `brightness` can dim them again later.
+    pub fn with_keyboard_at(
+        &self,
+        brightness: Option<u8>,
+        mut f: impl FnMut(&mut dyn KeyboardApi) -> Result<()>,
+    ) -> Result<()> {
+        let Some(percent) = brightness else {
+            return self.with_keyboard(f);
+        };
+        self.opener.with_keyboard(&mut |kbd| {
+            let mut dimmed = Dimmed::new(kbd, percent);
+            match self.history {
+                Some(history) => history.record(&mut dimmed, |rec| f(rec)),
+                None => f(&mut dimmed),
+            }
+        })
+    }
+
+    /// Run the animation `play`; if Ctrl-C or SIGTERM stopped it, leave the
+    /// keyboard as `on_interrupt` says. Restoring needs the history, so with
+    /// it disabled the last frame stays.
+    pub fn animate(&self, play: impl FnOnce() -> Result<()>) -> Result<()> {
+        shutdown::catch()?;
+        let before = self.history.map(History::latest);
+        play()?;
+        if !shutdown::requested() {
+            return Ok(());
+        }
+        match self.config.on_interrupt() {
+            OnInterrupt::Keep => Ok(()),
+            OnInterrupt::Restore => match before.filter(|snapshot| !snapshot.is_empty()) {
+                Some(snapshot) => self.with_keyboard(|kbd| snapshot.apply(kbd)),
+                None => Ok(()),
+            },
+            OnInterrupt::Apply(target) => schedule::Target::parse(&target)?.apply(self),
+        }
+    }
+}
+
+pub fn parse_period_arg(s: &str) -> Result<Duration, String> {
+    parse_period(s).ok_or_else(|| format!("invalid period: {s}"))
+}
+
+pub fn parse_duration_arg(s: &str) -> Result<Duration, String> {
+    parse_duration(s).ok_or_else(|| format!("invalid duration (e.g. 90s, 10m, 1h): {s}"))
+}
+
+/// A `--preview` length: a duration such as `5s`, or a number of frames.
+pub fn parse_preview_arg(s: &str) -> Result<Duration, String> {
+    match s.parse::<u32>() {
+        Ok(frames) => Ok(crate::animation::DEFAULT_TICK * frames),
+        Err(_) => parse_duration(s)
+            .ok_or_else(|| format!("invalid preview length (e.g. 5s or 100 frames): {s}")),
+    }
+}
+
+pub fn parse_time_arg(s: &str) -> Result<NaiveTime, String> {
+    crate::schedule::parse_time_of_day(s)
+        .ok_or_else(|| format!("invalid time (expected HH:MM): {s}"))
+}
+
+pub fn parse_u8_arg(s: &str) -> Result<u8, String> {
+    parse_u8(s).ok_or_else(|| format!("Invalid u8 value: {s}"))
+}
 
-pub use list::list_keyboards;
-pub use open::print_device;
+pub fn parse_u16_arg(s: &str) -> Result<u16, String> {
+    parse_u16(s).ok_or_else(|| format!("Invalid u16 value: {s}"))
+}
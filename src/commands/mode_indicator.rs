@@ -0,0 +1,232 @@
+use anyhow::Result;
+use clap::Args;
+use core::time::Duration;
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use strum_macros::EnumString;
+
+use super::{Command, Context};
+use crate::clock::{Clock, SystemClock};
+use crate::config::ModeIndicator as Indicator;
+use crate::keyboard::{KeyValue, api::KeyboardApi};
+use crate::output;
+
+/// Quiet time after a mode name before it is shown, so a burst of changes
+/// (e.g. a macro in vim) costs one write instead of one per change.
+const DEBOUNCE: Duration = Duration::from_millis(30);
+
+/// Where mode names come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum ModeSource {
+    /// One mode name per line on standard input.
+    Stdin,
+}
+
+/// Show the current vim, tmux or shell mode on keys, in colors from the config
+///
+/// Reads mode names such as `insert` or `copy-mode` and paints the keys in
+/// `[mode_indicator]` with the color configured for that mode, keeping the
+/// keyboard open in between so switches show at once. Unknown modes are
+/// skipped with a warning.
+#[derive(Args, Debug)]
+pub struct ModeIndicator {
+    #[arg(long, default_value = "stdin")]
+    pub source: ModeSource,
+}
+
+impl Command for ModeIndicator {
+    fn name(&self) -> &'static str {
+        "mode-indicator"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let indicator = ctx.config.mode_indicator()?;
+        let modes = match self.source {
+            ModeSource::Stdin => spawn_stdin_reader(),
+        };
+        // Not recorded: the mode is transient, like an alert.
+        ctx.opener.with_keyboard(&mut |kbd| {
+            debounce(&modes, &SystemClock, DEBOUNCE, |mode| {
+                show(kbd, &indicator, mode)
+            })
+        })
+    }
+}
+
+/// Read lines on a thread, so the main loop can wait with a timeout.
+fn spawn_stdin_reader() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Holds back mode names until `delay` passes without another, keeping
+/// only the last of each burst.
+struct Debouncer {
+    delay: Duration,
+    /// The latest mode and when it is due.
+    pending: Option<(String, Duration)>,
+    shown: Option<String>,
+}
+
+impl Debouncer {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: None,
+            shown: None,
+        }
+    }
+
+    /// Take `mode`, received at `now`, in place of any pending one.
+    fn push(&mut self, mode: String, now: Duration) {
+        self.pending = Some((mode, now + self.delay));
+    }
+
+    /// How long after `now` the pending mode is due; `None` with nothing pending.
+    fn wait(&self, now: Duration) -> Option<Duration> {
+        self.pending
+            .as_ref()
+            .map(|(_, due)| due.saturating_sub(now))
+    }
+
+    /// The pending mode if it is due at `now`, unless it is blank or
+    /// already shown.
+    fn due(&mut self, now: Duration) -> Option<String> {
+        if self.pending.as_ref().is_none_or(|(_, due)| now < *due) {
+            return None;
+        }
+        let (latest, _) = self.pending.take()?;
+        let mode = latest.trim().to_lowercase();
+        if mode.is_empty() || self.shown.as_deref() == Some(mode.as_str()) {
+            return None;
+        }
+        self.shown = Some(mode.clone());
+        Some(mode)
+    }
+
+    /// The pending mode right away, as [`due`](Self::due) would have it.
+    fn flush(&mut self) -> Option<String> {
+        self.due(Duration::MAX)
+    }
+}
+
+/// Call `apply` with the last of each burst of modes once `delay` passes
+/// without another, skipping repeats of the mode already shown.
+fn debounce(
+    modes: &Receiver<String>,
+    clock: &dyn Clock,
+    delay: Duration,
+    mut apply: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut debouncer = Debouncer::new(delay);
+    loop {
+        let next = match debouncer.wait(clock.now()) {
+            Some(wait) => modes.recv_timeout(wait),
+            None => modes.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let mode = match next {
+            Ok(mode) => {
+                debouncer.push(mode, clock.now());
+                debouncer.due(clock.now())
+            }
+            Err(RecvTimeoutError::Timeout) => debouncer.due(clock.now()),
+            Err(RecvTimeoutError::Disconnected) => {
+                if let Some(mode) = debouncer.flush() {
+                    apply(&mode)?;
+                }
+                return Ok(());
+            }
+        };
+        if let Some(mode) = mode {
+            apply(&mode)?;
+        }
+    }
+}
+
+/// Paint the indicator keys for `mode`.
+fn show(kbd: &mut dyn KeyboardApi, indicator: &Indicator, mode: &str) -> Result<()> {
+    let Some(&color) = indicator.colors.get(mode) else {
+        output::warn(format_args!(
+            "no color for mode {mode:?} in [mode_indicator.colors]"
+        ));
+        return Ok(());
+    };
+    let keys: Vec<KeyValue> = indicator
+        .keys
+        .iter()
+        .map(|&key| KeyValue { key, color })
+        .collect();
+    kbd.set_keys(&keys)?;
+    kbd.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::commands::testing::MockKeyboard;
+    use crate::keyboard::{Color, Key, KeyboardModel};
+
+    #[test]
+    fn bursts_show_only_their_last_mode() {
+        let clock = ManualClock::default();
+        let mut debouncer = Debouncer::new(DEBOUNCE);
+        for mode in ["normal", "Insert", "normal", "insert"] {
+            debouncer.push(mode.to_owned(), clock.now());
+            clock.advance(Duration::from_millis(10));
+        }
+        assert_eq!(debouncer.wait(clock.now()), Some(Duration::from_millis(20)));
+        assert_eq!(debouncer.due(clock.now()), None);
+        clock.sleep(Duration::from_millis(20));
+        assert_eq!(debouncer.due(clock.now()).as_deref(), Some("insert"));
+        assert_eq!(debouncer.wait(clock.now()), None);
+
+        // The mode already shown is not written again.
+        clock.advance(Duration::from_millis(100));
+        debouncer.push("insert".to_owned(), clock.now());
+        clock.sleep(DEBOUNCE);
+        assert_eq!(debouncer.due(clock.now()), None);
+
+        debouncer.push("visual".to_owned(), clock.now());
+        assert_eq!(debouncer.flush().as_deref(), Some("visual"));
+    }
+
+    #[test]
+    fn a_closed_source_shows_its_last_mode() {
+        let (tx, rx) = mpsc::channel();
+        for mode in ["normal", "visual", " Insert "] {
+            tx.send(mode.to_owned()).unwrap();
+        }
+        drop(tx);
+        let mut shown = Vec::new();
+        debounce(&rx, &ManualClock::default(), DEBOUNCE, |mode| {
+            shown.push(mode.to_owned());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(shown, ["insert"]);
+    }
+
+    #[test]
+    fn modes_paint_the_indicator_keys() {
+        let indicator = Indicator {
+            keys: vec![Key::Esc, Key::Logo],
+            colors: [("insert".to_owned(), Color::new(0, 0xff, 0))].into(),
+        };
+        let mut kbd = MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        };
+        show(&mut kbd, &indicator, "insert").unwrap();
+        show(&mut kbd, &indicator, "replace").unwrap();
+        assert_eq!(kbd.calls, ["key Esc 00ff00", "key Logo 00ff00", "commit"]);
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use clap::Args;
+
+use super::{Command, Context};
+use crate::keyboard::{OnBoardMode, StartupMode};
+
+/// Commit any buffered changes
+#[derive(Args, Debug)]
+pub struct Commit;
+
+impl Command for Commit {
+    fn name(&self) -> &'static str {
+        "commit"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.commit())
+    }
+}
+
+/// Set the MR key value
+#[derive(Args, Debug)]
+pub struct SetMr {
+    pub value: u8,
+}
+
+impl Command for SetMr {
+    fn name(&self) -> &'static str {
+        "set-mr"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.set_mr_key(self.value))
+    }
+}
+
+/// Set the Mn key value
+#[derive(Args, Debug)]
+pub struct SetMn {
+    pub value: u8,
+}
+
+impl Command for SetMn {
+    fn name(&self) -> &'static str {
+        "set-mn"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.set_mn_key(self.value))
+    }
+}
+
+/// Set the G-keys mode
+#[derive(Args, Debug)]
+pub struct SetGKeysMode {
+    pub value: u8,
+}
+
+impl Command for SetGKeysMode {
+    fn name(&self) -> &'static str {
+        "g-keys-mode"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.set_gkeys_mode(self.value))
+    }
+}
+
+/// Configure startup mode
+#[derive(Args, Debug)]
+pub struct SetStartupMode {
+    pub mode: StartupMode,
+}
+
+impl Command for SetStartupMode {
+    fn name(&self) -> &'static str {
+        "startup-mode"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.set_startup_mode(self.mode))
+    }
+}
+
+/// Configure on-board mode
+#[derive(Args, Debug)]
+pub struct SetOnBoardMode {
+    pub mode: OnBoardMode,
+}
+
+impl Command for SetOnBoardMode {
+    fn name(&self) -> &'static str {
+        "on-board-mode"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard(|kbd| kbd.set_on_board_mode(self.mode))
+    }
+}
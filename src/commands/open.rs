@@ -1,6 +1,9 @@
 use anyhow::Result;
+use clap::Args;
 
+use super::{Command, Context};
 use crate::keyboard::device::Keyboard;
+use crate::keyboard::hidpp;
 
 /// Try to open a device by serial (or pick the first one) and print its details
 pub fn print_device(serial: Option<&str>) -> Result<()> {
@@ -23,3 +26,58 @@ pub fn print_device(serial: Option<&str>) -> Result<()> {
 
     Ok(())
 }
+
+/// Open a specific keyboard and print its info
+#[derive(Args, Debug)]
+pub struct PrintDevice;
+
+impl Command for PrintDevice {
+    fn name(&self) -> &'static str {
+        "print-device"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        print_device(ctx.serial)
+    }
+}
+
+/// Ask the keyboard for its firmware version and HID++ features
+///
+/// Unlike `print-device`, which shows what the USB descriptors say, this
+/// talks HID++ 2.0 to the board itself: the protocol version, every feature
+/// page it implements and the firmware entities it reports. Worth including
+/// when asking for a new model to be supported.
+#[derive(Args, Debug)]
+pub struct Info;
+
+impl Command for Info {
+    fn name(&self) -> &'static str {
+        "info"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let mut kbd = ctx.open_keyboard()?;
+        if let Some(info) = kbd.current_device() {
+            println!(
+                "{:?} ({:04x}:{:04x})",
+                info.model, info.vendor_id, info.product_id
+            );
+        }
+        let found = hidpp::discover(|request| kbd.request(request))?;
+        println!("HID++ {}.{}", found.protocol.0, found.protocol.1);
+        for firmware in &found.firmware {
+            println!("  firmware: {firmware}");
+        }
+        println!("Features:");
+        for feature in &found.features {
+            println!(
+                "  {:#04x}  {:#06x} v{}  {}",
+                feature.index,
+                feature.id,
+                feature.version,
+                feature.name().unwrap_or("")
+            );
+        }
+        Ok(())
+    }
+}
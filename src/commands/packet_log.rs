@@ -0,0 +1,39 @@
+use anyhow::{Result, anyhow};
+use clap::Args;
+
+use super::{Command, Context};
+use crate::output;
+use crate::packet_log::{self, RUNS};
+
+/// Show the packets the last commands sent to the keyboard
+///
+/// Each run is headed by its command, how it ended and how long it took,
+/// followed by every packet with its offset in the run. The log keeps the
+/// last ten runs that talked to a keyboard, so a problem can be looked into
+/// after the fact. `report-bundle` includes the same log.
+#[derive(Args, Debug)]
+pub struct PacketLog {
+    /// Only the last N runs
+    #[arg(long, value_name = "N", default_value_t = RUNS)]
+    pub runs: usize,
+}
+
+impl Command for PacketLog {
+    fn name(&self) -> &'static str {
+        "packet-log"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> Result<()> {
+        let path = packet_log::path().ok_or_else(|| anyhow!("no log directory on this system"))?;
+        let log = match std::fs::read_to_string(&path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                output::note("no packets logged yet");
+                return Ok(());
+            }
+            Err(e) => return Err(anyhow!("{}: {e}", path.display())),
+        };
+        print!("{}", packet_log::last_runs(&log, self.runs));
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use anyhow::{Result, anyhow, bail};
+use clap::{Args, ValueHint};
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use strum_macros::EnumString;
+
+use super::{Command, Context};
+use crate::animation::{self, DEFAULT_TICK, KeySequences, preview};
+use crate::history::{Change, History, Recorder, Snapshot};
+use crate::keyboard::KeyboardModel;
+use crate::keyboard::api::{KeyboardApi, NullKeyboard};
+use crate::keyboard::brightness::Dimmed;
+use crate::keyboard::parser::parse_color;
+use crate::output;
+use crate::profile;
+use crate::state::KeyboardState;
+
+/// How `--preview-diff` shows what a profile changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum DiffLayout {
+    /// One line per key, group or mode.
+    List,
+    /// The list, then the changed keys on a drawing of the board.
+    Grid,
+}
+
+/// Load profile from a file
+///
+/// Keys with a color sequence (`k esc [red,orange,yellow] 500ms`) or a
+/// blink (`k caps blink red`) keep cycling in the foreground until
+/// interrupted.
+#[derive(Args, Debug)]
+pub struct LoadProfile {
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub path: PathBuf,
+    /// List what the profile changes from the current lighting and ask
+    /// before applying it; `--preview-diff=grid` also draws the keys
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "list"
+    )]
+    pub preview_diff: Option<DiffLayout>,
+    /// Scale colors to this percent brightness before sending them
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub brightness: Option<u8>,
+}
+
+impl Command for LoadProfile {
+    fn name(&self) -> &'static str {
+        "load-profile"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let load = |kbd: &mut dyn KeyboardApi| {
+            profile::load_profile_with_sequences(kbd, &self.path, ctx.strict).map(drop)
+        };
+        if !confirm_diff(ctx, self.preview_diff, load)? {
+            return Ok(());
+        }
+        let mut sequences = Vec::new();
+        ctx.with_keyboard_at(self.brightness, |kbd| {
+            sequences = profile::load_profile_with_sequences(kbd, &self.path, ctx.strict)?;
+            Ok(())
+        })?;
+        if sequences.is_empty() {
+            return Ok(());
+        }
+        output::note("cycling key color sequences; press Ctrl-C to stop");
+        let pacing = ctx.config.pacing(DEFAULT_TICK)?;
+        let mut effect = KeySequences::new(sequences);
+        // The history keeps the first colors; the animation is not recorded.
+        let percent = self.brightness.unwrap_or(100);
+        ctx.animate(|| {
+            ctx.opener.with_keyboard(&mut |kbd| {
+                animation::play(&mut Dimmed::new(kbd, percent), &mut effect, pacing, None)
+            })
+        })
+    }
+}
+
+/// Load a TOML configuration file
+#[derive(Args, Debug)]
+pub struct LoadConfig {
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub path: PathBuf,
+    /// List what the profile changes from the current lighting and ask
+    /// before applying it; `--preview-diff=grid` also draws the keys
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "list"
+    )]
+    pub preview_diff: Option<DiffLayout>,
+    /// Only check the key names against the connected model: typos, keys
+    /// it does not have and, with a `[layout]`, keys left out
+    #[arg(long, conflicts_with = "preview_diff")]
+    pub validate: bool,
+    /// Scale colors to this percent brightness before sending them
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub brightness: Option<u8>,
+}
+
+impl Command for LoadConfig {
+    fn name(&self) -> &'static str {
+        "load-config"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        if self.validate {
+            return validate(ctx, &self.path);
+        }
+        let load = |kbd: &mut dyn KeyboardApi| profile::load_toml_profile(kbd, &self.path);
+        if !confirm_diff(ctx, self.preview_diff, load)? {
+            return Ok(());
+        }
+        ctx.with_keyboard_at(self.brightness, load)
+    }
+}
+
+/// Print what is wrong with the profile's keys on the connected model,
+/// failing if anything is.
+fn validate(ctx: &Context<'_>, path: &Path) -> Result<()> {
+    let mut model = KeyboardModel::Unknown;
+    ctx.opener.with_keyboard(&mut |kbd| {
+        model = kbd.model();
+        Ok(())
+    })?;
+    let report = profile::validate_toml_profile(path, model)?;
+    for unknown in &report.unknown {
+        println!("unknown: {unknown}");
+    }
+    for key in &report.unsupported {
+        println!("not on the {model:?}: {key:?}");
+    }
+    for key in &report.missing {
+        println!("missing from the layout: {key:?}");
+    }
+    if !report.is_clean() {
+        bail!("{}: the keys do not match the {model:?}", path.display());
+    }
+    output::status(format_args!(
+        "{}: every key matches the {model:?}",
+        path.display()
+    ));
+    Ok(())
+}
+
+/// With `--preview-diff`, show what `load` changes from the last recorded
+/// lighting and ask whether to go ahead; without it, always go ahead.
+fn confirm_diff(
+    ctx: &Context<'_>,
+    layout: Option<DiffLayout>,
+    load: impl FnOnce(&mut dyn KeyboardApi) -> Result<()>,
+) -> Result<bool> {
+    let Some(layout) = layout else {
+        return Ok(true);
+    };
+    let before = ctx.history.map_or_else(
+        || {
+            output::note("the lighting history is off; showing changes from a dark keyboard");
+            Snapshot::default()
+        },
+        History::latest,
+    );
+    let mut dry_run = NullKeyboard;
+    let mut recorder = Recorder::new(&mut dry_run, before.clone());
+    load(&mut recorder)?;
+    let after = recorder.into_snapshot().unwrap_or_else(|| before.clone());
+
+    let changes = before.changes(&after);
+    if changes.is_empty() {
+        output::status("no changes from the current lighting");
+    }
+    print!("{}", diff_text(&changes, std::io::stdout().is_terminal()));
+    if layout == DiffLayout::Grid {
+        print!("{}", preview::changed_keys(&before, &after)?);
+    }
+    let confirmed = preview::confirm()?;
+    if !confirmed {
+        output::status("nothing sent");
+    }
+    Ok(confirmed)
+}
+
+/// One line per change, `target  before -> after`; with `swatches`, colors
+/// get a block of themselves in front.
+fn diff_text(changes: &[Change], swatches: bool) -> String {
+    let width = changes.iter().map(|c| c.target.len()).max().unwrap_or(0);
+    let value = |value: &Option<String>| {
+        let Some(value) = value else {
+            return "unset".to_owned();
+        };
+        let mut out = String::new();
+        for (i, word) in value.split_whitespace().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            if swatches
+                && word.len() == 6
+                && let Some(c) = parse_color(word)
+            {
+                let _ = write!(out, "\x1b[48;2;{};{};{}m  \x1b[0m ", c.red, c.green, c.blue);
+            }
+            out.push_str(word);
+        }
+        out
+    };
+    let mut out = String::new();
+    for change in changes {
+        let _ = writeln!(
+            out,
+            "  {:<width$}  {} -> {}",
+            change.target,
+            value(&change.before),
+            value(&change.after)
+        );
+    }
+    out
+}
+
+/// Load profile from stdin
+#[derive(Args, Debug)]
+pub struct PipeProfile {
+    /// Scale colors to this percent brightness before sending them
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub brightness: Option<u8>,
+}
+
+impl Command for PipeProfile {
+    fn name(&self) -> &'static str {
+        "pipe-profile"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        ctx.with_keyboard_at(self.brightness, |kbd| {
+            let stdin = std::io::stdin();
+            profile::load_profile_stdin(kbd, stdin.lock(), ctx.strict)
+        })
+    }
+}
+
+/// Save the lighting and modes logi-led last applied as a TOML profile
+///
+/// Like `backup`, this is what logi-led sent while the lighting history was
+/// kept, including stored effects; `load-config` applies the file again.
+#[derive(Args, Debug)]
+pub struct SaveProfile {
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: PathBuf,
+}
+
+impl Command for SaveProfile {
+    fn name(&self) -> &'static str {
+        "save-profile"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let history = ctx.history.ok_or_else(|| {
+            anyhow!("the saved state comes from the lighting history, which is disabled (history_limit = 0)")
+        })?;
+        let mut state = KeyboardState::new();
+        let stored = history.stored_effects()?.join("\n");
+        profile::parse_profile(&mut state, stored.as_bytes(), false)?;
+        history.latest().apply(&mut state)?;
+        if state.is_empty() {
+            bail!("nothing to save yet; apply a profile, color or mode first");
+        }
+        std::fs::write(&self.file, profile::toml_profile(&state)?)
+            .map_err(|e| anyhow!("{}: {e}", self.file.display()))?;
+        output::status(format_args!("wrote {}", self.file.display()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_line_up_and_mark_colors() {
+        let before = Snapshot::parse("a ffffff\nsm wave\n");
+        let after = Snapshot::parse("a ffffff\nsm color\ng arrows 00ff00\n");
+        let changes = before.changes(&after);
+        assert_eq!(
+            diff_text(&changes, false),
+            "  sm        wave -> color\n  g arrows  unset -> 00ff00\n"
+        );
+        assert!(
+            diff_text(&changes, true).ends_with("unset -> \x1b[48;2;0;255;0m  \x1b[0m 00ff00\n")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use clap::{Args, ValueHint};
+use core::time::Duration;
+use std::path::PathBuf;
+
+use super::{Command, Context, parse_duration_arg};
+use crate::clock::{Clock, SystemClock};
+use crate::help::COLOR_HELP;
+use crate::keyboard::{Color, api::KeyboardApi, brightness};
+use crate::profile;
+
+/// Gradually change brightness, e.g. as a wake-up light
+///
+/// Runs in the foreground until done; schedule it from cron or a systemd timer.
+#[derive(Args, Debug)]
+pub struct Ramp {
+    /// Starting brightness in percent
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub from: u8,
+    /// Final brightness in percent
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub to: u8,
+    /// How long the ramp takes, e.g. `90s`, `10m` or `1h`
+    #[arg(long, value_parser = parse_duration_arg)]
+    pub over: Duration,
+    /// Profile to ramp (`.toml` or line format); defaults to `default_color` on every key
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "color")]
+    pub profile: Option<PathBuf>,
+    /// Color to ramp on every key
+    #[arg(long, help = COLOR_HELP)]
+    pub color: Option<Color>,
+}
+
+impl Ramp {
+    fn run_on(&self, ctx: &Context<'_>, clock: &dyn Clock) -> Result<()> {
+        let color = self.color.map_or_else(|| ctx.config.default_color(), Ok)?;
+        ctx.with_keyboard(|kbd| {
+            brightness::ramp(
+                kbd,
+                self.from,
+                self.to,
+                self.over,
+                |dimmed| match &self.profile {
+                    Some(path) if path.extension().is_some_and(|ext| ext == "toml") => {
+                        profile::load_toml_profile(dimmed, path)
+                    }
+                    Some(path) => {
+                        // Line profiles only commit on `c`; each step must show.
+                        profile::load_profile(dimmed, path, ctx.strict)?;
+                        dimmed.commit()
+                    }
+                    None => {
+                        dimmed.set_all_keys(color)?;
+                        dimmed.commit()
+                    }
+                },
+                clock,
+            )
+        })
+    }
+}
+
+impl Command for Ramp {
+    fn name(&self) -> &'static str {
+        "ramp"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        self.run_on(ctx, &SystemClock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn ramp_dims_the_color() {
+        let cmd = Ramp {
+            from: 50,
+            to: 52,
+            over: Duration::from_secs(1),
+            profile: None,
+            color: Some(Color::new(200, 0, 0)),
+        };
+        let calls = run_with(KeyboardModel::G810, &Config::default(), |ctx| {
+            cmd.run_on(ctx, &ManualClock::default())
+        })
+        .unwrap();
+        assert_eq!(
+            calls,
+            [
+                "all 640000",
+                "commit",
+                "all 660000",
+                "commit",
+                "all 680000",
+                "commit"
+            ]
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use core::time::Duration;
+use std::cell::RefCell;
+
+use super::animate::{lookup, time_seed};
+use super::schedule::Target;
+use super::{Command, Context, Opener, parse_duration_arg};
+use crate::animation::{self, DEFAULT_TICK, Transition, TransitionStyle};
+use crate::history::{History, Recorder, Snapshot};
+use crate::keyboard::api::NullKeyboard;
+use crate::keyboard::{
+    Color, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+use crate::output;
+
+/// Apply or list the scenes in the config
+///
+/// A scene, `[scenes.<name>]`, bundles a profile or theme, an animation and
+/// keys kept lit on top of both.
+#[derive(Args, Debug)]
+pub struct SceneCommand {
+    #[command(subcommand)]
+    pub action: SceneAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SceneAction {
+    /// Switch to a scene
+    ///
+    /// The overlay keys are drawn before every commit, so they never go dark
+    /// while the profile loads. A scene with an animation runs until
+    /// interrupted.
+    Apply {
+        name: String,
+        /// Animate the keys from the current lighting to the scene's profile
+        /// instead of switching at once
+        #[arg(long, value_name = "STYLE")]
+        transition: Option<TransitionStyle>,
+        /// How long `--transition` takes, e.g. `800ms`
+        #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration_arg)]
+        transition_time: Duration,
+    },
+
+    /// List the scenes
+    List,
+}
+
+impl Command for SceneCommand {
+    fn name(&self) -> &'static str {
+        "scene"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        match &self.action {
+            SceneAction::Apply {
+                name,
+                transition,
+                transition_time,
+            } => apply(ctx, name, transition.map(|style| (style, *transition_time))),
+            SceneAction::List => {
+                for name in ctx.config.scene_names() {
+                    println!("{name}");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn apply(
+    ctx: &Context<'_>,
+    name: &str,
+    transition: Option<(TransitionStyle, Duration)>,
+) -> Result<()> {
+    let scene = ctx.config.scene(name)?;
+    // Resolve everything up front so a typo fails before anything changes.
+    let target = scene.profile.as_deref().map(Target::parse).transpose()?;
+    let mut animation = scene
+        .animation
+        .as_deref()
+        .map(|spec| lookup(spec, time_seed()))
+        .transpose()?;
+    let opener = OverlayOpener {
+        inner: ctx.opener,
+        overlay: &scene.overlay,
+    };
+    let ctx = Context {
+        opener: &opener,
+        ..*ctx
+    };
+
+    match (&target, &animation) {
+        (Some(target), _) => {
+            if let Some((style, duration)) = transition {
+                play_transition(&ctx, style, duration, |ctx| target.apply(ctx))?;
+            }
+            target.apply(&ctx)?;
+        }
+        // Just the overlay: a commit draws it.
+        (None, None) => ctx.with_keyboard(|kbd| kbd.commit())?,
+        (None, Some(_)) => {}
+    }
+    let Some(effect) = &mut animation else {
+        output::status(format_args!("applied scene {name}"));
+        return Ok(());
+    };
+    output::note(format_args!(
+        "scene {name} is animating; press Ctrl-C to stop"
+    ));
+    let pacing = ctx.config.pacing(DEFAULT_TICK)?;
+    // Only the profile and overlay belong in the history.
+    ctx.animate(|| {
+        ctx.opener
+            .with_keyboard(&mut |kbd| animation::play(kbd, effect.as_mut(), pacing, None))
+    })
+}
+
+/// Animate the keys from the latest recorded lighting to what `apply`
+/// would leave, unrecorded; `apply` itself still has to run afterwards to
+/// set everything the key grid does not cover.
+fn play_transition(
+    ctx: &Context<'_>,
+    style: TransitionStyle,
+    duration: Duration,
+    apply: impl Fn(&Context<'_>) -> Result<()>,
+) -> Result<()> {
+    let before = ctx.history.map_or_else(
+        || {
+            output::note("the lighting history is off; transitioning from a dark keyboard");
+            Snapshot::default()
+        },
+        History::latest,
+    );
+    let dry_run = DryRunOpener(RefCell::new(before.clone()));
+    apply(&Context {
+        opener: &dry_run,
+        history: None,
+        ..*ctx
+    })?;
+    let after = dry_run.0.into_inner();
+    let mut effect = Transition::new(&before, &after, style, duration)?;
+    let pacing = ctx.config.pacing(DEFAULT_TICK)?;
+    ctx.opener
+        .with_keyboard(&mut |kbd| animation::play(kbd, &mut effect, pacing, None))
+}
+
+/// Hands out a keyboard that only records the lighting it would be left with.
+struct DryRunOpener(RefCell<Snapshot>);
+
+impl Opener for DryRunOpener {
+    fn with_keyboard(&self, f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>) -> Result<()> {
+        let mut null = NullKeyboard;
+        let mut recorder = Recorder::new(&mut null, self.0.borrow().clone());
+        f(&mut recorder)?;
+        if let Some(snapshot) = recorder.into_snapshot() {
+            *self.0.borrow_mut() = snapshot;
+        }
+        Ok(())
+    }
+}
+
+/// Opens keyboards wrapped in an [`Overlay`].
+struct OverlayOpener<'a> {
+    inner: &'a dyn Opener,
+    overlay: &'a [KeyValue],
+}
+
+impl Opener for OverlayOpener<'_> {
+    fn with_keyboard(&self, f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>) -> Result<()> {
+        self.inner.with_keyboard(&mut |kbd| {
+            f(&mut Overlay {
+                inner: kbd,
+                keys: self.overlay,
+            })
+        })
+    }
+}
+
+/// Forwards every call to `inner`, drawing `keys` again before each commit
+/// so they stay on top of whatever else was drawn.
+struct Overlay<'a> {
+    inner: &'a mut dyn KeyboardApi,
+    keys: &'a [KeyValue],
+}
+
+impl KeyboardApi for Overlay<'_> {
+    fn model(&self) -> KeyboardModel {
+        self.inner.model()
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        if !self.keys.is_empty() {
+            self.inner.set_keys(self.keys)?;
+        }
+        self.inner.commit()
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.inner.set_all_keys(color)
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.inner.set_group_keys(group, color)
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        self.inner.set_keys(keys)
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        self.inner.set_key_refs(keys)
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.inner.set_region(region, color)
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mr_key(value)
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mn_key(value)
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.inner.set_gkeys_mode(value)
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        self.inner.set_startup_mode(mode)
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.inner.set_on_board_mode(mode)
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        self.inner.set_fx(effect, part, period, color, storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+
+    #[test]
+    fn the_overlay_lands_before_every_commit() {
+        let file = std::env::temp_dir().join("logi_led_scene.profile");
+        std::fs::write(&file, "a 102030\nc\n").unwrap();
+        let config = Config::from_toml(
+            &format!(
+                "[scenes.focus]\nprofile = {:?}\n[scenes.focus.overlay]\nesc = \"red\"\n\
+                 [scenes.badge.overlay]\nf1 = \"00ff00\"\n",
+                file.display().to_string()
+            ),
+            "c",
+        )
+        .unwrap();
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply(ctx, "focus", None)
+        })
+        .unwrap();
+        assert_eq!(calls, ["all 102030", "key Esc ff0000", "commit"]);
+
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply(ctx, "badge", None)
+        })
+        .unwrap();
+        assert_eq!(calls, ["key F1 00ff00", "commit"]);
+
+        // Working out where a transition ends sends nothing by itself.
+        let instant = Some((TransitionStyle::Wipe, Duration::ZERO));
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply(ctx, "focus", instant)
+        })
+        .unwrap();
+        let _ = std::fs::remove_file(file);
+        assert_eq!(calls, ["all 102030", "key Esc ff0000", "commit"]);
+    }
+}
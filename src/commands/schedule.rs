@@ -0,0 +1,171 @@
+use anyhow::{Result, anyhow, bail};
+use chrono::{NaiveDateTime, Weekday};
+use clap::{Args, Subcommand};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::{Command, Context};
+use crate::clock::{Clock, SystemClock};
+use crate::output;
+use crate::paths;
+use crate::profile;
+use crate::schedule::WeeklySchedule;
+use crate::theme::{ThemeSpec, ThemeVariant};
+
+/// Prefix of schedule entries that name a theme instead of a profile.
+const THEME_PREFIX: &str = "theme:";
+
+/// Apply or show the weekly schedule from the config
+#[derive(Args, Debug)]
+pub struct ScheduleCommand {
+    #[command(subcommand)]
+    pub action: ScheduleAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ScheduleAction {
+    /// Apply the profile or theme scheduled for the current local time
+    ///
+    /// Meant to be run periodically, e.g. from cron or a systemd timer.
+    Apply,
+
+    /// Print each day's entries and the one in force now
+    Show,
+}
+
+impl Command for ScheduleCommand {
+    fn name(&self) -> &'static str {
+        "schedule"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let schedule = ctx.config.schedule()?;
+        if schedule.is_empty() {
+            bail!("no [schedule.<day>] tables in the config");
+        }
+        let now = SystemClock.local().naive_local();
+        match self.action {
+            ScheduleAction::Apply => apply_at(ctx, &schedule, now),
+            ScheduleAction::Show => {
+                print!("{}", show(&schedule, now));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What a schedule entry applies.
+#[derive(Debug)]
+pub(super) enum Target {
+    Profile(PathBuf),
+    Theme(ThemeSpec),
+}
+
+impl Target {
+    /// `theme:name[:variant]`, or a profile path; relative paths are looked
+    /// up in the profiles directory.
+    pub(super) fn parse(entry: &str) -> Result<Self> {
+        if let Some(spec) = entry.strip_prefix(THEME_PREFIX) {
+            return spec.parse().map(Target::Theme).map_err(|e| anyhow!(e));
+        }
+        let path = Path::new(entry);
+        Ok(Target::Profile(match paths::profiles_dir() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_owned(),
+        }))
+    }
+
+    /// Apply the profile or the theme's dark variant.
+    pub(super) fn apply(&self, ctx: &Context<'_>) -> Result<()> {
+        match self {
+            Target::Theme(spec) => super::theme::apply(ctx, &spec.resolve(ThemeVariant::Dark)?),
+            Target::Profile(path) if path.extension().is_some_and(|ext| ext == "toml") => {
+                ctx.with_keyboard(|kbd| profile::load_toml_profile(kbd, path))
+            }
+            Target::Profile(path) => {
+                ctx.with_keyboard(|kbd| profile::load_profile(kbd, path, ctx.strict))
+            }
+        }
+    }
+}
+
+fn apply_at(ctx: &Context<'_>, schedule: &WeeklySchedule, now: NaiveDateTime) -> Result<()> {
+    let Some((since, entry)) = schedule.active(now) else {
+        bail!("the schedule has no entries");
+    };
+    Target::parse(entry)?.apply(ctx)?;
+    output::status(format_args!(
+        "applied {entry} (scheduled {})",
+        since.format("%A %H:%M")
+    ));
+    Ok(())
+}
+
+fn show(schedule: &WeeklySchedule, now: NaiveDateTime) -> String {
+    let mut out = String::new();
+    let mut day = Weekday::Mon;
+    for _ in 0..7 {
+        for (n, (time, entry)) in schedule.day(day).into_iter().enumerate() {
+            let name = if n == 0 {
+                day.to_string()
+            } else {
+                String::new()
+            };
+            let _ = writeln!(out, "{name:<4} {}  {entry}", time.format("%H:%M"));
+        }
+        day = day.succ();
+    }
+    if let Some((since, entry)) = schedule.active(now) {
+        let _ = writeln!(out, "now: {entry} (since {})", since.format("%a %H:%M"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::run_with;
+    use super::*;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+
+    const CONFIG: &str = "[schedule.weekdays]\n\
+                          \"09:00\" = \"theme:nord:light\"\n\
+                          \"18:00\" = \"theme:dracula\"\n";
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn applies_the_entry_in_force() {
+        let config = Config::from_toml(CONFIG, "c").unwrap();
+        let schedule = config.schedule().unwrap();
+        // 2024-06-08 is a Saturday: Friday evening's entry still holds.
+        let weekend = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply_at(ctx, &schedule, at("2024-06-08 12:00"))
+        })
+        .unwrap();
+        let friday = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply_at(ctx, &schedule, at("2024-06-07 19:00"))
+        })
+        .unwrap();
+        assert_eq!(weekend, friday);
+        let morning = run_with(KeyboardModel::G810, &config, |ctx| {
+            apply_at(ctx, &schedule, at("2024-06-07 10:00"))
+        })
+        .unwrap();
+        assert_ne!(morning, friday);
+        assert_eq!(morning.last().map(String::as_str), Some("commit"));
+    }
+
+    #[test]
+    fn show_lists_days_and_the_current_entry() {
+        let config = Config::from_toml(CONFIG, "c").unwrap();
+        let text = show(&config.schedule().unwrap(), at("2024-06-03 10:00"));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "Mon  09:00  theme:nord:light");
+        assert_eq!(lines[1], "     18:00  theme:dracula");
+        assert_eq!(lines[10], "now: theme:nord:light (since Mon 09:00)");
+    }
+}
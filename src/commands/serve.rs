@@ -0,0 +1,52 @@
+use anyhow::{Result, anyhow};
+use clap::Args;
+use std::net::{TcpListener, TcpStream};
+
+use super::{Command, Context};
+use crate::output;
+use crate::remote::Session;
+
+/// Let `--remote` commands on other machines drive this keyboard
+///
+/// Clients must have the same `remote_token` in their config. The
+/// connection is not encrypted; outside a trusted network, tunnel it.
+#[derive(Args, Debug)]
+pub struct Serve {
+    /// Address to listen on; `0.0.0.0:7717` accepts other hosts
+    #[arg(long, default_value = "127.0.0.1:7717")]
+    pub listen: String,
+}
+
+impl Command for Serve {
+    fn name(&self) -> &'static str {
+        "serve"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let token = ctx.config.remote_token()?;
+        let listener =
+            TcpListener::bind(&self.listen).map_err(|e| anyhow!("{}: {e}", self.listen))?;
+        output::status(format_args!("listening on {}", listener.local_addr()?));
+        // One client at a time: they all share the one keyboard.
+        for stream in listener.incoming() {
+            let result = stream
+                .map_err(anyhow::Error::from)
+                .and_then(|stream| serve_client(ctx, stream, token));
+            if let Err(e) = result {
+                output::warn(format_args!("remote client: {e:#}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn serve_client(ctx: &Context<'_>, stream: TcpStream, token: &str) -> Result<()> {
+    let peer = stream.peer_addr()?;
+    let mut session = Session::accept(stream, token).map_err(|e| anyhow!("{peer}: {e}"))?;
+    // Recorded like local commands, so `undo` here covers remote changes too.
+    let served = ctx.with_keyboard(|kbd| session.run(kbd));
+    if let Err(e) = &served {
+        session.refuse(format_args!("{e:#}"));
+    }
+    served.map_err(|e| anyhow!("{peer}: {e:#}"))
+}
@@ -0,0 +1,97 @@
+use anyhow::{Result, bail};
+use clap::Args;
+
+use super::animate::{lookup, time_seed};
+use super::schedule::Target;
+use super::{Command, Context};
+use crate::animation::{self, DEFAULT_TICK};
+use crate::output;
+use crate::shutdown;
+
+/// Run the `[startup]` sequence from the config: an intro animation, then a
+/// profile or theme
+///
+/// Meant for login, e.g. from a desktop autostart entry or a systemd user
+/// unit. Without a profile, the lighting last applied comes back after the
+/// intro.
+#[derive(Args, Debug)]
+pub struct Start;
+
+impl Command for Start {
+    fn name(&self) -> &'static str {
+        "start"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let Some(startup) = ctx.config.startup()? else {
+            bail!("no [startup] table in the config");
+        };
+        // Resolve everything up front so a typo fails before the intro plays.
+        let mut intro = startup
+            .intro
+            .as_deref()
+            .map(|spec| lookup(spec, time_seed()))
+            .transpose()?;
+        let target = startup.profile.as_deref().map(Target::parse).transpose()?;
+
+        if let Some(effect) = &mut intro {
+            let pacing = ctx.config.pacing(DEFAULT_TICK)?;
+            // Only what the intro settles into belongs in the history.
+            ctx.animate(|| {
+                ctx.opener.with_keyboard(&mut |kbd| {
+                    animation::play(kbd, effect.as_mut(), pacing, Some(startup.intro_for))
+                })
+            })?;
+            // `on_interrupt` already decided what stays.
+            if shutdown::requested() {
+                return Ok(());
+            }
+        }
+        match (target, ctx.history) {
+            (Some(target), _) => target.apply(ctx),
+            (None, Some(history)) => {
+                let latest = history.latest();
+                if latest.is_empty() {
+                    output::note("nothing to settle into yet; set a profile in [startup]");
+                    return Ok(());
+                }
+                ctx.opener.with_keyboard(&mut |kbd| latest.apply(kbd))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn intro_plays_then_the_profile_applies() {
+        let file = std::env::temp_dir().join("logi_led_start.profile");
+        std::fs::write(&file, "a 102030\nc\n").unwrap();
+        let config = Config::from_toml(
+            &format!(
+                "[startup]\nintro = \"demo:wave\"\nintro_for = \"120ms\"\nprofile = {:?}\n",
+                file.display().to_string()
+            ),
+            "c",
+        )
+        .unwrap();
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| Start.run(ctx)).unwrap();
+        let _ = std::fs::remove_file(file);
+
+        assert!(calls.len() > 2);
+        assert_eq!(calls[calls.len() - 2..], ["all 102030", "commit"]);
+    }
+
+    #[test]
+    fn typos_fail_before_the_intro() {
+        let config = Config::from_toml("[startup]\nintro = \"demo:wavy\"\n", "c").unwrap();
+        let calls = run_with(KeyboardModel::G810, &config, |ctx| Start.run(ctx));
+        assert!(calls.is_err());
+    }
+}
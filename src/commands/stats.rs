@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use clap::{Args, Subcommand};
+use core::time::Duration;
+use std::path::PathBuf;
+use strum_macros::{Display, EnumString};
+
+use super::{Command, Context, parse_duration_arg};
+use crate::clock::{Clock, SystemClock};
+use crate::idle::Activity;
+use crate::keyboard::Key;
+use crate::output;
+use crate::shutdown;
+use crate::stats::KeyCounts;
+
+/// How long to wait for a key between Ctrl-C checks.
+const RECORD_POLL: Duration = Duration::from_millis(200);
+
+/// Count key presses and show which keys are used most
+///
+/// Counts are kept in `key-stats.toml` in the state directory.
+#[derive(Args, Debug)]
+pub struct StatsCommand {
+    /// Read and write the counts here instead
+    #[arg(long, global = true)]
+    pub file: Option<PathBuf>,
+    #[command(subcommand)]
+    pub action: StatsAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StatsAction {
+    /// Count key presses until Ctrl-C or SIGTERM
+    ///
+    /// Key presses are read from `/dev/input`, which needs membership of the
+    /// `input` group.
+    Record {
+        /// How often the counts are saved while recording, e.g. `5m`
+        #[arg(long, default_value = "1m", value_parser = parse_duration_arg)]
+        save_every: Duration,
+    },
+
+    /// List the most pressed keys
+    Show {
+        /// Number of keys listed
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Print all counts for other tools
+    Export {
+        #[arg(long, default_value_t = StatsFormat::Csv)]
+        format: StatsFormat,
+    },
+
+    /// Color the counted keys from blue (fewest presses) to red (most)
+    Heatmap,
+}
+
+/// Output format of `stats export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum StatsFormat {
+    Csv,
+    Json,
+}
+
+impl StatsCommand {
+    fn path(&self) -> Result<PathBuf> {
+        self.file
+            .clone()
+            .or_else(KeyCounts::default_path)
+            .ok_or_else(|| anyhow!("cannot locate the state directory; pass --file"))
+    }
+}
+
+impl Command for StatsCommand {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let path = self.path()?;
+        let mut counts = KeyCounts::load(&path)?;
+        match &self.action {
+            StatsAction::Record { save_every } => {
+                let activity = Activity::watch()?;
+                shutdown::catch()?;
+                output::status(format_args!("counting key presses into {}", path.display()));
+                record(
+                    &mut counts,
+                    *save_every,
+                    &SystemClock,
+                    shutdown::requested,
+                    |timeout| activity.wait_keys(timeout),
+                    |counts| counts.save(&path),
+                )
+            }
+            StatsAction::Show { top } => {
+                let total = counts.total();
+                if total == 0 {
+                    output::note("no key presses counted yet; run `logi-led stats record`");
+                    return Ok(());
+                }
+                for (name, count) in counts.ranked().into_iter().take(*top) {
+                    // A percentage for people; the precision lost is invisible.
+                    #[allow(clippy::cast_precision_loss)]
+                    let share = count as f64 * 100.0 / total as f64;
+                    println!("{name:<12} {count:>8} {share:>5.1}%");
+                }
+                Ok(())
+            }
+            StatsAction::Export { format } => {
+                print!(
+                    "{}",
+                    match format {
+                        StatsFormat::Csv => counts.to_csv(),
+                        StatsFormat::Json => counts.to_json(),
+                    }
+                );
+                Ok(())
+            }
+            StatsAction::Heatmap => {
+                let keys = counts.heatmap();
+                if keys.is_empty() {
+                    output::note("no key presses counted yet; run `logi-led stats record`");
+                    return Ok(());
+                }
+                ctx.with_keyboard(|kbd| {
+                    kbd.set_keys(&keys)?;
+                    kbd.commit()
+                })
+            }
+        }
+    }
+}
+
+/// Count the keys `input` reports into `counts` until `stop` returns true,
+/// calling `save` every `save_every` and once more at the end.
+fn record(
+    counts: &mut KeyCounts,
+    save_every: Duration,
+    clock: &dyn Clock,
+    stop: impl Fn() -> bool,
+    mut input: impl FnMut(Duration) -> Option<Vec<Key>>,
+    mut save: impl FnMut(&KeyCounts) -> Result<()>,
+) -> Result<()> {
+    let mut saved = clock.now();
+    while !stop() {
+        for key in input(RECORD_POLL).unwrap_or_default() {
+            counts.press(key);
+        }
+        if clock.now().saturating_sub(saved) >= save_every {
+            save(counts)?;
+            saved = clock.now();
+        }
+    }
+    save(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::commands::testing::run_with;
+    use crate::config::Config;
+    use crate::keyboard::KeyboardModel;
+    use std::cell::Cell;
+
+    #[test]
+    fn recording_saves_periodically_and_at_the_end() {
+        let clock = ManualClock::default();
+        let mut counts = KeyCounts::default();
+        let mut batches = vec![vec![Key::A], vec![Key::A, Key::B], vec![Key::C]].into_iter();
+        let mut saves = Vec::new();
+        let done = Cell::new(false);
+        // Every input takes 30s, so the counts are saved at one and two
+        // minutes, then once more as running out of batches stands in for
+        // Ctrl-C.
+        record(
+            &mut counts,
+            Duration::from_secs(60),
+            &clock,
+            || done.get(),
+            |_| {
+                clock.advance(Duration::from_secs(30));
+                let batch = batches.next();
+                done.set(batch.is_none());
+                batch
+            },
+            |counts| {
+                saves.push(counts.total());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(saves, [3, 4, 4]);
+    }
+
+    #[test]
+    fn heatmap_colors_only_counted_keys() {
+        let path = std::env::temp_dir().join("logi_led_stats_heatmap_test.toml");
+        let mut counts = KeyCounts::default();
+        counts.press(Key::A);
+        counts.save(&path).unwrap();
+        let cmd = StatsCommand {
+            file: Some(path),
+            action: StatsAction::Heatmap,
+        };
+        let calls = run_with(KeyboardModel::G513, &Config::default(), |ctx| cmd.run(ctx)).unwrap();
+        assert_eq!(calls, ["key A ff0000", "commit"]);
+    }
+}
@@ -0,0 +1,36 @@
+//! A recording opener for command tests.
+
+use anyhow::Result;
+use std::cell::RefCell;
+
+use super::{Context, Opener, Target};
+use crate::config::Config;
+use crate::keyboard::{KeyboardModel, api::KeyboardApi};
+pub use crate::testing::MockKeyboard;
+
+pub struct MockOpener(pub RefCell<MockKeyboard>);
+
+impl Opener for MockOpener {
+    fn with_keyboard(&self, f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>) -> Result<()> {
+        f(&mut *self.0.borrow_mut())
+    }
+}
+
+/// Run `f` with a context around a mock `model` keyboard; returns its calls.
+pub fn run_with(
+    model: KeyboardModel,
+    config: &Config,
+    f: impl FnOnce(&Context<'_>) -> Result<()>,
+) -> Result<Vec<String>> {
+    let opener = MockOpener(RefCell::new(MockKeyboard::new(model)));
+    let ctx = Context {
+        config,
+        serial: None,
+        target: Target::default(),
+        strict: false,
+        opener: &opener,
+        history: None,
+    };
+    f(&ctx)?;
+    Ok(opener.0.into_inner().calls)
+}
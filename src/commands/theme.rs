@@ -0,0 +1,167 @@
+use anyhow::{Result, anyhow, bail};
+use chrono::NaiveTime;
+use clap::{Args, Subcommand};
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{Command, Context, parse_time_arg};
+use crate::clock::{Clock, SystemClock};
+use crate::output;
+use crate::paths;
+use crate::schedule::{self, DaySource};
+use crate::theme::{self, Palette, Theme, ThemeSpec, ThemeVariant, WalSlots};
+
+/// How often `from-pywal --watch` checks the colors file.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Apply or list color themes
+#[derive(Args, Debug)]
+pub struct ThemeCommand {
+    #[command(subcommand)]
+    pub action: ThemeAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ThemeAction {
+    /// Apply a built-in theme by name or a theme file by path
+    Apply {
+        name: String,
+        #[arg(long, default_value_t = ThemeVariant::Dark)]
+        variant: ThemeVariant,
+    },
+
+    /// List the built-in themes
+    List,
+
+    /// Apply the colors pywal generated for the current wallpaper
+    FromPywal {
+        /// Read this file instead of pywal's `colors.json`; JSON, CSS variables
+        /// and Xresources naming `color0`..`color15` all work
+        #[arg(long)]
+        colors_file: Option<PathBuf>,
+        /// Color slots (0-15) for base, accent, modifiers and alerts
+        #[arg(long, default_value = "4,5,6,1")]
+        slots: WalSlots,
+        /// Keep running and re-apply whenever the file changes
+        #[arg(long)]
+        watch: bool,
+    },
+
+    /// Apply the day or night theme for the current local time
+    ///
+    /// Meant to be run periodically, e.g. from cron or a systemd timer.
+    Auto {
+        /// Theme used during the day, as `name[:variant]` (default variant: light)
+        #[arg(long)]
+        day: ThemeSpec,
+        /// Theme used at night, as `name[:variant]` (default variant: dark)
+        #[arg(long)]
+        night: ThemeSpec,
+        /// Local time the day theme starts (HH:MM)
+        #[arg(long, value_parser = parse_time_arg, requires = "sunset", conflicts_with_all = ["latitude", "longitude"])]
+        sunrise: Option<NaiveTime>,
+        /// Local time the night theme starts (HH:MM)
+        #[arg(long, value_parser = parse_time_arg, requires = "sunrise")]
+        sunset: Option<NaiveTime>,
+        /// Latitude used to compute sunrise and sunset (north positive)
+        #[arg(long, requires = "longitude", allow_hyphen_values = true)]
+        latitude: Option<f64>,
+        /// Longitude used to compute sunrise and sunset (east positive)
+        #[arg(long, requires = "latitude", allow_hyphen_values = true)]
+        longitude: Option<f64>,
+    },
+}
+
+impl Command for ThemeCommand {
+    fn name(&self) -> &'static str {
+        "theme"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        match &self.action {
+            ThemeAction::Apply { name, variant } => {
+                apply(ctx, &Theme::load(name)?.palette(*variant)?)
+            }
+            ThemeAction::Auto {
+                day,
+                night,
+                sunrise,
+                sunset,
+                latitude,
+                longitude,
+            } => {
+                let source = match (sunrise, sunset, latitude, longitude) {
+                    (Some(sunrise), Some(sunset), _, _) => DaySource::Fixed {
+                        sunrise: *sunrise,
+                        sunset: *sunset,
+                    },
+                    (_, _, Some(latitude), Some(longitude)) => DaySource::Solar {
+                        latitude: *latitude,
+                        longitude: *longitude,
+                    },
+                    _ => bail!("either --sunrise/--sunset or --latitude/--longitude is required"),
+                };
+                let palette = if schedule::is_daytime(source, SystemClock.local()) {
+                    day.resolve(ThemeVariant::Light)?
+                } else {
+                    night.resolve(ThemeVariant::Dark)?
+                };
+                apply(ctx, &palette)
+            }
+            ThemeAction::FromPywal {
+                colors_file,
+                slots,
+                watch,
+            } => {
+                let path = colors_file
+                    .clone()
+                    .or_else(paths::pywal_colors)
+                    .ok_or_else(|| anyhow!("cannot locate pywal's cache; pass --colors-file"))?;
+                if *watch {
+                    watch_colors(ctx, &path, *slots)
+                } else {
+                    apply(ctx, &read_colors(&path, *slots)?)
+                }
+            }
+            ThemeAction::List => {
+                for name in theme::embedded_theme_names() {
+                    println!("{name}");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(super) fn apply(ctx: &Context<'_>, palette: &Palette) -> Result<()> {
+    ctx.with_keyboard(|kbd| {
+        let model = kbd.model();
+        theme::apply_palette(kbd, model, palette)
+    })
+}
+
+fn read_colors(path: &Path, slots: WalSlots) -> Result<Palette> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+    theme::palette_from_colors(&text, slots).map_err(|e| anyhow!("{}: {e}", path.display()))
+}
+
+/// Apply the colors file every time it changes; runs until interrupted.
+fn watch_colors(ctx: &Context<'_>, path: &Path, slots: WalSlots) -> Result<()> {
+    let mut seen: Option<SystemTime> = None;
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != seen {
+            seen = modified;
+            // A file caught mid-write only warns; pywal's next write retries it.
+            match read_colors(path, slots) {
+                Ok(palette) => {
+                    apply(ctx, &palette)?;
+                    output::status(format_args!("applied colors from {}", path.display()));
+                }
+                Err(e) => output::warn(e),
+            }
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
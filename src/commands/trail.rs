@@ -0,0 +1,80 @@
+use anyhow::Result;
+use clap::Args;
+use core::time::Duration;
+
+use super::{Command, Context, parse_duration_arg};
+use crate::animation::{DEFAULT_TICK, Trail};
+use crate::clock::{Clock, SystemClock};
+use crate::history::History;
+use crate::idle::Activity;
+use crate::keyboard::{Key, api::KeyboardApi};
+use crate::shutdown;
+use crate::state::KeyboardState;
+
+/// How long to wait for a key while nothing fades, between Ctrl-C checks.
+const IDLE_POLL: Duration = Duration::from_millis(200);
+
+/// Light each pressed key in its finger's color and let it fade back
+///
+/// Keys are colored by the touch-typing finger that presses them, left
+/// pinky to right pinky around the color wheel, and fade back to the latest
+/// lighting of `logi-led history` over `--fade`. Key presses are read from
+/// `/dev/input`, which needs membership of the `input` group. Runs in the
+/// foreground until Ctrl-C or SIGTERM.
+#[derive(Args, Debug)]
+pub struct TypingTrail {
+    /// How long a key takes to fade back, e.g. `800ms`
+    #[arg(long, default_value = "1s", value_parser = parse_duration_arg)]
+    pub fade: Duration,
+}
+
+impl Command for TypingTrail {
+    fn name(&self) -> &'static str {
+        "trail"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let mut base = KeyboardState::new();
+        ctx.history
+            .map(History::latest)
+            .unwrap_or_default()
+            .apply(&mut base)?;
+        let activity = Activity::watch()?;
+        let tick = ctx.config.pacing(DEFAULT_TICK)?.tick;
+        let mut trail = Trail::new(self.fade, base);
+        // The trail is transient, like an alert, so it is not recorded.
+        ctx.animate(|| {
+            ctx.opener.with_keyboard(&mut |kbd| {
+                follow(kbd, &mut trail, tick, &SystemClock, |timeout| {
+                    activity.wait_keys(timeout)
+                })
+            })
+        })
+    }
+}
+
+/// Light the keys `input` reports into `trail` until Ctrl-C or SIGTERM.
+///
+/// `input` waits up to the given time and returns the keys pressed; it also
+/// paces the frames while keys fade.
+fn follow(
+    kbd: &mut dyn KeyboardApi,
+    trail: &mut Trail,
+    tick: Duration,
+    clock: &dyn Clock,
+    mut input: impl FnMut(Duration) -> Option<Vec<Key>>,
+) -> Result<()> {
+    let start = clock.now();
+    while !shutdown::requested() {
+        let timeout = if trail.busy() { tick } else { IDLE_POLL };
+        let keys = input(timeout).unwrap_or_default();
+        let now = clock.now().saturating_sub(start);
+        for key in keys {
+            trail.press(key, now);
+        }
+        if trail.busy() {
+            trail.frame(kbd, now)?;
+        }
+    }
+    Ok(())
+}
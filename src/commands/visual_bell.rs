@@ -0,0 +1,128 @@
+use anyhow::Result;
+use clap::Args;
+use core::time::Duration;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver};
+
+use super::{Command, Context, parse_duration_arg};
+use crate::help::COLOR_HELP;
+use crate::history::{History, Snapshot};
+use crate::keyboard::{Color, api::KeyboardApi};
+use crate::output;
+
+/// The terminal bell character.
+const BEL: u8 = 0x07;
+
+const BLACK: Color = Color::new(0, 0, 0);
+
+/// Flash the whole board as a visual bell
+///
+/// Flashes once for every BEL character or line on standard input, keeping
+/// the keyboard open in between. Bells that ring while a flash shows are
+/// dropped, so a burst flashes once instead of strobing. With `--once` it
+/// flashes and exits, for hooks such as tmux's `alert-bell`. The lighting
+/// comes back from the latest history entry; flashes are not recorded.
+#[derive(Args, Debug)]
+pub struct VisualBell {
+    #[arg(long, default_value = "white", help = COLOR_HELP)]
+    pub color: Color,
+    /// Brightness of the flash in percent
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub intensity: u8,
+    /// How long each flash shows, e.g. `150ms`
+    #[arg(long = "for", default_value = "150ms", value_parser = parse_duration_arg)]
+    pub duration: Duration,
+    /// Flash once and exit instead of reading standard input
+    #[arg(long)]
+    pub once: bool,
+}
+
+impl Command for VisualBell {
+    fn name(&self) -> &'static str {
+        "visual-bell"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let base = ctx.history.map(History::latest).unwrap_or_default();
+        if base.is_empty() {
+            output::note("no recorded lighting to go back to; the board turns off after a flash");
+        }
+        let color = self.color.scaled(self.intensity);
+        let bells = (!self.once).then(spawn_bell_reader);
+        ctx.opener.with_keyboard(&mut |kbd| {
+            let Some(bells) = &bells else {
+                return flash(kbd, color, self.duration, &base);
+            };
+            while bells.recv().is_ok() {
+                flash(kbd, color, self.duration, &base)?;
+                // Bells that rang during the flash have been shown by it.
+                while bells.try_recv().is_ok() {}
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Read standard input on a thread, sending one bell per BEL character or
+/// line.
+fn spawn_bell_reader() -> Receiver<()> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for byte in std::io::stdin().lock().bytes().map_while(Result::ok) {
+            if matches!(byte, BEL | b'\n') && tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Light the whole board in `color` for `duration`, then put `base` back.
+fn flash(
+    kbd: &mut dyn KeyboardApi,
+    color: Color,
+    duration: Duration,
+    base: &Snapshot,
+) -> Result<()> {
+    kbd.set_all_keys(color)?;
+    kbd.commit()?;
+    std::thread::sleep(duration);
+    if base.is_empty() {
+        kbd.set_all_keys(BLACK)?;
+        return kbd.commit();
+    }
+    base.apply(kbd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::testing::MockKeyboard;
+    use crate::keyboard::KeyboardModel;
+
+    #[test]
+    fn flashes_put_the_lighting_back() {
+        let mut kbd = MockKeyboard {
+            model: KeyboardModel::G810,
+            calls: Vec::new(),
+        };
+        let color = Color::new(0xff, 0xff, 0xff).scaled(50);
+        let base = Snapshot::parse("a 0000ff\nk esc ff0000\n");
+        flash(&mut kbd, color, Duration::ZERO, &base).unwrap();
+        flash(&mut kbd, color, Duration::ZERO, &Snapshot::default()).unwrap();
+        assert_eq!(
+            kbd.calls,
+            [
+                "all 808080",
+                "commit",
+                "all 0000ff",
+                "key Esc ff0000",
+                "commit",
+                "all 808080",
+                "commit",
+                "all 000000",
+                "commit"
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use clap::Args;
+use std::io::BufRead;
+
+use super::{Command, Context};
+use crate::help::COLOR_HELP;
+use crate::history::History;
+use crate::keyboard::{Color, Key, KeyValue};
+use crate::output;
+
+/// Number-row keys for desktops 1 to 10.
+const DESKTOP_KEYS: [Key; 10] = [
+    Key::N1,
+    Key::N2,
+    Key::N3,
+    Key::N4,
+    Key::N5,
+    Key::N6,
+    Key::N7,
+    Key::N8,
+    Key::N9,
+    Key::N0,
+];
+
+/// Light the number-row key of the active virtual desktop
+///
+/// Reads "workspace changed" messages from standard input, one per line, until
+/// it closes: `3` or `workspace 3` lights `3` (desktops 1-10, where 10 is `0`).
+/// Each switch repaints the latest history entry underneath, so the rest of
+/// the board keeps its lighting. `contrib/workspace-leds.sh` feeds it from
+/// X11 desktops such as KDE and GNOME.
+#[derive(Args, Debug)]
+pub struct Workspaces {
+    #[arg(long, default_value = "white", help = COLOR_HELP)]
+    pub color: Color,
+}
+
+impl Command for Workspaces {
+    fn name(&self) -> &'static str {
+        "workspaces"
+    }
+
+    fn run(&self, ctx: &Context<'_>) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut lines = stdin.lock().lines();
+        // Not recorded: the history stays the lighting underneath.
+        ctx.opener.with_keyboard(&mut |kbd| {
+            for line in lines.by_ref() {
+                let line = line?;
+                let Some(desktop) = parse_message(&line) else {
+                    if !line.trim().is_empty() {
+                        output::warn(format_args!("ignoring {line:?}; expected a desktop 1-10"));
+                    }
+                    continue;
+                };
+                ctx.history
+                    .map(History::latest)
+                    .unwrap_or_default()
+                    .apply(kbd)?;
+                kbd.set_keys(&[KeyValue {
+                    key: DESKTOP_KEYS[desktop - 1],
+                    color: self.color,
+                }])?;
+                kbd.commit()?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The 1-based desktop in a `3` or `workspace 3` message.
+fn parse_message(line: &str) -> Option<usize> {
+    let line = line.trim();
+    let number = line.strip_prefix("workspace").unwrap_or(line).trim();
+    number
+        .parse()
+        .ok()
+        .filter(|desktop| (1..=DESKTOP_KEYS.len()).contains(desktop))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_name_a_desktop() {
+        assert_eq!(parse_message("3"), Some(3));
+        assert_eq!(parse_message(" workspace 10\r"), Some(10));
+        assert_eq!(parse_message("workspace 0"), None);
+        assert_eq!(parse_message("11"), None);
+        assert_eq!(parse_message("desktop 2"), None);
+        assert_eq!(DESKTOP_KEYS[10 - 1], Key::N0);
+    }
+}
@@ -0,0 +1,650 @@
+//! User configuration read from [`paths::config_file`].
+
+use anyhow::{Result, anyhow, bail};
+use core::time::Duration;
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::animation::Pacing;
+use crate::diagnostics::parse_toml;
+use crate::history;
+use crate::keyboard::{
+    Color, Key, KeyValue, KeyboardModel,
+    parser::{parse_color, parse_duration, parse_key},
+    spec::RateLimit,
+};
+use crate::paths;
+use crate::schedule::{ScheduleDays, WeeklySchedule, parse_time_of_day};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Extra `"vid:pid" = "model"` entries for PIDs the built-in table lacks.
+    #[serde(default)]
+    extra_devices: BTreeMap<String, String>,
+    /// Color used by `default` and when a command's color is left out.
+    default_color: Option<String>,
+    /// Applied states kept for `history` and `undo`; 0 turns recording off.
+    history_limit: Option<usize>,
+    /// User key names, `name = "key"`, accepted wherever a key is.
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    /// `[schedule.<days>]` tables of `"HH:MM" = "profile or theme:name"`.
+    #[serde(default)]
+    schedule: BTreeMap<String, BTreeMap<String, String>>,
+    /// Most frames a second software animations draw.
+    max_fps: Option<u32>,
+    /// Lower the frame rate while the keyboard cannot keep up with it.
+    #[serde(default)]
+    adaptive_fps: bool,
+    /// What an animation stopped by Ctrl-C or SIGTERM leaves on the keyboard.
+    on_interrupt: Option<String>,
+    /// Shared secret between `serve` and `--remote` clients.
+    remote_token: Option<String>,
+    /// The `[startup]` sequence run by `start`.
+    startup: Option<StartupTable>,
+    /// Keys and per-mode colors for `mode-indicator`.
+    mode_indicator: Option<ModeIndicatorTable>,
+    /// `[scenes.<name>]` tables applied with `scene apply`.
+    #[serde(default)]
+    scenes: BTreeMap<String, SceneTable>,
+    /// `[rate_limits.<model>]` tables replacing a model's built-in limits.
+    #[serde(default)]
+    rate_limits: BTreeMap<String, RateLimitTable>,
+}
+
+/// `[rate_limits.<model>]` as written in the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RateLimitTable {
+    packets_per_sec: Option<u32>,
+    min_commit_gap: Option<String>,
+}
+
+/// `[scenes.<name>]` as written in the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneTable {
+    profile: Option<String>,
+    animation: Option<String>,
+    #[serde(default)]
+    overlay: BTreeMap<String, String>,
+}
+
+/// A base profile, an animation over it and keys kept lit on top of both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scene {
+    /// Profile path or `theme:name` applied first.
+    pub profile: Option<String>,
+    /// Animation run until interrupted, as `demo:<name>`.
+    pub animation: Option<String>,
+    /// Keys drawn over the profile and every animation frame.
+    pub overlay: Vec<KeyValue>,
+}
+
+/// `[mode_indicator]` as written in the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModeIndicatorTable {
+    keys: Option<Vec<String>>,
+    #[serde(default)]
+    colors: BTreeMap<String, String>,
+}
+
+/// Which keys show an editor or tmux mode, and in what color per mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeIndicator {
+    pub keys: Vec<Key>,
+    /// Lowercase mode names and their colors.
+    pub colors: BTreeMap<String, Color>,
+}
+
+/// `[startup]` as written in the config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StartupTable {
+    intro: Option<String>,
+    intro_for: Option<String>,
+    profile: Option<String>,
+}
+
+/// An intro animation and the lighting to settle into after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Startup {
+    /// Animation to play first, as `demo:<name>`.
+    pub intro: Option<String>,
+    /// How long the intro plays.
+    pub intro_for: Duration,
+    /// Profile path or `theme:name` applied once the intro is over.
+    pub profile: Option<String>,
+}
+
+/// What an interrupted animation leaves behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnInterrupt {
+    /// The lighting from before the animation started.
+    Restore,
+    /// Whatever frame was last drawn.
+    Keep,
+    /// A profile path or `theme:name`.
+    Apply(String),
+}
+
+/// How long the intro plays when `intro_for` is unset.
+const DEFAULT_INTRO: Duration = Duration::from_secs(3);
+
+impl Config {
+    /// Default location of the config file, if a home directory is known.
+    pub fn path() -> Option<PathBuf> {
+        paths::config_file()
+    }
+
+    /// Read the config file; a missing file is an empty config.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let text =
+            std::fs::read_to_string(&path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        Self::from_toml(&text, &path.display().to_string())
+    }
+
+    /// Parse config text; `origin` names it in error messages.
+    pub fn from_toml(text: &str, origin: &str) -> Result<Self> {
+        parse_toml(text, origin)
+    }
+
+    /// Resolve `[extra_devices]` into VID/PID/model triples.
+    pub fn extra_devices(&self) -> Result<Vec<(u16, u16, KeyboardModel)>> {
+        self.extra_devices
+            .iter()
+            .map(|(id, model)| {
+                let (vid, pid) = id
+                    .split_once(':')
+                    .and_then(|(vid, pid)| Some((parse_hex_id(vid)?, parse_hex_id(pid)?)))
+                    .ok_or_else(|| {
+                        anyhow!("extra_devices: invalid device id {id:?}, expected \"046d:c343\"")
+                    })?;
+                let model = model
+                    .parse()
+                    .map_err(|_| anyhow!("extra_devices: unknown model {model:?} for {id}"))?;
+                Ok((vid, pid, model))
+            })
+            .collect()
+    }
+
+    /// Resolve `[rate_limits.<model>]`; a field left out keeps the model's
+    /// built-in value, or no limit if it has none.
+    pub fn rate_limits(&self) -> Result<Vec<(KeyboardModel, RateLimit)>> {
+        self.rate_limits
+            .iter()
+            .map(|(model_name, table)| {
+                let model: KeyboardModel = model_name
+                    .parse()
+                    .map_err(|_| anyhow!("rate_limits: unknown model {model_name:?}"))?;
+                let builtin = model.spec().rate_limit.unwrap_or_default();
+                let min_commit_gap = match table.min_commit_gap.as_deref() {
+                    Some(value) => parse_duration(value).ok_or_else(|| {
+                        anyhow!(
+                            "rate_limits.{model_name}: invalid min_commit_gap {value:?}, \
+                             expected e.g. \"40ms\""
+                        )
+                    })?,
+                    None => builtin.min_commit_gap,
+                };
+                let limit = RateLimit {
+                    packets_per_sec: table.packets_per_sec.unwrap_or(builtin.packets_per_sec),
+                    min_commit_gap,
+                };
+                Ok((model, limit))
+            })
+            .collect()
+    }
+
+    /// Resolve `[aliases]` into names and the keys they stand for.
+    pub fn key_aliases(&self) -> Result<Vec<(String, Key)>> {
+        self.aliases
+            .iter()
+            .map(|(name, key)| {
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    bail!("aliases: {name:?} cannot be empty or contain spaces");
+                }
+                if parse_key(name).is_ok() {
+                    bail!("aliases: {name:?} is already a key name");
+                }
+                let key = parse_key(key).map_err(|e| {
+                    let hint = e
+                        .suggestion
+                        .map(|s| format!(", did you mean `{s}`?"))
+                        .unwrap_or_default();
+                    anyhow!("aliases: unknown key {key:?} for {name}{hint}")
+                })?;
+                Ok((name.clone(), key))
+            })
+            .collect()
+    }
+
+    /// Resolve the `[schedule.<days>]` tables.
+    pub fn schedule(&self) -> Result<WeeklySchedule> {
+        self.schedule
+            .iter()
+            .map(|(days, table)| {
+                let days: ScheduleDays = days.parse().map_err(|_| {
+                    anyhow!(
+                        "schedule: unknown day {days:?}, expected a weekday, \"weekdays\", \
+                         \"weekends\" or \"daily\""
+                    )
+                })?;
+                let table = table
+                    .iter()
+                    .map(|(time, target)| {
+                        let time = parse_time_of_day(time).ok_or_else(|| {
+                            anyhow!("schedule.{days}: invalid time {time:?}, expected \"HH:MM\"")
+                        })?;
+                        Ok((time, target.clone()))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok((days, table))
+            })
+            .collect::<Result<_>>()
+            .map(WeeklySchedule::new)
+    }
+}
+
+impl Config {
+    /// Snapshots to keep, [`DEFAULT_LIMIT`](history::DEFAULT_LIMIT) when unset.
+    pub fn history_limit(&self) -> usize {
+        self.history_limit.unwrap_or(history::DEFAULT_LIMIT)
+    }
+
+    /// Animation pacing for `tick`, slowed to `max_fps` and adaptive as
+    /// configured.
+    pub fn pacing(&self, tick: Duration) -> Result<Pacing> {
+        if self.max_fps == Some(0) {
+            bail!("max_fps: must be at least 1");
+        }
+        Ok(Pacing::capped(tick, self.max_fps, self.adaptive_fps))
+    }
+
+    /// `on_interrupt`: `"restore"` (the default), `"keep"`, or a profile path
+    /// or `theme:name` to apply.
+    pub fn on_interrupt(&self) -> OnInterrupt {
+        match self.on_interrupt.as_deref() {
+            None | Some("restore") => OnInterrupt::Restore,
+            Some("keep") => OnInterrupt::Keep,
+            Some(target) => OnInterrupt::Apply(target.to_owned()),
+        }
+    }
+
+    /// Resolve `[startup]`; `None` when the table is absent.
+    pub fn startup(&self) -> Result<Option<Startup>> {
+        let Some(table) = &self.startup else {
+            return Ok(None);
+        };
+        if table.intro.is_none() && table.profile.is_none() {
+            bail!("startup: set an intro, a profile or both");
+        }
+        let intro_for = match table.intro_for.as_deref() {
+            Some(value) => parse_duration(value).ok_or_else(|| {
+                anyhow!("startup: invalid intro_for {value:?}, expected e.g. \"3s\"")
+            })?,
+            None => DEFAULT_INTRO,
+        };
+        Ok(Some(Startup {
+            intro: table.intro.clone(),
+            intro_for,
+            profile: table.profile.clone(),
+        }))
+    }
+
+    /// Resolve `[mode_indicator]`; the keys default to `esc`.
+    pub fn mode_indicator(&self) -> Result<ModeIndicator> {
+        let Some(table) = self
+            .mode_indicator
+            .as_ref()
+            .filter(|t| !t.colors.is_empty())
+        else {
+            bail!("no [mode_indicator.colors] in the config, e.g. insert = \"green\"");
+        };
+        let keys = match &table.keys {
+            Some(keys) => keys
+                .iter()
+                .map(|key| parse_key(key).map_err(|e| anyhow!("mode_indicator.keys: {e}")))
+                .collect::<Result<_>>()?,
+            None => vec![Key::Esc],
+        };
+        let colors = table
+            .colors
+            .iter()
+            .map(|(mode, color)| {
+                let color = parse_color(color).ok_or_else(|| {
+                    anyhow!("mode_indicator.colors: invalid color {color:?} for {mode}")
+                })?;
+                Ok((mode.to_lowercase(), color))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ModeIndicator { keys, colors })
+    }
+
+    /// Names of the `[scenes]`, in order.
+    pub fn scene_names(&self) -> impl Iterator<Item = &str> {
+        self.scenes.keys().map(String::as_str)
+    }
+
+    /// Resolve `[scenes.<name>]`.
+    pub fn scene(&self, name: &str) -> Result<Scene> {
+        let Some(table) = self.scenes.get(name) else {
+            let names: Vec<&str> = self.scene_names().collect();
+            if names.is_empty() {
+                bail!("no scene {name:?}; there are no [scenes] in the config");
+            }
+            bail!("no scene {name:?}; the config has {}", names.join(", "));
+        };
+        if table.profile.is_none() && table.animation.is_none() && table.overlay.is_empty() {
+            bail!("scenes.{name}: set a profile, an animation, an overlay or a mix");
+        }
+        let overlay = table
+            .overlay
+            .iter()
+            .map(|(key, color)| {
+                let key = parse_key(key).map_err(|e| anyhow!("scenes.{name}.overlay: {e}"))?;
+                let color = parse_color(color).ok_or_else(|| {
+                    anyhow!("scenes.{name}.overlay: invalid color {color:?} for {key:?}")
+                })?;
+                Ok(KeyValue { key, color })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Scene {
+            profile: table.profile.clone(),
+            animation: table.animation.clone(),
+            overlay,
+        })
+    }
+
+    /// The `remote_token` both ends of a `--remote` connection must share.
+    pub fn remote_token(&self) -> Result<&str> {
+        match self.remote_token.as_deref() {
+            None => {
+                bail!("remote_token is not set; add the same one to the config on both machines")
+            }
+            Some(token) if token.chars().count() < 16 || token.contains(char::is_whitespace) => {
+                bail!("remote_token: use at least 16 characters and no spaces")
+            }
+            Some(token) => Ok(token),
+        }
+    }
+
+    /// The configured `default_color`, or white when unset.
+    pub fn default_color(&self) -> Result<Color> {
+        self.default_color
+            .as_deref()
+            .map_or(Ok(Color::default()), |value| {
+                parse_color(value).ok_or_else(|| anyhow!("default_color: invalid color {value:?}"))
+            })
+    }
+}
+
+/// USB IDs are conventionally written in bare hex, as `lsusb` prints them.
+fn parse_hex_id(s: &str) -> Option<u16> {
+    let s = s.trim();
+    u16::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extra_devices_resolve() {
+        let config = Config::from_toml(
+            "[extra_devices]\n\"046d:c343\" = \"gpro\"\n\"0x046d:0xc33e\" = \"G512\"\n",
+            "config.toml",
+        )
+        .unwrap();
+        assert_eq!(
+            config.extra_devices().unwrap(),
+            vec![
+                (0x046d, 0xc343, KeyboardModel::GPro),
+                (0x046d, 0xc33e, KeyboardModel::G512),
+            ]
+        );
+    }
+
+    #[test]
+    fn rate_limits_override_the_builtin_ones() {
+        let config = Config::from_toml(
+            "[rate_limits.g610]\npackets_per_sec = 60\n\
+             [rate_limits.g910]\nmin_commit_gap = \"50ms\"\n",
+            "config.toml",
+        )
+        .unwrap();
+        assert_eq!(
+            config.rate_limits().unwrap(),
+            vec![
+                (
+                    KeyboardModel::G610,
+                    RateLimit {
+                        packets_per_sec: 60,
+                        min_commit_gap: Duration::from_millis(33),
+                    }
+                ),
+                (
+                    KeyboardModel::G910,
+                    RateLimit {
+                        packets_per_sec: 0,
+                        min_commit_gap: Duration::from_millis(50),
+                    }
+                ),
+            ]
+        );
+        let config = Config::from_toml("[rate_limits.g999]\n", "config.toml").unwrap();
+        assert!(config.rate_limits().is_err());
+    }
+
+    #[test]
+    fn default_color_falls_back_to_white() {
+        assert_eq!(Config::default().default_color().unwrap(), Color::default());
+
+        let config = Config::from_toml("default_color = \"ffe0c0\"\n", "c").unwrap();
+        assert_eq!(
+            config.default_color().unwrap(),
+            Color::new(0xff, 0xe0, 0xc0)
+        );
+
+        let config = Config::from_toml("default_color = \"beige-ish\"\n", "c").unwrap();
+        assert!(config.default_color().is_err());
+    }
+
+    #[test]
+    fn max_fps_caps_the_tick() {
+        let tick = Duration::from_millis(50);
+        assert_eq!(Config::default().pacing(tick).unwrap(), Pacing::fixed(tick));
+
+        let config = Config::from_toml(
+            "max_fps = 10
+adaptive_fps = true
+",
+            "c",
+        )
+        .unwrap();
+        let pacing = config.pacing(tick).unwrap();
+        assert_eq!(pacing.tick, Duration::from_millis(100));
+        assert!(pacing.adaptive);
+
+        let config = Config::from_toml(
+            "max_fps = 0
+",
+            "c",
+        )
+        .unwrap();
+        assert!(config.pacing(tick).is_err());
+    }
+
+    #[test]
+    fn interrupted_animations_restore_by_default() {
+        assert_eq!(Config::default().on_interrupt(), OnInterrupt::Restore);
+        let config = Config::from_toml("on_interrupt = \"keep\"\n", "c").unwrap();
+        assert_eq!(config.on_interrupt(), OnInterrupt::Keep);
+        let config = Config::from_toml("on_interrupt = \"theme:night\"\n", "c").unwrap();
+        assert_eq!(
+            config.on_interrupt(),
+            OnInterrupt::Apply("theme:night".to_owned())
+        );
+    }
+
+    #[test]
+    fn remote_tokens_must_be_long() {
+        assert!(Config::default().remote_token().is_err());
+        let config = Config::from_toml("remote_token = \"short\"\n", "c").unwrap();
+        assert!(config.remote_token().is_err());
+        let config = Config::from_toml("remote_token = \"0123456789abcdef\"\n", "c").unwrap();
+        assert_eq!(config.remote_token().unwrap(), "0123456789abcdef");
+    }
+
+    #[test]
+    fn startup_defaults_the_intro_length() {
+        assert_eq!(Config::default().startup().unwrap(), None);
+        let config = Config::from_toml(
+            "[startup]\nintro = \"demo:wave\"\nprofile = \"theme:nord\"\n",
+            "c",
+        )
+        .unwrap();
+        assert_eq!(
+            config.startup().unwrap(),
+            Some(Startup {
+                intro: Some("demo:wave".to_owned()),
+                intro_for: Duration::from_secs(3),
+                profile: Some("theme:nord".to_owned()),
+            })
+        );
+        let config = Config::from_toml("[startup]\n", "c").unwrap();
+        assert!(config.startup().is_err());
+        let config = Config::from_toml(
+            "[startup]\nintro = \"demo:wave\"\nintro_for = \"soon\"\n",
+            "c",
+        )
+        .unwrap();
+        assert!(config.startup().is_err());
+    }
+
+    #[test]
+    fn mode_indicator_resolves_keys_and_colors() {
+        let config = Config::from_toml(
+            "[mode_indicator]\nkeys = [\"esc\", \"logo\"]\n\
+             [mode_indicator.colors]\nInsert = \"green\"\nnormal = \"0000ff\"\n",
+            "c",
+        )
+        .unwrap();
+        let indicator = config.mode_indicator().unwrap();
+        assert_eq!(indicator.keys, [Key::Esc, Key::Logo]);
+        assert_eq!(
+            indicator.colors.get("insert"),
+            Some(&Color::new(0, 0xff, 0))
+        );
+
+        let err = |toml: &str| {
+            let config = Config::from_toml(toml, "c").unwrap();
+            config.mode_indicator().unwrap_err().to_string()
+        };
+        assert!(err("").contains("no [mode_indicator.colors]"));
+        assert!(err("[mode_indicator.colors]\ninsert = \"grean\"\n").contains("invalid color"));
+        assert!(
+            err("[mode_indicator]\nkeys = [\"escc\"]\n[mode_indicator.colors]\na = \"red\"\n")
+                .contains("mode_indicator.keys")
+        );
+    }
+
+    #[test]
+    fn scenes_resolve_their_overlay() {
+        let config = Config::from_toml(
+            "[scenes.focus]\nprofile = \"theme:nord\"\n\
+             [scenes.focus.overlay]\nesc = \"red\"\n\
+             [scenes.empty]\n",
+            "c",
+        )
+        .unwrap();
+        assert_eq!(config.scene_names().collect::<Vec<_>>(), ["empty", "focus"]);
+        assert_eq!(
+            config.scene("focus").unwrap(),
+            Scene {
+                profile: Some("theme:nord".to_owned()),
+                animation: None,
+                overlay: vec![KeyValue {
+                    key: Key::Esc,
+                    color: Color::new(0xff, 0, 0),
+                }],
+            }
+        );
+        assert!(config.scene("empty").is_err());
+        let err = config.scene("fcous").unwrap_err().to_string();
+        assert!(err.contains("empty, focus"));
+    }
+
+    #[test]
+    fn bad_entries_are_reported() {
+        let config = Config::from_toml("[extra_devices]\n\"c343\" = \"gpro\"\n", "c").unwrap();
+        assert!(config.extra_devices().is_err());
+
+        let config = Config::from_toml("[extra_devices]\n\"046d:c343\" = \"g999\"\n", "c").unwrap();
+        let err = config.extra_devices().unwrap_err();
+        assert!(err.to_string().contains("unknown model"));
+    }
+
+    #[test]
+    fn aliases_resolve_to_keys() {
+        let config = Config::from_toml(
+            "[aliases]\nptt = \"g5\"\npush-to-talk = \"Scroll_Lock\"\n",
+            "c",
+        )
+        .unwrap();
+        assert_eq!(
+            config.key_aliases().unwrap(),
+            [
+                ("ptt".to_owned(), Key::G5),
+                ("push-to-talk".to_owned(), Key::ScrollLock)
+            ]
+        );
+
+        let err = |toml: &str| {
+            let config = Config::from_toml(toml, "c").unwrap();
+            config.key_aliases().unwrap_err().to_string()
+        };
+        assert!(err("[aliases]\nesc = \"g1\"\n").contains("already a key name"));
+        assert!(err("[aliases]\nptt = \"g99\"\n").contains("unknown key"));
+        assert!(err("[aliases]\nptt = \"g99\"\n").contains("did you mean `g9`?"));
+        assert!(err("[aliases]\n\"push to talk\" = \"g1\"\n").contains("spaces"));
+    }
+
+    #[test]
+    fn schedule_tables_resolve() {
+        let config = Config::from_toml(
+            "[schedule.Monday]\n\"09:00\" = \"work.toml\"\n\
+             [schedule.weekends]\n\"10:30\" = \"theme:nord:light\"\n",
+            "c",
+        )
+        .unwrap();
+        let schedule = config.schedule().unwrap();
+        assert_eq!(
+            schedule.day(chrono::Weekday::Mon),
+            [(parse_time_of_day("09:00").unwrap(), "work.toml")]
+        );
+        assert_eq!(schedule.day(chrono::Weekday::Sun).len(), 1);
+
+        let config = Config::from_toml("[schedule.someday]\n\"09:00\" = \"a\"\n", "c").unwrap();
+        assert!(
+            config
+                .schedule()
+                .unwrap_err()
+                .to_string()
+                .contains("unknown day")
+        );
+        let config = Config::from_toml("[schedule.daily]\n\"9am\" = \"a\"\n", "c").unwrap();
+        assert!(
+            config
+                .schedule()
+                .unwrap_err()
+                .to_string()
+                .contains("invalid time")
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Helpers that turn parse failures into messages users can act on.
+
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+
+/// Edit distance between two strings, counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// The candidate closest to `input` (ignoring ASCII case), if any is within `max_distance`.
+pub fn closest<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    max_distance: usize,
+) -> Option<&'a str> {
+    let input = input.to_ascii_lowercase();
+    candidates
+        .into_iter()
+        .map(|c| (levenshtein(&input, &c.to_ascii_lowercase()), c))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, c)| c)
+}
+
+/// Deserialize TOML, prefixing errors with `origin` and suggesting the
+/// intended field name when an unknown one looks like a typo.
+pub fn parse_toml<T: DeserializeOwned>(text: &str, origin: &str) -> Result<T> {
+    toml::from_str(text).map_err(|e| {
+        let hint = unknown_field_hint(e.message())
+            .map(|hint| format!("help: {hint}\n"))
+            .unwrap_or_default();
+        anyhow!("{origin}: {e}{hint}")
+    })
+}
+
+/// serde reports typos as "unknown field `x`, expected one of `a`, `b`" or
+/// "expected `a` or `b`"; pull the names out and pick the nearest one.
+fn unknown_field_hint(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("unknown field ")?;
+    let mut names = rest.split('`').skip(1).step_by(2);
+    let field = names.next()?;
+    let suggestion = closest(field, names, 3)?;
+    Some(format!("did you mean `{suggestion}`?"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Sample {
+        all: Option<String>,
+        gkeys_mode: Option<u8>,
+    }
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("pgdwn", "pgdn"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("ÿes", "yes"), 1);
+    }
+
+    #[test]
+    fn closest_respects_max_distance() {
+        let names = ["gkeys_mode", "startup_mode", "all"];
+        assert_eq!(closest("GKEYS_MOD", names, 2), Some("gkeys_mode"));
+        assert_eq!(closest("completely_wrong", names, 2), None);
+    }
+
+    #[test]
+    fn unknown_field_gets_suggestion() {
+        let err = parse_toml::<Sample>("gkeys_mod = 1\n", "test.toml").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.starts_with("test.toml: "));
+        assert!(msg.contains("line 1"));
+        assert!(msg.contains("did you mean `gkeys_mode`?"));
+    }
+}
@@ -0,0 +1,477 @@
+//! An emulated G810 on a Linux USB gadget, for end-to-end tests of the real
+//! backends.
+//!
+//! [`Gadget::start`] binds a G810 look-alike to the `dummy_hcd` loopback
+//! controller through raw-gadget, so the kernel enumerates it like a plugged-in
+//! keyboard and both hidapi (through hidraw) and libusb talk to it over a real
+//! USB stack. Every `SET_REPORT` the LED interface receives is recorded.
+//!
+//! Needs the `dummy_hcd` and `raw_gadget` modules and write access to
+//! `/dev/raw-gadget` (normally root), so the end-to-end test is ignored
+//! unless asked for:
+//!
+//! ```text
+//! sudo modprobe dummy_hcd
+//! sudo modprobe raw_gadget
+//! sudo -E cargo test --features gadget-test gadget -- --ignored
+//! sudo -E cargo test --features gadget-test,libusb gadget -- --ignored
+//! ```
+
+use anyhow::{Context, Result, anyhow};
+use core::time::Duration;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+use crate::keyboard::device::Keyboard;
+
+const RAW_GADGET: &str = "/dev/raw-gadget";
+const UDC_DRIVER: &str = "dummy_udc";
+const UDC_DEVICE: &str = "dummy_udc.0";
+const USB_SPEED_HIGH: u8 = 3;
+
+/// `struct usb_raw_init`.
+#[repr(C)]
+struct Init {
+    driver_name: [u8; 128],
+    device_name: [u8; 128],
+    speed: u8,
+}
+
+/// Header of `struct usb_raw_event`.
+#[repr(C)]
+struct EventHeader {
+    kind: u32,
+    length: u32,
+}
+
+/// Header of `struct usb_raw_ep_io`.
+#[repr(C)]
+struct EpIoHeader {
+    ep: u16,
+    flags: u16,
+    length: u32,
+}
+
+/// An event with room for the 8-byte setup packet of a control request.
+#[repr(C)]
+struct Event {
+    header: EventHeader,
+    data: [u8; 8],
+}
+
+/// An ep0 transfer with room for the largest descriptor or report.
+#[repr(C)]
+struct EpIo {
+    header: EpIoHeader,
+    data: [u8; 256],
+}
+
+const IOCTL_TYPE: u32 = 0x55;
+const IOCTL_INIT: u32 = libc::_IOW::<Init>(IOCTL_TYPE, 0);
+const IOCTL_RUN: u32 = libc::_IO(IOCTL_TYPE, 1);
+const IOCTL_EVENT_FETCH: u32 = libc::_IOR::<EventHeader>(IOCTL_TYPE, 2);
+const IOCTL_EP0_WRITE: u32 = libc::_IOW::<EpIoHeader>(IOCTL_TYPE, 3);
+const IOCTL_EP0_READ: u32 = libc::_IOWR::<EpIoHeader>(IOCTL_TYPE, 4);
+const IOCTL_EP_ENABLE: u32 = libc::_IOW::<[u8; 9]>(IOCTL_TYPE, 5);
+const IOCTL_CONFIGURE: u32 = libc::_IO(IOCTL_TYPE, 9);
+const IOCTL_VBUS_DRAW: u32 = libc::_IOW::<u32>(IOCTL_TYPE, 10);
+const IOCTL_EP0_STALL: u32 = libc::_IO(IOCTL_TYPE, 12);
+
+const EVENT_CONTROL: u32 = 2;
+
+const VENDOR_ID: u16 = 0x046d;
+/// Product ID the emulated board enumerates with, a G810.
+pub const PRODUCT_ID: u16 = 0xc331;
+/// Serial number of the emulated board, to pick it over a real keyboard.
+pub const SERIAL: &str = "LOGI-LED-GADGET";
+
+const VENDOR: [u8; 2] = VENDOR_ID.to_le_bytes();
+const PRODUCT: [u8; 2] = PRODUCT_ID.to_le_bytes();
+
+const DEVICE_DESCRIPTOR: [u8; 18] = [
+    18, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 64, VENDOR[0], VENDOR[1], PRODUCT[0], PRODUCT[1], 0x01,
+    0x24, 1, 2, 3, 1,
+];
+
+/// Boot keyboard on interface 0, HID++ on interface 1, as on the real board.
+const CONFIG_DESCRIPTOR: [u8; 59] = [
+    9, 0x02, 59, 0, 2, 1, 0, 0xa0, 250, //
+    // Interface 0: boot keyboard.
+    9, 0x04, 0, 0, 1, 0x03, 0x01, 0x01, 0, //
+    9, 0x21, 0x11, 0x01, 0, 1, 0x22, 45, 0, //
+    7, 0x05, 0x81, 0x03, 8, 0, 8, //
+    // Interface 1: vendor-defined HID++ reports.
+    9, 0x04, 1, 0, 1, 0x03, 0x00, 0x00, 0, //
+    9, 0x21, 0x11, 0x01, 0, 1, 0x22, 54, 0, //
+    7, 0x05, 0x82, 0x03, 64, 0, 1,
+];
+
+const KEYBOARD_ENDPOINT: [u8; 9] = [7, 0x05, 0x81, 0x03, 8, 0, 8, 0, 0];
+const HIDPP_ENDPOINT: [u8; 9] = [7, 0x05, 0x82, 0x03, 64, 0, 1, 0, 0];
+
+const KEYBOARD_REPORT: [u8; 45] = [
+    0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25, 0x01,
+    0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x06, 0x75, 0x08,
+    0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xc0,
+];
+
+/// Long (0x11, 20 bytes) and very long (0x12, 64 bytes) HID++ reports.
+const HIDPP_REPORT: [u8; 54] = [
+    0x06, 0x43, 0xff, 0x0a, 0x02, 0x06, 0xa1, 0x01, 0x85, 0x11, 0x75, 0x08, 0x95, 0x13, 0x15, 0x00,
+    0x26, 0xff, 0x00, 0x09, 0x02, 0x81, 0x00, 0x09, 0x02, 0x91, 0x00, 0xc0, //
+    0x06, 0x43, 0xff, 0x0a, 0x04, 0x06, 0xa1, 0x01, 0x85, 0x12, 0x75, 0x08, 0x95, 0x3f, 0x26, 0xff,
+    0x00, 0x09, 0x04, 0x81, 0x00, 0x09, 0x04, 0x91, 0x00, 0xc0,
+];
+
+/// String descriptor `index`, encoded as UTF-16LE.
+fn string_descriptor(index: u8) -> Option<Vec<u8>> {
+    let text = match index {
+        0 => return Some(vec![4, 0x03, 0x09, 0x04]),
+        1 => "Logitech",
+        2 => "G810 Orion Spectrum Tactile Gaming Keyboard",
+        3 => SERIAL,
+        _ => return None,
+    };
+    let mut descriptor = vec![0, 0x03];
+    descriptor.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    descriptor[0] = u8::try_from(descriptor.len()).ok()?;
+    Some(descriptor)
+}
+
+/// A control request as delivered to ep0.
+#[derive(Debug, Clone, Copy)]
+struct Setup {
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+}
+
+impl Setup {
+    fn parse(data: [u8; 8]) -> Self {
+        Self {
+            request_type: data[0],
+            request: data[1],
+            value: u16::from_le_bytes([data[2], data[3]]),
+            index: u16::from_le_bytes([data[4], data[5]]),
+            length: u16::from_le_bytes([data[6], data[7]]),
+        }
+    }
+}
+
+/// What the host should get back for a control request.
+#[derive(Debug, PartialEq, Eq)]
+enum Reply {
+    /// Send this data stage.
+    Data(Vec<u8>),
+    /// Read and drop the data stage, if any, to acknowledge the request.
+    Ack,
+    /// Read the data stage and record it as an LED report.
+    Report,
+    /// Select the configuration, enabling the interrupt endpoints.
+    Configure,
+    Stall,
+}
+
+fn reply(setup: Setup) -> Reply {
+    let [index, kind] = setup.value.to_le_bytes();
+    match (setup.request_type, setup.request) {
+        // GET_DESCRIPTOR
+        (0x80, 0x06) => match kind {
+            0x01 => Reply::Data(DEVICE_DESCRIPTOR.to_vec()),
+            0x02 => Reply::Data(CONFIG_DESCRIPTOR.to_vec()),
+            0x03 => string_descriptor(index).map_or(Reply::Stall, Reply::Data),
+            _ => Reply::Stall,
+        },
+        // GET_DESCRIPTOR (HID report) on an interface
+        (0x81, 0x06) if kind == 0x22 => match setup.index {
+            0 => Reply::Data(KEYBOARD_REPORT.to_vec()),
+            1 => Reply::Data(HIDPP_REPORT.to_vec()),
+            _ => Reply::Stall,
+        },
+        // SET_CONFIGURATION
+        (0x00, 0x09) => Reply::Configure,
+        // SET_REPORT on the LED interface
+        (0x21, 0x09) if setup.index == 1 => Reply::Report,
+        // SET_INTERFACE; SET_REPORT (keyboard LEDs), SET_IDLE, SET_PROTOCOL
+        (0x01, 0x0b) | (0x21, 0x09..=0x0b) => Reply::Ack,
+        _ => Reply::Stall,
+    }
+}
+
+/// Ioctl is `c_ulong` on glibc and `c_int` on musl; only the bits matter.
+#[allow(clippy::cast_lossless, clippy::cast_possible_wrap)]
+const fn ioctl_request(request: u32) -> libc::Ioctl {
+    request as libc::Ioctl
+}
+
+fn ioctl_result(ret: libc::c_int) -> io::Result<usize> {
+    usize::try_from(ret).map_err(|_| io::Error::last_os_error())
+}
+
+/// An open `/dev/raw-gadget`.
+struct RawGadget(File);
+
+impl RawGadget {
+    fn open() -> Result<Self> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(RAW_GADGET)
+            .map(Self)
+            .with_context(|| {
+                format!(
+                    "could not open {RAW_GADGET}; load dummy_hcd and raw_gadget and run as root"
+                )
+            })
+    }
+
+    /// An ioctl whose argument is a plain value.
+    fn ioctl(&self, request: u32, value: libc::c_ulong) -> io::Result<usize> {
+        // SAFETY: raw-gadget reads these arguments as values, not pointers.
+        let ret = unsafe { libc::ioctl(self.0.as_raw_fd(), ioctl_request(request), value) };
+        ioctl_result(ret)
+    }
+
+    /// An ioctl that reads or fills `arg`, whose size `request` encodes.
+    fn ioctl_with<T>(&self, request: u32, arg: &mut T) -> io::Result<usize> {
+        let arg = core::ptr::from_mut(arg);
+        // SAFETY: `arg` is a live, exclusively borrowed buffer of the size in `request`.
+        let ret = unsafe { libc::ioctl(self.0.as_raw_fd(), ioctl_request(request), arg) };
+        ioctl_result(ret)
+    }
+
+    fn init(&self) -> io::Result<()> {
+        let mut init = Init {
+            driver_name: [0; 128],
+            device_name: [0; 128],
+            speed: USB_SPEED_HIGH,
+        };
+        init.driver_name[..UDC_DRIVER.len()].copy_from_slice(UDC_DRIVER.as_bytes());
+        init.device_name[..UDC_DEVICE.len()].copy_from_slice(UDC_DEVICE.as_bytes());
+        self.ioctl_with(IOCTL_INIT, &mut init)?;
+        self.ioctl(IOCTL_RUN, 0).map(drop)
+    }
+
+    /// Wait for the next event; `Some` setup packet for control requests.
+    fn next_control(&self) -> io::Result<Option<Setup>> {
+        let mut event = Event {
+            header: EventHeader { kind: 0, length: 8 },
+            data: [0; 8],
+        };
+        self.ioctl_with(IOCTL_EVENT_FETCH, &mut event)?;
+        Ok((event.header.kind == EVENT_CONTROL).then(|| Setup::parse(event.data)))
+    }
+
+    fn ep0_write(&self, data: &[u8]) -> io::Result<()> {
+        let mut io = EpIo {
+            header: EpIoHeader {
+                ep: 0,
+                flags: 0,
+                length: 0,
+            },
+            data: [0; 256],
+        };
+        let len = data.len().min(io.data.len());
+        io.data[..len].copy_from_slice(&data[..len]);
+        io.header.length = u32::try_from(len).unwrap_or(0);
+        self.ioctl_with(IOCTL_EP0_WRITE, &mut io).map(drop)
+    }
+
+    fn ep0_read(&self, length: u16) -> io::Result<Vec<u8>> {
+        let mut io = EpIo {
+            header: EpIoHeader {
+                ep: 0,
+                flags: 0,
+                length: u32::from(length).min(256),
+            },
+            data: [0; 256],
+        };
+        let read = self.ioctl_with(IOCTL_EP0_READ, &mut io)?;
+        Ok(io.data[..read.min(io.data.len())].to_vec())
+    }
+
+    fn configure(&self) -> io::Result<()> {
+        for endpoint in [KEYBOARD_ENDPOINT, HIDPP_ENDPOINT] {
+            let mut descriptor = endpoint;
+            self.ioctl_with(IOCTL_EP_ENABLE, &mut descriptor)?;
+        }
+        self.ioctl(IOCTL_VBUS_DRAW, 500)?;
+        self.ioctl(IOCTL_CONFIGURE, 0).map(drop)
+    }
+
+    fn stall(&self) -> io::Result<()> {
+        self.ioctl(IOCTL_EP0_STALL, 0).map(drop)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    configured: bool,
+    reports: Vec<Vec<u8>>,
+    error: Option<String>,
+}
+
+/// A running emulated G810.
+///
+/// The device stays attached until the process exits.
+pub struct Gadget {
+    state: Arc<Mutex<State>>,
+}
+
+impl Gadget {
+    /// Attach the emulated keyboard and start answering the host.
+    pub fn start() -> Result<Self> {
+        let raw = RawGadget::open()?;
+        raw.init()
+            .with_context(|| format!("could not bind to {UDC_DEVICE}"))?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(e) = serve(&raw, &shared) {
+                lock(&shared).error = Some(e.to_string());
+            }
+        });
+        Ok(Self { state })
+    }
+
+    /// Open the emulated keyboard through the compiled-in backend, once the
+    /// host has enumerated it.
+    pub fn open(&self, timeout: Duration) -> Result<Keyboard> {
+        let start = Instant::now();
+        loop {
+            self.check()?;
+            let configured = lock(&self.state).configured;
+            if configured && let Ok(kbd) = Keyboard::open(VENDOR_ID, PRODUCT_ID, Some(SERIAL)) {
+                return Ok(kbd);
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!("emulated G810 did not show up within {timeout:?}"));
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Wait until at least `count` LED reports arrived, and return all of them.
+    pub fn wait_for_reports(&self, count: usize, timeout: Duration) -> Result<Vec<Vec<u8>>> {
+        let start = Instant::now();
+        loop {
+            self.check()?;
+            let reports = lock(&self.state).reports.clone();
+            if reports.len() >= count {
+                return Ok(reports);
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!(
+                    "got {} of {count} reports within {timeout:?}",
+                    reports.len()
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        match &lock(&self.state).error {
+            Some(e) => Err(anyhow!("gadget stopped: {e}")),
+            None => Ok(()),
+        }
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Answer control requests until the gadget goes away.
+fn serve(raw: &RawGadget, state: &Mutex<State>) -> io::Result<()> {
+    loop {
+        let Some(setup) = raw.next_control()? else {
+            continue;
+        };
+        match reply(setup) {
+            Reply::Data(data) => {
+                let len = data.len().min(usize::from(setup.length));
+                raw.ep0_write(&data[..len])?;
+            }
+            Reply::Ack => {
+                raw.ep0_read(setup.length)?;
+            }
+            Reply::Report => {
+                let report = raw.ep0_read(setup.length)?;
+                lock(state).reports.push(report);
+            }
+            Reply::Configure => {
+                raw.configure()?;
+                raw.ep0_read(0)?;
+                lock(state).configured = true;
+            }
+            Reply::Stall => raw.stall()?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::api::KeyboardApi;
+    use crate::keyboard::{Color, KeyboardModel, packet::commit_packet};
+
+    #[test]
+    fn ioctl_numbers_match_the_kernel_header() {
+        assert_eq!(IOCTL_INIT, 0x4101_5500);
+        assert_eq!(IOCTL_EVENT_FETCH, 0x8008_5502);
+        assert_eq!(IOCTL_EP0_READ, 0xc008_5504);
+        assert_eq!(IOCTL_EP_ENABLE, 0x4009_5505);
+        assert_eq!(IOCTL_VBUS_DRAW, 0x4004_550a);
+    }
+
+    #[test]
+    fn descriptors_are_consistent() {
+        assert_eq!(usize::from(CONFIG_DESCRIPTOR[2]), CONFIG_DESCRIPTOR.len());
+        assert_eq!(CONFIG_DESCRIPTOR[25], 45);
+        assert_eq!(KEYBOARD_REPORT.len(), 45);
+        assert_eq!(CONFIG_DESCRIPTOR[50], 54);
+        assert_eq!(HIDPP_REPORT.len(), 54);
+        assert_eq!(CONFIG_DESCRIPTOR[27..34], KEYBOARD_ENDPOINT[..7]);
+        assert_eq!(CONFIG_DESCRIPTOR[52..], HIDPP_ENDPOINT[..7]);
+        let serial = string_descriptor(3).unwrap();
+        assert_eq!(usize::from(serial[0]), 2 + 2 * SERIAL.len());
+    }
+
+    #[test]
+    fn lighting_reports_are_recorded() {
+        let setup = |request_type, request, index| Setup {
+            request_type,
+            request,
+            value: 0x0211,
+            index,
+            length: 20,
+        };
+        assert_eq!(reply(setup(0x21, 0x09, 1)), Reply::Report);
+        assert_eq!(reply(setup(0x21, 0x09, 0)), Reply::Ack);
+        assert_eq!(reply(setup(0xa1, 0x01, 1)), Reply::Stall);
+    }
+
+    #[test]
+    #[ignore = "needs root and raw-gadget"]
+    fn backend_drives_the_emulated_g810() {
+        let gadget = Gadget::start().unwrap();
+        let mut kbd = gadget.open(Duration::from_secs(10)).unwrap();
+        assert_eq!(kbd.model(), KeyboardModel::G810);
+
+        kbd.set_all_keys(Color::new(0xff, 0, 0)).unwrap();
+        kbd.commit().unwrap();
+
+        let reports = gadget.wait_for_reports(2, Duration::from_secs(5)).unwrap();
+        let commit = commit_packet(KeyboardModel::G810).unwrap();
+        let last = reports.last().unwrap();
+        assert_eq!(last[..commit.len()], commit[..]);
+        assert!(reports.iter().any(|r| r[0] == 0x12));
+    }
+}
@@ -1,129 +1,405 @@
 // Helper functions to print additional help messages
 
-use crate::keyboard::colors;
-
-pub fn print_keys_help() {
-    const HELP: &str = r"Keys
-----
-
-Group List :
-    logo
-    indicators
-    fkeys
-    modifiers
-    multimedia
-    arrows
-    numeric
-    functions
-    keys
-
-Group Logo :
-    logo
-
-Group indicators :
-    num_indicator, numindicator, num
-    caps_indicator, capsindicator, caps
-    scroll_indicator, scrollindicator, scroll
-    game_mode, gamemode, game
-    back_light, backlight, light
-
-Group fkeys :
-    f1 - f12
-
-Group modifiers :
-    shift_left, shiftleft, shiftl
-    ctrl_left, ctrlleft, ctrll
-    win_left, winleft, win_left
-    alt_left, altleft, altl
-    alt_right, altright, altr, altgr
-    win_right, winright, winr
-    menu
-    ctrl_right, ctrlright, ctrlr
-    shift_right, shiftright, shiftr
-
-Group multimedia :
-    mute
-    play_pause, playpause, play
-    stop
-    previous, prev
-    next
-
-Group arrows :
-    arrow_top, arrowtop, top
-    arrow_left, arrowleft, left
-    arrow_bottom, arrowbottom, bottom
-    arrow_right, arrowright, right
-
-Group numeric :
-    num_lock, numlock
-    num_slash, numslash, num/
-    num_asterisk, numasterisk, num*
-    num_minus, numminus, num-
-    num_plus, numplus, num+
-    numenter
-    num0 - num9
-    num_dot, numdot, num.
-
-Group functions :
-    escape, esc
-    print_screen, printscreen, printscr
-    scroll_lock, scrolllock
-    pause_break, pausebreak
-    insert, ins
-    home
-    page_up, pageup
-    delete, del
-    end
-    page_down, pagedown
-
-Group keys :
-    0 - 9
-    a - z
-    tab
-    caps_lock, capslock
-    space
-    backspace, back
-    enter
-    tilde
-    minus
-    equal
-    open_bracket
-    close_bracket
-    backslash
-    semicolon
-    dollar
-    quote
-    intl_backslash
-    comma
-    period
-    slash
-";
-
-    println!("{HELP}");
+use std::io::{self, Write};
+use strum::IntoEnumIterator;
+
+use crate::i18n::{tr, tr_with};
+use crate::keyboard::{
+    EffectSupport, Key, KeyGroup, KeyboardModel, LOGO_WAVE_FALLBACK, NativeEffect,
+    NativeEffectPart, colors, parser::key_aliases,
+};
+
+/// Output style for the `help-*` commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpFormat {
+    Text,
+    Markdown,
 }
 
-pub fn print_effects_help() {
-    println!("Effects\n-------\n");
-    println!("  -fx <effect> <target> [args]\n");
-    println!("Examples:");
-    println!("  -fx color keys 00ff00");
-    println!("  -fx breathing logo 00ff00 0a");
-    println!("  -fx cycle all 0a");
+impl HelpFormat {
+    pub fn new(markdown: bool) -> Self {
+        if markdown { Self::Markdown } else { Self::Text }
+    }
 }
 
-pub fn print_samples_help() {
-    println!("Samples\n-------\n");
-    println!("logi-led -p profile.txt\n    Load a profile from a file");
-    println!("logi-led -k logo ff0000\n    Set the logo key red");
-    println!("logi-led -A 00ff00\n    Set all keys green");
+/// Write a translated title, underlined in text and as a heading in Markdown.
+fn write_title(out: &mut impl Write, format: HelpFormat, id: &str) -> io::Result<()> {
+    let title = tr(id);
+    match format {
+        HelpFormat::Text => writeln!(out, "{title}\n{}", "-".repeat(title.chars().count())),
+        HelpFormat::Markdown => writeln!(out, "# {title}"),
+    }
 }
 
-pub fn print_colors_help() {
-    println!("Colors\n------");
-    for name in colors::color_names() {
-        println!("{name}");
+/// Message id of a group's display name, e.g. `group-gkeys`.
+fn group_label(group: KeyGroup) -> String {
+    tr(&format!("group-{}", group.to_string().replace('-', "")))
+}
+
+/// Write the key names, only those `model` has if given.
+pub fn write_keys_help(
+    out: &mut impl Write,
+    format: HelpFormat,
+    model: Option<KeyboardModel>,
+) -> io::Result<()> {
+    write_title(out, format, "keys-title")?;
+
+    if format == HelpFormat::Text {
+        writeln!(out, "\n{} :", tr("keys-group-list"))?;
+        for group in KeyGroup::iter() {
+            writeln!(out, "    {:<12}{}", group.to_string(), group_label(group))?;
+        }
     }
+
+    let present = |key: Key| model.is_none_or(|model| model.has_key(key));
+    for group in KeyGroup::iter() {
+        if !group.keys().any(present) {
+            continue;
+        }
+        let name = group.to_string();
+        let heading = tr_with(
+            "keys-group",
+            &[("label", &group_label(group)), ("name", &name)],
+        );
+        match format {
+            HelpFormat::Text => writeln!(out, "\n{heading}")?,
+            HelpFormat::Markdown => {
+                writeln!(out, "\n## {}\n", heading.trim_end_matches([':', ' ']))?;
+            }
+        }
+
+        for key in group.keys().filter(|&key| present(key)) {
+            let aliases = key_aliases(key);
+            match format {
+                HelpFormat::Text => writeln!(out, "    {}", aliases.join(", "))?,
+                HelpFormat::Markdown => {
+                    let quoted: Vec<String> = aliases.iter().map(|a| format!("`{a}`")).collect();
+                    writeln!(out, "- {}", quoted.join(", "))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Options of `fx` that change what `effect` looks like.
+fn effect_options(effect: NativeEffect) -> Vec<&'static str> {
+    [
+        effect.uses_color().then_some("--color"),
+        effect.uses_period().then_some("--period"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+pub fn write_effects_help(
+    out: &mut impl Write,
+    format: HelpFormat,
+    models: &[KeyboardModel],
+) -> io::Result<()> {
+    write_title(out, format, "effects-title")?;
+
+    match format {
+        HelpFormat::Text => {
+            writeln!(out, "\n{}", tr("effects-usage"))?;
+            writeln!(out, "  {EFFECTS_USAGE}")?;
+            write_effects_matrix(out, format, models)?;
+            writeln!(out, "\n{}", tr("effects-examples"))?;
+            for example in EFFECTS_EXAMPLES {
+                writeln!(out, "  {example}")?;
+            }
+        }
+        HelpFormat::Markdown => {
+            writeln!(
+                out,
+                "\n{}\n\n```bash\n{EFFECTS_USAGE}\n```",
+                tr("effects-usage")
+            )?;
+            write_effects_matrix(out, format, models)?;
+            writeln!(out, "\n{}\n\n```bash", tr("effects-examples"))?;
+            for example in EFFECTS_EXAMPLES {
+                writeln!(out, "{example}")?;
+            }
+            writeln!(out, "```")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write, per model, the effects its firmware runs: on which parts, and
+/// where they can be stored.
+pub fn write_effects_matrix(
+    out: &mut impl Write,
+    format: HelpFormat,
+    models: &[KeyboardModel],
+) -> io::Result<()> {
+    let join = |items: Vec<String>| {
+        if items.is_empty() {
+            "-".to_owned()
+        } else {
+            items.join(", ")
+        }
+    };
+    let header = [
+        "effects-name",
+        "effects-options",
+        "effects-parts",
+        "effects-storage",
+    ]
+    .map(tr);
+
+    for &model in models {
+        match format {
+            HelpFormat::Text => writeln!(out, "\n{model:?}:")?,
+            HelpFormat::Markdown => writeln!(out, "\n## {model:?}\n")?,
+        }
+        let rows = EffectSupport::for_model(model);
+        if rows.is_empty() {
+            match format {
+                HelpFormat::Text => writeln!(out, "    {}", tr("effects-none"))?,
+                HelpFormat::Markdown => writeln!(out, "{}", tr("effects-none"))?,
+            }
+            continue;
+        }
+
+        let quote = |text: String| match format {
+            HelpFormat::Text => text,
+            HelpFormat::Markdown => format!("`{text}`"),
+        };
+        let part = |row: &EffectSupport, part: &NativeEffectPart| {
+            let name = quote(part.to_string());
+            if row.logo_fallback && *part == NativeEffectPart::Logo {
+                let color = LOGO_WAVE_FALLBACK.to_string();
+                tr_with(
+                    "effects-logo-fallback",
+                    &[("part", &name), ("color", &color)],
+                )
+            } else {
+                name
+            }
+        };
+        let cells: Vec<[String; 4]> = rows
+            .iter()
+            .map(|row| {
+                [
+                    quote(row.effect.to_string()),
+                    join(
+                        effect_options(row.effect)
+                            .into_iter()
+                            .map(|o| quote(o.to_owned()))
+                            .collect(),
+                    ),
+                    join(row.parts.iter().map(|p| part(row, p)).collect()),
+                    join(row.storage.iter().map(|s| quote(s.to_string())).collect()),
+                ]
+            })
+            .collect();
+
+        match format {
+            HelpFormat::Text => {
+                let widths: Vec<usize> = (0..header.len())
+                    .map(|col| {
+                        cells
+                            .iter()
+                            .map(|row| row[col].chars().count())
+                            .chain([header[col].chars().count()])
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                for row in [header.clone()].iter().chain(&cells) {
+                    let line: Vec<String> = row
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, &width)| format!("{cell:<width$}"))
+                        .collect();
+                    writeln!(out, "    {}", line.join("  ").trim_end())?;
+                }
+            }
+            HelpFormat::Markdown => {
+                writeln!(out, "| {} |", header.join(" | "))?;
+                writeln!(out, "|{}", " --- |".repeat(header.len()))?;
+                for row in &cells {
+                    writeln!(out, "| {} |", row.join(" | "))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+const EFFECTS_USAGE: &str = "logi-led fx <effect> <part> [--period <period>] [--color <color>]";
+
+const EFFECTS_EXAMPLES: &[&str] = &[
+    "logi-led fx color keys --color 00ff00",
+    "logi-led fx breathing logo --color 00ff00 --period 0a",
+    "logi-led fx cycle all --period 0a",
+];
+
+/// Sample command lines with the message id describing each.
+const SAMPLES: &[(&str, &str)] = &[
+    ("logi-led load-profile profile.txt", "sample-load-profile"),
+    ("logi-led set --key logo ff0000", "sample-set-logo"),
+    ("logi-led set --all 00ff00", "sample-set-all"),
+];
+
+pub fn write_samples_help(out: &mut impl Write, format: HelpFormat) -> io::Result<()> {
+    write_title(out, format, "samples-title")?;
+    for &(command, id) in SAMPLES {
+        match format {
+            HelpFormat::Text => writeln!(out, "\n{command}\n    {}", tr(id))?,
+            HelpFormat::Markdown => writeln!(out, "\n{}:\n\n```bash\n{command}\n```", tr(id))?,
+        }
+    }
+    Ok(())
+}
+
+/// Write the regions of each of `models`, numbered as `set-region` takes them.
+pub fn write_regions_help(
+    out: &mut impl Write,
+    format: HelpFormat,
+    models: &[KeyboardModel],
+) -> io::Result<()> {
+    write_title(out, format, "regions-title")?;
+
+    for &model in models {
+        let regions = model.spec().regions;
+        match format {
+            HelpFormat::Text => writeln!(out, "\n{model:?}:")?,
+            HelpFormat::Markdown => writeln!(out, "\n## {model:?}\n")?,
+        }
+        if regions.is_empty() {
+            match format {
+                HelpFormat::Text => writeln!(out, "    {}", tr("regions-none"))?,
+                HelpFormat::Markdown => writeln!(out, "{}", tr("regions-none"))?,
+            }
+            continue;
+        }
+        for (number, region) in (1..).zip(regions) {
+            match format {
+                HelpFormat::Text => writeln!(out, "    {number}  {}", region.name)?,
+                HelpFormat::Markdown => writeln!(out, "- `{number}`: {}", region.name)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_colors_help(out: &mut impl Write, format: HelpFormat) -> io::Result<()> {
+    write_title(out, format, "colors-title")?;
+
+    let mut names: Vec<&str> = colors::color_names().collect();
+    names.sort_unstable();
+    if format == HelpFormat::Markdown {
+        writeln!(out, "\n| | |\n| --- | --- |")?;
+    }
+    for name in names {
+        let Some(c) = colors::lookup_color(name) else {
+            continue;
+        };
+        match format {
+            HelpFormat::Text => writeln!(out, "{name:<22}{c}")?,
+            HelpFormat::Markdown => writeln!(out, "| `{name}` | `{c}` |")?,
+        }
+    }
+    Ok(())
 }
 
 // If additional  strings of this nature are added they should be moved to their own module
 pub const COLOR_HELP: &str = colors::COLOR_HELP;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(write: fn(&mut Vec<u8>, HelpFormat) -> io::Result<()>, format: HelpFormat) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn keys_help_lists_every_alias() {
+        let text = render(
+            |out, format| write_keys_help(out, format, None),
+            HelpFormat::Text,
+        );
+        for group in KeyGroup::iter() {
+            for key in group.keys() {
+                for alias in key_aliases(key) {
+                    assert!(text.contains(alias), "{alias} missing from help-keys");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keys_help_for_model_lists_only_its_keys() {
+        let g815 = render(
+            |out, format| write_keys_help(out, format, Some(KeyboardModel::G815)),
+            HelpFormat::Text,
+        );
+        let g910 = render(
+            |out, format| write_keys_help(out, format, Some(KeyboardModel::G910)),
+            HelpFormat::Text,
+        );
+        assert!(g815.contains("    g5") && !g815.contains("    g6"));
+        assert!(g910.contains("    g9"));
+    }
+
+    #[test]
+    fn regions_help_numbers_from_one() {
+        let text = render(
+            |out, format| {
+                write_regions_help(out, format, &[KeyboardModel::G213, KeyboardModel::G810])
+            },
+            HelpFormat::Text,
+        );
+        assert!(text.contains("G213:\n    1  left\n"));
+        assert!(text.contains("    5  numpad\n"));
+        assert!(text.contains("G810:\n    No regions\n"));
+    }
+
+    #[test]
+    fn effects_help_lists_every_effect() {
+        let markdown = render(
+            |out, format| write_effects_help(out, format, &[KeyboardModel::G810]),
+            HelpFormat::Markdown,
+        );
+        assert!(markdown.starts_with("# "));
+        for effect in NativeEffect::iter() {
+            assert!(markdown.contains(&format!("| `{effect}` |")));
+        }
+        assert!(markdown.contains(
+            "| `breathing` | `--color`, `--period` | `all`, `keys`, `logo` | `none`, `user` |"
+        ));
+        assert!(markdown.contains(
+            "| `waves` | `--period` | `all`, `keys`, `logo` (static 00ffff) | `none`, `user` |"
+        ));
+    }
+
+    #[test]
+    fn effects_matrix_follows_the_model() {
+        let text = render(
+            |out, format| {
+                write_effects_matrix(out, format, &[KeyboardModel::G213, KeyboardModel::Unknown])
+            },
+            HelpFormat::Text,
+        );
+        assert!(text.contains("G213:\n    Effect"));
+        assert!(text.contains("    breathing  --color, --period  all, keys  none, user\n"));
+        assert!(text.contains("Unknown:\n    No built-in effects\n"));
+    }
+
+    #[test]
+    fn color_help_mentions_every_color() {
+        let text = render(write_colors_help, HelpFormat::Text);
+        assert!(COLOR_HELP.contains("help-colors"));
+        for name in colors::color_names() {
+            assert!(text.contains(name), "{name} missing from help-colors");
+        }
+        assert!(text.contains("orange                ffa500"));
+        assert!(text.contains("rebeccapurple         663399"));
+    }
+}
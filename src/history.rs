@@ -0,0 +1,690 @@
+//! Rolling history of applied lighting, behind `logi-led history` and `undo`.
+//!
+//! A [`Snapshot`] is the lighting the keyboard should be showing, written as a
+//! g810-led profile, so undoing is loading the previous snapshot with
+//! [`parse_profile`]. Snapshots are cumulative: each one starts from the last
+//! and only replaces what a command changed.
+
+use anyhow::{Result, anyhow};
+use core::time::Duration;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::keyboard::{
+    Color, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi, parser::key_aliases,
+};
+use crate::output;
+use crate::paths;
+use crate::profile::parse_profile;
+
+/// Snapshots kept when the config does not set `history_limit`.
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// Profile commands that paint keys, as opposed to modes.
+const LIGHTING: [&str; 5] = ["a", "g", "k", "r", "fx"];
+
+/// File next to the snapshots holding effects stored in on-board memory.
+const STORED_EFFECTS: &str = "stored.profile";
+
+/// The lighting state left by a series of commands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    lines: Vec<String>,
+}
+
+impl Snapshot {
+    /// Read a snapshot back from its profile text.
+    pub fn parse(text: &str) -> Self {
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && *line != "c")
+            .map(ToOwned::to_owned)
+            .collect();
+        Self { lines }
+    }
+
+    /// Profile commands, without the final commit.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Replay the snapshot on `kbd`.
+    pub fn apply<K>(&self, kbd: &mut K) -> Result<()>
+    where
+        K: KeyboardApi + ?Sized,
+    {
+        parse_profile(kbd, self.to_string().as_bytes(), false)
+    }
+
+    /// The snapshot without what outlives a power cycle: the startup mode
+    /// and effects stored in on-board slots.
+    pub fn volatile(&self) -> Self {
+        let persists = |line: &&String| {
+            let (command, _) = slot(line);
+            // A stored effect has its slot as the sixth word.
+            command == "sm"
+                || (command == "fx"
+                    && line
+                        .split_whitespace()
+                        .nth(5)
+                        .is_some_and(|slot| !slot.eq_ignore_ascii_case("none")))
+        };
+        Self {
+            lines: self
+                .lines
+                .iter()
+                .filter(|line| !persists(line))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Record `line`, dropping whatever it overrides.
+    fn set(&mut self, line: String) {
+        let (command, target) = slot(&line);
+        let repaints = command == "a" || (command == "fx" && target == "all");
+        self.lines.retain(|old| {
+            let (old_command, old_target) = slot(old);
+            !(repaints && LIGHTING.contains(&old_command))
+                && (old_command, old_target) != (command, target)
+        });
+        self.lines.push(line);
+    }
+
+    /// Drop single-key lines for `keys`, which a group color now covers.
+    fn forget_keys(&mut self, group: KeyGroup) {
+        let names: Vec<&str> = group.keys().filter_map(key_name).collect();
+        self.lines.retain(|line| {
+            let (command, target) = slot(line);
+            command != "k" || !names.contains(&target)
+        });
+    }
+}
+
+/// One setting a new snapshot changes, e.g. `k esc` from `ff0000` to `00ff00`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The command and what it sets: `a`, `g arrows`, `k esc`, `fx logo`, `sm`.
+    pub target: String,
+    /// The rest of the line before and after; `None` when unset.
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl Snapshot {
+    /// What going from this snapshot to `after` changes, in `after`'s order
+    /// followed by settings it drops.
+    pub fn changes(&self, after: &Self) -> Vec<Change> {
+        let settings = |snapshot: &Self| -> Vec<(String, String)> {
+            snapshot.lines.iter().map(|line| split_slot(line)).collect()
+        };
+        let before = settings(self);
+        let after = settings(after);
+        let find = |list: &[(String, String)], target: &str| {
+            list.iter()
+                .find(|(other, _)| other == target)
+                .map(|(_, value)| value.clone())
+        };
+        let set = after.iter().filter_map(|(target, value)| {
+            let old = find(&before, target);
+            (old.as_ref() != Some(value)).then(|| Change {
+                target: target.clone(),
+                before: old,
+                after: Some(value.clone()),
+            })
+        });
+        let dropped = before
+            .iter()
+            .filter(|(target, _)| find(&after, target).is_none())
+            .map(|(target, value)| Change {
+                target: target.clone(),
+                before: Some(value.clone()),
+                after: None,
+            });
+        set.chain(dropped).collect()
+    }
+}
+
+/// A line as the [`slot`] it sets and the value it sets it to.
+fn split_slot(line: &str) -> (String, String) {
+    let (command, target) = slot(line);
+    let mut rest: Vec<&str> = line.split_whitespace().skip(1).collect();
+    if let Some(at) = rest.iter().position(|word| *word == target) {
+        rest.remove(at);
+    }
+    let target = if target.is_empty() {
+        command.to_owned()
+    } else {
+        format!("{command} {target}")
+    };
+    (target, rest.join(" "))
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+        writeln!(f, "c")
+    }
+}
+
+/// The command and the thing it sets, e.g. `("k", "esc")` or `("fx", "logo")`.
+fn slot(line: &str) -> (&str, &str) {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let target = match command {
+        "g" | "k" | "r" => words.next(),
+        "fx" => words.nth(1),
+        _ => None,
+    };
+    (command, target.unwrap_or_default())
+}
+
+/// The part and storage of a stored effect, `fx <effect> <part> ... <storage>`.
+fn stored_slot(line: &str) -> (&str, &str) {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    (
+        words.get(2).copied().unwrap_or_default(),
+        words.last().copied().unwrap_or_default(),
+    )
+}
+
+/// A name `parse_key` reads back as `key`.
+pub(crate) fn key_name(key: crate::keyboard::Key) -> Option<&'static str> {
+    key_aliases(key).last().copied()
+}
+
+/// A name `parse_key_ref` reads back as `key`.
+pub(crate) fn key_ref_name(key: KeyRef) -> String {
+    match key {
+        KeyRef::Known(known) => key_name(known).map(str::to_owned),
+        KeyRef::Raw { .. } => None,
+    }
+    .unwrap_or_else(|| key.to_string())
+}
+
+/// The profile line that repeats a `set_fx` call.
+pub(crate) fn fx_line(
+    effect: NativeEffect,
+    part: NativeEffectPart,
+    period: Duration,
+    color: Color,
+    storage: NativeEffectStorage,
+) -> String {
+    let ms = period.as_millis();
+    let line = match effect {
+        NativeEffect::Color => format!("fx {effect} {part} {color}"),
+        NativeEffect::Breathing => format!("fx {effect} {part} {color} {ms}ms"),
+        _ => format!("fx {effect} {part} {ms}ms {color}"),
+    };
+    match (storage, effect) {
+        (NativeEffectStorage::None, _) => line,
+        // The storage is the sixth word, after a period `color` has no use for.
+        (_, NativeEffect::Color) => format!("{line} 0ms {storage}"),
+        _ => format!("{line} {storage}"),
+    }
+}
+
+/// Passes calls through to a keyboard while noting the lighting they leave.
+pub struct Recorder<'a, K: ?Sized> {
+    inner: &'a mut K,
+    snapshot: Snapshot,
+    changed: bool,
+    /// Effects written to on-board memory, which the snapshot leaves out.
+    stored: Vec<String>,
+}
+
+impl<'a, K: KeyboardApi + ?Sized> Recorder<'a, K> {
+    /// Record on top of `snapshot`, the state before this command.
+    pub fn new(inner: &'a mut K, snapshot: Snapshot) -> Self {
+        Self {
+            inner,
+            snapshot,
+            changed: false,
+            stored: Vec::new(),
+        }
+    }
+
+    /// The state after the calls so far, if any of them changed it.
+    pub fn into_snapshot(self) -> Option<Snapshot> {
+        self.changed.then_some(self.snapshot)
+    }
+
+    fn note(&mut self, line: String) {
+        self.snapshot.set(line);
+        self.changed = true;
+    }
+}
+
+impl<K: KeyboardApi + ?Sized> KeyboardApi for Recorder<'_, K> {
+    fn model(&self) -> KeyboardModel {
+        self.inner.model()
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.inner.set_all_keys(color)?;
+        self.note(format!("a {color}"));
+        Ok(())
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.inner.set_group_keys(group, color)?;
+        self.snapshot.forget_keys(group);
+        self.note(format!("g {group} {color}"));
+        Ok(())
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        self.inner.set_keys(keys)?;
+        for kv in keys {
+            if let Some(name) = key_name(kv.key) {
+                self.note(format!("k {name} {}", kv.color));
+            }
+        }
+        Ok(())
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        self.inner.set_key_refs(keys)?;
+        for kv in keys {
+            self.note(format!("k {} {}", key_ref_name(kv.key), kv.color));
+        }
+        Ok(())
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.inner.set_region(region, color)?;
+        self.note(format!("r {region} {color}"));
+        Ok(())
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mr_key(value)?;
+        self.note(format!("mr {value}"));
+        Ok(())
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mn_key(value)?;
+        self.note(format!("mn {value}"));
+        Ok(())
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.inner.set_gkeys_mode(value)?;
+        self.note(format!("gkm {value}"));
+        Ok(())
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        self.inner.set_startup_mode(mode)?;
+        self.note(format!("sm {mode}"));
+        Ok(())
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.inner.set_on_board_mode(mode)?;
+        self.note(format!("obm {mode}"));
+        Ok(())
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        self.inner.set_fx(effect, part, period, color, storage)?;
+        let line = fx_line(effect, part, period, color, storage);
+        // Stored effects do not change what the keyboard shows right now.
+        if storage == NativeEffectStorage::None {
+            self.note(line);
+        } else {
+            self.stored.push(line);
+        }
+        Ok(())
+    }
+}
+
+/// A saved snapshot.
+pub struct Entry {
+    pub id: u64,
+    pub saved: SystemTime,
+    pub snapshot: Snapshot,
+}
+
+/// Snapshots on disk, one profile file per applied state.
+pub struct History {
+    dir: PathBuf,
+    limit: usize,
+}
+
+impl History {
+    pub fn new(dir: PathBuf, limit: usize) -> Self {
+        Self { dir, limit }
+    }
+
+    /// History under the state directory; `None` when `limit` is 0.
+    pub fn open(limit: usize) -> Option<Self> {
+        let dir = paths::state_dir()?.join("history");
+        (limit > 0).then(|| Self::new(dir, limit))
+    }
+
+    /// The newest snapshot, or an empty one when none is saved.
+    pub fn latest(&self) -> Snapshot {
+        self.entries()
+            .ok()
+            .and_then(|entries| entries.into_iter().next_back())
+            .map(|entry| entry.snapshot)
+            .unwrap_or_default()
+    }
+
+    /// Saved snapshots, oldest first.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Ok(Vec::new());
+        };
+        let mut entries = Vec::new();
+        for file in dir.flatten() {
+            let path = file.path();
+            let Some(id) = path
+                .file_stem()
+                .filter(|_| path.extension().is_some_and(|ext| ext == "profile"))
+                .and_then(|stem| stem.to_str()?.parse().ok())
+            else {
+                continue;
+            };
+            let text = fs::read_to_string(&path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+            entries.push(Entry {
+                id,
+                saved: file
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+                snapshot: Snapshot::parse(&text),
+            });
+        }
+        entries.sort_by_key(|entry| entry.id);
+        Ok(entries)
+    }
+
+    /// Save `snapshot` as the newest entry, dropping the oldest past the limit.
+    pub fn push(&self, snapshot: &Snapshot) -> Result<()> {
+        let entries = self.entries()?;
+        if entries
+            .last()
+            .is_some_and(|last| last.snapshot == *snapshot)
+        {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| anyhow!("{}: {e}", self.dir.display()))?;
+        let id = entries.last().map_or(1, |last| last.id + 1);
+        let path = self.path(id);
+        fs::write(&path, snapshot.to_string()).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+
+        let excess = (entries.len() + 1).saturating_sub(self.limit);
+        for entry in &entries[..excess.min(entries.len())] {
+            self.remove(entry.id)?;
+        }
+        Ok(())
+    }
+
+    /// Effects last stored in on-board memory, as profile lines.
+    ///
+    /// The firmware cannot report these back, so this is what was sent
+    /// while the history was kept.
+    pub fn stored_effects(&self) -> Result<Vec<String>> {
+        let path = self.dir.join(STORED_EFFECTS);
+        match fs::read_to_string(&path) {
+            Ok(text) => Ok(Snapshot::parse(&text).lines),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(anyhow!("{}: {e}", path.display())),
+        }
+    }
+
+    /// Add newly stored effects, replacing those stored for the same part.
+    fn remember_stored(&self, lines: Vec<String>) -> Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut stored = self.stored_effects()?;
+        for line in lines {
+            let (part, storage) = stored_slot(&line);
+            stored.retain(|old| {
+                let (old_part, old_storage) = stored_slot(old);
+                old_storage != storage || (part != "all" && old_part != part)
+            });
+            stored.push(line);
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| anyhow!("{}: {e}", self.dir.display()))?;
+        let path = self.dir.join(STORED_EFFECTS);
+        fs::write(&path, stored.join("\n") + "\n").map_err(|e| anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Delete entry `id`.
+    pub fn remove(&self, id: u64) -> Result<()> {
+        let path = self.path(id);
+        fs::remove_file(&path).map_err(|e| anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Run `f` against `kbd` and save the lighting it leaves behind.
+    ///
+    /// Failing to save is only a warning: the lighting was applied either way.
+    pub fn record<K>(
+        &self,
+        kbd: &mut K,
+        f: impl FnOnce(&mut Recorder<'_, K>) -> Result<()>,
+    ) -> Result<()>
+    where
+        K: KeyboardApi + ?Sized,
+    {
+        let mut recorder = Recorder::new(kbd, self.latest());
+        f(&mut recorder)?;
+        if let Err(e) = self.remember_stored(std::mem::take(&mut recorder.stored)) {
+            output::warn(format_args!("could not save stored effects: {e}"));
+        }
+        if let Some(snapshot) = recorder.into_snapshot()
+            && let Err(e) = self.push(&snapshot)
+        {
+            output::warn(format_args!("could not save lighting history: {e}"));
+        }
+        Ok(())
+    }
+
+    fn path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id:06}.profile"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::Key;
+    use crate::keyboard::api::NullKeyboard;
+    use crate::keyboard::parser::parse_key;
+    use strum::IntoEnumIterator;
+
+    fn history(name: &str, limit: usize) -> History {
+        let dir = std::env::temp_dir().join(format!("logi_led_history_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        History::new(dir, limit)
+    }
+
+    #[test]
+    fn every_key_round_trips() {
+        for key in Key::iter() {
+            let name = key_name(key).unwrap_or_else(|| panic!("{key:?} has no name"));
+            assert_eq!(parse_key(name), Ok(key));
+        }
+    }
+
+    #[test]
+    fn later_commands_replace_what_they_cover() {
+        let mut kbd = NullKeyboard;
+        let mut rec = Recorder::new(&mut kbd, Snapshot::default());
+        let red = Color::new(0xff, 0, 0);
+        let blue = Color::new(0, 0, 0xff);
+        rec.set_startup_mode(StartupMode::Wave).unwrap();
+        rec.set_all_keys(red).unwrap();
+        rec.set_keys(&[KeyValue {
+            key: Key::F1,
+            color: blue,
+        }])
+        .unwrap();
+        rec.set_keys(&[KeyValue {
+            key: Key::Esc,
+            color: blue,
+        }])
+        .unwrap();
+        rec.set_group_keys(KeyGroup::FKeys, red).unwrap();
+        rec.set_keys(&[KeyValue {
+            key: Key::Esc,
+            color: red,
+        }])
+        .unwrap();
+        let snapshot = rec.into_snapshot().unwrap();
+        assert_eq!(
+            snapshot.lines(),
+            ["sm wave", "a ff0000", "g f-keys ff0000", "k esc ff0000"]
+        );
+
+        let mut rec = Recorder::new(&mut kbd, snapshot);
+        rec.set_all_keys(blue).unwrap();
+        assert_eq!(
+            rec.into_snapshot().unwrap().lines(),
+            ["sm wave", "a 0000ff"]
+        );
+    }
+
+    #[test]
+    fn snapshots_replay_as_profiles() {
+        let mut kbd = NullKeyboard;
+        let mut rec = Recorder::new(&mut kbd, Snapshot::default());
+        rec.set_fx(
+            NativeEffect::Breathing,
+            NativeEffectPart::Logo,
+            Duration::from_millis(500),
+            Color::new(1, 2, 3),
+            NativeEffectStorage::None,
+        )
+        .unwrap();
+        rec.set_keys(&[KeyValue {
+            key: Key::Space,
+            color: Color::new(4, 5, 6),
+        }])
+        .unwrap();
+        let snapshot = rec.into_snapshot().unwrap();
+
+        let mut replayed = Recorder::new(&mut kbd, Snapshot::default());
+        snapshot.apply(&mut replayed).unwrap();
+        assert_eq!(replayed.into_snapshot().unwrap(), snapshot);
+        assert_eq!(Snapshot::parse(&snapshot.to_string()), snapshot);
+    }
+
+    #[test]
+    fn changes_list_what_a_snapshot_sets_and_drops() {
+        let before = Snapshot::parse("a ffffff\nk esc ff0000\nfx breathing logo 010203 500ms\n");
+        let after = Snapshot::parse("a ffffff\ng arrows 00ff00\nfx cycle logo 2000ms ffffff\n");
+        let change = |target: &str, before: Option<&str>, after: Option<&str>| Change {
+            target: target.to_owned(),
+            before: before.map(ToOwned::to_owned),
+            after: after.map(ToOwned::to_owned),
+        };
+        assert_eq!(
+            before.changes(&after),
+            [
+                change("g arrows", None, Some("00ff00")),
+                change(
+                    "fx logo",
+                    Some("breathing 010203 500ms"),
+                    Some("cycle 2000ms ffffff")
+                ),
+                change("k esc", Some("ff0000"), None),
+            ]
+        );
+        assert_eq!(after.changes(&after), []);
+    }
+
+    #[test]
+    fn history_is_capped_and_skips_repeats() {
+        let history = history("cap", 2);
+        for color in ["a 000001", "a 000002", "a 000002", "a 000003"] {
+            history.push(&Snapshot::parse(color)).unwrap();
+        }
+        let entries = history.entries().unwrap();
+        let lines: Vec<&str> = entries
+            .iter()
+            .map(|e| e.snapshot.lines()[0].as_str())
+            .collect();
+        assert_eq!(lines, ["a 000002", "a 000003"]);
+        assert_eq!(entries[1].id, 3);
+    }
+
+    #[test]
+    fn record_builds_on_the_last_snapshot() {
+        let history = history("record", 5);
+        history.push(&Snapshot::parse("a 102030")).unwrap();
+        let mut kbd = NullKeyboard;
+        history
+            .record(&mut kbd, |rec| rec.set_region(1, Color::new(0, 0, 0)))
+            .unwrap();
+        history.record(&mut kbd, |rec| rec.commit()).unwrap();
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].snapshot.lines(), ["a 102030", "r 1 000000"]);
+    }
+
+    #[test]
+    fn stored_effects_are_kept_apart_from_the_lighting() {
+        let history = history("stored", 5);
+        let mut kbd = NullKeyboard;
+        let store = |rec: &mut Recorder<'_, NullKeyboard>, effect, part| {
+            rec.set_fx(
+                effect,
+                part,
+                Duration::from_secs(2),
+                Color::new(0xff, 0, 0),
+                NativeEffectStorage::User,
+            )
+        };
+        history
+            .record(&mut kbd, |rec| {
+                store(rec, NativeEffect::Color, NativeEffectPart::Keys)?;
+                store(rec, NativeEffect::Cycle, NativeEffectPart::Logo)
+            })
+            .unwrap();
+        assert!(history.entries().unwrap().is_empty());
+        assert_eq!(
+            history.stored_effects().unwrap(),
+            [
+                "fx color keys ff0000 0ms user",
+                "fx cycle logo 2000ms ff0000 user"
+            ]
+        );
+
+        history
+            .record(&mut kbd, |rec| {
+                store(rec, NativeEffect::Breathing, NativeEffectPart::All)
+            })
+            .unwrap();
+        assert_eq!(
+            history.stored_effects().unwrap(),
+            ["fx breathing all ff0000 2000ms user"]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Translated user-facing strings, backed by Fluent catalogs in `locales/`.
+
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use std::ffi::OsString;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Catalogs built into the binary; English is the fallback for missing messages.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+/// Language codes accepted by `--lang`.
+pub const LANGUAGES: [&str; 4] = ["en", "de", "fr", "es"];
+
+type Bundle = FluentBundle<FluentResource>;
+
+struct Catalog {
+    selected: Option<Bundle>,
+    fallback: Bundle,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+impl Catalog {
+    fn new(locale: Option<&str>) -> Self {
+        let selected = locale
+            .map(language_code)
+            .filter(|&code| code != "en")
+            .and_then(bundle);
+        Self {
+            selected,
+            fallback: bundle("en").expect("English catalog is embedded"),
+        }
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        [self.selected.as_ref(), Some(&self.fallback)]
+            .into_iter()
+            .flatten()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = vec![];
+                Some(
+                    bundle
+                        .format_pattern(pattern, args, &mut errors)
+                        .into_owned(),
+                )
+            })
+            .unwrap_or_else(|| id.to_owned())
+    }
+}
+
+fn bundle(code: &str) -> Option<Bundle> {
+    let &(_, source) = LOCALES.iter().find(|(name, _)| *name == code)?;
+    let langid: LanguageIdentifier = code.parse().ok()?;
+    let resource = FluentResource::try_new(source.to_owned()).ok()?;
+    let mut bundle = Bundle::new_concurrent(vec![langid]);
+    // Directional isolation marks only confuse terminals.
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+/// Reduce a POSIX locale such as `de_DE.UTF-8` to its language code.
+fn language_code(locale: &str) -> &str {
+    let code = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default();
+    match code {
+        "" | "C" | "POSIX" => "en",
+        _ => code,
+    }
+}
+
+/// Locale from the environment, in the precedence gettext uses.
+fn env_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+}
+
+/// Pick the language before clap runs so its own errors are translated too.
+///
+/// `lang` is the `--lang` value if given; otherwise the environment decides.
+pub fn init(lang: Option<&str>) {
+    let locale = lang.map(str::to_owned).or_else(env_locale);
+    let _ = CATALOG.set(Catalog::new(locale.as_deref()));
+}
+
+/// Find `--lang <code>` or `--lang=<code>` in raw command-line arguments.
+///
+/// Arguments that are not UTF-8, such as file names, are skipped; clap
+/// reports them later if they matter.
+pub fn lang_from_args(args: impl IntoIterator<Item = OsString>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--lang" {
+            return args.next().and_then(|value| value.into_string().ok());
+        }
+        if let Some(value) = arg.to_str().and_then(|arg| arg.strip_prefix("--lang=")) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new(env_locale().as_deref()))
+}
+
+/// Translate a message without arguments.
+pub fn tr(id: &str) -> String {
+    catalog().format(id, None)
+}
+
+/// Translate a message, substituting `{ $name }` placeholders.
+pub fn tr_with(id: &str, args: &[(&str, &str)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for &(name, value) in args {
+        fluent_args.set(name, value);
+    }
+    catalog().format(id, Some(&fluent_args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter(|line| !line.starts_with(['#', ' ']) && line.contains(" = "))
+            .filter_map(|line| line.split(" = ").next())
+            .collect()
+    }
+
+    #[test]
+    fn language_code_from_locale() {
+        assert_eq!(language_code("de_DE.UTF-8"), "de");
+        assert_eq!(language_code("fr"), "fr");
+        assert_eq!(language_code("es-ES"), "es");
+        assert_eq!(language_code("C.UTF-8"), "en");
+        assert_eq!(language_code("POSIX"), "en");
+    }
+
+    #[test]
+    fn every_locale_is_complete() {
+        let english = message_ids(LOCALES[0].1);
+        for &(code, source) in LOCALES {
+            assert!(bundle(code).is_some(), "{code} failed to parse");
+            assert_eq!(message_ids(source), english, "{code} is out of sync");
+        }
+        let codes: Vec<&str> = LOCALES.iter().map(|&(code, _)| code).collect();
+        assert_eq!(codes, LANGUAGES);
+    }
+
+    #[test]
+    fn selected_language_with_fallback() {
+        let german = Catalog::new(Some("de_AT.UTF-8"));
+        assert_eq!(german.format("keys-title", None), "Tasten");
+        assert_eq!(german.format("no-such-message", None), "no-such-message");
+
+        let unknown = Catalog::new(Some("xx_XX"));
+        assert_eq!(unknown.format("keys-title", None), "Keys");
+
+        let mut args = FluentArgs::new();
+        args.set("value", "pruple");
+        assert_eq!(
+            Catalog::new(Some("es")).format("error-invalid-color", Some(&args)),
+            "color no válido: pruple"
+        );
+    }
+
+    #[test]
+    fn lang_flag_is_found() {
+        let args = |s: &str| s.split(' ').map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            lang_from_args(args("logi-led --lang de help-keys")),
+            Some("de".into())
+        );
+        assert_eq!(
+            lang_from_args(args("logi-led help-keys --lang=fr")),
+            Some("fr".into())
+        );
+        assert_eq!(lang_from_args(args("logi-led help-keys")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_arguments_are_skipped() {
+        use std::os::unix::ffi::OsStringExt;
+        let path = OsString::from_vec(b"\xff.txt".to_vec());
+        let args = ["logi-led".into(), "load-profile".into(), path.clone()];
+        assert_eq!(lang_from_args(args), None);
+        let args = ["logi-led".into(), path, "--lang=de".into()];
+        assert_eq!(lang_from_args(args), Some("de".into()));
+    }
+}
@@ -0,0 +1,283 @@
+//! Keyboard and mouse activity from `/dev/input`, for `logi-led idle` and
+//! `trail`.
+//!
+//! One thread per event device blocks on reads and sends the keys pressed
+//! in each to a channel, so waiting for input is a `recv_timeout` and needs
+//! nothing beyond std. The event nodes are readable by root and the `input`
+//! group.
+
+use anyhow::{Result, bail};
+use core::time::Duration;
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+
+use crate::keyboard::Key;
+use crate::shutdown;
+
+/// How often [`Activity::wait_idle`] checks for Ctrl-C.
+const STOP_POLL: Duration = Duration::from_millis(200);
+
+/// Where the kernel exposes input devices.
+const INPUT_DIR: &str = "/dev/input";
+
+/// Size of a `struct input_event`: a `timeval` of two longs, which are
+/// pointer-sized on Linux, then a `u16` type, a `u16` code and an `i32` value.
+const EVENT_SIZE: usize = 2 * size_of::<usize>() + 8;
+
+/// Event type of key presses and releases.
+const EV_KEY: u16 = 0x01;
+
+/// Input activity on any readable event device.
+pub struct Activity {
+    /// The keys pressed in each read; empty for other input, such as the mouse.
+    events: Receiver<Vec<Key>>,
+}
+
+impl Activity {
+    /// Watch every `/dev/input/event*` node this process can read.
+    pub fn watch() -> Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut watched = 0;
+        for entry in std::fs::read_dir(INPUT_DIR).into_iter().flatten().flatten() {
+            if !entry.file_name().to_string_lossy().starts_with("event") {
+                continue;
+            }
+            let Ok(mut file) = File::open(entry.path()) else {
+                continue;
+            };
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0; EVENT_SIZE * 64];
+                while let Ok(n) = file.read(&mut buf)
+                    && n > 0
+                    && tx.send(presses(&buf[..n])).is_ok()
+                {}
+            });
+            watched += 1;
+        }
+        if watched == 0 {
+            bail!(
+                "cannot read any {INPUT_DIR}/event* device; \
+                 add yourself to the `input` group and log in again"
+            );
+        }
+        Ok(Self { events })
+    }
+
+    /// Wait up to `timeout` for input; whether there was any.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        self.wait_keys(timeout).is_some()
+    }
+
+    /// Wait up to `timeout` for input; the keys pressed since the last call,
+    /// or `None` if there was no input at all.
+    pub fn wait_keys(&self, timeout: Duration) -> Option<Vec<Key>> {
+        match self.events.recv_timeout(timeout) {
+            Ok(mut keys) => {
+                // One keypress is several events; take whatever else is queued.
+                while let Ok(more) = self.events.try_recv() {
+                    keys.extend(more);
+                }
+                Some(keys)
+            }
+            Err(RecvTimeoutError::Timeout) => None,
+            // Every device is gone; keep the caller's pace instead of spinning.
+            Err(RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(timeout);
+                None
+            }
+        }
+    }
+
+    /// Block until there has been no input for `idle`; `false` if Ctrl-C or
+    /// SIGTERM came first.
+    pub fn wait_idle(&self, idle: Duration) -> bool {
+        let mut quiet = Duration::ZERO;
+        while quiet < idle {
+            if shutdown::requested() {
+                return false;
+            }
+            let step = STOP_POLL.min(idle.saturating_sub(quiet));
+            quiet = if self.wait(step) {
+                Duration::ZERO
+            } else {
+                quiet + step
+            };
+        }
+        true
+    }
+}
+
+/// The keys pressed in `events`, whole `input_event`s as read from a device.
+/// Releases, autorepeat and keys without an LED are left out.
+fn presses(events: &[u8]) -> Vec<Key> {
+    let (events, _) = events.as_chunks::<EVENT_SIZE>();
+    events
+        .iter()
+        .filter_map(|event| {
+            let [.., k0, k1, c0, c1, v0, v1, v2, v3] = *event;
+            let pressed =
+                u16::from_ne_bytes([k0, k1]) == EV_KEY && i32::from_ne_bytes([v0, v1, v2, v3]) == 1;
+            pressed.then(|| key_for_code(u16::from_ne_bytes([c0, c1])))?
+        })
+        .collect()
+}
+
+/// The key with Linux key code `code` (`KEY_*` in `input-event-codes.h`).
+#[allow(clippy::enum_glob_use)]
+fn key_for_code(code: u16) -> Option<Key> {
+    use Key::*;
+    Some(match code {
+        1 => Esc,
+        2 => N1,
+        3 => N2,
+        4 => N3,
+        5 => N4,
+        6 => N5,
+        7 => N6,
+        8 => N7,
+        9 => N8,
+        10 => N9,
+        11 => N0,
+        12 => Minus,
+        13 => Equal,
+        14 => Backspace,
+        15 => Tab,
+        16 => Q,
+        17 => W,
+        18 => E,
+        19 => R,
+        20 => T,
+        21 => Y,
+        22 => U,
+        23 => I,
+        24 => O,
+        25 => P,
+        26 => OpenBracket,
+        27 => CloseBracket,
+        28 => Enter,
+        29 => CtrlLeft,
+        30 => A,
+        31 => S,
+        32 => D,
+        33 => F,
+        34 => G,
+        35 => H,
+        36 => J,
+        37 => K,
+        38 => L,
+        39 => Semicolon,
+        40 => Quote,
+        41 => Tilde,
+        42 => ShiftLeft,
+        43 => Backslash,
+        44 => Z,
+        45 => X,
+        46 => C,
+        47 => V,
+        48 => B,
+        49 => N,
+        50 => M,
+        51 => Comma,
+        52 => Period,
+        53 => Slash,
+        54 => ShiftRight,
+        55 => NumAsterisk,
+        56 => AltLeft,
+        57 => Space,
+        58 => CapsLock,
+        59 => F1,
+        60 => F2,
+        61 => F3,
+        62 => F4,
+        63 => F5,
+        64 => F6,
+        65 => F7,
+        66 => F8,
+        67 => F9,
+        68 => F10,
+        69 => NumLock,
+        70 => ScrollLock,
+        71 => Num7,
+        72 => Num8,
+        73 => Num9,
+        74 => NumMinus,
+        75 => Num4,
+        76 => Num5,
+        77 => Num6,
+        78 => NumPlus,
+        79 => Num1,
+        80 => Num2,
+        81 => Num3,
+        82 => Num0,
+        83 => NumDot,
+        86 => IntlBackslash,
+        87 => F11,
+        88 => F12,
+        89 => AbntSlash,
+        96 => NumEnter,
+        97 => CtrlRight,
+        98 => NumSlash,
+        99 => PrintScreen,
+        100 => AltRight,
+        102 => Home,
+        103 => ArrowTop,
+        104 => PageUp,
+        105 => ArrowLeft,
+        106 => ArrowRight,
+        107 => End,
+        108 => ArrowBottom,
+        109 => PageDown,
+        110 => Insert,
+        111 => Del,
+        113 => Mute,
+        119 => PauseBreak,
+        125 => WinLeft,
+        126 => WinRight,
+        127 => Menu,
+        163 => Next,
+        164 => Play,
+        165 => Prev,
+        166 => Stop,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One `input_event` with a zero timestamp.
+    fn event(kind: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut event = vec![0; EVENT_SIZE - 8];
+        event.extend(kind.to_ne_bytes());
+        event.extend(code.to_ne_bytes());
+        event.extend(value.to_ne_bytes());
+        event
+    }
+
+    #[test]
+    fn only_key_presses_are_reported() {
+        let events = [
+            event(EV_KEY, 30, 1),  // A down
+            event(0x00, 0, 0),     // sync
+            event(EV_KEY, 30, 2),  // autorepeat
+            event(EV_KEY, 30, 0),  // A up
+            event(0x02, 0, 5),     // mouse motion
+            event(EV_KEY, 272, 1), // mouse button
+            event(EV_KEY, 57, 1),  // space down
+        ]
+        .concat();
+        assert_eq!(presses(&events), [Key::A, Key::Space]);
+    }
+
+    #[test]
+    fn key_codes_follow_the_kernel_table() {
+        assert_eq!(key_for_code(1), Some(Key::Esc));
+        assert_eq!(key_for_code(16), Some(Key::Q));
+        assert_eq!(key_for_code(96), Some(Key::NumEnter));
+        assert_eq!(key_for_code(125), Some(Key::WinLeft));
+        assert_eq!(key_for_code(0), None);
+    }
+}
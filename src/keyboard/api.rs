@@ -1,7 +1,8 @@
 use crate::keyboard::{
-    self as keyboard, Color, KeyGroup, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
-    NativeEffectStorage, OnBoardMode, StartupMode,
+    self as keyboard, Color, Key, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+    NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode,
 };
+use crate::timing::{self, Phase};
 use anyhow::{Result, anyhow};
 use core::time::Duration;
 use std::collections::BTreeMap;
@@ -9,8 +10,65 @@ use strum::IntoEnumIterator;
 
 /// High level keyboard operations.
 ///
-/// These are stubs for now so that the profile parser can call a uniform API.
+/// Every method is required, so a backend or wrapper that forgets one fails
+/// to compile instead of silently doing nothing. Tests that do not care about
+/// the keyboard can use [`NullKeyboard`].
 pub trait KeyboardApi {
+    /// Model of the board behind this handle, for model-specific layouts.
+    fn model(&self) -> KeyboardModel;
+
+    fn commit(&mut self) -> Result<()>;
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()>;
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()>;
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()>;
+
+    /// [`set_keys`](Self::set_keys) for keys given by
+    /// [`KeyRef`](crate::keyboard::KeyRef), which can name LEDs [`Key`] has
+    /// no variant for.
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()>;
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()>;
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()>;
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()>;
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()>;
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()>;
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()>;
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()>;
+
+    /// Keys that exist on the model and can be lit on their own.
+    ///
+    /// Derived from [`model`](Self::model), so wrappers that forward it need
+    /// not forward this too.
+    fn supported_keys(&self) -> Vec<Key> {
+        self.model().supported_keys().collect()
+    }
+}
+
+/// Accepts every call and does nothing, for tests and fuzz targets that only
+/// exercise the caller.
+pub struct NullKeyboard;
+
+impl KeyboardApi for NullKeyboard {
+    fn model(&self) -> KeyboardModel {
+        KeyboardModel::Unknown
+    }
+
     fn commit(&mut self) -> Result<()> {
         Ok(())
     }
@@ -27,6 +85,10 @@ pub trait KeyboardApi {
         Ok(())
     }
 
+    fn set_key_refs(&mut self, _keys: &[KeyRefValue]) -> Result<()> {
+        Ok(())
+    }
+
     fn set_region(&mut self, _region: u8, _color: Color) -> Result<()> {
         Ok(())
     }
@@ -64,13 +126,20 @@ pub trait KeyboardApi {
 }
 
 impl KeyboardApi for crate::keyboard::device::Keyboard {
+    fn model(&self) -> KeyboardModel {
+        self.current_device()
+            .map_or(KeyboardModel::Unknown, |info| info.model)
+    }
+
     fn commit(&mut self) -> Result<()> {
         let model = self
             .current_device()
             .ok_or_else(|| anyhow!("no device open"))?
             .model;
 
-        if let Some(packet) = keyboard::packet::commit_packet(model) {
+        if let Some(packet) = timing::time(Phase::Build, || keyboard::packet::commit_packet(model))
+        {
+            let _committing = timing::start(Phase::Commit);
             self.send_packet(&packet)?;
         }
 
@@ -78,63 +147,73 @@ impl KeyboardApi for crate::keyboard::device::Keyboard {
     }
 
     fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
-        if keys.is_empty() {
-            return Ok(());
-        }
-
         let model = self
             .current_device()
             .ok_or_else(|| anyhow!("no device open"))?
             .model;
+        let spec = model.spec();
+        if spec.regions.is_empty() {
+            return send_keys(self, keys);
+        }
 
-        match model {
-            KeyboardModel::G213 | KeyboardModel::G413 => return Ok(()),
-            KeyboardModel::G815 => {
-                let mut by_color: BTreeMap<(u8, u8, u8), Vec<KeyValue>> = BTreeMap::new();
-                for &kv in keys {
-                    by_color
-                        .entry((kv.color.red, kv.color.green, kv.color.blue))
-                        .or_default()
-                        .push(kv);
-                }
-
-                for vals in by_color.values() {
-                    for chunk in vals.chunks(13) {
-                        if let Some(packet) = keyboard::packet::set_keys_packet(model, chunk) {
-                            self.send_packet(&packet)?;
-                        }
-                    }
-                }
-            }
-            _ => {
-                let mut by_group: BTreeMap<u8, Vec<KeyValue>> = BTreeMap::new();
-                for &kv in keys {
-                    by_group.entry(kv.key.group()).or_default().push(kv);
-                }
-
-                for (group, vals) in by_group {
-                    let size = if group == 0 { 20 } else { 64 };
-                    let max_keys = (size - 8) / 4;
+        // A region filled in one color costs one packet instead of one per key.
+        let (whole, rest) = spec.whole_regions(keys);
+        for (region, color) in whole {
+            self.set_region(region, color)?;
+        }
 
-                    for chunk in vals.chunks(max_keys) {
-                        if let Some(packet) = keyboard::packet::set_keys_packet(model, chunk) {
-                            self.send_packet(&packet)?;
-                        }
-                    }
-                }
+        // Region-only boards show the rest in the color most of each region got.
+        if model.supported_keys().next().is_none() {
+            for (region, color) in spec.region_colors(&rest) {
+                self.set_region(region, color)?;
             }
+            return Ok(());
         }
 
-        Ok(())
+        send_keys(self, &rest)
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        send_keys(self, keys)
     }
 
     fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
-        let keys: Vec<KeyValue> = group.keys().map(|k| KeyValue { key: k, color }).collect();
+        let model = self
+            .current_device()
+            .ok_or_else(|| anyhow!("no device open"))?
+            .model;
+
+        // Region-only boards can't address keys; paint the regions the group overlaps.
+        if model == KeyboardModel::G213 {
+            let keys: Vec<Key> = group.keys().collect();
+            for region in model.spec().regions_covering(&keys) {
+                self.set_region(region, color)?;
+            }
+            return Ok(());
+        }
+
+        let keys: Vec<KeyValue> = group
+            .keys()
+            .filter(|&key| model.has_key(key))
+            .map(|key| KeyValue { key, color })
+            .collect();
 
         self.set_keys(&keys)
     }
 
     fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        let model = self
+            .current_device()
+            .ok_or_else(|| anyhow!("no device open"))?
+            .model;
+
+        if model == KeyboardModel::G213 {
+            for region in 1..=model.spec().region_count() {
+                self.set_region(region, color)?;
+            }
+            return Ok(());
+        }
+
         for group in KeyGroup::iter() {
             self.set_group_keys(group, color)?;
         }
@@ -147,7 +226,16 @@ impl KeyboardApi for crate::keyboard::device::Keyboard {
             .ok_or_else(|| anyhow!("no device open"))?
             .model;
 
-        if let Some(packet) = keyboard::packet::region_packet(model, region, color) {
+        let count = model.spec().region_count();
+        if count > 0 && !(1..=count).contains(&region) {
+            return Err(anyhow!(
+                "region {region} is out of range for {model:?} (valid: 1-{count})"
+            ));
+        }
+
+        if let Some(packet) = timing::time(Phase::Build, || {
+            keyboard::packet::region_packet(model, region, color)
+        }) {
             self.send_packet(&packet)?;
         }
 
@@ -287,9 +375,9 @@ impl KeyboardApi for crate::keyboard::device::Keyboard {
             .ok_or_else(|| anyhow!("no device open"))?
             .model;
 
-        if let Some(packets) =
+        if let Some(packets) = timing::time(Phase::Build, || {
             keyboard::native_effect_packets(model, effect, part, period, color, storage)
-        {
+        }) {
             for packet in packets {
                 self.send_packet(&packet)?;
             }
@@ -298,3 +386,62 @@ impl KeyboardApi for crate::keyboard::device::Keyboard {
         Ok(())
     }
 }
+
+/// Send `keys`, split into as few packets as `model` allows.
+fn send_keys<T>(kbd: &mut crate::keyboard::device::Keyboard, keys: &[T]) -> Result<()>
+where
+    T: Copy + Into<KeyRefValue>,
+{
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let model = kbd
+        .current_device()
+        .ok_or_else(|| anyhow!("no device open"))?
+        .model;
+
+    match model {
+        KeyboardModel::G213 | KeyboardModel::G413 => return Ok(()),
+        KeyboardModel::G815 => {
+            let mut by_color: BTreeMap<(u8, u8, u8), Vec<KeyRefValue>> = BTreeMap::new();
+            for kv in keys.iter().map(|&kv| -> KeyRefValue { kv.into() }) {
+                by_color
+                    .entry((kv.color.red, kv.color.green, kv.color.blue))
+                    .or_default()
+                    .push(kv);
+            }
+
+            for vals in by_color.values() {
+                for chunk in vals.chunks(13) {
+                    if let Some(packet) = timing::time(Phase::Build, || {
+                        keyboard::packet::set_key_refs_packet(model, chunk)
+                    }) {
+                        kbd.send_packet(&packet)?;
+                    }
+                }
+            }
+        }
+        _ => {
+            let mut by_group: BTreeMap<u8, Vec<KeyRefValue>> = BTreeMap::new();
+            for kv in keys.iter().map(|&kv| -> KeyRefValue { kv.into() }) {
+                by_group.entry(kv.key.group()).or_default().push(kv);
+            }
+
+            for (group, vals) in by_group {
+                let size = if group == 0 { 20 } else { 64 };
+                let max_keys = (size - 8) / 4;
+
+                for chunk in vals.chunks(max_keys) {
+                    if let Some(packet) = timing::time(Phase::Build, || {
+                        keyboard::packet::set_key_refs_packet(model, chunk)
+                    }) {
+                        kbd.send_packet(&packet)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Brightness scaling layered over any [`KeyboardApi`].
+
+use anyhow::Result;
+use core::time::Duration;
+
+use crate::clock::Clock;
+use crate::keyboard::{
+    Color, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+
+impl Color {
+    /// This color at `percent` brightness (clamped to 100).
+    #[must_use]
+    pub fn scaled(self, percent: u8) -> Self {
+        let percent = u16::from(percent.min(100));
+        // Rounded, and at most 255 * 100 / 100, so the cast back is lossless.
+        #[allow(clippy::cast_possible_truncation)]
+        let scale = |c: u8| ((u16::from(c) * percent + 50) / 100) as u8;
+        Self::new(scale(self.red), scale(self.green), scale(self.blue))
+    }
+
+    /// This color at `percent` of its saturation (clamped to 100), keeping
+    /// its brightness: 0 is the gray of the brightest channel.
+    #[must_use]
+    pub fn saturated(self, percent: u8) -> Self {
+        let percent = u16::from(percent.min(100));
+        let max = u16::from(self.red.max(self.green).max(self.blue));
+        // Between the channel and `max`, so the cast back is lossless.
+        #[allow(clippy::cast_possible_truncation)]
+        let scale = |c: u8| (max - ((max - u16::from(c)) * percent + 50) / 100) as u8;
+        Self::new(scale(self.red), scale(self.green), scale(self.blue))
+    }
+}
+
+/// Forwards every call to `inner` with colors scaled to `percent` brightness.
+pub struct Dimmed<'a, K: ?Sized> {
+    inner: &'a mut K,
+    percent: u8,
+    /// Brightness effects are sent at, or `None` to drop effects and mode
+    /// changes that an earlier step of a ramp already sent.
+    settings: Option<u8>,
+}
+
+impl<'a, K: KeyboardApi + ?Sized> Dimmed<'a, K> {
+    pub fn new(inner: &'a mut K, percent: u8) -> Self {
+        let percent = percent.min(100);
+        Self {
+            inner,
+            percent,
+            settings: Some(percent),
+        }
+    }
+}
+
+impl<K: KeyboardApi + ?Sized> KeyboardApi for Dimmed<'_, K> {
+    fn model(&self) -> KeyboardModel {
+        self.inner.model()
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.inner.set_all_keys(color.scaled(self.percent))
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.inner.set_group_keys(group, color.scaled(self.percent))
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        let scaled: Vec<KeyValue> = keys
+            .iter()
+            .map(|kv| KeyValue {
+                key: kv.key,
+                color: kv.color.scaled(self.percent),
+            })
+            .collect();
+        self.inner.set_keys(&scaled)
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        let scaled: Vec<KeyRefValue> = keys
+            .iter()
+            .map(|kv| KeyRefValue {
+                key: kv.key,
+                color: kv.color.scaled(self.percent),
+            })
+            .collect();
+        self.inner.set_key_refs(&scaled)
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.inner.set_region(region, color.scaled(self.percent))
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        if self.settings.is_none() {
+            return Ok(());
+        }
+        self.inner.set_mr_key(value)
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        if self.settings.is_none() {
+            return Ok(());
+        }
+        self.inner.set_mn_key(value)
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        if self.settings.is_none() {
+            return Ok(());
+        }
+        self.inner.set_gkeys_mode(value)
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        if self.settings.is_none() {
+            return Ok(());
+        }
+        self.inner.set_startup_mode(mode)
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        if self.settings.is_none() {
+            return Ok(());
+        }
+        self.inner.set_on_board_mode(mode)
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        let Some(percent) = self.settings else {
+            return Ok(());
+        };
+        self.inner
+            .set_fx(effect, part, period, color.scaled(percent), storage)
+    }
+}
+
+/// Step brightness from `from` to `to` percent over `over`, one percent at a time.
+///
+/// `apply` draws the layout through the dimmed keyboard and should commit;
+/// the steps are spaced on `clock`. Only colors are sent again at each step:
+/// effects and mode changes go out on the first step alone, with effect
+/// colors at `to` brightness, so a stored effect is written to on-board
+/// memory once rather than on every step.
+pub fn ramp<K, F>(
+    kbd: &mut K,
+    from: u8,
+    to: u8,
+    over: Duration,
+    mut apply: F,
+    clock: &dyn Clock,
+) -> Result<()>
+where
+    K: KeyboardApi + ?Sized,
+    F: FnMut(&mut Dimmed<'_, K>) -> Result<()>,
+{
+    let (from, to) = (from.min(100), to.min(100));
+    let steps = u32::from(from.abs_diff(to)).max(1);
+    let interval = over / steps;
+    for step in 0..=steps {
+        let offset = step * u32::from(to.abs_diff(from)) / steps;
+        // Bounded by `from.abs_diff(to)`, so this stays in 0..=100.
+        #[allow(clippy::cast_possible_truncation)]
+        let offset = offset as u8;
+        let percent = if to >= from {
+            from + offset
+        } else {
+            from - offset
+        };
+        apply(&mut Dimmed {
+            inner: &mut *kbd,
+            percent,
+            settings: (step == 0).then_some(to),
+        })?;
+        if step < steps {
+            clock.sleep(interval);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::keyboard::Key;
+    use crate::testing::MockKeyboard;
+
+    #[test]
+    fn scaling_rounds_and_clamps() {
+        let color = Color::new(0xff, 0x80, 0x01);
+        assert_eq!(color.scaled(100), color);
+        assert_eq!(color.scaled(0), Color::new(0, 0, 0));
+        assert_eq!(color.scaled(50), Color::new(0x80, 0x40, 0x01));
+        assert_eq!(color.scaled(250), color);
+    }
+
+    #[test]
+    fn saturation_moves_toward_the_brightest_channel() {
+        let color = Color::new(0xff, 0x80, 0x00);
+        assert_eq!(color.saturated(100), color);
+        assert_eq!(color.saturated(0), Color::new(0xff, 0xff, 0xff));
+        assert_eq!(color.saturated(50), Color::new(0xff, 0xbf, 0x7f));
+        assert_eq!(
+            Color::new(0x40, 0x40, 0x40).saturated(30),
+            Color::new(0x40, 0x40, 0x40)
+        );
+    }
+
+    #[test]
+    fn ramp_steps_each_percent() {
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        let clock = ManualClock::default();
+        ramp(
+            &mut mock,
+            0,
+            4,
+            Duration::from_secs(2),
+            |kbd| {
+                kbd.set_all_keys(Color::new(100, 100, 100))?;
+                kbd.commit()
+            },
+            &clock,
+        )
+        .unwrap();
+
+        let steps: Vec<String> = (0..=4)
+            .flat_map(|p| [format!("all {}", Color::new(p, p, p)), "commit".into()])
+            .collect();
+        assert_eq!(mock.calls, steps);
+        assert_eq!(clock.now(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn ramp_down_and_flat() {
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        let apply = |kbd: &mut Dimmed<'_, MockKeyboard>| kbd.set_all_keys(Color::new(100, 0, 0));
+        ramp(
+            &mut mock,
+            100,
+            98,
+            Duration::ZERO,
+            apply,
+            &ManualClock::default(),
+        )
+        .unwrap();
+        assert_eq!(mock.calls, vec!["all 640000", "all 630000", "all 620000"]);
+
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        ramp(
+            &mut mock,
+            40,
+            40,
+            Duration::ZERO,
+            apply,
+            &ManualClock::default(),
+        )
+        .unwrap();
+        assert_eq!(mock.calls.len(), 2);
+    }
+
+    #[test]
+    fn ramp_sends_effects_once() {
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        ramp(
+            &mut mock,
+            0,
+            2,
+            Duration::ZERO,
+            |kbd| {
+                kbd.set_fx(
+                    NativeEffect::Color,
+                    NativeEffectPart::All,
+                    Duration::ZERO,
+                    Color::new(200, 0, 0),
+                    NativeEffectStorage::User,
+                )?;
+                kbd.set_startup_mode(StartupMode::Color)?;
+                kbd.set_all_keys(Color::new(100, 0, 0))
+            },
+            &ManualClock::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            mock.calls,
+            [
+                "fx color all 0ms 040000 User",
+                "startup color",
+                "all 000000",
+                "all 010000",
+                "all 020000"
+            ]
+        );
+    }
+
+    #[test]
+    fn dimmed_scales_colors_and_forwards_commit() {
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        let mut dimmed = Dimmed::new(&mut mock, 50);
+        dimmed.set_all_keys(Color::new(200, 100, 0)).unwrap();
+        dimmed
+            .set_keys(&[KeyValue {
+                key: Key::A,
+                color: Color::new(20, 0, 0),
+            }])
+            .unwrap();
+        dimmed.commit().unwrap();
+
+        assert_eq!(mock.calls, vec!["all 643200", "key A 0a0000", "commit"]);
+    }
+}
@@ -1 +1,719 @@
+use anyhow::anyhow;
+use std::fmt::Write as _;
+use std::{
+    borrow::Cow,
+    fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::clock::Clock;
+use crate::keyboard::model::closest_model;
+use crate::keyboard::spec::RateLimit;
+use crate::output;
+
 pub use crate::keyboard::{DeviceInfo, KeyboardModel, lookup_model};
+
+/// Interface the LED protocol normally lives on.
+pub const LED_INTERFACE: u8 = 1;
+
+/// Output reports the LED interface declares, with their full lengths.
+const REPORT_LENGTHS: [(u8, usize); 3] = [(0x10, 7), (0x11, 20), (0x12, 64)];
+
+/// `data` padded to the length its report ID declares.
+///
+/// hidraw and Windows accept short writes; the macOS HID manager and the BSD
+/// USB stacks reject them.
+pub fn full_report(data: &[u8]) -> Cow<'_, [u8]> {
+    let declared = data.first().and_then(|id| {
+        REPORT_LENGTHS
+            .iter()
+            .find(|(report, _)| report == id)
+            .map(|&(_, len)| len)
+    });
+    match declared {
+        Some(len) if data.len() < len => {
+            let mut report = data.to_vec();
+            report.resize(len, 0x00);
+            Cow::Owned(report)
+        }
+        _ => Cow::Borrowed(data),
+    }
+}
+
+/// Vendor usage pages the LED collections sit on: 0xff43 on current boards,
+/// 0xff00 on older ones.
+pub const LED_USAGE_PAGES: [u16; 2] = [0xff43, 0xff00];
+
+/// Boot keyboard interface, which carries the keys themselves.
+const BOOT_INTERFACE: u8 = 0;
+
+/// Interfaces to try, in order: the LED interface first, then the others
+/// whose top-level usage page is an LED one, by number.
+///
+/// `available` pairs each interface with its usage page. The boot keyboard
+/// interface is never a candidate: taking it from the kernel would leave the
+/// board unable to type.
+pub fn interface_candidates(available: impl IntoIterator<Item = (u8, Option<u16>)>) -> Vec<u8> {
+    let mut others: Vec<u8> = available
+        .into_iter()
+        .filter(|&(n, page)| {
+            n != LED_INTERFACE
+                && n != BOOT_INTERFACE
+                && page.is_some_and(|page| LED_USAGE_PAGES.contains(&page))
+        })
+        .map(|(n, _)| n)
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+    [LED_INTERFACE].into_iter().chain(others).collect()
+}
+
+/// Error for when no candidate interface could be used, listing each attempt.
+pub fn no_interface_error(attempts: &[(u8, String)]) -> anyhow::Error {
+    let tried = attempts
+        .iter()
+        .map(|(iface, err)| format!("  interface {iface}: {err}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    // `bsd` implies `libusb`, so it goes first.
+    let quirk = if cfg!(feature = "bsd") {
+        "\nOn the BSDs, detaching uhid/hkbd needs root; alternatively disable the driver \
+         for this device and grant access to its ugen node with a devd rule (see `logi-led doctor`)."
+    } else if cfg!(feature = "libusb") {
+        "\nIf usbhid keeps grabbing it, the `usbhid.quirks=0x046d:<pid>:0x4` kernel \
+         parameter hands the whole device to libusb."
+    } else if cfg!(target_os = "macos") {
+        "\nOn macOS, allow your terminal under System Settings > Privacy & Security > \
+         Input Monitoring and run it again."
+    } else {
+        ""
+    };
+    anyhow!(
+        "could not claim any interface:\n{tried}\n\
+         hint: another driver or LED tool (OpenRGB, G HUB in a VM) may hold the device; \
+         run `logi-led doctor`.{quirk}"
+    )
+}
+
+/// Tell the user when the device was only reachable through a fallback interface.
+pub fn report_fallback(interface: u8) {
+    if interface != LED_INTERFACE {
+        output::note(format_args!(
+            "interface {LED_INTERFACE} was unavailable; using interface {interface}"
+        ));
+    }
+}
+
+/// One HID interface of a device, supported or not, for detection reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidInterface {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub product: Option<String>,
+    pub interface: Option<u8>,
+    /// Top-level usage page and usage, where the backend can see them.
+    pub usage_page: Option<u16>,
+    pub usage: Option<u16>,
+}
+
+/// `interface 1: usage page 0xff43, usage 0x0602`.
+impl fmt::Display for HidInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.interface {
+            Some(number) => write!(f, "interface {number}: ")?,
+            None => write!(f, "interface ?: ")?,
+        }
+        match (self.usage_page, self.usage) {
+            (Some(page), Some(usage)) => write!(f, "usage page {page:#06x}, usage {usage:#06x}"),
+            (Some(page), None) => write!(f, "usage page {page:#06x}"),
+            _ => write!(f, "usage unknown"),
+        }
+    }
+}
+
+/// Top-level usage page and usage of a HID report descriptor: the first of
+/// each short item, before any collection.
+pub fn report_usage(descriptor: &[u8]) -> (Option<u16>, Option<u16>) {
+    let (mut page, mut usage) = (None, None);
+    let mut rest = descriptor;
+    while let Some((&prefix, tail)) = rest.split_first() {
+        if prefix == 0xfe {
+            // Long item: data size, tag, data.
+            let size = tail.first().map_or(0, |&n| usize::from(n));
+            rest = tail.get(2 + size..).unwrap_or_default();
+            continue;
+        }
+        let size = [0, 1, 2, 4][usize::from(prefix & 0x03)];
+        let Some(data) = tail.get(..size) else { break };
+        let value = data
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &b| (acc << 8) | u32::from(b));
+        let value = u16::try_from(value & 0xffff).unwrap_or_default();
+        match prefix & 0xfc {
+            0x04 => page = page.or(Some(value)),
+            0x08 => usage = usage.or(Some(value)),
+            0xa0 => break, // Collection: the top-level usage is known by now.
+            _ => {}
+        }
+        rest = &tail[size..];
+    }
+    (page, usage)
+}
+
+/// Where unsupported devices are reported, with the report as the body.
+const NEW_ISSUE_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues/new");
+
+/// Describe the unsupported devices among `interfaces`, with a link that
+/// opens a support request carrying the same data. `None` when every device
+/// is supported, so there is nothing to report.
+pub fn detection_report(interfaces: &[HidInterface], backend: &str) -> Option<String> {
+    let mut devices: Vec<(u16, u16)> = interfaces
+        .iter()
+        .filter(|i| lookup_model(i.vendor_id, i.product_id) == KeyboardModel::Unknown)
+        .map(|i| (i.vendor_id, i.product_id))
+        .collect();
+    devices.sort_unstable();
+    devices.dedup();
+    let title = devices
+        .first()
+        .map(|(vid, pid)| format!("Support for {vid:04x}:{pid:04x}"))?;
+
+    let mut data = String::new();
+    for &(vid, pid) in &devices {
+        let ifaces: Vec<&HidInterface> = interfaces
+            .iter()
+            .filter(|i| (i.vendor_id, i.product_id) == (vid, pid))
+            .collect();
+        let name = ifaces.iter().find_map(|i| i.product.as_deref());
+        let _ = write!(data, "{vid:04x}:{pid:04x} {}", name.unwrap_or("(no name)"));
+        if let Some((model, known)) = closest_model(pid) {
+            let _ = write!(data, " (closest known: {model:?}, {known:04x})");
+        }
+        data.push('\n');
+        for iface in ifaces {
+            let _ = writeln!(data, "  {iface}");
+        }
+    }
+    let _ = writeln!(
+        data,
+        "backend: {backend}, logi-led {}",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let body = format!("Detection report:\n\n```\n{data}```\n");
+    let mut report =
+        String::from("no supported keyboard found; these devices are not supported yet:\n");
+    for line in data.lines() {
+        let _ = writeln!(report, "  {line}");
+    }
+    let _ = write!(
+        report,
+        "if one is a keyboard, try it with `--tuk <protocol> -p <pid>`, and ask for support at:\n  \
+         {NEW_ISSUE_URL}?title={}&body={}",
+        percent_encode(&title),
+        percent_encode(&body)
+    );
+    Some(report)
+}
+
+/// Percent-encode `s` for a URL query value.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+/// How long `--check-acks` waits for the board's response to each packet.
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Run after sending `request`: wait for its response if pacing or
+/// `check_acks` wants it, vet it when checking, then pause on `clock` as
+/// `model` requires.
+///
+/// `read` fills the buffer within the timeout and returns the length read; a
+/// board that stays silent is not an error. Reports that do not answer
+/// `request`, such as G-key presses or a late answer to an earlier packet,
+/// are read past, as [`Keyboard::request`](super::Keyboard::request) does.
+pub fn after_send(
+    clock: &dyn Clock,
+    model: KeyboardModel,
+    request: &[u8],
+    check_acks: bool,
+    mut read: impl FnMut(&mut [u8], Duration) -> usize,
+) -> anyhow::Result<()> {
+    let pacing = model.spec().pacing;
+    let timeout = if check_acks {
+        Some(ACK_TIMEOUT)
+    } else {
+        pacing.and_then(|p| p.ack_timeout)
+    };
+    if let Some(timeout) = timeout {
+        let deadline = clock.now() + timeout;
+        let mut response = [0u8; 64];
+        loop {
+            let left = deadline.saturating_sub(clock.now());
+            let len = if left.is_zero() {
+                0
+            } else {
+                read(&mut response, left).min(response.len())
+            };
+            if len == 0 {
+                break;
+            }
+            if answers(request, &response[..len]) {
+                if check_acks {
+                    check_response(&response[..len])?;
+                }
+                break;
+            }
+        }
+    }
+    if let Some(pacing) = pacing {
+        clock.sleep(pacing.delay);
+    }
+    Ok(())
+}
+
+/// Keeps a keyboard's packets within its model's [`RateLimit`].
+#[derive(Debug, Default)]
+pub struct Throttle {
+    last_packet: Option<Duration>,
+    last_commit: Option<Duration>,
+}
+
+impl Throttle {
+    /// Wait on `clock` until `data` may go out to `model`; call right before
+    /// sending it.
+    pub fn wait(&mut self, clock: &dyn Clock, model: KeyboardModel, data: &[u8]) {
+        let Some(limit) = model.rate_limit() else {
+            return;
+        };
+        let commit = model.spec().commit.is_some_and(|c| data.starts_with(c));
+        let delay = self.delay(limit, commit, clock.now());
+        if !delay.is_zero() {
+            clock.sleep(delay);
+        }
+        let now = clock.now();
+        self.last_packet = Some(now);
+        if commit {
+            self.last_commit = Some(now);
+        }
+    }
+
+    /// How long to hold a packet (a commit if `commit`) back at `now`.
+    fn delay(&self, limit: RateLimit, commit: bool, now: Duration) -> Duration {
+        let until = |last: Option<Duration>, gap: Duration| {
+            last.map_or(Duration::ZERO, |last| (last + gap).saturating_sub(now))
+        };
+        let spacing = match limit.packets_per_sec {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        };
+        let packet = until(self.last_packet, spacing);
+        if commit {
+            packet.max(until(self.last_commit, limit.min_commit_gap))
+        } else {
+            packet
+        }
+    }
+}
+
+/// Whether `report` answers HID++ `request`, or reports an error about it.
+pub fn answers(request: &[u8], report: &[u8]) -> bool {
+    let (Some(asked), Some(&id)) = (request.get(1..4), report.first()) else {
+        return false;
+    };
+    match report.get(1..5) {
+        // Errors move the feature and function one byte along.
+        Some(&[device, 0xff | 0x8f, feature, function]) if id != 0x12 => {
+            [device, feature, function] == asked
+        }
+        Some(answer) if (0x10..=0x12).contains(&id) => &answer[..3] == asked,
+        _ => false,
+    }
+}
+
+/// Turn an HID++ error report into an error; any other report is an ack.
+pub fn check_response(report: &[u8]) -> anyhow::Result<()> {
+    match *report {
+        [0x10 | 0x11, _, 0xff, feature, function, code, ..] => Err(anyhow!(
+            "keyboard rejected packet for feature {feature:#04x} function {}: {}",
+            function >> 4,
+            hidpp20_error(code)
+        )),
+        [0x10 | 0x11, _, 0x8f, sub_id, address, code, ..] => Err(anyhow!(
+            "keyboard rejected packet {sub_id:#04x}/{address:#04x}: {}",
+            hidpp10_error(code)
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn hidpp20_error(code: u8) -> String {
+    match code {
+        0x01 => "unknown error".into(),
+        0x02 => "invalid argument".into(),
+        0x03 => "value out of range".into(),
+        0x04 => "hardware error".into(),
+        0x05 => "internal error".into(),
+        0x06 => "invalid feature index".into(),
+        0x07 => "invalid function".into(),
+        0x08 => "device busy".into(),
+        0x09 => "unsupported".into(),
+        _ => format!("error {code:#04x}"),
+    }
+}
+
+fn hidpp10_error(code: u8) -> String {
+    match code {
+        0x01 => "invalid command".into(),
+        0x02 => "invalid address".into(),
+        0x03 => "invalid value".into(),
+        0x07 => "device busy".into(),
+        0x0a => "request unavailable".into(),
+        0x0b => "invalid parameter".into(),
+        _ => format!("error {code:#04x}"),
+    }
+}
+
+/// A process that has a device node open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holder {
+    pub pid: u32,
+    pub name: String,
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (pid {})", self.name, self.pid)
+    }
+}
+
+/// Other processes holding any of `paths` open, found by scanning `/proc/*/fd`.
+///
+/// Processes owned by other users are skipped silently unless we are root,
+/// and the result is empty on systems without `/proc`.
+pub fn processes_holding(paths: &[&str]) -> Vec<Holder> {
+    let own = std::process::id();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            if pid == own {
+                return None;
+            }
+            let holds = std::fs::read_dir(entry.path().join("fd"))
+                .ok()?
+                .flatten()
+                .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+                .any(|target| paths.iter().any(|p| target == Path::new(p)));
+            holds.then(|| Holder {
+                pid,
+                name: std::fs::read_to_string(entry.path().join("comm"))
+                    .map_or_else(|_| "?".to_owned(), |comm| comm.trim().to_owned()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn reports_are_padded_to_their_declared_length() {
+        let commit = [0x11, 0xff, 0x0c, 0x5a];
+        assert_eq!(full_report(&commit).len(), 20);
+        assert_eq!(full_report(&commit)[..4], commit);
+        assert_eq!(full_report(&[0x12, 0xff]).len(), 64);
+        assert!(matches!(full_report(&[0x42, 1]), Cow::Borrowed(_)));
+        assert!(matches!(full_report(&[]), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn hidpp_errors_are_decoded() {
+        let err = check_response(&[0x11, 0xff, 0xff, 0x0c, 0x3a, 0x03, 0, 0]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "keyboard rejected packet for feature 0x0c function 3: value out of range"
+        );
+        let err = check_response(&[0x10, 0xff, 0x8f, 0x81, 0x02, 0x02, 0]).unwrap_err();
+        assert!(err.to_string().ends_with("invalid address"));
+        // An echo of the request, or nothing at all, is an ack.
+        assert!(check_response(&[0x11, 0xff, 0x0c, 0x3a, 0x00, 0x10]).is_ok());
+        assert!(check_response(&[]).is_ok());
+    }
+
+    /// A request for feature 0x0c, function 3, as `--check-acks` sees it.
+    const REQUEST: [u8; 6] = [0x11, 0xff, 0x0c, 0x3a, 0x00, 0x10];
+
+    /// Feeds `reports` to `after_send` one read at a time, then silence.
+    fn reader(reports: &[&[u8]]) -> impl FnMut(&mut [u8], Duration) -> usize + use<> {
+        let mut reports: Vec<Vec<u8>> = reports.iter().rev().map(|r| r.to_vec()).collect();
+        move |buf, _| {
+            reports.pop().map_or(0, |report| {
+                buf[..report.len()].copy_from_slice(&report);
+                report.len()
+            })
+        }
+    }
+
+    #[test]
+    fn after_send_reads_only_when_needed() {
+        // Unpaced boards neither read nor wait.
+        for model in [KeyboardModel::G810, KeyboardModel::G815] {
+            let clock = ManualClock::default();
+            let mut reads = 0;
+            after_send(&clock, model, &REQUEST, false, |_, _| {
+                reads += 1;
+                0
+            })
+            .unwrap();
+            assert_eq!(reads, 0);
+            assert_eq!(clock.now(), Duration::ZERO);
+        }
+
+        // The G910 waits for its ack, then pauses on the clock.
+        let clock = ManualClock::default();
+        after_send(
+            &clock,
+            KeyboardModel::G910,
+            &REQUEST,
+            false,
+            |_, timeout| {
+                assert_eq!(timeout, Duration::from_millis(20));
+                0
+            },
+        )
+        .unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(1));
+
+        let err = after_send(
+            &ManualClock::default(),
+            KeyboardModel::G810,
+            &REQUEST,
+            true,
+            |buf, timeout| {
+                assert_eq!(timeout, ACK_TIMEOUT);
+                buf[..6].copy_from_slice(&[0x11, 0xff, 0xff, 0x0c, 0x3a, 0x06]);
+                6
+            },
+        );
+        assert!(
+            err.unwrap_err()
+                .to_string()
+                .contains("invalid feature index")
+        );
+    }
+
+    #[test]
+    fn acks_are_matched_to_their_packet() {
+        let gkey: &[u8] = &[0x11, 0xff, 0x08, 0x00, 0x01];
+        let earlier_error: &[u8] = &[0x11, 0xff, 0xff, 0x0d, 0x1a, 0x06];
+        let ack: &[u8] = &REQUEST;
+        let error: &[u8] = &[0x11, 0xff, 0xff, 0x0c, 0x3a, 0x06];
+        let check = |reports: &[&[u8]]| {
+            let read = reader(reports);
+            after_send(
+                &ManualClock::default(),
+                KeyboardModel::G810,
+                &REQUEST,
+                true,
+                read,
+            )
+        };
+        // Neither a key press nor another packet's error counts against this one.
+        assert!(check(&[gkey, earlier_error, ack]).is_ok());
+        assert!(check(&[gkey, earlier_error]).is_ok());
+        assert!(check(&[gkey, error]).is_err());
+        // Reading stops at the answer, leaving later reports for the next packet.
+        let mut reads = reader(&[ack, error]);
+        let mut count = 0;
+        after_send(
+            &ManualClock::default(),
+            KeyboardModel::G810,
+            &REQUEST,
+            true,
+            |buf, left| {
+                count += 1;
+                reads(buf, left)
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn finds_our_own_open_file_in_other_processes_only() {
+        let path = std::env::temp_dir().join("logi-led-holder-test");
+        let _file = std::fs::File::create(&path).unwrap();
+        let path = path.to_str().unwrap();
+        // We hold it ourselves, which must not count as a conflict.
+        assert_eq!(processes_holding(&[path]), vec![]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reports_a_child_holding_the_file() {
+        let path = std::env::temp_dir().join("logi-led-child-holder-test");
+        std::fs::File::create(&path).unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .stdin(std::fs::File::open(&path).unwrap())
+            .spawn()
+            .unwrap();
+        let holders = processes_holding(&[path.to_str().unwrap()]);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(
+            holders,
+            vec![Holder {
+                pid: child.id(),
+                name: "sleep".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn led_interface_is_tried_first() {
+        let led = Some(0xff43);
+        assert_eq!(interface_candidates([(2, led), (1, led)]), vec![1, 2]);
+        assert_eq!(interface_candidates([]), vec![1]);
+    }
+
+    #[test]
+    fn only_led_interfaces_are_fallbacks() {
+        let candidates = interface_candidates([
+            (0, Some(0xff43)),
+            (3, Some(0xff00)),
+            (2, Some(0x0001)),
+            (3, Some(0xff00)),
+            (4, None),
+            (2, Some(0xff43)),
+        ]);
+        assert_eq!(candidates, vec![1, 2, 3]);
+    }
+
+    fn interface(pid: u16, number: u8, usage_page: u16) -> HidInterface {
+        HidInterface {
+            vendor_id: 0x046d,
+            product_id: pid,
+            product: Some("G915 Keyboard".into()),
+            interface: Some(number),
+            usage_page: Some(usage_page),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn detection_report_lists_unsupported_devices() {
+        let supported = interface(0xc331, 1, 0xff43);
+        assert_eq!(
+            detection_report(std::slice::from_ref(&supported), "hidapi"),
+            None
+        );
+
+        let report = detection_report(
+            &[
+                supported,
+                interface(0xc340, 0, 0x0001),
+                interface(0xc340, 1, 0xff43),
+            ],
+            "hidapi",
+        )
+        .unwrap();
+        assert!(!report.contains("c331"));
+        assert!(report.contains("  046d:c340 G915 Keyboard (closest known: G815, c33f)\n"));
+        assert!(report.contains("    interface 1: usage page 0xff43\n"));
+        assert!(report.contains("/issues/new?title=Support%20for%20046d%3Ac340&body=Detection"));
+    }
+
+    #[test]
+    fn usage_comes_from_the_top_level_items() {
+        // Boot keyboard, then the G810's vendor-defined HID++ interface.
+        assert_eq!(
+            report_usage(&[0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07]),
+            (Some(0x0001), Some(0x0006))
+        );
+        assert_eq!(
+            report_usage(&[0x06, 0x43, 0xff, 0x0a, 0x02, 0x06, 0xa1, 0x01]),
+            (Some(0xff43), Some(0x0602))
+        );
+        assert_eq!(report_usage(&[0x06, 0x43]), (None, None));
+    }
+
+    #[test]
+    fn urls_are_percent_encoded() {
+        assert_eq!(percent_encode("a b:c\n~"), "a%20b%3Ac%0A~");
+    }
+
+    #[test]
+    fn error_lists_attempts() {
+        let err = no_interface_error(&[(1, "Busy".into()), (0, "Access denied".into())]);
+        let msg = err.to_string();
+        assert!(msg.contains("interface 1: Busy"));
+        assert!(msg.contains("interface 0: Access denied"));
+    }
+
+    #[cfg(feature = "bsd")]
+    #[test]
+    fn bsd_error_explains_devd() {
+        let msg = no_interface_error(&[(1, "Access denied".into())]).to_string();
+        assert!(msg.contains("detaching uhid/hkbd needs root"));
+        assert!(!msg.contains("usbhid.quirks"));
+    }
+
+    #[cfg(all(feature = "libusb", not(feature = "bsd")))]
+    #[test]
+    fn libusb_error_suggests_the_usbhid_quirk() {
+        let msg = no_interface_error(&[(1, "Busy".into())]).to_string();
+        assert!(msg.contains("usbhid.quirks=0x046d:<pid>:0x4"));
+    }
+
+    #[test]
+    fn throttle_spaces_packets_and_commits() {
+        let limit = RateLimit {
+            packets_per_sec: 100,
+            min_commit_gap: Duration::from_millis(30),
+        };
+        let start = Duration::from_secs(10);
+        let mut throttle = Throttle::default();
+        assert_eq!(throttle.delay(limit, true, start), Duration::ZERO);
+
+        throttle.last_packet = Some(start);
+        throttle.last_commit = Some(start);
+        let later = start + Duration::from_millis(4);
+        assert_eq!(
+            throttle.delay(limit, false, later),
+            Duration::from_millis(6)
+        );
+        assert_eq!(
+            throttle.delay(limit, true, later),
+            Duration::from_millis(26)
+        );
+        let unlimited = RateLimit::default();
+        assert_eq!(throttle.delay(unlimited, true, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn answers_match_the_request() {
+        let request = [0x11, 0xff, 0x02, 0x1b, 0x00];
+        assert!(answers(&request, &[0x11, 0xff, 0x02, 0x1b, 0x04]));
+        assert!(answers(&request, &[0x11, 0xff, 0xff, 0x02, 0x1b, 0x05]));
+        assert!(!answers(&request, &[0x11, 0xff, 0x02, 0x0b, 0x04]));
+        assert!(!answers(&request, &[0x11, 0x01, 0x02, 0x1b, 0x04]));
+        assert!(!answers(&request, &[]));
+    }
+}
@@ -1,10 +1,20 @@
-use super::common::{DeviceInfo, KeyboardModel, lookup_model};
+use super::common::{
+    DeviceInfo, HidInterface, KeyboardModel, LED_USAGE_PAGES, Throttle, after_send, full_report,
+    interface_candidates, lookup_model, no_interface_error, report_fallback,
+};
+use crate::clock::SystemClock;
+use crate::i18n::tr;
+use crate::packet_log;
+use crate::timing::{self, Phase};
 use anyhow::{Result, anyhow};
 use hidapi::{HidApi, HidDevice};
 use std::borrow::ToOwned;
+use std::collections::HashSet;
+use std::time::Duration;
 
 fn to_device_info_hid(dev: &hidapi::DeviceInfo) -> DeviceInfo {
     DeviceInfo {
+        path: dev.path().to_string_lossy().into_owned(),
         vendor_id: dev.vendor_id(),
         product_id: dev.product_id(),
         manufacturer: dev.manufacturer_string().map(ToOwned::to_owned),
@@ -14,27 +24,109 @@ fn to_device_info_hid(dev: &hidapi::DeviceInfo) -> DeviceInfo {
     }
 }
 
+/// Keep the first device listed under each path, in enumeration order.
+///
+/// macOS lists each top-level collection of an interface under the same path,
+/// not necessarily next to each other.
+fn drop_repeated_paths(devices: &mut Vec<DeviceInfo>) {
+    let mut seen = HashSet::new();
+    devices.retain(|d| seen.insert(d.path.clone()));
+}
+
+/// A context that has not listed any devices yet.
+///
+/// `HidApi::new` would otherwise read the strings of every HID device on the
+/// system, which is most of a command's run time on busy machines. Discovery
+/// is off for the whole process, so every context must come from here.
+fn context() -> Result<HidApi> {
+    HidApi::disable_device_discovery();
+    Ok(HidApi::new()?)
+}
+
+/// Usage of the LED collection that takes 64-byte reports (ID 0x12).
+const LONG_REPORT_USAGE: u16 = 0x0604;
+
+/// Order to try the top-level collections of one interface in: the LED
+/// collection for short reports, then the one for long reports, then the rest.
+///
+/// Windows lists every collection on its own and only accepts a report on the
+/// collection that declares it.
+fn collection_rank(usage_page: u16, usage: u16) -> u8 {
+    match (
+        LED_USAGE_PAGES.contains(&usage_page),
+        usage == LONG_REPORT_USAGE,
+    ) {
+        (true, false) => 0,
+        (true, true) => 1,
+        _ => 2,
+    }
+}
+
+/// The handle a report of `len` bytes goes through: the long-report
+/// collection when it was opened on its own, the main handle otherwise.
+fn handle<'a>(
+    device: Option<&'a HidDevice>,
+    long: Option<&'a HidDevice>,
+    len: usize,
+) -> Result<&'a HidDevice> {
+    let dev = device.ok_or_else(|| anyhow!("no device open"))?;
+    Ok(match long {
+        Some(long) if len == 64 => long,
+        _ => dev,
+    })
+}
+
+/// Human-readable name of this backend.
+pub const BACKEND: &str = "hidapi";
+
 pub struct Keyboard {
     _api: HidApi,
     device: Option<HidDevice>,
+    /// The 64-byte report collection, where the platform opens it separately.
+    long: Option<HidDevice>,
     current: Option<DeviceInfo>,
+    check_acks: bool,
+    throttle: Throttle,
 }
 
 impl Keyboard {
     /// Enumerate supported keyboards.
     pub fn list_keyboards() -> Result<Vec<DeviceInfo>> {
-        let api = HidApi::new()?;
-        let devices = api
+        let _enumerating = timing::start(Phase::Enumerate);
+        let mut api = context()?;
+        api.add_devices(0, 0)?;
+        let mut devices: Vec<DeviceInfo> = api
             .device_list()
             .filter(|d| lookup_model(d.vendor_id(), d.product_id()) != KeyboardModel::Unknown)
             .map(to_device_info_hid)
             .collect();
+        drop_repeated_paths(&mut devices);
         Ok(devices)
     }
 
+    /// Every HID interface of `vendor_id`'s devices, supported or not.
+    pub fn vendor_interfaces(vendor_id: u16) -> Result<Vec<HidInterface>> {
+        let mut api = context()?;
+        api.add_devices(vendor_id, 0)?;
+        Ok(api
+            .device_list()
+            .map(|d| HidInterface {
+                vendor_id: d.vendor_id(),
+                product_id: d.product_id(),
+                product: d.product_string().map(ToOwned::to_owned),
+                interface: u8::try_from(d.interface_number()).ok(),
+                usage_page: Some(d.usage_page()).filter(|&page| page != 0),
+                usage: (d.usage_page() != 0).then_some(d.usage()),
+            })
+            .collect())
+    }
+
     /// Open a keyboard. If `vendor_id` or `product_id` are 0 they are ignored.
     pub fn open(vendor_id: u16, product_id: u16, serial: Option<&str>) -> Result<Self> {
-        let api = HidApi::new()?;
+        let enumerating = timing::start(Phase::Enumerate);
+        let mut api = context()?;
+        // Only walk the requested IDs; 0 matches any.
+        api.add_devices(vendor_id, product_id)?;
         let devices = api
             .device_list()
             .filter(|d| lookup_model(d.vendor_id(), d.product_id()) != KeyboardModel::Unknown)
@@ -43,23 +135,80 @@ impl Keyboard {
                     && (product_id == 0 || d.product_id() == product_id)
             })
             .collect::<Vec<_>>();
+        drop(enumerating);
 
-        let dev_info = if let Some(sn) = serial {
+        let first = if let Some(sn) = serial {
             devices
-                .into_iter()
+                .iter()
                 .find(|d| d.serial_number().is_some_and(|s| s == sn))
         } else {
-            devices.into_iter().next()
+            devices.first()
+        }
+        .ok_or_else(|| anyhow!(tr("error-no-device")))?;
+
+        // hidapi lists one entry per interface; keep the chosen keyboard's.
+        let same_board = |d: &&hidapi::DeviceInfo| {
+            d.vendor_id() == first.vendor_id()
+                && d.product_id() == first.product_id()
+                && d.serial_number() == first.serial_number()
+        };
+        let interfaces: Vec<&hidapi::DeviceInfo> =
+            devices.iter().copied().filter(same_board).collect();
+        let numbers = interfaces.iter().filter_map(|d| {
+            let number = u8::try_from(d.interface_number()).ok()?;
+            Some((number, Some(d.usage_page())))
+        });
+
+        let _opening = timing::start(Phase::Open);
+        let mut attempts = Vec::new();
+        for interface in interface_candidates(numbers) {
+            let Some(dev_info) = interfaces
+                .iter()
+                .filter(|d| d.interface_number() == i32::from(interface))
+                .min_by_key(|d| collection_rank(d.usage_page(), d.usage()))
+            else {
+                continue;
+            };
+            match api.open_path(dev_info.path()) {
+                Ok(device) => {
+                    report_fallback(interface);
+                    let long = interfaces
+                        .iter()
+                        .find(|d| {
+                            d.interface_number() == i32::from(interface)
+                                && d.path() != dev_info.path()
+                                && LED_USAGE_PAGES.contains(&d.usage_page())
+                                && d.usage() == LONG_REPORT_USAGE
+                        })
+                        .and_then(|d| api.open_path(d.path()).ok());
+                    let info = to_device_info_hid(dev_info);
+                    return Ok(Self {
+                        _api: api,
+                        device: Some(device),
+                        long,
+                        current: Some(info),
+                        check_acks: false,
+                        throttle: Throttle::default(),
+                    });
+                }
+                Err(e) => attempts.push((interface, e.to_string())),
+            }
         }
-        .ok_or_else(|| anyhow!("No matching device"))?;
 
-        let device = api.open_path(dev_info.path())?;
-        let info = to_device_info_hid(dev_info);
-        Ok(Self {
-            _api: api,
-            device: Some(device),
-            current: Some(info),
-        })
+        // Backends that do not report interface numbers: fall back to the first entry.
+        if attempts.is_empty() {
+            let device = api.open_path(first.path())?;
+            let info = to_device_info_hid(first);
+            return Ok(Self {
+                _api: api,
+                device: Some(device),
+                long: None,
+                current: Some(info),
+                check_acks: false,
+                throttle: Throttle::default(),
+            });
+        }
+        Err(no_interface_error(&attempts))
     }
 
     /// Close the currently open keyboard handle.
@@ -67,6 +216,21 @@ impl Keyboard {
         if let Some(dev) = self.device.take() {
             drop(dev);
         }
+        self.long = None;
+    }
+
+    /// Close the handle; hidapi has no way to reset the device on the bus,
+    /// so this fails once the handle is closed.
+    pub fn reset(&mut self) -> Result<()> {
+        self.close();
+        Err(anyhow!(
+            "the {BACKEND} backend cannot reset a USB device; unplug the keyboard and plug it back in"
+        ))
+    }
+
+    /// Read each packet's response and fail on firmware errors (slower).
+    pub fn set_check_acks(&mut self, check: bool) {
+        self.check_acks = check;
     }
 
     /// Get information about the currently opened device.
@@ -76,12 +240,33 @@ impl Keyboard {
 
     /// Send a raw HID packet to the keyboard.
     pub fn send_packet(&mut self, data: &[u8]) -> Result<()> {
-        let dev = self
-            .device
-            .as_ref()
-            .ok_or_else(|| anyhow!("no device open"))?;
+        let _writing = timing::start(Phase::Write);
+        self.write_report(data)?;
+        let model = self.model();
+        let dev = handle(self.device.as_ref(), self.long.as_ref(), data.len())?;
+        after_send(
+            &SystemClock,
+            model,
+            data,
+            self.check_acks,
+            |response, timeout| {
+                let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+                dev.read_timeout(response, millis).unwrap_or(0)
+            },
+        )
+    }
+
+    /// Write `data` as it is, leaving any response unread.
+    pub(super) fn write_report(&mut self, data: &[u8]) -> Result<()> {
+        packet_log::record(data);
+        let model = self.model();
+        let dev = handle(self.device.as_ref(), self.long.as_ref(), data.len())?;
+        self.throttle.wait(&SystemClock, model, data);
 
         match data.len() {
+            0..=20 | 64 if cfg!(target_os = "macos") => {
+                dev.write(&full_report(data))?;
+            }
             0..=20 | 64 => {
                 dev.write(data)?;
             }
@@ -89,6 +274,22 @@ impl Keyboard {
         }
         Ok(())
     }
+
+    /// Read one input report into `buf` within `timeout`; 0 if none came.
+    pub(super) fn read_report(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let dev = self
+            .device
+            .as_ref()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        Ok(dev.read_timeout(buf, millis)?)
+    }
+
+    fn model(&self) -> KeyboardModel {
+        self.current
+            .as_ref()
+            .map_or(KeyboardModel::Unknown, |d| d.model)
+    }
 }
 
 impl Drop for Keyboard {
@@ -97,3 +298,40 @@ impl Drop for Keyboard {
         crate::keyboard::model::clear_supported_override();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_paths_are_dropped_wherever_they_are_listed() {
+        let device = |path: &str, product_id| DeviceInfo {
+            path: path.to_owned(),
+            vendor_id: 0x046d,
+            product_id,
+            manufacturer: None,
+            product: None,
+            serial_number: None,
+            model: KeyboardModel::Unknown,
+        };
+        let mut devices = vec![
+            device("a", 1),
+            device("b", 2),
+            device("a", 3),
+            device("b", 4),
+            device("c", 5),
+        ];
+        drop_repeated_paths(&mut devices);
+        let kept: Vec<_> = devices.iter().map(|d| d.product_id).collect();
+        assert_eq!(kept, [1, 2, 5]);
+    }
+
+    #[test]
+    fn led_collections_are_tried_first() {
+        assert_eq!(collection_rank(0xff43, 0x0602), 0);
+        assert_eq!(collection_rank(0xff00, 0x0001), 0);
+        assert_eq!(collection_rank(0xff43, LONG_REPORT_USAGE), 1);
+        assert_eq!(collection_rank(0x000c, 0x0001), 2);
+        assert_eq!(collection_rank(0, 0), 2);
+    }
+}
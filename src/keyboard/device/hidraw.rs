@@ -0,0 +1,343 @@
+//! Linux hidraw backend: talks to `/dev/hidraw*` directly, found through
+//! sysfs, so the binary needs neither hidapi nor libusb.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::common::{
+    DeviceInfo, HidInterface, KeyboardModel, Throttle, after_send, full_report,
+    interface_candidates, lookup_model, no_interface_error, report_fallback, report_usage,
+};
+use crate::clock::SystemClock;
+use crate::i18n::tr;
+use crate::packet_log;
+use crate::timing::{self, Phase};
+use anyhow::{Result, anyhow};
+
+/// Human-readable name of this backend.
+pub const BACKEND: &str = "hidraw";
+
+/// One entry per hidraw node, each describing its HID device.
+const SYSFS_CLASS: &str = "/sys/class/hidraw";
+
+/// A hidraw node and the USB interface it belongs to.
+#[derive(Debug)]
+struct Node {
+    number: u32,
+    info: DeviceInfo,
+    interface: Option<u8>,
+}
+
+/// Describe `hidrawN` from its HID device's `uevent` file.
+///
+/// The kernel writes `HID_ID=<bus>:<vendor>:<product>` in hex, the device
+/// name, the serial as `HID_UNIQ` and the physical path, which ends in
+/// `/input<interface>` for USB devices.
+fn parse_uevent(name: &str, uevent: &str) -> Option<Node> {
+    let number = name.strip_prefix("hidraw")?.parse().ok()?;
+    let field = |key: &str| {
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .filter(|value| !value.is_empty())
+    };
+    let mut ids = field("HID_ID")?.split(':').skip(1);
+    let vendor_id = u16::from_str_radix(ids.next()?, 16).ok()?;
+    let product_id = u16::from_str_radix(ids.next()?, 16).ok()?;
+    let interface = field("HID_PHYS")
+        .and_then(|phys| phys.rsplit_once("/input"))
+        .and_then(|(_, n)| n.parse().ok());
+    Some(Node {
+        number,
+        info: DeviceInfo {
+            path: format!("/dev/{name}"),
+            vendor_id,
+            product_id,
+            manufacturer: None,
+            product: field("HID_NAME").map(ToOwned::to_owned),
+            serial_number: field("HID_UNIQ").map(ToOwned::to_owned),
+            model: lookup_model(vendor_id, product_id),
+        },
+        interface,
+    })
+}
+
+/// Every hidraw node with its sysfs directory, in node order.
+fn nodes() -> Result<Vec<(Node, PathBuf)>> {
+    let mut nodes: Vec<(Node, PathBuf)> = fs::read_dir(SYSFS_CLASS)
+        .map_err(|e| anyhow!("{SYSFS_CLASS}: {e}"))?
+        .flatten()
+        .filter_map(|entry| {
+            let uevent = fs::read_to_string(entry.path().join("device/uevent")).ok()?;
+            let node = parse_uevent(entry.file_name().to_str()?, &uevent)?;
+            Some((node, entry.path()))
+        })
+        .collect();
+    nodes.sort_by_key(|(node, _)| node.number);
+    Ok(nodes)
+}
+
+/// Top-level usage page of `hidrawN`, from its HID device's report descriptor.
+fn usage_page(number: u32) -> Option<u16> {
+    let path = PathBuf::from(SYSFS_CLASS).join(format!("hidraw{number}/device/report_descriptor"));
+    fs::read(path).ok().and_then(|d| report_usage(&d).0)
+}
+
+/// Every hidraw node of a supported keyboard, in node order.
+fn supported_nodes() -> Result<Vec<Node>> {
+    Ok(nodes()?
+        .into_iter()
+        .map(|(node, _)| node)
+        .filter(|node| node.info.model != KeyboardModel::Unknown)
+        .collect())
+}
+
+/// Wait up to `timeout` for a report and read it; a silent board reads nothing.
+fn read_timeout(file: &mut File, buf: &mut [u8], timeout: Duration) -> usize {
+    let mut fd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+    // SAFETY: `fd` is a single live pollfd for the duration of the call.
+    let ready = unsafe { libc::poll(&raw mut fd, 1, millis) };
+    if ready > 0 {
+        file.read(buf).unwrap_or(0)
+    } else {
+        0
+    }
+}
+
+pub struct Keyboard {
+    file: Option<File>,
+    current: Option<DeviceInfo>,
+    check_acks: bool,
+    throttle: Throttle,
+}
+
+impl Keyboard {
+    /// Enumerate supported keyboards.
+    pub fn list_keyboards() -> Result<Vec<DeviceInfo>> {
+        let _enumerating = timing::start(Phase::Enumerate);
+        Ok(supported_nodes()?
+            .into_iter()
+            .map(|node| node.info)
+            .collect())
+    }
+
+    /// Every HID interface of `vendor_id`'s devices, supported or not.
+    pub fn vendor_interfaces(vendor_id: u16) -> Result<Vec<HidInterface>> {
+        Ok(nodes()?
+            .into_iter()
+            .filter(|(node, _)| node.info.vendor_id == vendor_id)
+            .map(|(node, sysfs)| {
+                let (usage_page, usage) = fs::read(sysfs.join("device/report_descriptor"))
+                    .map_or((None, None), |d| report_usage(&d));
+                HidInterface {
+                    vendor_id,
+                    product_id: node.info.product_id,
+                    product: node.info.product,
+                    interface: node.interface,
+                    usage_page,
+                    usage,
+                }
+            })
+            .collect())
+    }
+
+    /// Open a keyboard. If `vendor_id` or `product_id` are 0 they are ignored.
+    pub fn open(vendor_id: u16, product_id: u16, serial: Option<&str>) -> Result<Self> {
+        let enumerating = timing::start(Phase::Enumerate);
+        let nodes: Vec<Node> = supported_nodes()?
+            .into_iter()
+            .filter(|node| {
+                (vendor_id == 0 || node.info.vendor_id == vendor_id)
+                    && (product_id == 0 || node.info.product_id == product_id)
+            })
+            .collect();
+        drop(enumerating);
+
+        let first = if let Some(sn) = serial {
+            nodes
+                .iter()
+                .find(|node| node.info.serial_number.as_deref() == Some(sn))
+        } else {
+            nodes.first()
+        }
+        .ok_or_else(|| anyhow!(tr("error-no-device")))?;
+
+        // Each interface has its own node; keep the chosen keyboard's.
+        let interfaces: Vec<&Node> = nodes
+            .iter()
+            .filter(|node| {
+                node.info.vendor_id == first.info.vendor_id
+                    && node.info.product_id == first.info.product_id
+                    && node.info.serial_number == first.info.serial_number
+            })
+            .collect();
+        let numbers = interfaces
+            .iter()
+            .filter_map(|node| Some((node.interface?, usage_page(node.number))));
+
+        let _opening = timing::start(Phase::Open);
+        let open = |node: &Node| {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&node.info.path)
+                .map(|file| Self {
+                    file: Some(file),
+                    current: Some(node.info.clone()),
+                    check_acks: false,
+                    throttle: Throttle::default(),
+                })
+        };
+        let mut attempts = Vec::new();
+        for interface in interface_candidates(numbers) {
+            let Some(node) = interfaces
+                .iter()
+                .find(|node| node.interface == Some(interface))
+            else {
+                continue;
+            };
+            match open(node) {
+                Ok(keyboard) => {
+                    report_fallback(interface);
+                    return Ok(keyboard);
+                }
+                Err(e) => attempts.push((interface, e.to_string())),
+            }
+        }
+
+        // Nodes without a USB interface (e.g. Bluetooth): fall back to the first.
+        if attempts.is_empty() {
+            return open(first).map_err(|e| anyhow!("{}: {e}", first.info.path));
+        }
+        Err(no_interface_error(&attempts))
+    }
+
+    /// Close the currently open keyboard handle.
+    pub fn close(&mut self) {
+        self.file.take();
+    }
+
+    /// Close the handle; hidraw has no way to reset the device on the bus,
+    /// so this fails once the handle is closed.
+    pub fn reset(&mut self) -> Result<()> {
+        self.close();
+        Err(anyhow!(
+            "the {BACKEND} backend cannot reset a USB device; unplug the keyboard and plug it back in"
+        ))
+    }
+
+    /// Read each packet's response and fail on firmware errors (slower).
+    pub fn set_check_acks(&mut self, check: bool) {
+        self.check_acks = check;
+    }
+
+    /// Get information about the currently opened device.
+    pub fn current_device(&self) -> Option<&DeviceInfo> {
+        self.current.as_ref()
+    }
+
+    /// Send a raw HID output report to the keyboard, padded to its declared length.
+    pub fn send_packet(&mut self, data: &[u8]) -> Result<()> {
+        let _writing = timing::start(Phase::Write);
+        self.write_report(data)?;
+        let model = self.model();
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        after_send(
+            &SystemClock,
+            model,
+            data,
+            self.check_acks,
+            |response, timeout| read_timeout(file, response, timeout),
+        )
+    }
+
+    /// Write `data`, padded, leaving any response unread.
+    pub(super) fn write_report(&mut self, data: &[u8]) -> Result<()> {
+        packet_log::record(data);
+        let model = self.model();
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        self.throttle.wait(&SystemClock, model, data);
+
+        match data.len() {
+            0..=20 | 64 => file.write_all(&full_report(data))?,
+            n => return Err(anyhow!("invalid packet length: {n}")),
+        }
+        Ok(())
+    }
+
+    /// Read one input report into `buf` within `timeout`; 0 if none came.
+    pub(super) fn read_report(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        Ok(read_timeout(file, buf, timeout))
+    }
+
+    fn model(&self) -> KeyboardModel {
+        self.current
+            .as_ref()
+            .map_or(KeyboardModel::Unknown, |d| d.model)
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        self.close();
+        crate::keyboard::model::clear_supported_override();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G810_LED: &str = "DRIVER=hid-generic\n\
+        HID_ID=0003:0000046D:0000C331\n\
+        HID_NAME=Logitech G810 Orion Spectrum\n\
+        HID_PHYS=usb-0000:00:14.0-2/input1\n\
+        HID_UNIQ=0B3D38673932\n\
+        MODALIAS=hid:b0003g0001v0000046Dp0000C331\n";
+
+    #[test]
+    fn uevent_describes_the_node() {
+        let node = parse_uevent("hidraw3", G810_LED).unwrap();
+        assert_eq!(node.number, 3);
+        assert_eq!(node.interface, Some(1));
+        assert_eq!(node.info.path, "/dev/hidraw3");
+        assert_eq!(
+            (node.info.vendor_id, node.info.product_id),
+            (0x046d, 0xc331)
+        );
+        assert_eq!(node.info.model, KeyboardModel::G810);
+        assert_eq!(node.info.serial_number.as_deref(), Some("0B3D38673932"));
+        assert_eq!(
+            node.info.product.as_deref(),
+            Some("Logitech G810 Orion Spectrum")
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_tolerated() {
+        let uevent = "HID_ID=0005:0000046D:0000C331\nHID_UNIQ=\n";
+        let node = parse_uevent("hidraw0", uevent).unwrap();
+        assert_eq!(node.interface, None);
+        assert_eq!(node.info.serial_number, None);
+        assert!(parse_uevent("hidraw0", "HID_NAME=no ids\n").is_none());
+        assert!(parse_uevent("event0", G810_LED).is_none());
+    }
+}
@@ -1,16 +1,35 @@
+use std::borrow::Cow;
 use std::time::Duration;
 
-use super::common::{DeviceInfo, KeyboardModel, lookup_model};
+use super::common::{
+    DeviceInfo, HidInterface, KeyboardModel, Throttle, after_send, full_report,
+    interface_candidates, lookup_model, no_interface_error, report_fallback, report_usage,
+};
+use crate::clock::SystemClock;
+use crate::i18n::tr;
+use crate::packet_log;
+use crate::timing::{self, Phase};
 use anyhow::{Result, anyhow};
 use rusb::{
     self, Context, DeviceHandle, Direction, Recipient, RequestType, UsbContext, request_type,
 };
 
+/// Human-readable name of this backend.
+pub const BACKEND: &str = "libusb";
+
+/// FreeBSD/OpenBSD handling, enabled by the `bsd` feature.
+const BSD: bool = cfg!(feature = "bsd");
+
 pub struct Keyboard {
     _ctx: rusb::Context,
     handle: Option<DeviceHandle<Context>>,
     current: Option<DeviceInfo>,
+    interface: u8,
+    /// Interrupt IN endpoint of `interface`, where paced models echo packets.
+    ack_endpoint: Option<u8>,
     kernel_detached: bool,
+    check_acks: bool,
+    throttle: Throttle,
 }
 
 fn read_string<T>(handle: &DeviceHandle<T>, index: u8) -> Option<String>
@@ -20,10 +39,66 @@ where
     handle.read_string_descriptor_ascii(index).ok()
 }
 
+/// The interrupt IN endpoint of `interface` in the active configuration.
+fn interrupt_in_endpoint<T>(handle: &DeviceHandle<T>, interface: u8) -> Option<u8>
+where
+    T: rusb::UsbContext,
+{
+    let config = handle.device().active_config_descriptor().ok()?;
+    config
+        .interfaces()
+        .filter(|i| i.number() == interface)
+        .flat_map(|i| i.descriptors())
+        .flat_map(|d| d.endpoint_descriptors().collect::<Vec<_>>())
+        .find(|ep| {
+            ep.direction() == Direction::In && ep.transfer_type() == rusb::TransferType::Interrupt
+        })
+        .map(|ep| ep.address())
+}
+
+/// USB interface class of HID interfaces.
+const HID_CLASS: u8 = 0x03;
+
+/// Read the HID report descriptor of `interface`; `None` where the OS wants
+/// the interface claimed first.
+fn report_descriptor<T>(handle: &DeviceHandle<T>, interface: u8) -> Option<Vec<u8>>
+where
+    T: rusb::UsbContext,
+{
+    let mut buf = vec![0u8; 4096];
+    let req_type = request_type(Direction::In, RequestType::Standard, Recipient::Interface);
+    let len = handle
+        .read_control(
+            req_type,
+            0x06, // GET_DESCRIPTOR
+            0x2200,
+            u16::from(interface),
+            &mut buf,
+            Duration::from_millis(500),
+        )
+        .ok()?;
+    buf.truncate(len);
+    Some(buf)
+}
+
+/// Device node of the USB device at `bus` and `address`.
+fn device_path(bus: u8, address: u8) -> String {
+    if BSD {
+        // FreeBSD names ugen nodes after bus and address; OpenBSD numbers them
+        // by unit, so `doctor` cannot check access there.
+        format!("/dev/ugen{bus}.{address}")
+    } else {
+        format!("/dev/bus/usb/{bus:03}/{address:03}")
+    }
+}
+
 fn to_device_info<T>(handle: &mut DeviceHandle<T>, desc: &rusb::DeviceDescriptor) -> DeviceInfo
 where
     T: rusb::UsbContext,
 {
+    let device = handle.device();
+    let path = device_path(device.bus_number(), device.address());
+
     let get_string = |idx: Option<u8>| match idx {
         Some(i) if i > 0 => read_string(handle, i),
         _ => None,
@@ -34,6 +109,7 @@ where
     let serial_number = get_string(desc.serial_number_string_index());
 
     DeviceInfo {
+        path,
         vendor_id: desc.vendor_id(),
         product_id: desc.product_id(),
         manufacturer,
@@ -43,9 +119,38 @@ where
     }
 }
 
+/// The first supported device matching the IDs (0 matches any) and `serial`.
+fn find_device(
+    ctx: &Context,
+    vendor_id: u16,
+    product_id: u16,
+    serial: Option<&str>,
+) -> Result<Option<(DeviceHandle<Context>, DeviceInfo)>> {
+    for device in ctx.devices()?.iter() {
+        let desc = device.device_descriptor()?;
+        if lookup_model(desc.vendor_id(), desc.product_id()) == KeyboardModel::Unknown {
+            continue;
+        }
+        if vendor_id != 0 && desc.vendor_id() != vendor_id {
+            continue;
+        }
+        if product_id != 0 && desc.product_id() != product_id {
+            continue;
+        }
+        if let Ok(mut handle) = device.open() {
+            let info = to_device_info(&mut handle, &desc);
+            if serial.is_none_or(|sn| info.serial_number.as_deref() == Some(sn)) {
+                return Ok(Some((handle, info)));
+            }
+        }
+    }
+    Ok(None)
+}
+
 impl Keyboard {
     /// Enumerate supported keyboards
     pub fn list_keyboards() -> Result<Vec<DeviceInfo>> {
+        let _enumerating = timing::start(Phase::Enumerate);
         let ctx = rusb::Context::new()?;
         let mut list = Vec::new();
         for device in ctx.devices()?.iter() {
@@ -61,64 +166,156 @@ impl Keyboard {
         Ok(list)
     }
 
-    /// Open a keyboard. If `vendor_id` or `product_id` are 0 they are ignored.
-    pub fn open(vendor_id: u16, product_id: u16, serial: Option<&str>) -> Result<Self> {
+    /// Every HID interface of `vendor_id`'s devices, supported or not.
+    pub fn vendor_interfaces(vendor_id: u16) -> Result<Vec<HidInterface>> {
         let ctx = rusb::Context::new()?;
-        let mut selected = None;
-        let mut device_handle = None;
+        let mut list = Vec::new();
         for device in ctx.devices()?.iter() {
             let desc = device.device_descriptor()?;
-            if lookup_model(desc.vendor_id(), desc.product_id()) == KeyboardModel::Unknown {
+            if desc.vendor_id() != vendor_id {
                 continue;
             }
-            if vendor_id != 0 && desc.vendor_id() != vendor_id {
+            let handle = device.open().ok();
+            let product = handle
+                .as_ref()
+                .and_then(|h| desc.product_string_index().and_then(|i| read_string(h, i)));
+            let Ok(config) = device.active_config_descriptor() else {
                 continue;
+            };
+            let numbers = config
+                .interfaces()
+                .flat_map(|i| i.descriptors())
+                .filter(|d| d.class_code() == HID_CLASS)
+                .map(|d| d.interface_number());
+            for number in numbers {
+                let (usage_page, usage) = handle
+                    .as_ref()
+                    .and_then(|h| report_descriptor(h, number))
+                    .map_or((None, None), |d| report_usage(&d));
+                list.push(HidInterface {
+                    vendor_id,
+                    product_id: desc.product_id(),
+                    product: product.clone(),
+                    interface: Some(number),
+                    usage_page,
+                    usage,
+                });
             }
-            if product_id != 0 && desc.product_id() != product_id {
-                continue;
+        }
+        Ok(list)
+    }
+
+    /// Open a keyboard. If `vendor_id` or `product_id` are 0 they are ignored.
+    pub fn open(vendor_id: u16, product_id: u16, serial: Option<&str>) -> Result<Self> {
+        let enumerating = timing::start(Phase::Enumerate);
+        let ctx = rusb::Context::new()?;
+        let direct = vendor_id != 0
+            && product_id != 0
+            && serial.is_none()
+            && lookup_model(vendor_id, product_id) != KeyboardModel::Unknown;
+        let found = if direct {
+            // Nothing to compare, so open the IDs without walking the bus.
+            ctx.open_device_with_vid_pid(vendor_id, product_id)
+                .and_then(|mut handle| {
+                    let desc = handle.device().device_descriptor().ok()?;
+                    let info = to_device_info(&mut handle, &desc);
+                    Some((handle, info))
+                })
+        } else {
+            find_device(&ctx, vendor_id, product_id, serial)?
+        };
+        let (handle, info) = found.ok_or_else(|| anyhow!(tr("error-no-device")))?;
+        drop(enumerating);
+        let _opening = timing::start(Phase::Open);
+
+        let available: Vec<(u8, Option<u16>)> = handle
+            .device()
+            .active_config_descriptor()
+            .map(|config| {
+                config
+                    .interfaces()
+                    .map(|i| {
+                        let page =
+                            report_descriptor(&handle, i.number()).and_then(|d| report_usage(&d).0);
+                        (i.number(), page)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut attempts = Vec::new();
+        for interface in interface_candidates(available) {
+            let driver_active = handle.kernel_driver_active(interface).unwrap_or(false);
+            if driver_active {
+                handle.detach_kernel_driver(interface).ok();
             }
-            if let Ok(mut handle) = device.open() {
-                let info = to_device_info(&mut handle, &desc);
-                if let Some(sn) = serial {
-                    if info.serial_number.as_ref().is_some_and(|s| s == sn) {
-                        selected = Some(info);
-                        device_handle = Some(handle);
-                        break;
+            match handle.claim_interface(interface) {
+                Ok(()) => {
+                    report_fallback(interface);
+                    let ack_endpoint = interrupt_in_endpoint(&handle, interface);
+                    return Ok(Self {
+                        _ctx: ctx,
+                        handle: Some(handle),
+                        current: Some(info),
+                        interface,
+                        ack_endpoint,
+                        kernel_detached: driver_active,
+                        check_acks: false,
+                        throttle: Throttle::default(),
+                    });
+                }
+                Err(e) => {
+                    if driver_active {
+                        handle.attach_kernel_driver(interface).ok();
                     }
-                } else if selected.is_none() {
-                    selected = Some(info);
-                    device_handle = Some(handle);
+                    attempts.push((interface, e.to_string()));
                 }
             }
         }
-        let handle = device_handle.ok_or_else(|| anyhow!("no matching device"))?;
-        let info = selected.unwrap();
-
-        let driver_active = handle.kernel_driver_active(1).unwrap_or(false);
-        if driver_active {
-            handle.detach_kernel_driver(1).ok();
-        }
-        if let Err(e) = handle.claim_interface(1) {
-            return Err(anyhow!("{e}"));
-        }
-        Ok(Self {
-            _ctx: ctx,
-            handle: Some(handle),
-            current: Some(info),
-            kernel_detached: driver_active,
-        })
+        Err(no_interface_error(&attempts))
     }
 
     /// Close the currently open keyboard handle.
     pub fn close(&mut self) {
         if let Some(h) = self.handle.take() {
-            h.release_interface(1).ok();
+            h.release_interface(self.interface).ok();
             if self.kernel_detached {
-                h.attach_kernel_driver(1).ok();
+                h.attach_kernel_driver(self.interface).ok();
             }
         }
     }
 
+    /// The interface that was claimed, normally [`LED_INTERFACE`](super::common::LED_INTERFACE).
+    pub fn interface(&self) -> u8 {
+        self.interface
+    }
+
+    /// Whether a kernel driver had to be detached to claim the interface.
+    pub fn detached_kernel_driver(&self) -> bool {
+        self.kernel_detached
+    }
+
+    /// Reset the keyboard on the USB bus, as replugging it would, and close
+    /// the handle; open it again to go on.
+    pub fn reset(&mut self) -> Result<()> {
+        let handle = self
+            .handle
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        let reset = match handle.reset() {
+            // The board came back as a new device, which is what we wanted.
+            Ok(()) | Err(rusb::Error::NotFound) => Ok(()),
+            Err(e) => Err(anyhow!("USB reset failed: {e}")),
+        };
+        self.close();
+        reset
+    }
+
+    /// Read each packet's response and fail on firmware errors (slower).
+    pub fn set_check_acks(&mut self, check: bool) {
+        self.check_acks = check;
+    }
+
     /// Get information about the currently opened device.
     pub fn current_device(&self) -> Option<&DeviceInfo> {
         self.current.as_ref()
@@ -132,21 +329,82 @@ impl Keyboard {
     /// - `report_id` = **0x12** if `data.len() > 20`, else **0x11**
     ///
     /// These report IDs and behavior are defined by the keyboard's firmware.
+    /// With the `bsd` feature reports are padded to their full length, which
+    /// the BSD USB stacks require.
     pub fn send_packet(&mut self, data: &[u8]) -> Result<()> {
+        let _writing = timing::start(Phase::Write);
+        self.write_report(data)?;
+        let model = self.model();
+        let handle = self
+            .handle
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        let endpoint = self.ack_endpoint;
+        after_send(
+            &SystemClock,
+            model,
+            data,
+            self.check_acks,
+            |response, timeout| {
+                endpoint.map_or(0, |ep| {
+                    handle.read_interrupt(ep, response, timeout).unwrap_or(0)
+                })
+            },
+        )
+    }
+
+    /// Write `data` as a `SET_REPORT` request, leaving any response unread.
+    pub(super) fn write_report(&mut self, data: &[u8]) -> Result<()> {
+        packet_log::record(data);
+        let model = self.model();
         let handle = self
             .handle
             .as_mut()
             .ok_or_else(|| anyhow!("no device open"))?;
+        self.throttle.wait(&SystemClock, model, data);
 
+        let data = if BSD {
+            full_report(data)
+        } else {
+            Cow::Borrowed(data)
+        };
         let value = if data.len() > 20 { 0x0212 } else { 0x0211 };
         let req_type = request_type(Direction::Out, RequestType::Class, Recipient::Interface);
 
         handle
-            .write_control(req_type, 0x09, value, 1, data, Duration::from_millis(2000))
+            .write_control(
+                req_type,
+                0x09,
+                value,
+                u16::from(self.interface),
+                &data,
+                Duration::from_millis(2000),
+            )
             .map_err(|e| anyhow!("{e}"))?;
-
         Ok(())
     }
+
+    /// Read one input report into `buf` within `timeout`; 0 if none came.
+    pub(super) fn read_report(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let handle = self
+            .handle
+            .as_mut()
+            .ok_or_else(|| anyhow!("no device open"))?;
+        let endpoint = self
+            .ack_endpoint
+            .ok_or_else(|| anyhow!("interface {} has no endpoint to read", self.interface))?;
+        match handle.read_interrupt(endpoint, buf, timeout) {
+            Ok(len) => Ok(len),
+            Err(rusb::Error::Timeout) => Ok(0),
+            Err(e) => Err(anyhow!("{e}")),
+        }
+    }
+
+    fn model(&self) -> KeyboardModel {
+        self.current
+            .as_ref()
+            .map_or(KeyboardModel::Unknown, |d| d.model)
+    }
 }
 
 impl Drop for Keyboard {
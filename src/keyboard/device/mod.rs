@@ -2,15 +2,149 @@
 #![allow(unused_imports)]
 
 mod common;
-pub use common::DeviceInfo;
+pub use common::{DeviceInfo, HidInterface, Holder, processes_holding, report_usage};
 
-// Feature-gated backends
+use std::time::{Duration, Instant};
+
+// Feature-gated backends: libusb, then hidraw, then hidapi.
 #[cfg(feature = "libusb")]
 mod libusb;
 #[cfg(feature = "libusb")]
-pub use libusb::Keyboard;
+pub use libusb::{BACKEND, Keyboard};
+
+#[cfg(all(feature = "hidraw", not(feature = "libusb")))]
+mod hidraw;
+#[cfg(all(feature = "hidraw", not(feature = "libusb")))]
+pub use hidraw::{BACKEND, Keyboard};
 
-#[cfg(not(feature = "libusb"))]
+#[cfg(all(
+    any(feature = "hidapi", feature = "vendored-hidapi"),
+    not(any(feature = "libusb", feature = "hidraw"))
+))]
 mod hid;
-#[cfg(not(feature = "libusb"))]
-pub use hid::Keyboard;
+#[cfg(all(
+    any(feature = "hidapi", feature = "vendored-hidapi"),
+    not(any(feature = "libusb", feature = "hidraw"))
+))]
+pub use hid::{BACKEND, Keyboard};
+
+#[cfg(not(any(
+    feature = "hidapi",
+    feature = "vendored-hidapi",
+    feature = "libusb",
+    feature = "hidraw"
+)))]
+compile_error!("enable one of the `hidapi`, `libusb` or `hidraw` features");
+
+#[cfg(all(feature = "hidraw", not(target_os = "linux")))]
+compile_error!("the `hidraw` backend is Linux-only");
+
+impl Keyboard {
+    /// Open every supported keyboard matching the IDs, one handle each. If
+    /// `vendor_id` or `product_id` are 0 they are ignored.
+    ///
+    /// Boards are told apart by their IDs and serial number, so two of the
+    /// same model without serial numbers open as one.
+    pub fn open_all(vendor_id: u16, product_id: u16) -> anyhow::Result<Vec<Self>> {
+        let mut boards: Vec<(u16, u16, Option<String>)> = Self::list_keyboards()?
+            .into_iter()
+            .filter(|d| vendor_id == 0 || d.vendor_id == vendor_id)
+            .filter(|d| product_id == 0 || d.product_id == product_id)
+            .map(|d| (d.vendor_id, d.product_id, d.serial_number))
+            .collect();
+        // Backends list a board once per interface.
+        boards.sort();
+        boards.dedup();
+        if boards.is_empty() {
+            anyhow::bail!(crate::i18n::tr("error-no-device"));
+        }
+        boards
+            .iter()
+            .map(|(vid, pid, serial)| Self::open(*vid, *pid, serial.as_deref()))
+            .collect()
+    }
+
+    /// Send an HID++ request and return the report answering it, skipping
+    /// any the board sends in between. An error report becomes an error.
+    pub fn request(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.write_report(data)?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        let mut report = [0u8; 64];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                anyhow::bail!(
+                    "the keyboard did not answer {:02x?}",
+                    &data[..4.min(data.len())]
+                );
+            }
+            let len = self.read_report(&mut report, left)?.min(report.len());
+            if common::answers(data, &report[..len]) {
+                common::check_response(&report[..len])?;
+                return Ok(report[..len].to_vec());
+            }
+        }
+    }
+}
+
+/// How long [`Keyboard::request`] waits for an answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Add the names of processes holding the device to an open failure, since
+/// the backends only report an opaque "busy" or "access denied" string.
+/// When only unsupported devices match, describe them so they can be
+/// reported; inside a sandbox, explain how to grant device access instead.
+pub fn explain_open_error(
+    err: anyhow::Error,
+    vendor_id: u16,
+    product_id: u16,
+    serial: Option<&str>,
+) -> anyhow::Error {
+    let Ok(devices) = Keyboard::list_keyboards() else {
+        return err;
+    };
+    let paths: Vec<&str> = devices
+        .iter()
+        .filter(|d| vendor_id == 0 || d.vendor_id == vendor_id)
+        .filter(|d| product_id == 0 || d.product_id == product_id)
+        .filter(|d| serial.is_none_or(|sn| d.serial_number.as_deref() == Some(sn)))
+        .map(|d| d.path.as_str())
+        .collect();
+
+    if paths.is_empty()
+        && let Some(report) = unsupported_report(vendor_id, product_id)
+    {
+        return err.context(report);
+    }
+
+    let holders = processes_holding(&paths);
+    if holders.is_empty() {
+        return match crate::sandbox::Sandbox::detect() {
+            Some(sandbox) => err.context(format!(
+                "running inside {sandbox}: {}",
+                sandbox.access_hint()
+            )),
+            None => err,
+        };
+    }
+    let names: Vec<String> = holders.iter().map(ToString::to_string).collect();
+    err.context(format!(
+        "the keyboard is in use by {}; close it and try again",
+        names.join(", ")
+    ))
+}
+
+/// [`common::detection_report`] for the vendor's devices matching the IDs.
+fn unsupported_report(vendor_id: u16, product_id: u16) -> Option<String> {
+    let vendor_id = if vendor_id == 0 {
+        crate::keyboard::model::LOGITECH_VENDOR_ID
+    } else {
+        vendor_id
+    };
+    let interfaces: Vec<HidInterface> = Keyboard::vendor_interfaces(vendor_id)
+        .ok()?
+        .into_iter()
+        .filter(|i| product_id == 0 || i.product_id == product_id)
+        .collect();
+    common::detection_report(&interfaces, BACKEND)
+}
@@ -1,5 +1,6 @@
-use core::time::Duration;
-use strum_macros::{Display, EnumString};
+use core::{fmt, time::Duration};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
 
 use crate::keyboard::{
     Color, KeyboardModel,
@@ -22,7 +23,7 @@ pub enum NativeEffectGroup {
 }
 
 #[repr(u16)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, EnumIter)]
 #[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
 pub enum NativeEffect {
     Off = 0,
@@ -43,10 +44,20 @@ impl NativeEffect {
         // Safety: the first 8 bits of every `NativeEffect` encode its group.
         unsafe { core::mem::transmute::<u8, NativeEffectGroup>((self as u16 >> 8) as u8) }
     }
+
+    /// Whether the color argument changes what the effect looks like.
+    pub const fn uses_color(self) -> bool {
+        matches!(self, Self::Color | Self::Breathing | Self::Ripple)
+    }
+
+    /// Whether the period argument changes the effect's speed.
+    pub const fn uses_period(self) -> bool {
+        !matches!(self, Self::Off | Self::Color)
+    }
 }
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, EnumIter)]
 #[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
 pub enum NativeEffectPart {
     All = 0xff,
@@ -55,7 +66,7 @@ pub enum NativeEffectPart {
 }
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, EnumIter)]
 #[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
 pub enum NativeEffectStorage {
     None = 0x00,
@@ -63,6 +74,183 @@ pub enum NativeEffectStorage {
     User,
 }
 
+/// What the logo shows on boards that cannot run a wave there.
+pub const LOGO_WAVE_FALLBACK: Color = Color {
+    red: 0x00,
+    green: 0xff,
+    blue: 0xff,
+};
+
+/// Whether `model` shows [`LOGO_WAVE_FALLBACK`] on the logo when asked to
+/// run `effect` there.
+pub fn logo_falls_back(model: KeyboardModel, effect: NativeEffect) -> bool {
+    effect.group() == NativeEffectGroup::Waves
+        && !matches!(
+            model,
+            KeyboardModel::G815 | KeyboardModel::G213 | KeyboardModel::G413
+        )
+}
+
+/// Longest period the firmware can encode: a 16-bit millisecond count.
+pub const MAX_EFFECT_PERIOD: Duration = Duration::from_millis(u16::MAX as u64);
+
+/// The parameters an effect request actually reaches the firmware with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectSummary {
+    pub model: KeyboardModel,
+    pub effect: NativeEffect,
+    pub part: NativeEffectPart,
+    /// `None` when the effect ignores color.
+    pub color: Option<Color>,
+    /// `None` when the effect ignores period; zero leaves it to the firmware.
+    pub period: Option<Duration>,
+    /// The requested period, when it was longer than the firmware allows.
+    pub clamped_from: Option<Duration>,
+    pub storage: NativeEffectStorage,
+    /// The logo half was dropped because the model has no logo LEDs.
+    pub logo_skipped: bool,
+    /// The wave asked for on the logo, which got [`LOGO_WAVE_FALLBACK`]
+    /// instead.
+    pub logo_fallback: Option<NativeEffect>,
+}
+
+impl EffectSummary {
+    pub fn new(
+        model: KeyboardModel,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Self {
+        let logo_fallback =
+            (part != NativeEffectPart::Keys && logo_falls_back(model, effect)).then_some(effect);
+        // Only the logo was asked for, so only the fallback color was sent.
+        let (effect, period, color) = match logo_fallback {
+            Some(_) if part == NativeEffectPart::Logo => {
+                (NativeEffect::Color, Duration::ZERO, LOGO_WAVE_FALLBACK)
+            }
+            _ => (effect, period, color),
+        };
+        let clamped = period.min(MAX_EFFECT_PERIOD);
+        Self {
+            model,
+            effect,
+            part,
+            color: effect.uses_color().then_some(color),
+            period: effect.uses_period().then_some(clamped),
+            clamped_from: (effect.uses_period() && clamped != period).then_some(period),
+            storage,
+            logo_skipped: part != NativeEffectPart::Keys
+                && matches!(model, KeyboardModel::G213 | KeyboardModel::G413),
+            logo_fallback,
+        }
+    }
+
+    /// The summary of what [`native_effect_packets`] sends for the request,
+    /// or `None` when it sends nothing.
+    pub fn sent(
+        model: KeyboardModel,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Option<Self> {
+        native_effect_packets(model, effect, part, period, color, storage)
+            .filter(|packets| !packets.is_empty())
+            .map(|_| Self::new(model, effect, part, period, color, storage))
+    }
+}
+
+impl fmt::Display for EffectSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fx {} {}", self.effect, self.part)?;
+        if let Some(color) = self.color {
+            write!(f, ", color {color}")?;
+        }
+        match self.period {
+            Some(period) if period.is_zero() => write!(f, ", period firmware default")?,
+            Some(period) => write!(f, ", period {} ms", period.as_millis())?,
+            None => {}
+        }
+        write!(f, ", storage {}", self.storage)?;
+
+        if let Some(requested) = self.clamped_from {
+            write!(
+                f,
+                "\nnote: period clamped from {} ms to the {} ms maximum",
+                requested.as_millis(),
+                MAX_EFFECT_PERIOD.as_millis()
+            )?;
+        }
+        if self.logo_skipped {
+            write!(
+                f,
+                "\nnote: {:?} has no logo LEDs; logo part skipped",
+                self.model
+            )?;
+        }
+        if let Some(wave) = self.logo_fallback {
+            write!(
+                f,
+                "\nnote: the {:?} logo cannot run {wave}; it shows {LOGO_WAVE_FALLBACK} instead",
+                self.model
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What a model's firmware takes for one effect, read off the packets
+/// [`native_effect_packets`] would send rather than kept in a separate table.
+///
+/// Periods are left out: every model takes the same 16-bit range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectSupport {
+    pub effect: NativeEffect,
+    /// Parts the effect runs on; `all` whenever any of them does.
+    pub parts: Vec<NativeEffectPart>,
+    pub storage: Vec<NativeEffectStorage>,
+    /// The logo shows [`LOGO_WAVE_FALLBACK`] instead of running the effect.
+    pub logo_fallback: bool,
+}
+
+impl EffectSupport {
+    /// Every effect `model` runs on at least one part.
+    pub fn for_model(model: KeyboardModel) -> Vec<Self> {
+        let sends = |effect, part, storage| {
+            native_effect_packets(
+                model,
+                effect,
+                part,
+                Duration::ZERO,
+                Color::default(),
+                storage,
+            )
+            .is_some_and(|packets| !packets.is_empty())
+        };
+        NativeEffect::iter()
+            .filter_map(|effect| {
+                let parts: Vec<NativeEffectPart> = NativeEffectPart::iter()
+                    .filter(|&part| NativeEffectStorage::iter().any(|s| sends(effect, part, s)))
+                    .collect();
+                let storage = NativeEffectStorage::iter()
+                    .filter(|&s| parts.iter().any(|&part| sends(effect, part, s)))
+                    .collect();
+                let logo_fallback =
+                    parts.contains(&NativeEffectPart::Logo) && logo_falls_back(model, effect);
+                (!parts.is_empty()).then_some(Self {
+                    effect,
+                    parts,
+                    storage,
+                    logo_fallback,
+                })
+            })
+            .collect()
+    }
+}
+
 /// Translate a lighting effect into one or more HID packets.
 ///
 /// *Returns*
@@ -138,19 +326,14 @@ pub fn native_effect_packets(
 
         // 4.b Everything else
         _ => {
-            // Waves on the logo fal back to a static cyan color.
-            if effect.group() == NativeEffectGroup::Waves && part == NativeEffectPart::Logo {
-                const CYAN: Color = Color {
-                    red: 0x00,
-                    green: 0xff,
-                    blue: 0xff,
-                };
+            // Waves on the logo fall back to a static color.
+            if part == NativeEffectPart::Logo && logo_falls_back(model, effect) {
                 return native_effect_packets(
                     model,
                     NativeEffect::Color,
                     part,
                     Duration::ZERO,
-                    CYAN,
+                    LOGO_WAVE_FALLBACK,
                     storage,
                 );
             }
@@ -161,3 +344,111 @@ pub fn native_effect_packets(
     packets.push(data);
     Some(packets)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_reports_clamping_and_skipped_logo() {
+        let summary = EffectSummary::new(
+            KeyboardModel::G213,
+            NativeEffect::Breathing,
+            NativeEffectPart::All,
+            Duration::from_secs(100),
+            Color::new(0xff, 0, 0),
+            NativeEffectStorage::None,
+        );
+        assert_eq!(summary.period, Some(MAX_EFFECT_PERIOD));
+        assert_eq!(summary.clamped_from, Some(Duration::from_secs(100)));
+        assert_eq!(
+            summary.to_string(),
+            "fx breathing all, color ff0000, period 65535 ms, storage none\n\
+             note: period clamped from 100000 ms to the 65535 ms maximum\n\
+             note: G213 has no logo LEDs; logo part skipped"
+        );
+    }
+
+    #[test]
+    fn summary_omits_unused_parameters() {
+        let summary = EffectSummary::new(
+            KeyboardModel::G810,
+            NativeEffect::Cycle,
+            NativeEffectPart::Keys,
+            Duration::ZERO,
+            Color::default(),
+            NativeEffectStorage::User,
+        );
+        assert_eq!(
+            summary.to_string(),
+            "fx cycle keys, period firmware default, storage user"
+        );
+    }
+
+    #[test]
+    fn summary_reports_the_logo_fallback() {
+        let summary = |part| {
+            EffectSummary::sent(
+                KeyboardModel::G810,
+                NativeEffect::HWave,
+                part,
+                Duration::from_secs(2),
+                Color::default(),
+                NativeEffectStorage::None,
+            )
+            .unwrap()
+            .to_string()
+        };
+        assert_eq!(
+            summary(NativeEffectPart::Logo),
+            "fx color logo, color 00ffff, storage none\n\
+             note: the G810 logo cannot run h-wave; it shows 00ffff instead"
+        );
+        assert_eq!(
+            summary(NativeEffectPart::All),
+            "fx h-wave all, period 2000 ms, storage none\n\
+             note: the G810 logo cannot run h-wave; it shows 00ffff instead"
+        );
+        assert_eq!(
+            EffectSummary::sent(
+                KeyboardModel::G213,
+                NativeEffect::Breathing,
+                NativeEffectPart::Logo,
+                Duration::ZERO,
+                Color::default(),
+                NativeEffectStorage::None,
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn support_follows_the_packets() {
+        let g213 = EffectSupport::for_model(KeyboardModel::G213);
+        let breathing = g213
+            .iter()
+            .find(|row| row.effect == NativeEffect::Breathing)
+            .unwrap();
+        assert_eq!(
+            breathing.parts,
+            [NativeEffectPart::All, NativeEffectPart::Keys]
+        );
+        assert!(!breathing.logo_fallback);
+
+        // Every effect reaches the G810 logo, but waves only as a static color.
+        let g810 = EffectSupport::for_model(KeyboardModel::G810);
+        assert_eq!(g810.len(), NativeEffect::iter().count());
+        for row in &g810 {
+            assert_eq!(row.parts.len(), 3);
+            assert_eq!(
+                row.logo_fallback,
+                row.effect.group() == NativeEffectGroup::Waves,
+                "{}",
+                row.effect
+            );
+        }
+        let g815 = EffectSupport::for_model(KeyboardModel::G815);
+        assert!(g815.iter().all(|row| !row.logo_fallback));
+        assert_eq!(EffectSupport::for_model(KeyboardModel::Unknown), []);
+    }
+}
@@ -0,0 +1,198 @@
+//! HID++ 2.0 feature discovery: the feature pages a board implements and the
+//! firmware it runs, read through the root and feature set features.
+
+use anyhow::{Context as _, Result};
+use std::fmt;
+
+/// Every HID++ 2.0 device has the root feature at index 0.
+const ROOT_INDEX: u8 = 0x00;
+pub const FEATURE_SET: u16 = 0x0001;
+pub const FIRMWARE_INFO: u16 = 0x0003;
+
+/// Tells our requests' answers apart from reports the board sends by itself.
+const SOFTWARE_ID: u8 = 0x0b;
+/// Corded boards answer as the receiver-less device index.
+const DEVICE_INDEX: u8 = 0xff;
+/// Echoed back by the protocol version request.
+const PING: u8 = 0x5a;
+
+/// A long report calling `function` of the feature at `index`.
+pub fn request(index: u8, function: u8, params: &[u8]) -> [u8; 20] {
+    let mut report = [0u8; 20];
+    report[..4].copy_from_slice(&[0x11, DEVICE_INDEX, index, function << 4 | SOFTWARE_ID]);
+    report[4..4 + params.len()].copy_from_slice(params);
+    report
+}
+
+/// A feature page and the index the board serves it at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feature {
+    pub index: u8,
+    pub id: u16,
+    pub version: u8,
+}
+
+impl Feature {
+    /// The feature's name, for the ones worth knowing by name.
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self.id {
+            0x0000 => "root",
+            0x0001 => "feature set",
+            0x0002 => "feature info",
+            0x0003 => "firmware info",
+            0x0005 => "device name",
+            0x0007 => "friendly name",
+            0x0020 => "config change",
+            0x00c2 => "dfu control",
+            0x00d0 => "dfu",
+            0x1000 => "battery status",
+            0x1b04 => "reprogrammable keys",
+            0x1d4b => "wireless status",
+            0x8010 => "g-keys",
+            0x8020 => "m-keys",
+            0x8030 => "macro record",
+            0x8040 => "brightness control",
+            0x8060 => "report rate",
+            0x8070 => "color led effects",
+            0x8071 => "rgb effects",
+            0x8080 => "per-key lighting",
+            0x8081 => "per-key lighting v2",
+            0x8100 => "onboard profiles",
+            _ => return None,
+        })
+    }
+}
+
+/// One firmware entity: the application, its bootloader or the hardware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firmware {
+    pub kind: u8,
+    pub prefix: String,
+    pub number: u8,
+    pub revision: u8,
+    pub build: u16,
+}
+
+impl fmt::Display for Firmware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            0 => "application",
+            1 => "bootloader",
+            2 => "hardware",
+            _ => "other",
+        };
+        write!(
+            f,
+            "{kind} {} {:02X}.{:02X}.B{:04X}",
+            self.prefix, self.number, self.revision, self.build
+        )
+    }
+}
+
+/// What a board told about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    /// Protocol major and minor version.
+    pub protocol: (u8, u8),
+    pub features: Vec<Feature>,
+    pub firmware: Vec<Firmware>,
+}
+
+/// Ask a board for its protocol version, features and firmware.
+///
+/// `send` writes one request and returns the report that answers it.
+pub fn discover(mut send: impl FnMut(&[u8]) -> Result<Vec<u8>>) -> Result<Discovery> {
+    let mut call = |index: u8, function: u8, params: &[u8]| -> Result<[u8; 16]> {
+        let answer = send(&request(index, function, params))?;
+        let mut out = [0u8; 16];
+        let params = answer.get(4..).unwrap_or_default();
+        let len = params.len().min(out.len());
+        out[..len].copy_from_slice(&params[..len]);
+        Ok(out)
+    };
+
+    let version = call(ROOT_INDEX, 1, &[0, 0, PING])
+        .context("the keyboard does not answer HID++ 2.0 requests")?;
+    let mut features = vec![Feature {
+        index: ROOT_INDEX,
+        id: 0x0000,
+        version: 0,
+    }];
+    let set_index = call(ROOT_INDEX, 0, &FEATURE_SET.to_be_bytes())?[0];
+    if set_index != 0 {
+        let count = call(set_index, 0, &[])?[0];
+        for index in 1..=count {
+            let answer = call(set_index, 1, &[index])?;
+            features.push(Feature {
+                index,
+                id: u16::from_be_bytes([answer[0], answer[1]]),
+                version: answer[3],
+            });
+        }
+    }
+
+    let mut firmware = Vec::new();
+    if let Some(info) = features.iter().find(|f| f.id == FIRMWARE_INFO) {
+        let entities = call(info.index, 0, &[])?[0];
+        for entity in 0..entities {
+            let answer = call(info.index, 1, &[entity])?;
+            firmware.push(Firmware {
+                kind: answer[0],
+                prefix: String::from_utf8_lossy(&answer[1..4])
+                    .trim_end_matches('\0')
+                    .to_owned(),
+                number: answer[4],
+                revision: answer[5],
+                build: u16::from_be_bytes([answer[6], answer[7]]),
+            });
+        }
+    }
+
+    Ok(Discovery {
+        protocol: (version[0], version[1]),
+        features,
+        firmware,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+
+    /// A G810-like board: feature set at 1, firmware info at 2, RGB at 3.
+    fn board(request: &[u8]) -> Result<Vec<u8>> {
+        let mut answer = request[..4].to_vec();
+        answer.extend_from_slice(
+            match (request[2], request[3] >> 4, request[4], request[5]) {
+                (0, 1, ..) => &[4, 2, PING],
+                // The feature set's index, and the one firmware entity.
+                (0, 0, 0x00, 0x01) | (2, 0, ..) => &[1],
+                (1, 0, ..) => &[3],
+                (1, 1, 1, _) => &[0x00, 0x01, 0, 1],
+                (1, 1, 2, _) => &[0x00, 0x03, 0, 2],
+                (1, 1, 3, _) => &[0x80, 0x71, 0, 0],
+                (2, 1, 0, _) => &[0, b'U', b'1', 0, 0x12, 0x01, 0x00, 0x40],
+                _ => bail!("unexpected request {request:02x?}"),
+            },
+        );
+        Ok(answer)
+    }
+
+    #[test]
+    fn discovery_walks_the_feature_table() {
+        let found = discover(board).unwrap();
+        assert_eq!(found.protocol, (4, 2));
+        let ids: Vec<u16> = found.features.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [0x0000, 0x0001, 0x0003, 0x8071]);
+        assert_eq!(found.features[3].name(), Some("rgb effects"));
+        assert_eq!(found.firmware[0].to_string(), "application U1 12.01.B0040");
+
+        assert_eq!(
+            request(3, 2, &[7]),
+            [
+                0x11, 0xff, 3, 0x2b, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+            ]
+        );
+    }
+}
@@ -1,10 +1,13 @@
 pub mod api;
+pub mod brightness;
 pub mod colors;
 pub mod device;
 pub mod effects;
+pub mod hidpp;
 pub mod model;
 pub mod packet;
 pub mod parser;
+pub mod persist;
 pub mod spec;
 pub mod types;
 
@@ -1,8 +1,11 @@
 use std::sync::{LazyLock, RwLock};
+use strum_macros::EnumString;
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum KeyboardModel {
+    #[strum(disabled)]
     Unknown = 0x00,
     G213,
     G410,
@@ -19,31 +22,20 @@ pub enum KeyboardModel {
 // Logitech's USB vendor ID (VID) used across all their HID keyboard products.
 pub const LOGITECH_VENDOR_ID: u16 = 0x046d;
 
-// Helper macro to define supported keyboards using a shared Logitech vendor ID.
-// Saves repetition in the `SUPPORTED_KEYBOARDS` list.
-macro_rules! kb {
-    ($pid:expr, $model:expr) => {
-        (LOGITECH_VENDOR_ID, $pid, $model)
-    };
-}
-
-/// List of known supported Logitech keyboards, identified by their USB product IDs (PIDs).
-/// Note: Some models have multiple PIDs for reasons unknown (blame Logitech). It's probably
-/// firmware or regional stuff though.
-pub const SUPPORTED_KEYBOARDS: &[(u16, u16, KeyboardModel)] = &[
-    kb!(0xc336, KeyboardModel::G213),
-    kb!(0xc330, KeyboardModel::G410),
-    kb!(0xc33a, KeyboardModel::G413),
-    kb!(0xc342, KeyboardModel::G512),
-    kb!(0xc33c, KeyboardModel::G513),
-    kb!(0xc333, KeyboardModel::G610),
-    kb!(0xc338, KeyboardModel::G610),
-    kb!(0xc331, KeyboardModel::G810),
-    kb!(0xc337, KeyboardModel::G810),
-    kb!(0xc33f, KeyboardModel::G815),
-    kb!(0xc32b, KeyboardModel::G910),
-    kb!(0xc335, KeyboardModel::G910),
-    kb!(0xc339, KeyboardModel::GPro), // Covers both G Pro and Pro X
+/// Known supported Logitech keyboards and every product ID (PID) each ships under.
+/// Note: Logitech reuses boards under new PIDs for revisions, regional variants and
+/// special editions, so a model may list several.
+pub const SUPPORTED_KEYBOARDS: &[(KeyboardModel, &[u16])] = &[
+    (KeyboardModel::G213, &[0xc336]),
+    (KeyboardModel::G410, &[0xc330]),
+    (KeyboardModel::G413, &[0xc33a]),
+    (KeyboardModel::G512, &[0xc342, 0xc33e]), // 0xc33e: G512 SE
+    (KeyboardModel::G513, &[0xc33c]),
+    (KeyboardModel::G610, &[0xc333, 0xc338]),
+    (KeyboardModel::G810, &[0xc331, 0xc337]),
+    (KeyboardModel::G815, &[0xc33f]),
+    (KeyboardModel::G910, &[0xc32b, 0xc335]),
+    (KeyboardModel::GPro, &[0xc339, 0xc343]), // 0xc343: Pro X revision
 ];
 
 type ModelOverride = Vec<(u16, u16, KeyboardModel)>;
@@ -62,28 +54,82 @@ pub fn clear_supported_override() {
     *SUPPORTED_OVERRIDE.write().unwrap() = None;
 }
 
+/// Devices added at runtime (e.g. from the config file) on top of the built-in table.
+static EXTRA_DEVICES: LazyLock<RwLock<ModelOverride>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Register additional VID/PID pairs, consulted before the built-in table.
+pub fn add_extra_devices(list: impl IntoIterator<Item = (u16, u16, KeyboardModel)>) {
+    EXTRA_DEVICES.write().unwrap().extend(list);
+}
+
+fn find_model(list: &[(u16, u16, KeyboardModel)], vid: u16, pid: u16) -> Option<KeyboardModel> {
+    list.iter()
+        .find(|&&(v, p, _)| v == vid && p == pid)
+        .map(|&(_, _, model)| model)
+}
+
 // Lookup a model by VID/PID, falls back to `Unknown`
 pub fn lookup_model(vid: u16, pid: u16) -> KeyboardModel {
     if let Some(list) = &*SUPPORTED_OVERRIDE.read().unwrap() {
-        return list
-            .iter()
-            .find_map(|&(v, p, model)| {
-                if v == vid && p == pid {
-                    Some(model)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(KeyboardModel::Unknown);
+        return find_model(list, vid, pid).unwrap_or(KeyboardModel::Unknown);
+    }
+    if let Some(model) = find_model(&EXTRA_DEVICES.read().unwrap(), vid, pid) {
+        return model;
+    }
+    if vid != LOGITECH_VENDOR_ID {
+        return KeyboardModel::Unknown;
     }
     SUPPORTED_KEYBOARDS
         .iter()
-        .find_map(|&(v, p, model)| {
-            if v == vid && p == pid {
-                Some(model)
-            } else {
-                None
+        .find(|(_, pids)| pids.contains(&pid))
+        .map_or(KeyboardModel::Unknown, |&(model, _)| model)
+}
+
+/// The built-in model whose product ID is numerically nearest to `pid`, and
+/// that ID. Logitech tends to number a board's revisions close together.
+pub fn closest_model(pid: u16) -> Option<(KeyboardModel, u16)> {
+    SUPPORTED_KEYBOARDS
+        .iter()
+        .flat_map(|&(model, pids)| pids.iter().map(move |&known| (model, known)))
+        .min_by_key(|&(_, known)| known.abs_diff(pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_covers_every_pid() {
+        for &(model, pids) in SUPPORTED_KEYBOARDS {
+            for &pid in pids {
+                assert_eq!(lookup_model(LOGITECH_VENDOR_ID, pid), model);
             }
-        })
-        .unwrap_or(KeyboardModel::Unknown)
+        }
+        assert_eq!(
+            lookup_model(LOGITECH_VENDOR_ID, 0xc33e),
+            KeyboardModel::G512
+        );
+        assert_eq!(lookup_model(0x1234, 0xc336), KeyboardModel::Unknown);
+    }
+
+    #[test]
+    fn extra_devices_are_consulted() {
+        assert_eq!(lookup_model(0x1234, 0x5678), KeyboardModel::Unknown);
+        add_extra_devices([(0x1234, 0x5678, KeyboardModel::G610)]);
+        assert_eq!(lookup_model(0x1234, 0x5678), KeyboardModel::G610);
+    }
+
+    #[test]
+    fn closest_model_by_pid() {
+        assert_eq!(closest_model(0xc331), Some((KeyboardModel::G810, 0xc331)));
+        assert_eq!(closest_model(0xc344), Some((KeyboardModel::GPro, 0xc343)));
+        assert_eq!(closest_model(0xc32c), Some((KeyboardModel::G910, 0xc32b)));
+    }
+
+    #[test]
+    fn model_names_parse() {
+        assert_eq!("gpro".parse(), Ok(KeyboardModel::GPro));
+        assert_eq!("G512".parse(), Ok(KeyboardModel::G512));
+        assert!("unknown".parse::<KeyboardModel>().is_err());
+    }
 }
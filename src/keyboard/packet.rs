@@ -1,5 +1,6 @@
 use crate::keyboard::{
-    Color, Key, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart, NativeEffectStorage,
+    Color, Key, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+    NativeEffectPart, NativeEffectStorage,
 };
 use core::time::Duration;
 
@@ -31,21 +32,39 @@ fn group_address(model: KeyboardModel, group: u8) -> Option<Packet> {
         .map(|&(_, packet)| packet)
 }
 
+/// Whether [`set_keys_packet`] can address `key` on `model`.
+pub fn can_address(model: KeyboardModel, key: Key) -> bool {
+    match model {
+        KeyboardModel::G213 | KeyboardModel::G413 => false,
+        KeyboardModel::G815 => g815_key_id(key).is_some(),
+        _ => group_address(model, key.group()).is_some() && key_code(model, key).is_some(),
+    }
+}
+
+/// Byte identifying `key` in a key group packet.
+fn key_code(model: KeyboardModel, key: Key) -> Option<u8> {
+    if key.key_group() == KeyGroup::GKeys {
+        model.spec().gkey_code(key)
+    } else {
+        Some(key.hid_code())
+    }
+}
+
+/// Byte identifying `key` in a packet for `model`; raw keys go as given.
+fn key_ref_code(model: KeyboardModel, key: KeyRef) -> Option<u8> {
+    match (model, key) {
+        (_, KeyRef::Raw { code, .. }) => Some(code),
+        (KeyboardModel::G815, KeyRef::Known(key)) => g815_key_id(key),
+        (_, KeyRef::Known(key)) => key_code(model, key),
+    }
+}
+
 /// Translate a [`Key`] into the byte identifier used by the G815.
 fn g815_key_id(key: Key) -> Option<u8> {
     let low = key.hid_code();
 
     Some(match key {
-        Key::Logo2
-        | Key::Game
-        | Key::Caps
-        | Key::Scroll
-        | Key::Num
-        | Key::Stop
-        | Key::G6
-        | Key::G7
-        | Key::G8
-        | Key::G9 => return None,
+        Key::Logo2 | Key::Game | Key::Caps | Key::Scroll | Key::Num | Key::Stop => return None,
 
         Key::Play => 0x9b,
         Key::Mute => 0x9c,
@@ -64,7 +83,7 @@ fn g815_key_id(key: Key) -> Option<u8> {
         _ => match key.group() {
             0 => low.wrapping_add(0xd1),
             1 => low.wrapping_add(0x98),
-            3 => low.wrapping_add(0xb3),
+            3 => KeyboardModel::G815.spec().gkey_code(key)?,
             4 => low.wrapping_sub(0x03),
             _ => return None,
         },
@@ -74,17 +93,28 @@ fn g815_key_id(key: Key) -> Option<u8> {
 /// Build a HID report that sets one or more keys.
 /// The slice must contain keys from the same address group.
 pub fn set_keys_packet(model: KeyboardModel, keys: &[KeyValue]) -> Option<Vec<u8>> {
-    if keys.is_empty() {
-        return None;
-    }
+    keys_packet(model, keys)
+}
+
+/// [`set_keys_packet`] for keys given by [`KeyRef`].
+pub fn set_key_refs_packet(model: KeyboardModel, keys: &[KeyRefValue]) -> Option<Vec<u8>> {
+    keys_packet(model, keys)
+}
+
+fn keys_packet<T>(model: KeyboardModel, keys: &[T]) -> Option<Vec<u8>>
+where
+    T: Copy + Into<KeyRefValue>,
+{
+    let refs = || keys.iter().map(|&kv| -> KeyRefValue { kv.into() });
+    let first = refs().next()?;
 
     match model {
         KeyboardModel::G213 | KeyboardModel::G413 => None,
 
         KeyboardModel::G815 => {
             // G815 requires a single color for the entire packet
-            let color = keys[0].color;
-            if keys.iter().any(|k| k.color != color) {
+            let color = first.color;
+            if refs().any(|k| k.color != color) {
                 return None;
             }
 
@@ -93,8 +123,8 @@ pub fn set_keys_packet(model: KeyboardModel, keys: &[KeyValue]) -> Option<Vec<u8
             data.extend_from_slice(header);
             data.extend_from_slice(&[color.red, color.green, color.blue]);
 
-            for kv in keys.iter().take(13) {
-                if let Some(id) = g815_key_id(kv.key) {
+            for kv in refs().take(13) {
+                if let Some(id) = key_ref_code(model, kv.key) {
                     data.push(id);
                 }
             }
@@ -107,8 +137,8 @@ pub fn set_keys_packet(model: KeyboardModel, keys: &[KeyValue]) -> Option<Vec<u8
         }
 
         _ => {
-            let group = keys[0].key.group();
-            if keys.iter().any(|k| k.key.group() != group) {
+            let group = first.key.group();
+            if refs().any(|k| k.key.group() != group) {
                 return None;
             }
 
@@ -116,13 +146,11 @@ pub fn set_keys_packet(model: KeyboardModel, keys: &[KeyValue]) -> Option<Vec<u8
             let max_keys = (size - 8) / 4;
             let mut data = group_address(model, group)?.to_vec();
 
-            for kv in keys.iter().take(max_keys) {
-                data.extend_from_slice(&[
-                    kv.key.hid_code(),
-                    kv.color.red,
-                    kv.color.green,
-                    kv.color.blue,
-                ]);
+            for kv in refs().take(max_keys) {
+                let Some(code) = key_ref_code(model, kv.key) else {
+                    continue;
+                };
+                data.extend_from_slice(&[code, kv.color.red, kv.color.green, kv.color.blue]);
             }
 
             Some(pad(data, size))
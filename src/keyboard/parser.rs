@@ -1,12 +1,17 @@
+use core::fmt;
 use core::time::Duration;
 use phf::{Map, phf_map};
-use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{LazyLock, PoisonError, RwLock};
+use std::{borrow::Cow, ops::RangeInclusive};
 
 use super::{
-    Color, Key, KeyGroup, OnBoardMode, StartupMode,
-    colors::lookup_color,
+    Color, Key, KeyGroup, KeyRef, OnBoardMode, StartupMode,
+    colors::{lookup_color, mix},
     effects::{NativeEffect, NativeEffectPart, NativeEffectStorage},
 };
+use crate::diagnostics::closest;
+use crate::i18n::tr_with;
 
 /// Parse a startup mode string.
 pub fn parse_startup_mode(s: &str) -> Option<StartupMode> {
@@ -30,16 +35,39 @@ fn ascii_lower(input: &str) -> Cow<'_, str> {
     }
 }
 
-/// Parse a color in hexadecimal `rrggbb` form (optionally `rr` for G610).
+/// Parse a color in hexadecimal `rrggbb` form (optionally `rr` for G610),
+/// a color name, or an expression such as `mix(red, blue, 30%)`.
 pub fn parse_color(val: &str) -> Option<Color> {
+    parse_color_nested(val, 0)
+}
+
+/// How deeply color expressions may nest, e.g. `mix(darken(red, 10%), ...)`.
+const MAX_COLOR_DEPTH: usize = 8;
+
+fn parse_color_nested(val: &str, depth: usize) -> Option<Color> {
     // Accept  name, "rrggbb" or "rr" (G610 grayscale). Optional leading '#'.
     let lower = ascii_lower(val);
     let value = lower.trim_start_matches('#');
 
+    if let Some((func, args)) = value
+        .strip_suffix(')')
+        .and_then(|call| call.split_once('('))
+    {
+        return (depth < MAX_COLOR_DEPTH)
+            .then(|| eval_color_fn(func.trim_end(), args, depth + 1))
+            .flatten();
+    }
+
     if let Some(color) = lookup_color(value) {
         return Some(color);
     }
 
+    // Checking the digits up front keeps the slicing below on ASCII boundaries
+    // and stops `from_str_radix` from accepting a sign such as "+f".
+    if !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
     let bytes: [u8; 3] = match value.len() {
         6 => {
             let r = u8::from_str_radix(&value[0..2], 16).ok()?;
@@ -57,6 +85,57 @@ pub fn parse_color(val: &str) -> Option<Color> {
     Some(Color::new(bytes[0], bytes[1], bytes[2]))
 }
 
+/// Apply one of the color functions to its comma-separated `args`.
+///
+/// Percentages are 0-100 with an optional `%`: `darken(c, 20%)` is `c` at 80%
+/// brightness, `lighten` blends toward white and `desaturate` toward gray.
+fn eval_color_fn(func: &str, args: &str, depth: usize) -> Option<Color> {
+    let args = split_top_level(args);
+    let color = |i: usize| parse_color_nested(args[i].trim(), depth);
+    let percent = |i: usize| {
+        args[i]
+            .trim()
+            .trim_end_matches('%')
+            .trim_end()
+            .parse::<u8>()
+            .ok()
+            .filter(|&p| p <= 100)
+    };
+    let fraction = |p: u8| f64::from(p) / 100.0;
+    match (func, args.len()) {
+        ("mix", 3) => Some(mix(color(0)?, color(1)?, fraction(percent(2)?))),
+        ("darken", 2) => Some(color(0)?.scaled(100 - percent(1)?)),
+        ("lighten", 2) => Some(mix(
+            color(0)?,
+            Color::new(0xff, 0xff, 0xff),
+            fraction(percent(1)?),
+        )),
+        ("desaturate", 2) => Some(color(0)?.saturated(100 - percent(1)?)),
+        _ => None,
+    }
+}
+
+/// Split `list` on the commas that are not inside parentheses, so
+/// `red, mix(red, blue, 30%)` is two items.
+pub(crate) fn split_top_level(list: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&list[start..]);
+    items
+}
+
 /// Parse a key group name.
 pub fn parse_key_group(s: &str) -> Option<KeyGroup> {
     s.parse::<KeyGroup>().ok()
@@ -224,12 +303,120 @@ static KEY_LOOKUP: Map<&'static str, Key> = phf_map! {
     "0" => Key::N0,
 };
 
+/// Key names added at runtime from the config's `[aliases]`, lowercased.
+static USER_ALIASES: LazyLock<RwLock<HashMap<String, Key>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Register user-defined key names; like the built-in ones they match
+/// case-insensitively.
+pub fn add_key_aliases(aliases: impl IntoIterator<Item = (String, Key)>) {
+    USER_ALIASES
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .extend(
+            aliases
+                .into_iter()
+                .map(|(name, key)| (name.to_ascii_lowercase(), key)),
+        );
+}
+
+/// Every built-in spelling `parse_key` accepts for `key`, longest first.
+pub fn key_aliases(key: Key) -> Vec<&'static str> {
+    let mut aliases: Vec<&'static str> = KEY_LOOKUP
+        .entries()
+        .filter(|&(_, &k)| k == key)
+        .map(|(&name, _)| name)
+        .collect();
+    aliases.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    aliases
+}
+
+/// A key name `parse_key` does not know, with the closest one it does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    pub name: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr_with("error-invalid-key", &[("value", &self.name)]))?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(
+                f,
+                ", {}",
+                tr_with("error-did-you-mean", &[("suggestion", suggestion)])
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownKey {}
+
 /// Case-insensitive parse of a key name / alias.
 /// *Single ASCII letter or digit* is handled in O(1) without the map.
-pub fn parse_key(s: &str) -> Option<Key> {
-    let lower_cow = ascii_lower(s);
-    let lower = lower_cow.as_ref();
+///
+/// `usage:0x04` names a key by its HID keyboard-page usage and `code:0x0404`
+/// by its full scan code, for keys whose name is not known yet.
+pub fn parse_key(s: &str) -> Result<Key, UnknownKey> {
+    let lower = ascii_lower(s);
+    if let Some(key) = parse_raw_key(s, &lower) {
+        return key;
+    }
+    lookup_key(&lower).ok_or_else(|| UnknownKey {
+        name: s.to_owned(),
+        suggestion: suggest_key(&lower),
+    })
+}
+
+/// The closest built-in or user-defined name to the lowercase `lower`.
+///
+/// Allows two edits, but no more than half the name, so short typos are not
+/// "corrected" into unrelated keys.
+fn suggest_key(lower: &str) -> Option<String> {
+    let max_distance = 2.min(lower.chars().count() / 2);
+    let aliases = USER_ALIASES.read().unwrap_or_else(PoisonError::into_inner);
+    let names = KEY_LOOKUP
+        .keys()
+        .copied()
+        .chain(aliases.keys().map(String::as_str));
+    closest(lower, names, max_distance).map(str::to_owned)
+}
+
+/// A `usage:` or `code:` key, or `None` if `lower` has neither prefix.
+fn parse_raw_key(s: &str, lower: &str) -> Option<Result<Key, UnknownKey>> {
+    if !lower.starts_with("usage:") && !lower.starts_with("code:") {
+        return None;
+    }
+    Some(
+        raw_code(lower)
+            .and_then(|code| Key::try_from(code).ok())
+            .ok_or_else(|| UnknownKey {
+                name: s.to_owned(),
+                suggestion: None,
+            }),
+    )
+}
 
+/// The scan code a `usage:` or `code:` name stands for.
+fn raw_code(lower: &str) -> Option<u16> {
+    if let Some(usage) = lower.strip_prefix("usage:") {
+        parse_u8(usage).map(|usage| 0x0400 | u16::from(usage))
+    } else {
+        parse_u16(lower.strip_prefix("code:")?)
+    }
+}
+
+/// Like [`parse_key`], but `usage:` and `code:` names no [`Key`] has give a
+/// [`KeyRef::Raw`] instead of an error.
+pub fn parse_key_ref(s: &str) -> Result<KeyRef, UnknownKey> {
+    parse_key(s)
+        .map(KeyRef::Known)
+        .or_else(|err| raw_code(&ascii_lower(s)).map(KeyRef::from_code).ok_or(err))
+}
+
+fn lookup_key(lower: &str) -> Option<Key> {
     if let Some(key) = KEY_LOOKUP.get(lower) {
         return Some(*key);
     }
@@ -251,7 +438,11 @@ pub fn parse_key(s: &str) -> Option<Key> {
             _ => return None,
         });
     }
-    None
+    USER_ALIASES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(lower)
+        .copied()
 }
 
 pub fn parse_period(val: &str) -> Option<Duration> {
@@ -267,24 +458,53 @@ pub fn parse_period(val: &str) -> Option<Duration> {
     }
 
     // 2. hex byte (length 1 or 2)
-    let hex: Cow<'_, str> = if v.len() == 1 {
-        // avoid allocation; build two-char stack buffer
-        let mut buf = [0u8; 2];
-        buf[0] = b'0';
-        buf[1] = v.as_bytes()[0];
-        Cow::Owned(core::str::from_utf8(&buf).unwrap().to_string())
-    } else {
-        Cow::Borrowed(v)
-    };
-
-    if hex.len() == 2 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
-        let byte = u8::from_str_radix(&hex, 16).ok()?;
+    if matches!(v.len(), 1 | 2) && v.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let byte = u8::from_str_radix(v, 16).ok()?;
         return Some(Duration::from_millis(u64::from(byte) << 8));
     }
 
     None
 }
 
+/// Parse a wall-clock duration: a whole number with `ms`, `s`, `m` or `h`.
+///
+/// Unlike [`parse_period`] this is for schedules and ramps, not firmware timing.
+pub fn parse_duration(val: &str) -> Option<Duration> {
+    let v = val.trim();
+    let (number, unit) = v.split_at(v.find(|c: char| !c.is_ascii_digit())?);
+    let n: u64 = number.parse().ok()?;
+    match unit.to_ascii_lowercase().as_str() {
+        "ms" => Some(Duration::from_millis(n)),
+        "s" => Some(Duration::from_secs(n)),
+        "m" | "min" => n.checked_mul(60).map(Duration::from_secs),
+        "h" => n.checked_mul(3600).map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+/// Parse a region selector: an index, a region name from `names`, or an
+/// inclusive `first-last` range of either (e.g. `2`, `numpad`, `1-5`).
+pub fn parse_regions(s: &str, names: &[&str]) -> Option<RangeInclusive<u8>> {
+    let single = |tok: &str| {
+        let tok = tok.trim();
+        names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(tok))
+            .and_then(|idx| u8::try_from(idx + 1).ok())
+            .or_else(|| parse_u8(tok))
+    };
+
+    // Names may themselves contain '-', so try the whole string first.
+    if let Some(region) = single(s) {
+        return Some(region..=region);
+    }
+
+    s.match_indices('-')
+        .find_map(|(split, _)| Some((single(&s[..split])?, single(&s[split + 1..])?)))
+        .filter(|(first, last)| first <= last)
+        .map(|(first, last)| first..=last)
+}
+
 /// Parse a native effect name.
 pub fn parse_native_effect(s: &str) -> Option<NativeEffect> {
     s.parse::<NativeEffect>().ok()
@@ -323,12 +543,124 @@ pub fn parse_u16(val: &str) -> Option<u16> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn user_aliases_resolve_case_insensitively() {
+        assert!(parse_key("test-ptt").is_err());
+        add_key_aliases([("Test-PTT".to_owned(), Key::G5)]);
+        assert_eq!(parse_key("test-ptt"), Ok(Key::G5));
+        assert_eq!(parse_key("TEST-ptt"), Ok(Key::G5));
+    }
+
+    #[test]
+    fn user_aliases_survive_a_poisoned_lock() {
+        let _ = std::thread::spawn(|| {
+            let _aliases = USER_ALIASES.write().unwrap();
+            panic!("poison the aliases");
+        })
+        .join();
+        add_key_aliases([("test-mic".to_owned(), Key::G4)]);
+        assert_eq!(parse_key("test-mic"), Ok(Key::G4));
+        assert!(parse_key("test-mik").is_err());
+    }
 
     #[test]
     fn parse_color_valid() {
         assert_eq!(parse_color("#ff3366"), Some(Color::new(0xff, 0x33, 0x66)));
         assert_eq!(parse_color("80"), Some(Color::new(0x80, 0x80, 0x80)));
         assert_eq!(parse_color("red"), Some(Color::new(0xff, 0x00, 0x00)));
+        assert_eq!(
+            parse_color("RebeccaPurple"),
+            Some(Color::new(0x66, 0x33, 0x99))
+        );
+        assert_eq!(parse_color("green"), parse_color("lime"));
+    }
+
+    #[test]
+    fn parse_color_evaluates_expressions() {
+        assert_eq!(
+            parse_color("mix(red, blue, 30%)"),
+            Some(Color::new(0xb3, 0x00, 0x4d))
+        );
+        assert_eq!(
+            parse_color("darken(#FF8800, 20%)"),
+            Some(Color::new(0xcc, 0x6d, 0x00))
+        );
+        assert_eq!(parse_color("lighten(000000, 50)"), parse_color("80"));
+        assert_eq!(parse_color("desaturate(red, 100%)"), parse_color("white"));
+        assert_eq!(
+            parse_color("mix(darken(white,50%),black,0%)"),
+            parse_color("darken(white, 50%)")
+        );
+
+        assert_eq!(parse_color("mix(red, blue)"), None);
+        assert_eq!(parse_color("darken(red, 120%)"), None);
+        assert_eq!(parse_color("shade(red, 20%)"), None);
+        assert_eq!(parse_color("darken(nope, 20%)"), None);
+        let deep = "darken(".repeat(20) + "red" + &", 1%)".repeat(20);
+        assert_eq!(parse_color(&deep), None);
+    }
+
+    #[test]
+    fn parse_color_multibyte_is_rejected() {
+        assert_eq!(parse_color("aÿÿb"), None);
+        assert_eq!(parse_color("ÿÿÿ"), None);
+        assert_eq!(parse_color("#é"), None);
+        assert_eq!(parse_color("+f"), None);
+        assert_eq!(parse_color("+fffff"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn parse_color_never_panics(s in "\\PC*") {
+            let _ = parse_color(&s);
+        }
+
+        #[test]
+        fn parse_color_accepts_only_names_and_hex(s in "#?[0-9a-fA-Fÿé+-]{0,8}") {
+            if parse_color(&s).is_some() {
+                let value = s.trim_start_matches('#');
+                prop_assert!(
+                    lookup_color(value).is_some()
+                        || (matches!(value.len(), 2 | 6)
+                            && value.bytes().all(|b| b.is_ascii_hexdigit()))
+                );
+            }
+        }
+
+        #[test]
+        fn parse_color_round_trips_hex(r: u8, g: u8, b: u8, upper: bool, hash: bool) {
+            let mut s = format!("{r:02x}{g:02x}{b:02x}");
+            if upper {
+                s = s.to_ascii_uppercase();
+            }
+            if hash {
+                s.insert(0, '#');
+            }
+            prop_assert_eq!(parse_color(&s), Some(Color::new(r, g, b)));
+        }
+
+        #[test]
+        fn parse_period_never_panics(s in "\\PC*") {
+            let _ = parse_period(&s);
+        }
+
+        #[test]
+        fn parse_period_units(n: u32, byte: u8) {
+            prop_assert_eq!(
+                parse_period(&format!("{n}ms")),
+                Some(Duration::from_millis(u64::from(n)))
+            );
+            prop_assert_eq!(
+                parse_period(&format!("{n}s")),
+                Some(Duration::from_secs(u64::from(n)))
+            );
+            prop_assert_eq!(
+                parse_period(&format!("{byte:02x}")),
+                Some(Duration::from_millis(u64::from(byte) << 8))
+            );
+        }
     }
 
     #[test]
@@ -338,9 +670,45 @@ mod tests {
 
     #[test]
     fn parse_key_alias_and_single() {
-        assert_eq!(parse_key("light"), Some(Key::Backlight));
-        assert_eq!(parse_key("A"), Some(Key::A));
-        assert_eq!(parse_key("5"), Some(Key::N5));
+        assert_eq!(parse_key("light"), Ok(Key::Backlight));
+        assert_eq!(parse_key("A"), Ok(Key::A));
+        assert_eq!(parse_key("5"), Ok(Key::N5));
+    }
+
+    #[test]
+    fn parse_key_by_usage_and_scan_code() {
+        assert_eq!(parse_key("usage:0x04"), Ok(Key::A));
+        assert_eq!(parse_key("USAGE:0xE0"), Ok(Key::CtrlLeft));
+        assert_eq!(parse_key("code:0x0404"), Ok(Key::A));
+        assert_eq!(parse_key("code:0x0309"), Ok(Key::G9));
+        assert_eq!(parse_key("code:1"), Ok(Key::Logo));
+        let err = parse_key("code:0x04ff").unwrap_err();
+        assert_eq!((err.name.as_str(), err.suggestion), ("code:0x04ff", None));
+        assert!(parse_key("usage:0x100").is_err());
+    }
+
+    #[test]
+    fn parse_key_ref_keeps_unknown_codes() {
+        assert_eq!(parse_key_ref("esc"), Ok(KeyRef::Known(Key::Esc)));
+        assert_eq!(parse_key_ref("usage:0x04"), Ok(KeyRef::Known(Key::A)));
+        let raw = KeyRef::Raw {
+            group: 0x04,
+            code: 0xff,
+        };
+        assert_eq!(parse_key_ref("code:0x04ff"), Ok(raw));
+        assert_eq!(parse_key_ref(&raw.to_string()), Ok(raw));
+        assert!(parse_key_ref("usage:0x100").is_err());
+        assert!(parse_key_ref("scape").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_suggest_the_closest_name() {
+        let err = parse_key("PgDwn").unwrap_err();
+        assert_eq!(err.name, "PgDwn");
+        assert_eq!(err.suggestion.as_deref(), Some("pgdn"));
+        assert!(err.to_string().ends_with(", did you mean `pgdn`?"));
+        assert_eq!(parse_key("qqq").unwrap_err().suggestion, None);
+        assert_eq!(parse_key("completely_wrong").unwrap_err().suggestion, None);
     }
 
     #[test]
@@ -361,9 +729,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_regions_index_name_and_range() {
+        let names = [
+            "left",
+            "center-left",
+            "center-right",
+            "navigation",
+            "numpad",
+        ];
+        assert_eq!(parse_regions("3", &names), Some(3..=3));
+        assert_eq!(parse_regions("numpad", &names), Some(5..=5));
+        assert_eq!(parse_regions("1-5", &names), Some(1..=5));
+        assert_eq!(parse_regions("center-left", &names), Some(2..=2));
+        assert_eq!(parse_regions("center-left-navigation", &names), Some(2..=4));
+        assert_eq!(parse_regions("4-2", &names), None);
+        assert_eq!(parse_regions("nowhere", &names), None);
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("10"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("5ÿ"), None);
+    }
+
     #[test]
     fn parse_u8_decimal_and_hex() {
         assert_eq!(parse_u8("80"), Some(80));
         assert_eq!(parse_u16("0xff"), Some(0xff));
     }
+
+    #[test]
+    fn key_aliases_cover_lookup() {
+        use strum::IntoEnumIterator;
+
+        let total: usize = Key::iter().map(|k| key_aliases(k).len()).sum();
+        assert_eq!(total, KEY_LOOKUP.len());
+        assert_eq!(
+            key_aliases(Key::Backlight),
+            vec!["back_light", "backlight", "light"]
+        );
+        for alias in key_aliases(Key::PageDown) {
+            assert_eq!(parse_key(alias), Ok(Key::PageDown));
+        }
+    }
 }
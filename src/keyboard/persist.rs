@@ -0,0 +1,114 @@
+//! `--no-persist`: refuse writes to on-board memory, whatever asks for them.
+
+use anyhow::{Result, bail};
+use core::time::Duration;
+
+use crate::keyboard::{
+    Color, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+
+/// Forwards every call to `inner` except those that would change what the
+/// keyboard keeps across power cycles: the startup mode and effects stored
+/// in on-board slots. Those fail instead, so a downloaded profile cannot
+/// change the board for good.
+pub struct NoPersist<'a, K: ?Sized> {
+    inner: &'a mut K,
+}
+
+impl<'a, K: KeyboardApi + ?Sized> NoPersist<'a, K> {
+    pub fn new(inner: &'a mut K) -> Self {
+        Self { inner }
+    }
+}
+
+impl<K: KeyboardApi + ?Sized> KeyboardApi for NoPersist<'_, K> {
+    fn model(&self) -> KeyboardModel {
+        self.inner.model()
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.inner.set_all_keys(color)
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.inner.set_group_keys(group, color)
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        self.inner.set_keys(keys)
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        self.inner.set_key_refs(keys)
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.inner.set_region(region, color)
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mr_key(value)
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.inner.set_mn_key(value)
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.inner.set_gkeys_mode(value)
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        bail!("--no-persist: refusing to set the startup mode to {mode:?}")
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.inner.set_on_board_mode(mode)
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        if storage != NativeEffectStorage::None {
+            bail!("--no-persist: refusing to store the {effect} effect in the {storage} slot");
+        }
+        self.inner.set_fx(effect, part, period, color, storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::api::NullKeyboard;
+
+    #[test]
+    fn only_persistent_writes_are_refused() {
+        let mut null = NullKeyboard;
+        let mut kbd = NoPersist::new(&mut null);
+        let red = Color::new(0xff, 0, 0);
+        let fx = |kbd: &mut NoPersist<'_, NullKeyboard>, storage| {
+            kbd.set_fx(
+                NativeEffect::Color,
+                NativeEffectPart::All,
+                Duration::ZERO,
+                red,
+                storage,
+            )
+        };
+        assert!(fx(&mut kbd, NativeEffectStorage::None).is_ok());
+        assert!(fx(&mut kbd, NativeEffectStorage::User).is_err());
+        assert!(kbd.set_startup_mode(StartupMode::Color).is_err());
+        assert!(kbd.set_all_keys(red).is_ok());
+        assert!(kbd.set_on_board_mode(OnBoardMode::Board).is_ok());
+    }
+}
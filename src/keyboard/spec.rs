@@ -1,4 +1,48 @@
-use crate::keyboard::KeyboardModel;
+use core::time::Duration;
+use std::sync::RwLock;
+
+use strum::IntoEnumIterator;
+
+use crate::keyboard::{Color, Key, KeyGroup, KeyValue, KeyboardModel, packet};
+
+/// A lighting zone on region-based boards and the keys it physically covers.
+pub struct Region {
+    pub name: &'static str,
+    pub keys: &'static [Key],
+}
+
+/// Host-side pacing for firmware that drops packets sent back to back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    /// Pause after every packet.
+    pub delay: Duration,
+    /// Wait up to this long for the board to echo a packet before sending the next.
+    pub ack_timeout: Option<Duration>,
+}
+
+/// The most a model's firmware takes before it locks up until replugged.
+///
+/// Zero means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Packets a second, commits included.
+    pub packets_per_sec: u32,
+    /// Shortest time from one commit to the next.
+    pub min_commit_gap: Duration,
+}
+
+/// A feature only some models have, for checking a command applies before
+/// sending it anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Regions,
+    NativeEffects,
+    MrKey,
+    MnKey,
+    GKeysMode,
+    StartupMode,
+    OnBoardMode,
+}
 
 pub struct ModelSpec {
     pub commit: Option<&'static [u8]>,
@@ -12,6 +56,14 @@ pub struct ModelSpec {
     pub onboard_header: Option<&'static [u8]>,
     pub keys_header: Option<&'static [u8]>,
     pub region_header: Option<&'static [u8]>,
+    /// Regions in physical order, left to right; region `n` is `regions[n - 1]`.
+    pub regions: &'static [Region],
+    pub pacing: Option<Pacing>,
+    pub rate_limit: Option<RateLimit>,
+    /// No numeric keypad.
+    pub tenkeyless: bool,
+    /// Scan codes of the G-keys the model has, `G1` first.
+    pub gkeys: &'static [u8],
 }
 
 impl ModelSpec {
@@ -28,6 +80,11 @@ impl ModelSpec {
             onboard_header: None,
             keys_header: None,
             region_header: None,
+            regions: &[],
+            pacing: None,
+            rate_limit: None,
+            tenkeyless: false,
+            gkeys: &[],
         }
     }
 
@@ -108,6 +165,117 @@ impl ModelSpec {
         self
     }
 
+    #[must_use]
+    pub const fn regions(mut self, region_list: &'static [Region]) -> Self {
+        self.regions = region_list;
+        self
+    }
+
+    #[must_use]
+    pub const fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = Some(pacing);
+        self
+    }
+
+    #[must_use]
+    pub const fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    pub const fn tenkeyless(mut self) -> Self {
+        self.tenkeyless = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn gkeys(mut self, scan_codes: &'static [u8]) -> Self {
+        self.gkeys = scan_codes;
+        self
+    }
+
+    /// Scan code of `key` if it is a G-key this model has.
+    pub fn gkey_code(&self, key: Key) -> Option<u8> {
+        let index = key.hid_code().checked_sub(1)?;
+        (key.key_group() == KeyGroup::GKeys)
+            .then(|| self.gkeys.get(usize::from(index)).copied())
+            .flatten()
+    }
+
+    /// Number of addressable lighting regions (0 for per-key-only boards).
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn region_count(&self) -> u8 {
+        self.regions.len() as u8
+    }
+
+    /// Region names in physical order.
+    pub fn region_names(&self) -> Vec<&'static str> {
+        self.regions.iter().map(|region| region.name).collect()
+    }
+
+    /// 1-based indices of the regions covering any of `keys`.
+    pub fn regions_covering(&self, keys: &[Key]) -> Vec<u8> {
+        (1..=self.region_count())
+            .zip(self.regions)
+            .filter(|(_, region)| region.keys.iter().any(|k| keys.contains(k)))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Splits `keys` into the regions they fill in a single color, each of
+    /// which one region packet can set, and the keys left over.
+    ///
+    /// When a key appears more than once its last color counts.
+    pub fn whole_regions(&self, keys: &[KeyValue]) -> (Vec<(u8, Color)>, Vec<KeyValue>) {
+        let color_of = |key: &Key| {
+            keys.iter()
+                .rev()
+                .find(|kv| kv.key == *key)
+                .map(|kv| kv.color)
+        };
+        let whole: Vec<(u8, Color)> = (1..=self.region_count())
+            .zip(self.regions)
+            .filter_map(|(idx, region)| {
+                let first = color_of(region.keys.first()?)?;
+                region
+                    .keys
+                    .iter()
+                    .all(|key| color_of(key) == Some(first))
+                    .then_some((idx, first))
+            })
+            .collect();
+        let rest = keys
+            .iter()
+            .filter(|kv| {
+                !whole
+                    .iter()
+                    .any(|&(idx, _)| self.regions[usize::from(idx - 1)].keys.contains(&kv.key))
+            })
+            .copied()
+            .collect();
+        (whole, rest)
+    }
+
+    /// The color most of `keys` give each region they fall in, the closest a
+    /// board that can only light whole regions gets to them.
+    pub fn region_colors(&self, keys: &[KeyValue]) -> Vec<(u8, Color)> {
+        (1..=self.region_count())
+            .zip(self.regions)
+            .filter_map(|(idx, region)| {
+                let inside: Vec<Color> = keys
+                    .iter()
+                    .filter(|kv| region.keys.contains(&kv.key))
+                    .map(|kv| kv.color)
+                    .collect();
+                let color = inside
+                    .iter()
+                    .max_by_key(|&c| inside.iter().filter(|&o| o == c).count())?;
+                Some((idx, *color))
+            })
+            .collect()
+    }
+
     /// Applies the standard lighting effect parameters and startup header used by most GX-series models.
     ///
     /// This is a convenience helper for models like G410, G512, G610, G810, and G Pro,
@@ -142,6 +310,64 @@ const ADDR_G910: &[(u8, &[u8])] = &[
     (4, &[0x12, 0xff, 0x0f, 0x3d, 0x00, 0x01, 0x00, 0x0e]),
 ];
 
+#[rustfmt::skip]
+// The G213 splits its board into five zones: the main block in thirds, the
+// navigation cluster, and the numpad (which also carries the indicators and
+// media keys above it).
+const REGIONS_G213: &[Region] = &[
+    Region {
+        name: "left",
+        keys: &[
+            Key::Esc, Key::F1, Key::F2, Key::F3, Key::F4, Key::Tilde, Key::N1, Key::N2, Key::N3,
+            Key::N4, Key::N5, Key::Tab, Key::Q, Key::W, Key::E, Key::R, Key::T, Key::CapsLock,
+            Key::A, Key::S, Key::D, Key::F, Key::G, Key::ShiftLeft, Key::IntlBackslash, Key::Z,
+            Key::X, Key::C, Key::V, Key::CtrlLeft, Key::WinLeft, Key::AltLeft,
+        ],
+    },
+    Region {
+        name: "center-left",
+        keys: &[
+            Key::F5, Key::F6, Key::F7, Key::F8, Key::N6, Key::N7, Key::N8, Key::N9, Key::Y,
+            Key::U, Key::I, Key::O, Key::H, Key::J, Key::K, Key::L, Key::B, Key::N, Key::M,
+            Key::Comma, Key::Space, Key::Logo,
+        ],
+    },
+    Region {
+        name: "center-right",
+        keys: &[
+            Key::F9, Key::F10, Key::F11, Key::F12, Key::N0, Key::Minus, Key::Equal,
+            Key::Backspace, Key::P, Key::OpenBracket, Key::CloseBracket, Key::Backslash,
+            Key::Semicolon, Key::Quote, Key::Dollar, Key::Enter, Key::Period, Key::Slash,
+            Key::AbntSlash, Key::ShiftRight, Key::AltRight, Key::WinRight, Key::Menu,
+            Key::CtrlRight,
+        ],
+    },
+    Region {
+        name: "navigation",
+        keys: &[
+            Key::PrintScreen, Key::ScrollLock, Key::PauseBreak, Key::Insert, Key::Home,
+            Key::PageUp, Key::Del, Key::End, Key::PageDown, Key::ArrowTop, Key::ArrowLeft,
+            Key::ArrowBottom, Key::ArrowRight,
+        ],
+    },
+    Region {
+        name: "numpad",
+        keys: &[
+            Key::NumLock, Key::NumSlash, Key::NumAsterisk, Key::NumMinus, Key::NumPlus,
+            Key::NumEnter, Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5,
+            Key::Num6, Key::Num7, Key::Num8, Key::Num9, Key::NumDot, Key::Backlight, Key::Game,
+            Key::Caps, Key::Scroll, Key::Num, Key::Next, Key::Prev, Key::Stop, Key::Play,
+            Key::Mute,
+        ],
+    },
+];
+
+// The G815 has five G-keys and numbers them after its key IDs.
+const GKEYS_G815: &[u8] = &[0xb4, 0xb5, 0xb6, 0xb7, 0xb8];
+
+// Both G910 revisions (Orion Spark and Orion Spectrum) have nine.
+const GKEYS_G910: &[u8] = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+
 const MN_MAP_G815: &[(u8, u8)] = &[(0x01, 0x01), (0x02, 0x02), (0x03, 0x04)];
 
 pub const MODEL_SPECS: [ModelSpec; 11] = [
@@ -151,12 +377,14 @@ pub const MODEL_SPECS: [ModelSpec; 11] = [
     ModelSpec::builder()
         .group_addresses(ADDR_GX)
         .with_gx_defaults(0x0c)
-        .region_header(&[0x11, 0xff, 0x0c, 0x3a]),
+        .region_header(&[0x11, 0xff, 0x0c, 0x3a])
+        .regions(REGIONS_G213),
     // G410
     ModelSpec::builder()
         .commit(&[0x11, 0xff, 0x0c, 0x5a])
         .group_addresses(ADDR_GX)
-        .with_gx_defaults(0x0d),
+        .with_gx_defaults(0x0d)
+        .tenkeyless(),
     // G413
     ModelSpec::builder()
         .group_addresses(ADDR_GX)
@@ -175,7 +403,12 @@ pub const MODEL_SPECS: [ModelSpec; 11] = [
     ModelSpec::builder()
         .commit(&[0x11, 0xff, 0x0c, 0x5a])
         .group_addresses(ADDR_G610_G810)
-        .with_gx_defaults(0x0d),
+        .with_gx_defaults(0x0d)
+        // Faster animations hang the G610's lighting until it is replugged.
+        .rate_limit(RateLimit {
+            packets_per_sec: 120,
+            min_commit_gap: Duration::from_millis(33),
+        }),
     // G810
     ModelSpec::builder()
         .commit(&[0x11, 0xff, 0x0c, 0x5a])
@@ -191,7 +424,8 @@ pub const MODEL_SPECS: [ModelSpec; 11] = [
         .mn_map(MN_MAP_G815)
         .gkeys_header(&[0x11, 0xff, 0x0a, 0x2b])
         .onboard_header(&[0x11, 0xff, 0x11, 0x1a])
-        .keys_header(&[0x11, 0xff, 0x10, 0x6c]),
+        .keys_header(&[0x11, 0xff, 0x10, 0x6c])
+        .gkeys(GKEYS_G815),
     // G910
     ModelSpec::builder()
         .commit(&[0x11, 0xff, 0x0f, 0x5d])
@@ -200,16 +434,151 @@ pub const MODEL_SPECS: [ModelSpec; 11] = [
         .mr_header(&[0x11, 0xff, 0x0a, 0x0e])
         .mn_header(&[0x11, 0xff, 0x09, 0x1e])
         .gkeys_header(&[0x11, 0xff, 0x08, 0x2e])
-        .startup_header(&[0x11, 0xff, 0x10, 0x5e, 0x00, 0x01]),
+        .startup_header(&[0x11, 0xff, 0x10, 0x5e, 0x00, 0x01])
+        .gkeys(GKEYS_G910)
+        // Without this the G910 intermittently drops key color packets.
+        .pacing(Pacing {
+            delay: Duration::from_millis(1),
+            ack_timeout: Some(Duration::from_millis(20)),
+        }),
     // GPro
     ModelSpec::builder()
         .commit(&[0x11, 0xff, 0x0c, 0x5a])
         .group_addresses(ADDR_GX)
-        .with_gx_defaults(0x0d),
+        .with_gx_defaults(0x0d)
+        .tenkeyless(),
 ];
 
+/// Rate limits from the config, used instead of the built-in ones.
+static RATE_LIMITS: RwLock<Vec<(KeyboardModel, RateLimit)>> = RwLock::new(Vec::new());
+
+/// Replace the built-in rate limits of the models in `list`.
+pub fn set_rate_limits(list: impl IntoIterator<Item = (KeyboardModel, RateLimit)>) {
+    *RATE_LIMITS.write().unwrap() = list.into_iter().collect();
+}
+
 impl KeyboardModel {
     pub fn spec(self) -> &'static ModelSpec {
         &MODEL_SPECS[self as usize]
     }
+
+    /// The configured rate limit, or else the built-in one.
+    pub fn rate_limit(self) -> Option<RateLimit> {
+        RATE_LIMITS
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(model, _)| *model == self)
+            .map_or(self.spec().rate_limit, |&(_, limit)| Some(limit))
+    }
+
+    /// Whether this model has `capability`.
+    pub fn supports(self, capability: Capability) -> bool {
+        let spec = self.spec();
+        match capability {
+            Capability::Regions => spec.region_header.is_some(),
+            Capability::NativeEffects => spec.effect_params.is_some(),
+            Capability::MrKey => spec.mr_header.is_some(),
+            Capability::MnKey => spec.mn_header.is_some(),
+            Capability::GKeysMode => spec.gkeys_header.is_some(),
+            Capability::StartupMode => spec.startup_header.is_some(),
+            Capability::OnBoardMode => spec.onboard_header.is_some(),
+        }
+    }
+
+    /// Whether `key` exists on this model and can be lit on its own.
+    pub fn has_key(self, key: Key) -> bool {
+        !(self.spec().tenkeyless && key.key_group() == KeyGroup::Numeric)
+            && packet::can_address(self, key)
+    }
+
+    /// Keys that exist on this model and can be lit on their own.
+    pub fn supported_keys(self) -> impl Iterator<Item = Key> {
+        Key::iter().filter(move |&key| self.has_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyGroup;
+
+    fn covering(group: KeyGroup) -> Vec<u8> {
+        let keys: Vec<Key> = group.keys().collect();
+        KeyboardModel::G213.spec().regions_covering(&keys)
+    }
+
+    #[test]
+    fn g213_groups_map_to_regions() {
+        assert_eq!(covering(KeyGroup::Arrows), vec![4]);
+        assert_eq!(covering(KeyGroup::Numeric), vec![5]);
+        assert_eq!(covering(KeyGroup::FKeys), vec![1, 2, 3]);
+        assert_eq!(covering(KeyGroup::Keys), vec![1, 2, 3]);
+        assert_eq!(covering(KeyGroup::Indicators), vec![5]);
+        assert_eq!(covering(KeyGroup::GKeys), vec![]);
+    }
+
+    #[test]
+    fn whole_regions_take_one_packet() {
+        let spec = KeyboardModel::G213.spec();
+        let red = Color::new(0xff, 0, 0);
+        let blue = Color::new(0, 0, 0xff);
+        let mut keys: Vec<KeyValue> = spec.regions[3]
+            .keys
+            .iter()
+            .map(|&key| KeyValue { key, color: red })
+            .collect();
+        keys.push(KeyValue {
+            key: Key::Num5,
+            color: blue,
+        });
+
+        let (whole, rest) = spec.whole_regions(&keys);
+        assert_eq!(whole, [(4, red)]);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(spec.region_colors(&rest), [(5, blue)]);
+    }
+
+    #[test]
+    fn supported_keys_follow_layout_and_addressing() {
+        let g810: Vec<Key> = KeyboardModel::G810.supported_keys().collect();
+        assert!(g810.contains(&Key::Num5) && g810.contains(&Key::Logo));
+        assert!(!g810.contains(&Key::G1));
+        assert!(!KeyboardModel::GPro.has_key(Key::Num5));
+        assert!(KeyboardModel::GPro.has_key(Key::Esc));
+        assert!(KeyboardModel::G815.has_key(Key::G5));
+        assert!(!KeyboardModel::G815.has_key(Key::G6));
+        assert!(KeyboardModel::G910.has_key(Key::G9));
+        assert_eq!(KeyboardModel::G213.supported_keys().count(), 0);
+        assert_eq!(KeyboardModel::Unknown.supported_keys().count(), 0);
+    }
+
+    #[test]
+    fn gkey_codes_are_per_model() {
+        assert_eq!(KeyboardModel::G910.spec().gkey_code(Key::G9), Some(0x09));
+        assert_eq!(KeyboardModel::G815.spec().gkey_code(Key::G1), Some(0xb4));
+        assert_eq!(KeyboardModel::G815.spec().gkey_code(Key::G6), None);
+        assert_eq!(KeyboardModel::G810.spec().gkey_code(Key::G1), None);
+        assert_eq!(KeyboardModel::G910.spec().gkey_code(Key::A), None);
+    }
+
+    #[test]
+    fn capabilities_follow_spec() {
+        assert!(KeyboardModel::G213.supports(Capability::Regions));
+        assert!(!KeyboardModel::G810.supports(Capability::Regions));
+        assert!(KeyboardModel::G910.supports(Capability::MrKey));
+        assert!(!KeyboardModel::G910.supports(Capability::OnBoardMode));
+        assert!(KeyboardModel::G815.supports(Capability::OnBoardMode));
+        assert!(!KeyboardModel::G815.supports(Capability::StartupMode));
+        assert!(!KeyboardModel::Unknown.supports(Capability::NativeEffects));
+    }
+
+    #[test]
+    fn per_key_models_have_no_regions() {
+        assert_eq!(KeyboardModel::G810.spec().region_count(), 0);
+        assert_eq!(
+            KeyboardModel::G810.spec().regions_covering(&[Key::A]),
+            vec![]
+        );
+    }
 }
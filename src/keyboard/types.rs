@@ -5,6 +5,7 @@ use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString};
 
 use super::parser::{parse_color, parse_key};
+use crate::i18n::tr_with;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
@@ -56,7 +57,7 @@ impl FromStr for KeyGroup {
             "arrows" => Ok(KeyGroup::Arrows),
             "numeric" => Ok(KeyGroup::Numeric),
             "keys" => Ok(KeyGroup::Keys),
-            _ => Err(format!("invalid key group: {s}")),
+            _ => Err(tr_with("error-invalid-group", &[("value", s)])),
         }
     }
 }
@@ -64,7 +65,7 @@ impl FromStr for KeyGroup {
 impl KeyGroup {
     /// Lazily iterate the keys that belong to this group.
     pub fn keys(self) -> impl Iterator<Item = Key> {
-        Key::iter().filter(move |k| k.group() == self as u8)
+        Key::iter().filter(move |k| k.key_group() == self)
     }
 }
 
@@ -222,6 +223,78 @@ impl Key {
         (self as u16 >> 8) as u8
     }
 
+    /// User-facing group this key belongs to (see `help-keys`).
+    ///
+    /// Unlike [`Key::group`] this splits the big address group 4 into
+    /// function keys, modifiers, navigation, arrows, numpad and the rest.
+    pub const fn key_group(self) -> KeyGroup {
+        match self.group() {
+            0 => return KeyGroup::Logo,
+            1 => return KeyGroup::Indicators,
+            2 => return KeyGroup::Multimedia,
+            3 => return KeyGroup::GKeys,
+            _ => {}
+        }
+
+        match self {
+            Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+            | Key::F12 => KeyGroup::FKeys,
+
+            Key::CtrlLeft
+            | Key::ShiftLeft
+            | Key::AltLeft
+            | Key::WinLeft
+            | Key::CtrlRight
+            | Key::ShiftRight
+            | Key::AltRight
+            | Key::WinRight
+            | Key::Menu => KeyGroup::Modifiers,
+
+            Key::Esc
+            | Key::PrintScreen
+            | Key::ScrollLock
+            | Key::PauseBreak
+            | Key::Insert
+            | Key::Home
+            | Key::PageUp
+            | Key::Del
+            | Key::End
+            | Key::PageDown => KeyGroup::Functions,
+
+            Key::ArrowRight | Key::ArrowLeft | Key::ArrowBottom | Key::ArrowTop => KeyGroup::Arrows,
+
+            Key::NumLock
+            | Key::NumSlash
+            | Key::NumAsterisk
+            | Key::NumMinus
+            | Key::NumPlus
+            | Key::NumEnter
+            | Key::Num1
+            | Key::Num2
+            | Key::Num3
+            | Key::Num4
+            | Key::Num5
+            | Key::Num6
+            | Key::Num7
+            | Key::Num8
+            | Key::Num9
+            | Key::Num0
+            | Key::NumDot => KeyGroup::Numeric,
+
+            _ => KeyGroup::Keys,
+        }
+    }
+
     /// Low byte of the HID usage ID.
     ///
     /// This intentionally extracts only the HID usage byte from the two-byte
@@ -243,7 +316,7 @@ impl FromStr for Key {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_key(s).ok_or_else(|| format!("invalid key: {s}"))
+        parse_key(s).map_err(|e| e.to_string())
     }
 }
 
@@ -264,11 +337,17 @@ impl Default for Color {
     }
 }
 
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
 impl FromStr for Color {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_color(s).ok_or_else(|| format!("invalid color: {s}"))
+        parse_color(s).ok_or_else(|| tr_with("error-invalid-color", &[("value", s)]))
     }
 }
 
@@ -284,8 +363,75 @@ pub struct KeyValue {
     pub color: Color,
 }
 
+/// A key to light: a known [`Key`], or the raw address of an LED that `Key`
+/// has no variant for yet, such as a badge or an extra zone on a new model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRef {
+    Known(Key),
+    /// Address group and code within it, as in a [`Key`] discriminant.
+    Raw {
+        group: u8,
+        code: u8,
+    },
+}
+
+impl KeyRef {
+    /// A key by its full scan code, [`KeyRef::Known`] if a [`Key`] has it.
+    pub fn from_code(code: u16) -> Self {
+        let [group, low] = code.to_be_bytes();
+        Key::try_from(code).map_or(Self::Raw { group, code: low }, Self::Known)
+    }
+
+    /// Address group, as [`Key::group`].
+    pub const fn group(self) -> u8 {
+        match self {
+            Self::Known(key) => key.group(),
+            Self::Raw { group, .. } => group,
+        }
+    }
+
+    /// Code within the group, as [`Key::hid_code`].
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Known(key) => key.hid_code(),
+            Self::Raw { code, .. } => code,
+        }
+    }
+}
+
+impl From<Key> for KeyRef {
+    fn from(key: Key) -> Self {
+        Self::Known(key)
+    }
+}
+
+/// `code:0x0404`, which `parse_key_ref` reads back.
+impl fmt::Display for KeyRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "code:0x{:02x}{:02x}", self.group(), self.code())
+    }
+}
+
+/// [`KeyValue`] for a [`KeyRef`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRefValue {
+    pub key: KeyRef,
+    pub color: Color,
+}
+
+impl From<KeyValue> for KeyRefValue {
+    fn from(kv: KeyValue) -> Self {
+        Self {
+            key: kv.key.into(),
+            color: kv.color,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
+    /// Backend-specific device path (a hidraw node, or the USB bus node for libusb).
+    pub path: String,
     pub vendor_id: u16,
     pub product_id: u16,
     pub manufacturer: Option<String>,
@@ -0,0 +1,83 @@
+//! Library half of `logi-led`: keyboard protocol, parsers and profile loading.
+//!
+//! The CLI in `main.rs` is a thin layer over these modules, and other
+//! programs can embed the same keyboard control. The fuzz targets in `fuzz/`
+//! drive the parsers through it too. What most programs need is re-exported
+//! here:
+//!
+//! - [`Keyboard`] opens a board with the backend chosen by the crate features,
+//!   and [`KeyboardApi`] is everything it can do; write a wrapper or a mock
+//!   against the trait.
+//! - [`Color`], [`Key`], [`KeyGroup`] and [`KeyValue`] describe lighting;
+//!   colors and keys parse from the same strings the CLI accepts. [`KeyRef`]
+//!   also reaches LEDs that `Key` has no variant for.
+//! - [`parse_profile`] and [`load_profile`] apply g810-led profiles.
+//! - [`NativeEffect`] and friends are the firmware effects for
+//!   [`KeyboardApi::set_fx`]; [`Effect`] and [`play`] run software
+//!   animations.
+//! - [`clock::Clock`] is the time animations, ramps and the packet throttle
+//!   run on; tests step a [`clock::ManualClock`] instead of waiting.
+//!
+//! ```no_run
+//! use logi_led::{Color, Key, KeyValue, Keyboard, KeyboardApi};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! // 0 matches any vendor or product ID; `None` any serial number.
+//! let mut kbd = Keyboard::open(0, 0, None)?;
+//! kbd.set_all_keys(Color::new(0, 0, 0xff))?;
+//! kbd.set_keys(&[KeyValue {
+//!     key: Key::Esc,
+//!     color: Color::new(0xff, 0, 0),
+//! }])?;
+//! kbd.commit()?;
+//!
+//! logi_led::load_profile(&mut kbd, "desk.profile", false)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Errors are [`anyhow::Error`]s with messages meant for people.
+
+// `anyhow` errors throughout make `# Errors` sections repeat the summaries.
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate,
+    clippy::return_self_not_must_use
+)]
+
+pub mod animation;
+pub mod clock;
+pub mod commands;
+pub mod config;
+pub mod diagnostics;
+#[cfg(feature = "gadget-test")]
+pub mod gadget;
+pub mod help;
+pub mod history;
+pub mod i18n;
+pub mod idle;
+pub mod keyboard;
+pub mod output;
+pub mod packet_log;
+pub mod paths;
+pub mod profile;
+pub mod remote;
+pub mod sandbox;
+pub mod schedule;
+pub mod shutdown;
+pub mod state;
+pub mod stats;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod theme;
+pub mod timing;
+
+pub use animation::{Effect, Pacing, play};
+pub use keyboard::api::KeyboardApi;
+pub use keyboard::device::Keyboard;
+pub use keyboard::{
+    Color, Key, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+    NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode,
+};
+pub use profile::{load_profile, parse_profile};
@@ -1,26 +1,32 @@
-use clap::{Args, CommandFactory, Parser, Subcommand, ValueHint};
-use std::path::PathBuf;
-
-use keyboard::api::KeyboardApi;
-
-mod commands;
-mod help;
-mod keyboard;
-mod profile;
-
-use crate::keyboard::{
-    Color, Key, KeyGroup, NativeEffect, NativeEffectPart, NativeEffectStorage, OnBoardMode,
-    StartupMode,
-    device::Keyboard,
-    parser::{parse_period, parse_u8, parse_u16},
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use std::time::{Duration, Instant};
+
+use logi_led::commands::{
+    Alert, Animate, Backup, Brightness, Command, Commit, Context, Doctor, EffectsCommand, Fx, Fx2,
+    FxRecall, FxStore, GenerateInitramfsHook, Guard, HelpColors, HelpEffects, HelpKeys,
+    HelpRegions, HelpSamples, Idle, Info, ListKeyboards, LoadConfig, LoadProfile, ModeIndicator,
+    Opener, PacketLog, PipeProfile, PrintDevice, Ramp, ReportBundle, Restore, SaveProfile,
+    SceneCommand, ScheduleCommand, Serve, SetColor, SetDefault, SetDefaultBoot, SetGKeysMode,
+    SetMn, SetMr, SetOnBoardMode, SetRegion, SetStartupMode, ShowHistory, Start, StatsCommand,
+    Target, ThemeCommand, TypingTrail, Undo, VisualBell, Workspaces, parse_duration_arg,
+    parse_u8_arg, parse_u16_arg, required_capability,
 };
-use crate::{
-    commands::{list_keyboards, print_device},
-    keyboard::{
-        KeyboardModel,
-        model::{self, LOGITECH_VENDOR_ID},
-    },
+use logi_led::config::Config;
+use logi_led::history::History;
+use logi_led::keyboard::{
+    KeyboardModel,
+    api::KeyboardApi,
+    device::{self, Keyboard},
+    model::{self, LOGITECH_VENDOR_ID},
+    parser,
+    persist::NoPersist,
+    spec,
 };
+use logi_led::remote::RemoteOpener;
+use logi_led::{i18n, output, packet_log, timing};
+
+/// Pause between open attempts while `--open-timeout` has not expired.
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(250);
 
 // Control LEDS via HID
 #[derive(Parser, Debug)]
@@ -29,8 +35,10 @@ use crate::{
     version,
     about,
     propagate_version = true,
-    arg_required_else_help = true
+    arg_required_else_help = true,
+    disable_help_subcommand = true
 )]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
     /// Device vendor ID (hex or decimal)   [env: `LOGI_VENDOR_ID`=]
     #[arg(long = "vendor-id", short = 'v', value_parser = parse_u16_arg)]
@@ -52,341 +60,414 @@ struct Cli {
     #[arg(long, global = true)]
     serial: Option<String>,
 
+    /// Run the command on every connected supported keyboard, one after another
+    #[arg(long, global = true, conflicts_with = "serial")]
+    all_devices: bool,
+
+    /// Keep retrying a busy or absent keyboard for this long (e.g. `5s`)
+    #[arg(long, global = true, value_parser = parse_duration_arg)]
+    open_timeout: Option<Duration>,
+
+    /// Read the keyboard's response to every packet and fail on firmware errors (slower)
+    #[arg(long, global = true)]
+    check_acks: bool,
+
+    /// Drive the keyboard of another machine running `logi-led serve` (`host[:port]`)
+    #[arg(long, global = true, env = "LOGI_LED_REMOTE", value_name = "HOST")]
+    remote: Option<String>,
+
+    /// Report time spent enumerating, opening, building packets, writing and committing
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Refuse anything that writes to on-board memory (stored effects, the
+    /// startup mode), e.g. when trying out a downloaded profile
+    #[arg(long, global = true)]
+    no_persist: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Language for help and messages (defaults to `LC_ALL`/`LC_MESSAGES`/`LANG`)
+    #[arg(long, global = true, value_parser = i18n::LANGUAGES)]
+    lang: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Args, Debug)]
-#[group(required = true, multiple = false)]
-struct ColorTarget {
-    #[arg(short, long)]
-    key: Option<Key>,
-    #[arg(short, long)]
-    group: Option<KeyGroup>,
-    #[arg(short = 'A', long)]
-    all: bool,
+struct OutputArgs {
+    /// Only print errors and output the command exists to produce
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Suppress warnings, e.g. about unknown profile commands
+    #[arg(long, global = true)]
+    no_warnings: bool,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
-    /// List all connected Logitech HID devices
-    ListKeyboards,
-
-    /// Open a specific keyboard and print its info
-    PrintDevice,
-
-    /// Commit any buffered changes
-    Commit,
-
-    /// Set key colors
+    ListKeyboards(ListKeyboards),
+    PrintDevice(PrintDevice),
+    Info(Info),
+    Doctor(Doctor),
+    Commit(Commit),
     #[command(name = "set")]
-    SetColor {
-        #[command(flatten)]
-        target: ColorTarget,
-        #[arg(help = help::COLOR_HELP)]
-        color: Color,
-        #[arg(long)]
-        no_commit: bool,
-    },
-
-    /// Set a region color
-    SetRegion {
-        /// Region index
-        region: u8,
-        #[arg(help = help::COLOR_HELP)]
-        color: Color,
-    },
-
-    /// Set the MR key value
-    SetMr { value: u8 },
-
-    /// Set the Mn key value
-    SetMn { value: u8 },
-
-    /// Set the G-keys mode
-    GKeysMode { value: u8 },
-
-    /// Load profile from a file
-    LoadProfile {
-        #[arg(value_hint = ValueHint::FilePath)]
-        path: PathBuf,
-    },
-
-    /// Load a TOML configuration file
-    LoadConfig {
-        #[arg(value_hint = ValueHint::FilePath)]
-        path: PathBuf,
-    },
-
-    /// Load profile from stdin
-    PipeProfile,
-
-    /// Apply a lighting effect
-    Fx {
-        effect: NativeEffect,
-        part: NativeEffectPart,
-        #[arg(long, value_parser = parse_period_arg)]
-        period: Option<std::time::Duration>,
-        #[arg(long, help = help::COLOR_HELP)]
-        color: Option<Color>,
-    },
-
-    /// Store a lighting effect in memory
-    FxStore {
-        effect: NativeEffect,
-        part: NativeEffectPart,
-        #[arg(long, value_parser = parse_period_arg)]
-        period: Option<std::time::Duration>,
-        #[arg(long, help = help::COLOR_HELP)]
-        color: Option<Color>,
-        storage: NativeEffectStorage,
-    },
-
-    /// Configure startup mode
-    StartupMode { mode: StartupMode },
-
-    /// Configure on-board mode
-    OnBoardMode { mode: OnBoardMode },
-
-    /// Display help for keys
+    SetColor(SetColor),
+    Default(SetDefault),
+    SetRegion(SetRegion),
+    SetMr(SetMr),
+    SetMn(SetMn),
+    GKeysMode(SetGKeysMode),
+    LoadProfile(LoadProfile),
+    LoadConfig(LoadConfig),
+    PipeProfile(PipeProfile),
+    SaveProfile(SaveProfile),
+    Fx(Fx),
+    FxStore(FxStore),
+    FxRecall(FxRecall),
+    Effects(EffectsCommand),
+    Fx2(Fx2),
+    Animate(Animate),
+    Idle(Idle),
+    Trail(TypingTrail),
+    Stats(StatsCommand),
+    Guard(Guard),
+    Alert(Alert),
+    VisualBell(VisualBell),
+    Workspaces(Workspaces),
+    ModeIndicator(ModeIndicator),
+    StartupMode(SetStartupMode),
+    OnBoardMode(SetOnBoardMode),
+    SetDefaultBoot(SetDefaultBoot),
     #[command(name = "help-keys")]
-    HelpKeys,
-
-    /// Display help for lighting effects
+    HelpKeys(HelpKeys),
     #[command(name = "help-effects")]
-    HelpEffects,
-
-    /// Display help for color names
+    HelpEffects(HelpEffects),
     #[command(name = "help-colors")]
-    HelpColors,
-
-    /// Show usage samples
+    HelpColors(HelpColors),
     #[command(name = "help-samples")]
-    HelpSamples,
-
-    /// Generate shell completion scripts
-    Completions { shell: clap_complete::Shell },
+    HelpSamples(HelpSamples),
+    #[command(name = "help-regions")]
+    HelpRegions(HelpRegions),
+    Ramp(Ramp),
+    Completions(Completions),
+    Theme(ThemeCommand),
+    Schedule(ScheduleCommand),
+    Scene(SceneCommand),
+    Start(Start),
+    #[command(name = "history")]
+    ShowHistory(ShowHistory),
+    Undo(Undo),
+    Brightness(Brightness),
+    Backup(Backup),
+    Restore(Restore),
+    Serve(Serve),
+    GenerateInitramfsHook(GenerateInitramfsHook),
+    ReportBundle(ReportBundle),
+    PacketLog(PacketLog),
+    Help(Help),
 }
 
 impl Commands {
-    fn run(&self, opts: &Cli) -> anyhow::Result<()> {
+    /// The handler registered for this subcommand.
+    fn handler(&self) -> &dyn Command {
         match self {
-            Commands::ListKeyboards => list_keyboards(),
-            Commands::PrintDevice => print_device(opts.serial.as_deref()),
-            Commands::Commit => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                keyboard::api::KeyboardApi::commit,
-            ),
-            Commands::SetColor {
-                target,
-                color,
-                no_commit,
-            } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| {
-                    if target.all {
-                        kbd.set_all_keys(*color)?;
-                    } else if let Some(group) = target.group {
-                        kbd.set_group_keys(group, *color)?;
-                    } else if let Some(key) = target.key {
-                        kbd.set_keys(&[keyboard::KeyValue { key, color: *color }])?;
-                    }
-                    if !*no_commit {
-                        kbd.commit()?;
-                    }
-                    Ok(())
-                },
-            ),
-            Commands::SetRegion { region, color } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| {
-                    kbd.set_region(*region, *color)?;
-                    Ok(())
-                },
-            ),
-            Commands::SetMr { value } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| kbd.set_mr_key(*value),
-            ),
-            Commands::SetMn { value } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| kbd.set_mn_key(*value),
-            ),
-            Commands::GKeysMode { value } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| kbd.set_gkeys_mode(*value),
-            ),
-            Commands::LoadProfile { path } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| profile::load_profile(kbd, path, opts.strict),
-            ),
-            Commands::LoadConfig { path } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| profile::load_toml_profile(kbd, path),
-            ),
-            Commands::PipeProfile => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| {
-                    let stdin = std::io::stdin();
-                    profile::load_profile_stdin(kbd, stdin.lock(), opts.strict)
-                },
-            ),
-            Commands::Fx {
-                effect,
-                part,
-                period,
-                color,
-            } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| {
-                    kbd.set_fx(
-                        *effect,
-                        *part,
-                        period.unwrap_or_default(),
-                        color.unwrap_or_default(),
-                        NativeEffectStorage::None,
-                    )
-                },
-            ),
-            Commands::FxStore {
-                effect,
-                part,
-                period,
-                color,
-                storage,
-            } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| {
-                    kbd.set_fx(
-                        *effect,
-                        *part,
-                        period.unwrap_or_default(),
-                        color.unwrap_or_default(),
-                        *storage,
-                    )
-                },
-            ),
-            Commands::StartupMode { mode } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| kbd.set_startup_mode(*mode),
-            ),
-            Commands::OnBoardMode { mode } => with_keyboard(
-                opts.vendor_id,
-                opts.product_id,
-                opts.protocol,
-                opts.serial.as_deref(),
-                |kbd| kbd.set_on_board_mode(*mode),
-            ),
-            Commands::HelpKeys => {
-                help::print_keys_help();
-                Ok(())
-            }
-            &Commands::HelpEffects => {
-                help::print_effects_help();
-                Ok(())
-            }
-            &Commands::HelpColors => {
-                help::print_colors_help();
-                Ok(())
-            }
-            &Commands::HelpSamples => {
-                help::print_samples_help();
-                Ok(())
+            Commands::ListKeyboards(cmd) => cmd,
+            Commands::PrintDevice(cmd) => cmd,
+            Commands::Info(cmd) => cmd,
+            Commands::Doctor(cmd) => cmd,
+            Commands::Commit(cmd) => cmd,
+            Commands::SetColor(cmd) => cmd,
+            Commands::Default(cmd) => cmd,
+            Commands::SetRegion(cmd) => cmd,
+            Commands::SetMr(cmd) => cmd,
+            Commands::SetMn(cmd) => cmd,
+            Commands::GKeysMode(cmd) => cmd,
+            Commands::LoadProfile(cmd) => cmd,
+            Commands::LoadConfig(cmd) => cmd,
+            Commands::PipeProfile(cmd) => cmd,
+            Commands::SaveProfile(cmd) => cmd,
+            Commands::Fx(cmd) => cmd,
+            Commands::FxStore(cmd) => cmd,
+            Commands::FxRecall(cmd) => cmd,
+            Commands::Effects(cmd) => cmd,
+            Commands::Fx2(cmd) => cmd,
+            Commands::Animate(cmd) => cmd,
+            Commands::Idle(cmd) => cmd,
+            Commands::Trail(cmd) => cmd,
+            Commands::Stats(cmd) => cmd,
+            Commands::Guard(cmd) => cmd,
+            Commands::Alert(cmd) => cmd,
+            Commands::VisualBell(cmd) => cmd,
+            Commands::Workspaces(cmd) => cmd,
+            Commands::ModeIndicator(cmd) => cmd,
+            Commands::StartupMode(cmd) => cmd,
+            Commands::OnBoardMode(cmd) => cmd,
+            Commands::SetDefaultBoot(cmd) => cmd,
+            Commands::HelpKeys(cmd) => cmd,
+            Commands::HelpEffects(cmd) => cmd,
+            Commands::HelpColors(cmd) => cmd,
+            Commands::HelpSamples(cmd) => cmd,
+            Commands::HelpRegions(cmd) => cmd,
+            Commands::Ramp(cmd) => cmd,
+            Commands::Completions(cmd) => cmd,
+            Commands::Theme(cmd) => cmd,
+            Commands::Schedule(cmd) => cmd,
+            Commands::Scene(cmd) => cmd,
+            Commands::Start(cmd) => cmd,
+            Commands::ShowHistory(cmd) => cmd,
+            Commands::Undo(cmd) => cmd,
+            Commands::Brightness(cmd) => cmd,
+            Commands::Backup(cmd) => cmd,
+            Commands::Restore(cmd) => cmd,
+            Commands::Serve(cmd) => cmd,
+            Commands::GenerateInitramfsHook(cmd) => cmd,
+            Commands::ReportBundle(cmd) => cmd,
+            Commands::PacketLog(cmd) => cmd,
+            Commands::Help(cmd) => cmd,
+        }
+    }
+}
+
+/// Generate shell completion scripts
+#[derive(Args, Debug)]
+struct Completions {
+    shell: clap_complete::Shell,
+}
+
+impl Command for Completions {
+    fn name(&self) -> &'static str {
+        "completions"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> anyhow::Result<()> {
+        let mut cmd = Cli::command();
+        clap_complete::generate(self.shell, &mut cmd, "logi-led", &mut std::io::stdout());
+        Ok(())
+    }
+}
+
+/// Print help for a subcommand, or list only the subcommands a model supports
+#[derive(Args, Debug)]
+struct Help {
+    /// Subcommand to describe, e.g. `scene apply`
+    #[arg(conflicts_with = "for_model")]
+    command: Vec<String>,
+
+    /// List only the subcommands that do something on this model (e.g. `g910`)
+    #[arg(long, value_name = "MODEL")]
+    for_model: Option<KeyboardModel>,
+}
+
+impl Command for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn run(&self, _ctx: &Context<'_>) -> anyhow::Result<()> {
+        let mut cli = Cli::command();
+        cli.build();
+        let Some(model) = self.for_model else {
+            let mut cmd = &mut cli;
+            for name in &self.command {
+                cmd = cmd
+                    .find_subcommand_mut(name)
+                    .ok_or_else(|| anyhow::anyhow!("no such subcommand: {name}"))?;
             }
-            Commands::Completions { shell } => {
-                let mut cmd = Cli::command();
-                clap_complete::generate(*shell, &mut cmd, "logi-led", &mut std::io::stdout());
-                Ok(())
+            cmd.print_help()?;
+            return Ok(());
+        };
+
+        println!("Commands for the {model:?}:");
+        let mut omitted = Vec::new();
+        for sub in cli.get_subcommands() {
+            let name = sub.get_name();
+            if required_capability(name).is_some_and(|needed| !model.supports(needed)) {
+                omitted.push(name);
+                continue;
             }
+            let about = sub.get_about().map(ToString::to_string).unwrap_or_default();
+            println!("  {name:<24}{about}");
+        }
+        if !omitted.is_empty() {
+            println!("\nNot supported on the {model:?}: {}", omitted.join(", "));
         }
+        Ok(())
     }
 }
 
-fn parse_period_arg(s: &str) -> Result<std::time::Duration, String> {
-    parse_period(s).ok_or_else(|| format!("invalid period: {s}"))
+impl Cli {
+    /// The keyboard `-v`, `-p` and `--tuk` pick.
+    fn target(&self) -> Target {
+        Target {
+            vendor_id: self.vendor_id.unwrap_or(LOGITECH_VENDOR_ID),
+            product_id: self.product_id.unwrap_or(0),
+            model: self.protocol.and_then(|id| match id {
+                1 => Some(KeyboardModel::G810),
+                2 => Some(KeyboardModel::G910),
+                3 => Some(KeyboardModel::G213),
+                4 => Some(KeyboardModel::G815),
+                _ => None,
+            }),
+        }
+    }
 }
 
-fn parse_u8_arg(s: &str) -> Result<u8, String> {
-    parse_u8(s).ok_or_else(|| format!("Invalid u8 value: {s}"))
+impl Opener for Cli {
+    fn with_keyboard(
+        &self,
+        f: &mut dyn FnMut(&mut dyn KeyboardApi) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        with_keyboard(self, |kbd| f(kbd))
+    }
+
+    fn reset(&self) -> anyhow::Result<()> {
+        with_keyboard(self, Keyboard::reset)
+    }
 }
 
-fn parse_u16_arg(s: &str) -> Result<u16, String> {
-    parse_u16(s).ok_or_else(|| format!("Invalid u16 value: {s}"))
+/// Hands out every keyboard `.0` opens wrapped in [`NoPersist`].
+struct NoPersistOpener<'a>(&'a dyn Opener);
+
+impl Opener for NoPersistOpener<'_> {
+    fn with_keyboard(
+        &self,
+        f: &mut dyn FnMut(&mut dyn KeyboardApi) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.0.with_keyboard(&mut |kbd| f(&mut NoPersist::new(kbd)))
+    }
+
+    fn reset(&self) -> anyhow::Result<()> {
+        self.0.reset()
+    }
 }
 
-fn with_keyboard<F>(
-    vendor_id: Option<u16>,
-    product_id: Option<u16>,
-    protocol: Option<u8>,
-    serial: Option<&str>,
-    mut f: F,
-) -> anyhow::Result<()>
+fn with_keyboard<F>(opts: &Cli, mut f: F) -> anyhow::Result<()>
 where
     F: FnMut(&mut Keyboard) -> anyhow::Result<()>,
 {
-    let vid = vendor_id.unwrap_or(LOGITECH_VENDOR_ID);
-    let pid = product_id.unwrap_or(0);
-
-    if let Some(model) = protocol.and_then(|id| match id {
-        1 => Some(KeyboardModel::G810),
-        2 => Some(KeyboardModel::G910),
-        3 => Some(KeyboardModel::G213),
-        4 => Some(KeyboardModel::G815),
-        _ => None,
-    }) {
-        // NOTE: this could probably be a static sized array,
-        // and the SUPPORTED_DEVICES and override could be a
-        // triplet.
-        model::set_supported_override(vec![(vid, pid, model)]);
-    }
-
-    let mut kbd = match Keyboard::open(vid, pid, serial) {
-        Ok(k) => k,
-        Err(e) => {
-            model::clear_supported_override();
-            return Err(e);
+    let target = opts.target();
+    let (vid, pid) = (target.vendor_id, target.product_id);
+    let serial = opts.serial.as_deref();
+    target.install_override();
+
+    let open = || {
+        if opts.all_devices {
+            Keyboard::open_all(vid, pid)
+        } else {
+            Keyboard::open(vid, pid, serial).map(|kbd| vec![kbd])
+        }
+    };
+    let deadline = Instant::now() + opts.open_timeout.unwrap_or_default();
+    let mut boards = loop {
+        match open() {
+            Ok(boards) => break boards,
+            Err(_) if Instant::now() < deadline => std::thread::sleep(OPEN_RETRY_INTERVAL),
+            Err(e) => {
+                model::clear_supported_override();
+                return Err(device::explain_open_error(e, vid, pid, serial));
+            }
         }
     };
-    f(&mut kbd)
+    // Say so before doing anything rather than have the board ignore it.
+    let name = opts.command.handler().name();
+    if let Some(capability) = required_capability(name) {
+        let mut unsupported = Vec::new();
+        boards.retain(|kbd| {
+            let model = kbd
+                .current_device()
+                .map_or(KeyboardModel::Unknown, |dev| dev.model);
+            model.supports(capability) || {
+                unsupported.push(model);
+                false
+            }
+        });
+        if boards.is_empty() {
+            anyhow::bail!("{name} is not supported on the {:?}", unsupported[0]);
+        }
+        for model in unsupported {
+            output::warn(format!(
+                "{name} is not supported on the {model:?}, skipping it"
+            ));
+        }
+    }
+    for kbd in &mut boards {
+        kbd.set_check_acks(opts.check_acks);
+        if let Err(e) = f(kbd) {
+            // With several boards, say which one failed.
+            return Err(match kbd.current_device().filter(|_| opts.all_devices) {
+                Some(dev) => e.context(format!(
+                    "{:?} {:04x}:{:04x}",
+                    dev.model, dev.vendor_id, dev.product_id
+                )),
+                None => e,
+            });
+        }
+    }
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
+    let started = Instant::now();
+    i18n::init(i18n::lang_from_args(std::env::args_os()).as_deref());
+    // Loaded before parsing so `[aliases]` work for keys on the command line;
+    // errors wait until after it so `--help` still works with a broken config.
+    let config = Config::load().and_then(|config| {
+        parser::add_key_aliases(config.key_aliases()?);
+        Ok(config)
+    });
     let cli = Cli::parse();
-    cli.command.run(&cli)
+    output::configure(cli.output.quiet, cli.output.no_warnings);
+    if cli.timing {
+        timing::enable();
+    }
+    let config = config?;
+    model::add_extra_devices(config.extra_devices()?);
+    spec::set_rate_limits(config.rate_limits()?);
+    let history = History::open(config.history_limit());
+    let remote = match &cli.remote {
+        Some(addr) => Some(RemoteOpener {
+            addr: addr.clone(),
+            token: config.remote_token()?.to_owned(),
+        }),
+        None => None,
+    };
+    let opener = remote.as_ref().map_or(&cli as &dyn Opener, |remote| remote);
+    let no_persist = NoPersistOpener(opener);
+    let ctx = Context {
+        config: &config,
+        serial: cli.serial.as_deref(),
+        target: cli.target(),
+        strict: cli.strict,
+        opener: if cli.no_persist { &no_persist } else { opener },
+        // The server keeps the history of a remote keyboard.
+        history: history.as_ref().filter(|_| remote.is_none()),
+    };
+    let handler = cli.command.handler();
+    let result = handler.run(&ctx);
+    // Best effort: a read-only home must not turn a successful run into a failure.
+    let _ = packet_log::save(handler.name(), &result, started.elapsed());
+    if let Some(report) = timing::report(started.elapsed()) {
+        eprint!("{report}");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_timeout_takes_a_duration() {
+        let timeout = |value: &str| {
+            Cli::try_parse_from(["logi-led", "--open-timeout", value, "list-keyboards"])
+                .map(|cli| cli.open_timeout)
+        };
+        assert_eq!(timeout("5s").unwrap(), Some(Duration::from_secs(5)));
+        assert_eq!(timeout("500ms").unwrap(), Some(Duration::from_millis(500)));
+        assert!(timeout("0x05").is_err());
+    }
 }
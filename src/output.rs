@@ -0,0 +1,75 @@
+//! Status, note and warning output, filtered by `--quiet` and `--no-warnings`.
+//!
+//! Output a command exists to produce (listings, help, `doctor`) is printed
+//! directly; everything incidental goes through here so cron jobs stay silent.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static NO_WARNINGS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Status,
+    Note,
+    Warning,
+}
+
+fn allowed(kind: Kind, quiet: bool, no_warnings: bool) -> bool {
+    match kind {
+        Kind::Status | Kind::Note => !quiet,
+        Kind::Warning => !quiet && !no_warnings,
+    }
+}
+
+fn enabled(kind: Kind) -> bool {
+    allowed(
+        kind,
+        QUIET.load(Ordering::Relaxed),
+        NO_WARNINGS.load(Ordering::Relaxed),
+    )
+}
+
+/// Apply the global output flags; call once after parsing arguments.
+pub fn configure(quiet: bool, no_warnings: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    NO_WARNINGS.store(no_warnings, Ordering::Relaxed);
+}
+
+/// Report what a command did, on stdout.
+pub fn status(message: impl Display) {
+    if enabled(Kind::Status) {
+        println!("{message}");
+    }
+}
+
+/// Informational remark on stderr.
+pub fn note(message: impl Display) {
+    if enabled(Kind::Note) {
+        eprintln!("note: {message}");
+    }
+}
+
+/// Something the user probably wants to fix, on stderr.
+pub fn warn(message: impl Display) {
+    if enabled(Kind::Warning) {
+        eprintln!("warning: {message}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_filter_by_kind() {
+        for kind in [Kind::Status, Kind::Note, Kind::Warning] {
+            assert!(allowed(kind, false, false));
+            assert!(!allowed(kind, true, false));
+        }
+        assert!(allowed(Kind::Status, false, true));
+        assert!(allowed(Kind::Note, false, true));
+        assert!(!allowed(Kind::Warning, false, true));
+    }
+}
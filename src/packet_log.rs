@@ -0,0 +1,141 @@
+//! The last packets sent to a keyboard, kept for `packet-log` and
+//! `report-bundle`.
+//!
+//! The backends [`record`] every packet before writing it; `main` calls
+//! [`save`] on exit, which adds the run to `packets.log` in the log
+//! directory under a header naming the command, how it ended and how long it
+//! took. The log keeps the last [`RUNS`] runs. Runs that never talk to a
+//! keyboard leave it alone, so it still describes the last commands that did.
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::paths;
+
+/// Packets kept per run; older ones are dropped first.
+const CAPACITY: usize = 256;
+
+/// Runs kept in the saved log; older ones are dropped first.
+pub const RUNS: usize = 10;
+
+struct Entry {
+    /// Time since the first packet of the run.
+    at: Duration,
+    data: Vec<u8>,
+}
+
+static PACKETS: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+static FIRST: OnceLock<Instant> = OnceLock::new();
+
+/// Remember `data` as sent.
+pub fn record(data: &[u8]) {
+    let at = FIRST.get_or_init(Instant::now).elapsed();
+    let mut packets = PACKETS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if packets.len() == CAPACITY {
+        packets.pop_front();
+    }
+    packets.push_back(Entry {
+        at,
+        data: data.to_vec(),
+    });
+}
+
+/// Where the log is saved, e.g. `~/.local/state/logi-led/logs/packets.log`.
+pub fn path() -> Option<PathBuf> {
+    paths::log_dir().map(|dir| dir.join("packets.log"))
+}
+
+/// Add this run's packets to [`path`], if any were sent, under a header
+/// with `command`, its `result` and how long it `took`.
+pub fn save(command: &str, result: &Result<()>, took: Duration) -> Result<()> {
+    let packets = PACKETS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let Some(path) = path().filter(|_| !packets.is_empty()) else {
+        return Ok(());
+    };
+    let outcome = match result {
+        Ok(()) => "ok".to_owned(),
+        // One header line, however the error was formatted.
+        Err(e) => format!("failed: {}", format!("{e:#}").replace('\n', " ")),
+    };
+    let header = format!(
+        "# logi-led {}, {}, {command}: {outcome} in {}ms",
+        env!("CARGO_PKG_VERSION"),
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        took.as_millis()
+    );
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let earlier = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut log = last_runs(&earlier, RUNS - 1).to_owned();
+    log.push_str(&format_log(&header, packets.iter()));
+    std::fs::write(&path, log)?;
+    Ok(())
+}
+
+/// The last `n` runs of a saved log, each starting at its `#` header.
+pub fn last_runs(log: &str, n: usize) -> &str {
+    if n == 0 {
+        return "";
+    }
+    let starts: Vec<usize> = core::iter::once(0)
+        .chain(log.match_indices('\n').map(|(i, _)| i + 1))
+        .filter(|&i| log[i..].starts_with("# "))
+        .collect();
+    match starts.len().checked_sub(n) {
+        Some(first) => &log[starts[first]..],
+        None => log,
+    }
+}
+
+fn format_log<'a>(header: &str, packets: impl IntoIterator<Item = &'a Entry>) -> String {
+    let mut out = format!("{header}\n");
+    for entry in packets {
+        let _ = write!(out, "{:>10.3}ms ", entry.at.as_secs_f64() * 1000.0);
+        for byte in &entry.data {
+            let _ = write!(out, " {byte:02x}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_are_listed_in_hex_with_their_offset() {
+        let packets = [
+            Entry {
+                at: Duration::ZERO,
+                data: vec![0x11, 0xff, 0x0c, 0x3a],
+            },
+            Entry {
+                at: Duration::from_micros(12_500),
+                data: vec![0x11, 0xff, 0x0c, 0x5a],
+            },
+        ];
+        assert_eq!(
+            format_log("# run", &packets),
+            "# run\n     0.000ms  11 ff 0c 3a\n    12.500ms  11 ff 0c 5a\n"
+        );
+    }
+
+    #[test]
+    fn only_the_last_runs_are_kept() {
+        let log = "# one\n 0.000ms  11\n# two: ok\n 0.000ms  12\n# three\n";
+        assert_eq!(last_runs(log, 2), "# two: ok\n 0.000ms  12\n# three\n");
+        assert_eq!(last_runs(log, 3), log);
+        assert_eq!(last_runs(log, 5), log);
+        assert_eq!(last_runs(log, 0), "");
+    }
+}
@@ -0,0 +1,210 @@
+//! Where logi-led keeps its files.
+//!
+//! Linux and the BSDs follow the XDG Base Directory spec; macOS and Windows use
+//! their usual per-user locations. `LOGI_LED_{CONFIG,STATE,CACHE,RUNTIME}_DIR`
+//! override the matching directory on every platform.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const APP: &str = "logi-led";
+
+/// Kinds of per-user directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    /// Settings and user profiles.
+    Config,
+    /// Data that should survive a restart but is not configuration (last applied state, logs).
+    State,
+    /// Data that can be regenerated.
+    Cache,
+    /// Sockets and other files that only live as long as the session.
+    Runtime,
+}
+
+impl Dir {
+    const fn override_var(self) -> &'static str {
+        match self {
+            Dir::Config => "LOGI_LED_CONFIG_DIR",
+            Dir::State => "LOGI_LED_STATE_DIR",
+            Dir::Cache => "LOGI_LED_CACHE_DIR",
+            Dir::Runtime => "LOGI_LED_RUNTIME_DIR",
+        }
+    }
+}
+
+/// Directory for user settings, e.g. `~/.config/logi-led`.
+pub fn config_dir() -> Option<PathBuf> {
+    dir(Dir::Config)
+}
+
+/// The config file, e.g. `~/.config/logi-led/config.toml`.
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Directory for named profiles, e.g. `~/.config/logi-led/profiles`.
+pub fn profiles_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("profiles"))
+}
+
+/// Directory for persistent state, e.g. `~/.local/state/logi-led`.
+pub fn state_dir() -> Option<PathBuf> {
+    dir(Dir::State)
+}
+
+/// Directory for log files, inside the state directory as XDG recommends.
+pub fn log_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("logs"))
+}
+
+/// Directory for regenerable data, e.g. `~/.cache/logi-led`.
+pub fn cache_dir() -> Option<PathBuf> {
+    dir(Dir::Cache)
+}
+
+/// Directory for sockets, e.g. `$XDG_RUNTIME_DIR/logi-led`.
+pub fn runtime_dir() -> Option<PathBuf> {
+    dir(Dir::Runtime)
+}
+
+/// The `colors.json` pywal writes for the current wallpaper.
+///
+/// pywal keeps its cache in `$PYWAL_CACHE_DIR` or `$XDG_CACHE_HOME/wal` (with
+/// the `~/.cache` fallback) on every platform.
+pub fn pywal_colors() -> Option<PathBuf> {
+    resolve_pywal(&|var| std::env::var_os(var))
+}
+
+fn dir(kind: Dir) -> Option<PathBuf> {
+    resolve(kind, &|var| std::env::var_os(var))
+}
+
+/// `env(name)` as a path; relative paths are ignored, as the XDG spec requires.
+fn absolute(env: &dyn Fn(&str) -> Option<OsString>, name: &str) -> Option<PathBuf> {
+    env(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+/// Resolve `kind` with `env` standing in for the environment.
+fn resolve(kind: Dir, env: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let var = |name: &str| absolute(env, name);
+    if let Some(path) = var(kind.override_var()) {
+        return Some(path);
+    }
+    platform_dir(kind, &var).map(|base| base.join(APP))
+}
+
+/// Resolve [`pywal_colors`] with `env` standing in for the environment.
+fn resolve_pywal(env: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let var = |name: &str| absolute(env, name);
+    var("PYWAL_CACHE_DIR")
+        .or_else(|| xdg_dir(Dir::Cache, &var).map(|cache| cache.join("wal")))
+        .map(|dir| dir.join("colors.json"))
+}
+
+/// The XDG base directory for `kind`, before the app name is added.
+fn xdg_dir(kind: Dir, var: &dyn Fn(&str) -> Option<PathBuf>) -> Option<PathBuf> {
+    let (xdg, fallback) = match kind {
+        Dir::Config => ("XDG_CONFIG_HOME", ".config"),
+        Dir::State => ("XDG_STATE_HOME", ".local/state"),
+        Dir::Cache => ("XDG_CACHE_HOME", ".cache"),
+        // Without a runtime dir there is no safe per-user place in /tmp;
+        // the state dir is private to the user and good enough for a socket.
+        Dir::Runtime => return var("XDG_RUNTIME_DIR").or_else(|| xdg_dir(Dir::State, var)),
+    };
+    var(xdg).or_else(|| var("HOME").map(|home| home.join(fallback)))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_dir(kind: Dir, var: &dyn Fn(&str) -> Option<PathBuf>) -> Option<PathBuf> {
+    xdg_dir(kind, var)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_dir(kind: Dir, var: &dyn Fn(&str) -> Option<PathBuf>) -> Option<PathBuf> {
+    let library = var("HOME")?.join("Library");
+    Some(match kind {
+        Dir::Config | Dir::State => library.join("Application Support"),
+        Dir::Cache => library.join("Caches"),
+        Dir::Runtime => var("TMPDIR").unwrap_or_else(|| library.join("Caches")),
+    })
+}
+
+#[cfg(windows)]
+fn platform_dir(kind: Dir, var: &dyn Fn(&str) -> Option<PathBuf>) -> Option<PathBuf> {
+    match kind {
+        Dir::Config => var("APPDATA"),
+        Dir::State | Dir::Cache | Dir::Runtime => var("LOCALAPPDATA"),
+    }
+}
+
+#[cfg(all(test, not(any(target_os = "macos", windows))))]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: Vec<(String, OsString)> = vars
+            .iter()
+            .map(|&(k, v)| (k.to_owned(), v.into()))
+            .collect();
+        move |name| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn xdg_variables_win_over_home() {
+        let env = env(&[("HOME", "/home/a"), ("XDG_CONFIG_HOME", "/cfg")]);
+        assert_eq!(resolve(Dir::Config, &env), Some("/cfg/logi-led".into()));
+        assert_eq!(
+            resolve(Dir::State, &env),
+            Some("/home/a/.local/state/logi-led".into())
+        );
+        assert_eq!(
+            resolve(Dir::Cache, &env),
+            Some("/home/a/.cache/logi-led".into())
+        );
+    }
+
+    #[test]
+    fn overrides_and_relative_paths() {
+        let env = env(&[
+            ("HOME", "/home/a"),
+            ("XDG_CONFIG_HOME", "relative"),
+            ("LOGI_LED_CACHE_DIR", "/tmp/c"),
+        ]);
+        assert_eq!(
+            resolve(Dir::Config, &env),
+            Some("/home/a/.config/logi-led".into())
+        );
+        assert_eq!(resolve(Dir::Cache, &env), Some("/tmp/c".into()));
+    }
+
+    #[test]
+    fn runtime_falls_back_to_state() {
+        let with = env(&[("HOME", "/home/a"), ("XDG_RUNTIME_DIR", "/run/user/1000")]);
+        assert_eq!(
+            resolve(Dir::Runtime, &with),
+            Some("/run/user/1000/logi-led".into())
+        );
+        let without = env(&[("HOME", "/home/a")]);
+        assert_eq!(
+            resolve(Dir::Runtime, &without),
+            Some("/home/a/.local/state/logi-led".into())
+        );
+        assert_eq!(resolve(Dir::Config, &env(&[])), None);
+    }
+
+    #[test]
+    fn pywal_follows_its_own_cache_dir_then_xdg() {
+        let relative = env(&[("HOME", "/home/a"), ("XDG_CACHE_HOME", "relative")]);
+        assert_eq!(
+            resolve_pywal(&relative),
+            Some("/home/a/.cache/wal/colors.json".into())
+        );
+        let xdg = env(&[("HOME", "/home/a"), ("XDG_CACHE_HOME", "/c")]);
+        assert_eq!(resolve_pywal(&xdg), Some("/c/wal/colors.json".into()));
+        let own = env(&[("PYWAL_CACHE_DIR", "/w"), ("XDG_CACHE_HOME", "/c")]);
+        assert_eq!(resolve_pywal(&own), Some("/w/colors.json".into()));
+    }
+}
@@ -1,76 +1,146 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
     io::{BufRead, BufReader, StdinLock},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{Result, anyhow};
+use core::time::Duration;
 
+use crate::animation::KeySequence;
+use crate::diagnostics::parse_toml;
+use crate::history::key_ref_name;
 use crate::keyboard::parser::{
-    parse_board_mode, parse_color, parse_key, parse_key_group, parse_native_effect,
-    parse_native_effect_part, parse_native_effect_storage, parse_period, parse_startup_mode,
-    parse_u8,
+    parse_board_mode, parse_color, parse_duration, parse_key, parse_key_group, parse_key_ref,
+    parse_native_effect, parse_native_effect_part, parse_native_effect_storage, parse_period,
+    parse_startup_mode, parse_u8, split_top_level,
 };
-use crate::keyboard::{Color, KeyValue, NativeEffect, NativeEffectStorage, api::KeyboardApi};
+use crate::keyboard::{
+    Color, Key, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectStorage,
+    api::KeyboardApi,
+};
+use crate::output;
+use crate::state::KeyboardState;
 
-#[derive(Deserialize)]
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct Profile {
+    /// Path of a parent profile, relative to this file, applied underneath.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     all: Option<String>,
-    #[serde(default)]
-    groups: Vec<GroupEntry>,
-    #[serde(default)]
-    key: Vec<KeyEntry>,
-    #[serde(default)]
-    regions: Vec<RegionEntry>,
-    #[serde(default)]
-    effects: Vec<EffectEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     mr: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     mn: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     gkeys_mode: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     startup_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     on_board_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<GroupEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    key: Vec<KeyEntry>,
+    /// Key name to color for every key at once, `esc = "red"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    layout: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    regions: Vec<RegionEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    effects: Vec<EffectEntry>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct GroupEntry {
     group: String,
     color: String,
+    /// Percent of the color's brightness to keep, 0-100.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brightness: Option<u8>,
+    /// Percent of the color's saturation to keep, 0-100.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saturation: Option<u8>,
+}
+
+impl GroupEntry {
+    /// The entry's color with its adjustments applied.
+    fn color(&self) -> Option<Color> {
+        let color = parse_color(&self.color)?;
+        let color = self
+            .saturation
+            .map_or(color, |percent| color.saturated(percent));
+        Some(
+            self.brightness
+                .map_or(color, |percent| color.scaled(percent)),
+        )
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct KeyEntry {
     key: String,
     color: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct RegionEntry {
     region: String,
     color: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 struct EffectEntry {
     effect: String,
     part: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     period: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     color: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     storage: Option<String>,
 }
 
+/// How long each color of a `k <key> [colors]` sequence shows by default.
+const DEFAULT_SEQUENCE_STEP: Duration = Duration::from_millis(500);
+
 /// Parse a profile from any buffered reader
-pub fn parse_profile<K>(kbd: &mut K, mut reader: impl BufRead, strict: bool) -> Result<()>
+///
+/// Keys given a color sequence are set to its first color; use
+/// [`parse_profile_with_sequences`] to animate them.
+pub fn parse_profile<K>(kbd: &mut K, reader: impl BufRead, strict: bool) -> Result<()>
+where
+    K: KeyboardApi + ?Sized,
+{
+    parse_profile_with_sequences(kbd, reader, strict).map(drop)
+}
+
+/// Parse a profile, returning the `k <key> [c1,c2,...] [step]` color
+/// sequences and `k <key> blink <color> [step]` blinks for the caller to
+/// animate.
+pub fn parse_profile_with_sequences<K>(
+    kbd: &mut K,
+    mut reader: impl BufRead,
+    strict: bool,
+) -> Result<Vec<KeySequence>>
 where
-    K: KeyboardApi,
+    K: KeyboardApi + ?Sized,
 {
     let mut vars = HashMap::<String, String>::new();
     let mut keys = Vec::<KeyValue>::new();
+    let mut raw_keys = Vec::<KeyRefValue>::new();
+    let mut sequences = Vec::<KeySequence>::new();
+    // What the lines so far light each key in, for `blink` to return to.
+    let mut lit = KeyboardState::new();
     let mut line = String::new();
 
     while reader.read_line(&mut line)? != 0 {
@@ -85,15 +155,10 @@ where
         }
 
         // Tokenize with cheap borrowing where possible
+        let joined = join_lists(trimmed);
         let mut args: Vec<Cow<'_, str>> = Vec::with_capacity(8);
-        for tok in trimmed.split_whitespace() {
-            let cow = if let Some(var) = tok.strip_prefix('$') {
-                vars.get(var)
-                    .map_or_else(|| Cow::Borrowed(tok), |v| Cow::Owned(v.clone()))
-            } else {
-                Cow::Borrowed(tok)
-            };
-            args.push(cow);
+        for tok in joined.split_whitespace() {
+            args.push(expand_vars(tok, &vars));
         }
 
         match args.first().map(Cow::as_ref) {
@@ -106,12 +171,17 @@ where
                     kbd.set_keys(&keys)?;
                     keys.clear();
                 }
+                if !raw_keys.is_empty() {
+                    kbd.set_key_refs(&raw_keys)?;
+                    raw_keys.clear();
+                }
                 kbd.commit()?;
             }
 
-            Some("a") => {
+            Some("a") if args.len() >= 2 => {
                 if let Some(color) = parse_color(&args[1]) {
                     kbd.set_all_keys(color)?;
+                    lit.set_all_keys(color)?;
                 }
             }
 
@@ -120,14 +190,56 @@ where
                     (parse_key_group(&args[1]), parse_color(&args[2]))
                 {
                     kbd.set_group_keys(group, color)?;
+                    lit.set_group_keys(group, color)?;
                 }
             }
 
-            Some("k") if args.len() >= 3 => {
-                if let (Some(key), Some(color)) = (parse_key(&args[1]), parse_color(&args[2])) {
-                    keys.push(KeyValue { key, color });
+            Some("k") if args.len() >= 3 => match parse_key_ref(&args[1]) {
+                Err(e) if strict => return Err(e.into()),
+                Err(e) => output::warn(e),
+                // Raw keys only take a plain color.
+                Ok(key @ KeyRef::Raw { .. }) => {
+                    if let Some(color) = parse_color(&args[2]) {
+                        raw_keys.push(KeyRefValue { key, color });
+                    }
                 }
-            }
+                Ok(KeyRef::Known(key)) => {
+                    if let Some(list) = args[2].strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+                    {
+                        // k <key> [c1,c2,...] [step]: start on the first color.
+                        if let Some(colors) = parse_colors(list, &vars) {
+                            keys.push(KeyValue {
+                                key,
+                                color: colors[0],
+                            });
+                            let step = args
+                                .get(3)
+                                .and_then(|arg| parse_duration(arg))
+                                .unwrap_or(DEFAULT_SEQUENCE_STEP);
+                            sequences.push(KeySequence { key, colors, step });
+                        }
+                    } else if args[2] == "blink" {
+                        // k <key> blink <color> [step]: alternate with the
+                        // color the key had before this line.
+                        if let Some(color) = args.get(3).and_then(|arg| parse_color(arg)) {
+                            keys.push(KeyValue { key, color });
+                            let base = lit.key_color(key).unwrap_or(Color::new(0, 0, 0));
+                            let step = args
+                                .get(4)
+                                .and_then(|arg| parse_duration(arg))
+                                .unwrap_or(DEFAULT_SEQUENCE_STEP);
+                            sequences.push(KeySequence {
+                                key,
+                                colors: vec![color, base],
+                                step,
+                            });
+                        }
+                    } else if let Some(color) = parse_color(&args[2]) {
+                        keys.push(KeyValue { key, color });
+                        lit.set_keys(&[KeyValue { key, color }])?;
+                    }
+                }
+            },
 
             Some("r") if args.len() >= 3 => {
                 if let (Some(region), Some(color)) = (parse_u8(&args[1]), parse_color(&args[2])) {
@@ -135,31 +247,31 @@ where
                 }
             }
 
-            Some("mr") => {
+            Some("mr") if args.len() >= 2 => {
                 if let Some(v) = parse_u8(&args[1]) {
                     kbd.set_mr_key(v)?;
                 }
             }
 
-            Some("mn") => {
+            Some("mn") if args.len() >= 2 => {
                 if let Some(v) = parse_u8(&args[1]) {
                     kbd.set_mn_key(v)?;
                 }
             }
 
-            Some("gkm") => {
+            Some("gkm") if args.len() >= 2 => {
                 if let Some(v) = parse_u8(&args[1]) {
                     kbd.set_gkeys_mode(v)?;
                 }
             }
 
-            Some("sm") => {
+            Some("sm") if args.len() >= 2 => {
                 if let Some(mode) = parse_startup_mode(&args[1]) {
                     kbd.set_startup_mode(mode)?;
                 }
             }
 
-            Some("obm") => {
+            Some("obm") if args.len() >= 2 => {
                 if let Some(mode) = parse_board_mode(&args[1]) {
                     kbd.set_on_board_mode(mode)?;
                 }
@@ -207,7 +319,7 @@ where
                 if strict {
                     return Err(anyhow!("unknown command: {trimmed}"));
                 }
-                eprintln!("warning: unknown command: {trimmed}");
+                output::warn(format_args!("unknown command: {trimmed}"));
             }
         }
 
@@ -217,23 +329,96 @@ where
     if !keys.is_empty() {
         kbd.set_keys(&keys)?;
     }
+    if !raw_keys.is_empty() {
+        kbd.set_key_refs(&raw_keys)?;
+    }
 
-    Ok(())
+    Ok(sequences)
+}
+
+/// Drop whitespace inside `[...]` and `(...)`, so `[red, blue]` and
+/// `mix(red, blue, 30%)` are single tokens.
+fn join_lists(line: &str) -> Cow<'_, str> {
+    if !line.contains(['[', '(']) {
+        return Cow::Borrowed(line);
+    }
+    let mut depth = 0_usize;
+    let mut joined = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            c if depth > 0 && c.is_whitespace() => continue,
+            _ => {}
+        }
+        joined.push(c);
+    }
+    Cow::Owned(joined)
+}
+
+/// Replace each known `$variable` in `tok`, including inside lists and
+/// color expressions such as `mix($accent, white, 40%)`.
+fn expand_vars<'a>(tok: &'a str, vars: &HashMap<String, String>) -> Cow<'a, str> {
+    if !tok.contains('$') {
+        return Cow::Borrowed(tok);
+    }
+    let mut expanded = String::with_capacity(tok.len());
+    let mut rest = tok;
+    while let Some(idx) = rest.find('$') {
+        expanded.push_str(&rest[..idx]);
+        let after = &rest[idx + 1..];
+        let len = after.find([',', '(', ')', '[', ']']).unwrap_or(after.len());
+        match vars.get(&after[..len]) {
+            Some(value) => expanded.push_str(value),
+            None => expanded.push_str(&rest[idx..=idx + len]),
+        }
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    Cow::Owned(expanded)
+}
+
+/// Parse a comma-separated color list, expanding `$variables`.
+fn parse_colors(list: &str, vars: &HashMap<String, String>) -> Option<Vec<Color>> {
+    let colors = split_top_level(list)
+        .into_iter()
+        .map(|item| {
+            let item = item
+                .strip_prefix('$')
+                .and_then(|var| vars.get(var))
+                .map_or(item, String::as_str);
+            parse_color(item)
+        })
+        .collect::<Option<Vec<Color>>>()?;
+    (!colors.is_empty()).then_some(colors)
 }
 
 /// Load a profile from a file path.
 pub fn load_profile<K>(kbd: &mut K, path: impl AsRef<Path>, strict: bool) -> Result<()>
 where
-    K: KeyboardApi,
+    K: KeyboardApi + ?Sized,
 {
     let file = File::open(path)?;
     parse_profile(kbd, BufReader::new(file), strict)
 }
 
+/// Load a profile from a file path, returning its color sequences.
+pub fn load_profile_with_sequences<K>(
+    kbd: &mut K,
+    path: impl AsRef<Path>,
+    strict: bool,
+) -> Result<Vec<KeySequence>>
+where
+    K: KeyboardApi + ?Sized,
+{
+    let file = File::open(path)?;
+    parse_profile_with_sequences(kbd, BufReader::new(file), strict)
+}
+
 /// Parse a profile from standard input.
 pub fn load_profile_stdin<K>(kbd: &mut K, stdin: StdinLock<'_>, strict: bool) -> Result<()>
 where
-    K: KeyboardApi,
+    K: KeyboardApi + ?Sized,
 {
     parse_profile(kbd, stdin, strict)
 }
@@ -241,38 +426,281 @@ where
 /// Load a TOML profile from a file path.
 pub fn load_toml_profile<K>(kbd: &mut K, path: impl AsRef<Path>) -> Result<()>
 where
-    K: KeyboardApi,
+    K: KeyboardApi + ?Sized,
 {
-    let text = std::fs::read_to_string(path)?;
-    let profile: Profile = toml::from_str(&text)?;
+    let profile = read_toml_profile(path.as_ref(), &mut Vec::new())?;
     apply_toml_profile(kbd, profile)
 }
 
+/// Apply a TOML profile given as text; `origin` names it in error messages.
+///
+/// `extends` needs a file to resolve against, so it is rejected here.
+pub fn load_toml_profile_str<K>(kbd: &mut K, text: &str, origin: &str) -> Result<()>
+where
+    K: KeyboardApi + ?Sized,
+{
+    let profile: Profile = parse_toml(text, origin)?;
+    if profile.extends.is_some() {
+        return Err(anyhow!(
+            "{origin}: extends is only supported for profiles loaded from a file"
+        ));
+    }
+    apply_toml_profile(kbd, profile)
+}
+
+/// What [`validate_toml_profile`] found in a profile's key names.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LayoutReport {
+    /// Names that are no key at all, each with its "did you mean".
+    pub unknown: Vec<String>,
+    /// Keys the model does not have.
+    pub unsupported: Vec<Key>,
+    /// Keys the model has that the `[layout]` leaves out; only checked when
+    /// the profile has a layout.
+    pub missing: Vec<Key>,
+}
+
+impl LayoutReport {
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty() && self.unsupported.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Check the `[layout]` and `[[key]]` names of a TOML profile, `extends`
+/// included, against `model` without sending anything.
+pub fn validate_toml_profile(path: impl AsRef<Path>, model: KeyboardModel) -> Result<LayoutReport> {
+    let profile = read_toml_profile(path.as_ref(), &mut Vec::new())?;
+    let names = profile
+        .layout
+        .keys()
+        .chain(profile.key.iter().map(|entry| &entry.key));
+
+    let mut report = LayoutReport::default();
+    let mut covered = Vec::new();
+    for name in names {
+        match parse_key_ref(name) {
+            // Nothing is known about raw keys to check them against.
+            Ok(KeyRef::Raw { .. }) => {}
+            Ok(KeyRef::Known(key)) if !model.has_key(key) => {
+                if !report.unsupported.contains(&key) {
+                    report.unsupported.push(key);
+                }
+            }
+            Ok(KeyRef::Known(key)) => covered.push(key),
+            Err(e) => report.unknown.push(e.to_string()),
+        }
+    }
+    if !profile.layout.is_empty() {
+        report.missing = model
+            .supported_keys()
+            .filter(|key| !covered.contains(key))
+            .collect();
+    }
+    Ok(report)
+}
+
+/// `state` written as a TOML profile that `load-config` applies again.
+pub fn toml_profile(state: &KeyboardState) -> Result<String> {
+    let color = |color: Color| color.to_string();
+    let profile = Profile {
+        all: state.all.map(color),
+        mr: state.mr,
+        mn: state.mn,
+        gkeys_mode: state.gkeys_mode,
+        startup_mode: state.startup_mode.map(|mode| mode.to_string()),
+        on_board_mode: state.on_board_mode.map(|mode| mode.to_string()),
+        groups: state
+            .groups
+            .iter()
+            .map(|&(group, value)| GroupEntry {
+                group: group.to_string(),
+                color: color(value),
+                brightness: None,
+                saturation: None,
+            })
+            .collect(),
+        layout: state
+            .keys
+            .iter()
+            .map(|kv| (key_ref_name(kv.key), color(kv.color)))
+            .collect(),
+        regions: state
+            .regions
+            .iter()
+            .map(|&(region, value)| RegionEntry {
+                region: region.to_string(),
+                color: color(value),
+            })
+            .collect(),
+        effects: state
+            .effects
+            .iter()
+            .map(|fx| EffectEntry {
+                effect: fx.effect.to_string(),
+                part: fx.part.to_string(),
+                period: fx
+                    .effect
+                    .uses_period()
+                    .then(|| format!("{}ms", fx.period.as_millis())),
+                color: fx.effect.uses_color().then(|| color(fx.color)),
+                storage: (fx.storage != NativeEffectStorage::None).then(|| fx.storage.to_string()),
+            })
+            .collect(),
+        ..Profile::default()
+    };
+    Ok(toml::to_string(&profile)?)
+}
+
+/// Read a TOML profile, resolving its `extends` chain into a single profile.
+///
+/// `chain` holds the files already visited, and how each was named, so
+/// cycles are reported instead of recursing forever.
+fn read_toml_profile(path: &Path, chain: &mut Vec<(PathBuf, String)>) -> Result<Profile> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+    // The first file as given, then just the names of the files it extends.
+    let name = match path.file_name().filter(|_| !chain.is_empty()) {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => path.display().to_string(),
+    };
+    if let Some(start) = chain.iter().position(|(seen, _)| *seen == canonical) {
+        let names: Vec<&str> = chain[start..]
+            .iter()
+            .map(|(_, name)| name.as_str())
+            .collect();
+        return Err(anyhow!(
+            "profile inheritance cycle: {} -> {name}",
+            names.join(" -> ")
+        ));
+    }
+    chain.push((canonical, name));
+
+    let text = std::fs::read_to_string(path)?;
+    let mut profile: Profile = parse_toml(&text, &path.display().to_string())?;
+
+    if let Some(parent) = profile.extends.take() {
+        let parent_path = path.parent().unwrap_or_else(|| Path::new(".")).join(parent);
+        let base = read_toml_profile(&parent_path, chain)?;
+        profile = base.merge(profile);
+    }
+
+    Ok(profile)
+}
+
+impl Profile {
+    /// Layer `child` on top of `self`: scalars in the child win, and list
+    /// entries replace the parent's entry for the same group/key/region/part.
+    fn merge(self, child: Profile) -> Profile {
+        Profile {
+            extends: None,
+            all: child.all.or(self.all),
+            groups: merge_entries(self.groups, child.groups, |e| {
+                parse_key_group(&e.group)
+                    .map_or_else(|| e.group.to_ascii_lowercase(), |g| g.to_string())
+            }),
+            key: merge_entries(self.key, child.key, |e| {
+                parse_key(&e.key).map_or_else(|_| e.key.to_ascii_lowercase(), |k| format!("{k:?}"))
+            }),
+            layout: merge_layout(self.layout, child.layout),
+            regions: merge_entries(self.regions, child.regions, |e| {
+                parse_u8(&e.region).map_or_else(|| e.region.clone(), |r| r.to_string())
+            }),
+            effects: merge_entries(self.effects, child.effects, |e| {
+                parse_native_effect_part(&e.part)
+                    .map_or_else(|| e.part.to_ascii_lowercase(), |p| p.to_string())
+            }),
+            mr: child.mr.or(self.mr),
+            mn: child.mn.or(self.mn),
+            gkeys_mode: child.gkeys_mode.or(self.gkeys_mode),
+            startup_mode: child.startup_mode.or(self.startup_mode),
+            on_board_mode: child.on_board_mode.or(self.on_board_mode),
+        }
+    }
+}
+
+/// Keep the parent's order, replacing entries the child overrides and
+/// appending the child's new ones.
+fn merge_entries<T>(base: Vec<T>, overrides: Vec<T>, id: impl Fn(&T) -> String) -> Vec<T> {
+    let mut overrides: Vec<Option<T>> = overrides.into_iter().map(Some).collect();
+    let mut merged: Vec<T> = base
+        .into_iter()
+        .map(|entry| {
+            let key = id(&entry);
+            overrides
+                .iter_mut()
+                .rev()
+                .find(|o| o.as_ref().is_some_and(|o| id(o) == key))
+                .and_then(Option::take)
+                .unwrap_or(entry)
+        })
+        .collect();
+    merged.extend(overrides.into_iter().flatten());
+    merged
+}
+
+/// The parent's layout with the child's keys on top; names are compared
+/// as keys, so `Esc` in the child replaces `esc` in the parent.
+fn merge_layout(
+    base: BTreeMap<String, String>,
+    overrides: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let id = |name: &str| parse_key(name).map_err(|_| name.to_ascii_lowercase());
+    let overridden: Vec<_> = overrides.keys().map(|name| id(name)).collect();
+    let mut merged: BTreeMap<_, _> = base
+        .into_iter()
+        .filter(|(name, _)| !overridden.contains(&id(name)))
+        .collect();
+    merged.extend(overrides);
+    merged
+}
+
 fn apply_toml_profile<K>(kbd: &mut K, profile: Profile) -> Result<()>
 where
-    K: KeyboardApi,
+    K: KeyboardApi + ?Sized,
 {
     if let Some(color) = profile.all.as_deref().and_then(parse_color) {
         kbd.set_all_keys(color)?;
     }
 
     for entry in profile.groups {
-        if let (Some(group), Some(color)) =
-            (parse_key_group(&entry.group), parse_color(&entry.color))
-        {
+        if let (Some(group), Some(color)) = (parse_key_group(&entry.group), entry.color()) {
             kbd.set_group_keys(group, color)?;
         }
     }
 
     let mut keys: Vec<KeyValue> = Vec::new();
+    let mut raw_keys: Vec<KeyRefValue> = Vec::new();
+    let mut push = |key, color| match key {
+        KeyRef::Known(key) => keys.push(KeyValue { key, color }),
+        KeyRef::Raw { .. } => raw_keys.push(KeyRefValue { key, color }),
+    };
+    // The layout goes first so single `[[key]]` entries can still win.
+    for (name, color) in &profile.layout {
+        match parse_key_ref(name) {
+            Ok(key) => match parse_color(color) {
+                Some(color) => push(key, color),
+                None => output::warn(format_args!("layout: {name}: invalid color {color:?}")),
+            },
+            Err(e) => output::warn(format_args!("layout: {e}")),
+        }
+    }
     for entry in profile.key {
-        if let (Some(key), Some(color)) = (parse_key(&entry.key), parse_color(&entry.color)) {
-            keys.push(KeyValue { key, color });
+        match parse_key_ref(&entry.key) {
+            Ok(key) => {
+                if let Some(color) = parse_color(&entry.color) {
+                    push(key, color);
+                }
+            }
+            Err(e) => output::warn(e),
         }
     }
     if !keys.is_empty() {
         kbd.set_keys(&keys)?;
     }
+    if !raw_keys.is_empty() {
+        kbd.set_key_refs(&raw_keys)?;
+    }
 
     for entry in profile.regions {
         if let (Some(region), Some(color)) = (parse_u8(&entry.region), parse_color(&entry.color)) {
@@ -328,8 +756,8 @@ where
 mod tests {
     use super::*;
     use crate::keyboard::{
-        Color, Key, KeyGroup, KeyValue, NativeEffect, NativeEffectPart, NativeEffectStorage,
-        api::KeyboardApi,
+        Color, Key, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+        NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
     };
     use std::fs::File;
     use std::io::Write;
@@ -341,6 +769,7 @@ mod tests {
         all_calls: Vec<Color>,
         group_calls: Vec<(KeyGroup, Color)>,
         key_calls: Vec<Vec<KeyValue>>, // each call collects slice
+        raw_key_calls: Vec<Vec<KeyRefValue>>,
         region_calls: Vec<(u8, Color)>,
         fx_calls: Vec<(
             NativeEffect,
@@ -349,9 +778,14 @@ mod tests {
             Color,
             NativeEffectStorage,
         )>,
+        setting_calls: Vec<String>,
     }
 
     impl KeyboardApi for MockKeyboard {
+        fn model(&self) -> KeyboardModel {
+            KeyboardModel::Unknown
+        }
+
         fn commit(&mut self) -> anyhow::Result<()> {
             self.commits += 1;
             Ok(())
@@ -372,11 +806,41 @@ mod tests {
             Ok(())
         }
 
+        fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> anyhow::Result<()> {
+            self.raw_key_calls.push(keys.to_vec());
+            Ok(())
+        }
+
         fn set_region(&mut self, region: u8, color: Color) -> anyhow::Result<()> {
             self.region_calls.push((region, color));
             Ok(())
         }
 
+        fn set_mr_key(&mut self, value: u8) -> anyhow::Result<()> {
+            self.setting_calls.push(format!("mr {value}"));
+            Ok(())
+        }
+
+        fn set_mn_key(&mut self, value: u8) -> anyhow::Result<()> {
+            self.setting_calls.push(format!("mn {value}"));
+            Ok(())
+        }
+
+        fn set_gkeys_mode(&mut self, value: u8) -> anyhow::Result<()> {
+            self.setting_calls.push(format!("gkm {value}"));
+            Ok(())
+        }
+
+        fn set_startup_mode(&mut self, mode: StartupMode) -> anyhow::Result<()> {
+            self.setting_calls.push(format!("sm {mode:?}"));
+            Ok(())
+        }
+
+        fn set_on_board_mode(&mut self, mode: OnBoardMode) -> anyhow::Result<()> {
+            self.setting_calls.push(format!("obm {mode:?}"));
+            Ok(())
+        }
+
         fn set_fx(
             &mut self,
             effect: NativeEffect,
@@ -390,6 +854,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn raw_keys_reach_the_keyboard() {
+        let input = "k code:0x04ff ff0000\nk usage:0x04 00ff00\nc\n";
+        let mut mock = MockKeyboard::default();
+        parse_profile(&mut mock, input.as_bytes(), true).unwrap();
+        let red = Color::new(0xff, 0, 0);
+        let raw = KeyRef::Raw {
+            group: 0x04,
+            code: 0xff,
+        };
+        assert_eq!(
+            mock.raw_key_calls,
+            [[KeyRefValue {
+                key: raw,
+                color: red
+            }]]
+        );
+        assert_eq!(mock.key_calls[0][0].key, Key::A);
+    }
+
+    #[test]
+    fn settings_reach_the_keyboard() {
+        let input = "mr 1\nmn 2\ngkm 0\nsm wave\nobm software\nc\n";
+        let mut mock = MockKeyboard::default();
+        parse_profile(&mut mock, input.as_bytes(), true).unwrap();
+
+        assert_eq!(
+            mock.setting_calls,
+            ["mr 1", "mn 2", "gkm 0", "sm Wave", "obm Software"]
+        );
+        assert_eq!(mock.commits, 1);
+    }
+
     #[test]
     fn parse_keys_and_commit() {
         let input = "k a ff0000\nk b 00ff00\nc\n";
@@ -421,6 +918,84 @@ mod tests {
         assert_eq!(mock.commits, 1);
     }
 
+    #[test]
+    fn key_sequences_start_on_their_first_color() {
+        let input = "var warm ff8000\n\
+                     k esc [red, $warm,yellow] 250ms\n\
+                     k f1 [blue,nope]\n\
+                     k f2 [00ff00]\n\
+                     c\n";
+        let mut mock = MockKeyboard::default();
+        let sequences = parse_profile_with_sequences(&mut mock, input.as_bytes(), true).unwrap();
+
+        let red = Color::new(0xff, 0, 0);
+        assert_eq!(
+            mock.key_calls,
+            [vec![
+                KeyValue {
+                    key: Key::Esc,
+                    color: red
+                },
+                KeyValue {
+                    key: Key::F2,
+                    color: Color::new(0, 0xff, 0)
+                },
+            ]]
+        );
+        assert_eq!(sequences.len(), 2);
+        assert_eq!(
+            sequences[0].colors,
+            [red, Color::new(0xff, 0x80, 0), Color::new(0xff, 0xff, 0)]
+        );
+        assert_eq!(sequences[0].step, Duration::from_millis(250));
+        assert_eq!(sequences[1].step, DEFAULT_SEQUENCE_STEP);
+    }
+
+    #[test]
+    fn blinking_keys_return_to_their_base_color() {
+        let input = "a blue\n\
+                     g arrows green\n\
+                     k caps blink red 250ms\n\
+                     k up blink red\n\
+                     k esc white\n\
+                     k esc blink red\n";
+        let mut mock = MockKeyboard::default();
+        let sequences = parse_profile_with_sequences(&mut mock, input.as_bytes(), true).unwrap();
+
+        let red = Color::new(0xff, 0, 0);
+        let bases: Vec<_> = sequences.iter().map(|s| (s.key, s.colors[1])).collect();
+        assert_eq!(
+            bases,
+            [
+                (Key::Caps, Color::new(0, 0, 0xff)),
+                (Key::ArrowTop, Color::new(0, 0xff, 0)),
+                (Key::Esc, Color::new(0xff, 0xff, 0xff)),
+            ]
+        );
+        assert!(sequences.iter().all(|s| s.colors[0] == red));
+        assert_eq!(sequences[0].step, Duration::from_millis(250));
+        assert_eq!(sequences[1].step, DEFAULT_SEQUENCE_STEP);
+    }
+
+    #[test]
+    fn color_expressions_span_spaces() {
+        let input = "var accent darken(ff8800, 20%)\n\
+                     a mix(red, blue, 30%)\n\
+                     k esc $accent\n\
+                     k f2 mix($accent, $accent, 50%)\n\
+                     k f1 [lighten(black, 50%), red] 100ms\n";
+        let mut mock = MockKeyboard::default();
+        let sequences = parse_profile_with_sequences(&mut mock, input.as_bytes(), true).unwrap();
+
+        assert_eq!(mock.all_calls, [Color::new(0xb3, 0x00, 0x4d)]);
+        assert_eq!(mock.key_calls[0][0].color, Color::new(0xcc, 0x6d, 0x00));
+        assert_eq!(mock.key_calls[0][1].color, Color::new(0xcc, 0x6d, 0x00));
+        assert_eq!(
+            sequences[0].colors,
+            [Color::new(0x80, 0x80, 0x80), Color::new(0xff, 0, 0)]
+        );
+    }
+
     #[test]
     fn parse_group_region_effect() {
         let input = "a 010203\ng arrows ff0000\nr 2 00ff00\nfx color keys ff0000\n";
@@ -490,6 +1065,55 @@ mod tests {
         assert!(err.to_string().contains("unknown command"));
     }
 
+    #[test]
+    fn unknown_keys_are_reported_with_a_suggestion() {
+        let mut mock = MockKeyboard::default();
+        parse_profile(
+            &mut mock,
+            "k esx ff0000\nk esc 00ff00\nc\n".as_bytes(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(mock.key_calls.concat().len(), 1);
+        let err = parse_profile(&mut mock, "k esx ff0000\n".as_bytes(), true).unwrap_err();
+        assert!(err.to_string().contains("did you mean `esc`?"));
+    }
+
+    #[test]
+    fn missing_arguments_are_unknown_commands() {
+        for input in ["a\n", "mr\n", "mn\n", "gkm\n", "sm\n", "obm\n"] {
+            let mut mock = MockKeyboard::default();
+            let err = parse_profile(&mut mock, input.as_bytes(), true).unwrap_err();
+            assert!(err.to_string().contains("unknown command"));
+        }
+    }
+
+    #[test]
+    fn toml_profile_from_text() {
+        let mut mock = MockKeyboard::default();
+        load_toml_profile_str(&mut mock, "all = \"red\"\n", "inline").unwrap();
+        assert_eq!(mock.all_calls, vec![Color::new(0xff, 0, 0)]);
+
+        let err =
+            load_toml_profile_str(&mut mock, "extends = \"base.toml\"\n", "inline").unwrap_err();
+        assert!(err.to_string().contains("extends"));
+    }
+
+    #[test]
+    fn group_colors_can_be_dimmed_and_desaturated() {
+        let mut mock = MockKeyboard::default();
+        let toml = "[[groups]]\n\
+                    group = \"fkeys\"\n\
+                    color = \"red\"\n\
+                    brightness = 40\n\
+                    saturation = 80\n";
+        load_toml_profile_str(&mut mock, toml, "inline").unwrap();
+        assert_eq!(
+            mock.group_calls,
+            [(KeyGroup::FKeys, Color::new(0x66, 0x14, 0x14))]
+        );
+    }
+
     #[test]
     fn apply_toml_profile_basic() {
         let toml = r#"
@@ -579,4 +1203,196 @@ color = "ff00ff"
         );
         assert_eq!(*storage, NativeEffectStorage::None);
     }
+
+    #[test]
+    fn toml_profile_extends_chain() {
+        let dir = std::env::temp_dir().join("logi_led_extends_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.toml"),
+            r#"
+all = "010203"
+mr = 1
+
+[[groups]]
+group = "arrows"
+color = "ff0000"
+
+[[groups]]
+group = "fkeys"
+color = "0000ff"
+
+[[key]]
+key = "a"
+color = "00ff00"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("middle.toml"),
+            r#"
+extends = "base.toml"
+
+[[groups]]
+group = "Arrows"
+color = "ffffff"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("child.toml"),
+            r#"
+extends = "middle.toml"
+all = "000000"
+
+[[key]]
+key = "A"
+color = "ff00ff"
+
+[[key]]
+key = "b"
+color = "00ffff"
+"#,
+        )
+        .unwrap();
+
+        let mut mock = MockKeyboard::default();
+        load_toml_profile(&mut mock, dir.join("child.toml")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(mock.all_calls, vec![Color::new(0, 0, 0)]);
+        assert_eq!(
+            mock.group_calls,
+            vec![
+                (KeyGroup::Arrows, Color::new(0xff, 0xff, 0xff)),
+                (KeyGroup::FKeys, Color::new(0x00, 0x00, 0xff)),
+            ]
+        );
+        assert_eq!(
+            mock.key_calls,
+            vec![vec![
+                KeyValue {
+                    key: Key::A,
+                    color: Color::new(0xff, 0x00, 0xff),
+                },
+                KeyValue {
+                    key: Key::B,
+                    color: Color::new(0x00, 0xff, 0xff),
+                },
+            ]]
+        );
+    }
+
+    #[test]
+    fn toml_profile_typo_suggests_field() {
+        let mut path = std::env::temp_dir();
+        path.push("test_profile_typo.toml");
+        std::fs::write(&path, "[[key]]\nkey = \"a\"\ncolour = \"red\"\n").unwrap();
+
+        let mut mock = MockKeyboard::default();
+        let err = load_toml_profile(&mut mock, &path).unwrap_err();
+        let _ = std::fs::remove_file(path);
+
+        let msg = err.to_string();
+        assert!(msg.contains("line 3"));
+        assert!(msg.contains("did you mean `color`?"));
+        assert_eq!(mock.commits, 0);
+    }
+
+    #[test]
+    fn toml_profile_extends_cycle() {
+        let dir = std::env::temp_dir().join("logi_led_extends_cycle_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("one.toml"), "extends = \"two.toml\"\n").unwrap();
+        std::fs::write(dir.join("two.toml"), "extends = \"one.toml\"\n").unwrap();
+
+        let mut mock = MockKeyboard::default();
+        let err = load_toml_profile(&mut mock, dir.join("one.toml")).unwrap_err();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "profile inheritance cycle: {} -> two.toml -> one.toml",
+                dir.join("one.toml").display()
+            )
+        );
+        assert_eq!(mock.commits, 0);
+    }
+
+    #[test]
+    fn toml_layout_sets_keys_and_merges_by_key() {
+        let dir = std::env::temp_dir().join("logi_led_layout_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.toml"),
+            "[layout]\nesc = \"red\"\nf1 = \"blue\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("child.toml"),
+            "extends = \"base.toml\"\n[layout]\nEsc = \"00ff00\"\n\
+             [[key]]\nkey = \"f1\"\ncolor = \"white\"\n",
+        )
+        .unwrap();
+
+        let mut mock = MockKeyboard::default();
+        load_toml_profile(&mut mock, dir.join("child.toml")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            mock.key_calls,
+            [vec![
+                KeyValue {
+                    key: Key::Esc,
+                    color: Color::new(0, 0xff, 0),
+                },
+                KeyValue {
+                    key: Key::F1,
+                    color: Color::new(0, 0, 0xff),
+                },
+                KeyValue {
+                    key: Key::F1,
+                    color: Color::new(0xff, 0xff, 0xff),
+                },
+            ]]
+        );
+    }
+
+    #[test]
+    fn validate_reports_typos_and_key_coverage() {
+        let path = std::env::temp_dir().join("logi_led_validate_layout.toml");
+        std::fs::write(
+            &path,
+            "[layout]\nesc = \"red\"\nnum1 = \"red\"\nscape = \"red\"\n",
+        )
+        .unwrap();
+        let report = validate_toml_profile(&path, KeyboardModel::G410).unwrap();
+        let full = validate_toml_profile(&path, KeyboardModel::G810).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(report.unknown.len(), 1);
+        assert!(report.unknown[0].contains("scape"));
+        assert_eq!(report.unsupported, [Key::Num1]);
+        assert!(!report.missing.contains(&Key::Esc));
+        assert!(report.missing.contains(&Key::F1));
+        assert!(!report.missing.contains(&Key::Num1));
+        assert_eq!(full.unsupported, []);
+        assert!(!full.is_clean());
+    }
+
+    #[test]
+    fn saved_state_loads_back_the_same() {
+        let text = "a 102030\ng arrows ff0000\nk esc 00ff00\nk f1 0000ff\n\
+                    fx breathing logo 00ffff 4s\nfx cycle keys 5s ffffff user\n\
+                    mr 1\nsm color\nc\n";
+        let mut state = KeyboardState::new();
+        parse_profile(&mut state, text.as_bytes(), true).unwrap();
+
+        let toml = toml_profile(&state).unwrap();
+        let mut loaded = KeyboardState::new();
+        load_toml_profile_str(&mut loaded, &toml, "saved").unwrap();
+        assert_eq!(loaded, state, "{toml}");
+        assert_eq!(loaded.effects.len(), 2);
+    }
 }
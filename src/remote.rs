@@ -0,0 +1,344 @@
+//! Driving a keyboard plugged into another machine, e.g. behind a KVM switch.
+//!
+//! `logi-led serve` runs on the machine with the keyboard and listens on TCP.
+//! Elsewhere, `--remote host:port` makes commands send their calls there
+//! instead of opening a local keyboard. Calls travel as g810-led profile
+//! lines, the same text the lighting history records, and the server applies
+//! each committed batch with [`parse_profile`].
+//!
+//! The protocol, one line each way:
+//!
+//! ```text
+//! > logi-led-remote 1 <token>
+//! < ok G810                      (or `error <message>`)
+//! > a ff0000
+//! > k esc 00ff00
+//! > c
+//! < ok
+//! > end
+//! < ok
+//! ```
+//!
+//! Both sides share `remote_token` from their config. The token keeps other
+//! hosts out, but the connection is not encrypted; beyond a trusted network,
+//! tunnel it (e.g. `ssh -L`).
+
+use anyhow::{Result, anyhow, bail};
+use core::fmt::Display;
+use core::time::Duration;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+
+use crate::commands::Opener;
+use crate::history::{fx_line, key_name, key_ref_name};
+use crate::keyboard::{
+    Color, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+use crate::profile::parse_profile;
+
+/// Port `serve` listens on and `--remote` connects to when none is given.
+pub const DEFAULT_PORT: u16 = 7717;
+
+/// First words of the client's greeting; the number is the protocol version.
+const GREETING: &str = "logi-led-remote 1";
+
+/// How long a new connection may take to identify itself.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest line either side reads; profile lines are far shorter.
+const MAX_LINE: u64 = 4096;
+
+/// Compare tokens without stopping at the first difference, so response
+/// times do not reveal how much of a guess was right.
+fn same_token(offered: &str, token: &str) -> bool {
+    offered.len() == token.len()
+        && offered
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Read one line, without its line ending; `None` once the peer hangs up.
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    line.truncate(line.trim_end().len());
+    Ok(Some(line))
+}
+
+/// A keyboard on another machine, reached through `logi-led serve`.
+pub struct RemoteKeyboard {
+    addr: String,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    model: KeyboardModel,
+}
+
+impl RemoteKeyboard {
+    /// Connect to `addr` (`host` or `host:port`) and authenticate with `token`.
+    pub fn connect(addr: &str, token: &str) -> Result<Self> {
+        let addr = if addr.contains(':') {
+            addr.to_owned()
+        } else {
+            format!("{addr}:{DEFAULT_PORT}")
+        };
+        let stream = TcpStream::connect(&addr).map_err(|e| anyhow!("{addr}: {e}"))?;
+        // Animations send a frame at a time; do not hold them back.
+        stream.set_nodelay(true)?;
+        let mut remote = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            addr,
+            model: KeyboardModel::Unknown,
+        };
+        let model = remote.exchange(format_args!("{GREETING} {token}"))?;
+        remote.model = model.parse().unwrap_or(KeyboardModel::Unknown);
+        Ok(remote)
+    }
+
+    /// Tell the server to apply what is left uncommitted and hang up.
+    pub fn finish(mut self) -> Result<()> {
+        self.exchange("end").map(drop)
+    }
+
+    fn send(&mut self, line: impl Display) -> Result<()> {
+        writeln!(self.writer, "{line}").map_err(|e| anyhow!("{}: {e}", self.addr))
+    }
+
+    /// Send `line` and wait for the reply, returning what follows `ok`.
+    fn exchange(&mut self, line: impl Display) -> Result<String> {
+        self.send(line)?;
+        self.writer
+            .flush()
+            .map_err(|e| anyhow!("{}: {e}", self.addr))?;
+        let Some(reply) = read_line(&mut self.reader)? else {
+            bail!("{}: the server closed the connection", self.addr);
+        };
+        match reply.split_once(' ').unwrap_or((&reply, "")) {
+            ("ok", rest) => Ok(rest.to_owned()),
+            ("error", message) => bail!("{}: {message}", self.addr),
+            _ => bail!("{}: unexpected reply {reply:?}", self.addr),
+        }
+    }
+}
+
+impl KeyboardApi for RemoteKeyboard {
+    fn model(&self) -> KeyboardModel {
+        self.model
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.exchange("c").map(drop)
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.send(format_args!("a {color}"))
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.send(format_args!("g {group} {color}"))
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        for kv in keys {
+            let name = key_name(kv.key)
+                .ok_or_else(|| anyhow!("{:?} has no name to send it by", kv.key))?;
+            self.send(format_args!("k {name} {}", kv.color))?;
+        }
+        Ok(())
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        for kv in keys {
+            self.send(format_args!("k {} {}", key_ref_name(kv.key), kv.color))?;
+        }
+        Ok(())
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.send(format_args!("r {region} {color}"))
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.send(format_args!("mr {value}"))
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.send(format_args!("mn {value}"))
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.send(format_args!("gkm {value}"))
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        self.send(format_args!("sm {mode}"))
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.send(format_args!("obm {mode}"))
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        self.send(fx_line(effect, part, period, color, storage))
+    }
+}
+
+/// Opens a [`RemoteKeyboard`] in place of a local one.
+pub struct RemoteOpener {
+    pub addr: String,
+    pub token: String,
+}
+
+impl Opener for RemoteOpener {
+    fn with_keyboard(&self, f: &mut dyn FnMut(&mut dyn KeyboardApi) -> Result<()>) -> Result<()> {
+        let mut kbd = RemoteKeyboard::connect(&self.addr, &self.token)?;
+        f(&mut kbd)?;
+        kbd.finish()
+    }
+}
+
+/// The server's side of one connection.
+pub struct Session {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Session {
+    /// Read the client's greeting and check its token.
+    pub fn accept(stream: TcpStream, token: &str) -> Result<Self> {
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut session = Self {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        };
+        let greeting = read_line(&mut session.reader)?.unwrap_or_default();
+        let Some(offered) = greeting
+            .strip_prefix(GREETING)
+            .and_then(|rest| rest.strip_prefix(' '))
+        else {
+            session.refuse("not a logi-led remote client, or a different version");
+            bail!("not a logi-led remote client");
+        };
+        if !same_token(offered, token) {
+            session.refuse("wrong remote_token");
+            bail!("wrong remote_token");
+        }
+        session.reader.get_ref().set_read_timeout(None)?;
+        Ok(session)
+    }
+
+    /// Apply the client's calls to `kbd` until it says `end` or hangs up.
+    ///
+    /// A batch that fails is reported to the client, which decides whether
+    /// to go on.
+    pub fn run(&mut self, kbd: &mut dyn KeyboardApi) -> Result<()> {
+        self.reply(Ok(format!("{:?}", kbd.model())))?;
+        let mut batch = String::new();
+        while let Some(line) = read_line(&mut self.reader)? {
+            match line.as_str() {
+                "end" => {
+                    let applied = parse_profile(kbd, batch.as_bytes(), true);
+                    return self.reply(applied.map(|()| String::new()));
+                }
+                "c" => {
+                    batch.push_str("c\n");
+                    let applied = parse_profile(kbd, batch.as_bytes(), true);
+                    batch.clear();
+                    self.reply(applied.map(|()| String::new()))?;
+                }
+                _ => {
+                    batch.push_str(&line);
+                    batch.push('\n');
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the client why it is being turned away; best effort, as the
+    /// connection may already be gone.
+    pub fn refuse(&mut self, reason: impl Display) {
+        let _ = self.reply(Err(anyhow!("{reason}")));
+    }
+
+    fn reply(&mut self, result: Result<String>) -> Result<()> {
+        let line = match result {
+            Ok(rest) if rest.is_empty() => "ok".to_owned(),
+            Ok(rest) => format!("ok {rest}"),
+            // One line per reply, however the error was formatted.
+            Err(e) => format!("error {}", format!("{e:#}").replace('\n', " ")),
+        };
+        writeln!(self.writer, "{line}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockKeyboard;
+    use std::net::TcpListener;
+
+    /// Serve one connection on a loopback port, returning the address and
+    /// the calls the keyboard received.
+    fn serve_once(token: &'static str) -> (String, std::thread::JoinHandle<Result<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            let mut session = Session::accept(stream, token)?;
+            let mut kbd = MockKeyboard {
+                model: KeyboardModel::G810,
+                calls: Vec::new(),
+            };
+            session.run(&mut kbd)?;
+            Ok(kbd.calls)
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn calls_reach_the_remote_keyboard() {
+        let (addr, server) = serve_once("0123456789abcdef");
+        let opener = RemoteOpener {
+            addr,
+            token: "0123456789abcdef".to_owned(),
+        };
+        opener
+            .with_keyboard(&mut |kbd| {
+                assert_eq!(kbd.model(), KeyboardModel::G810);
+                kbd.set_all_keys(Color::new(0xff, 0, 0))?;
+                kbd.set_keys(&[KeyValue {
+                    key: crate::keyboard::Key::Esc,
+                    color: Color::new(0, 0xff, 0),
+                }])?;
+                kbd.commit()?;
+                kbd.set_gkeys_mode(1)
+            })
+            .unwrap();
+        assert_eq!(
+            server.join().unwrap().unwrap(),
+            ["all ff0000", "key Esc 00ff00", "commit", "gkm 1"]
+        );
+    }
+
+    #[test]
+    fn wrong_tokens_are_turned_away() {
+        let (addr, server) = serve_once("0123456789abcdef");
+        let err = RemoteKeyboard::connect(&addr, "guess").err().unwrap();
+        assert!(err.to_string().ends_with("wrong remote_token"));
+        assert!(server.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Detection of application sandboxes that hide device nodes from us.
+//!
+//! This only detects and explains: nothing here talks to the XDG USB portal,
+//! so the sandbox has to be granted the device nodes directly.
+
+use std::fmt;
+
+/// Marker file Flatpak mounts into every sandbox.
+const FLATPAK_INFO: &str = "/.flatpak-info";
+
+/// The sandbox logi-led is running in, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sandbox {
+    Flatpak { app_id: String },
+    Snap { name: String },
+}
+
+impl Sandbox {
+    /// Detect the current sandbox from its marker file or environment.
+    pub fn detect() -> Option<Self> {
+        if let Ok(info) = std::fs::read_to_string(FLATPAK_INFO) {
+            let app_id = std::env::var("FLATPAK_ID")
+                .ok()
+                .or_else(|| flatpak_app_id(&info))
+                .unwrap_or_else(|| "<app-id>".to_owned());
+            return Some(Self::Flatpak { app_id });
+        }
+        std::env::var("SNAP_NAME")
+            .ok()
+            .map(|name| Self::Snap { name })
+    }
+
+    /// How to grant the sandbox access to the keyboard.
+    pub fn access_hint(&self) -> String {
+        match self {
+            Self::Flatpak { app_id } => format!(
+                "the Flatpak sandbox hides /dev/hidraw* and USB devices unless granted; run\n  \
+                 flatpak override --user --device=all {app_id}\n\
+                 and make sure the host has the udev rule from `logi-led doctor`"
+            ),
+            Self::Snap { name } => format!(
+                "snaps need the raw-usb and hardware-observe interfaces connected; run\n  \
+                 sudo snap connect {name}:raw-usb\n  \
+                 sudo snap connect {name}:hardware-observe"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Sandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flatpak { app_id } => write!(f, "Flatpak ({app_id})"),
+            Self::Snap { name } => write!(f, "snap ({name})"),
+        }
+    }
+}
+
+/// Pull `name=` out of the `[Application]` group of `/.flatpak-info`.
+fn flatpak_app_id(info: &str) -> Option<String> {
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application && let Some(name) = line.strip_prefix("name=") {
+            return Some(name.to_owned());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_id_from_flatpak_info() {
+        let info = "[Runtime]\nname=org.freedesktop.Platform\n\n\
+                    [Application]\nname=io.github.LogiLed\nruntime=runtime/x\n";
+        assert_eq!(flatpak_app_id(info), Some("io.github.LogiLed".into()));
+        assert_eq!(flatpak_app_id("[Runtime]\nname=x\n"), None);
+    }
+
+    #[test]
+    fn hint_names_the_app() {
+        let sandbox = Sandbox::Flatpak {
+            app_id: "io.github.LogiLed".into(),
+        };
+        assert!(
+            sandbox
+                .access_hint()
+                .contains("--device=all io.github.LogiLed")
+        );
+        assert_eq!(sandbox.to_string(), "Flatpak (io.github.LogiLed)");
+    }
+}
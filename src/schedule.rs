@@ -0,0 +1,327 @@
+use chrono::{
+    DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use std::collections::BTreeMap;
+use strum_macros::{Display, EnumString};
+
+/// Julian date of the Unix epoch.
+const JULIAN_UNIX_EPOCH: f64 = 2_440_587.5;
+/// Julian date of the J2000.0 epoch.
+const JULIAN_2000: f64 = 2_451_545.0;
+/// Solar altitude at sunrise/sunset, accounting for refraction and disc size.
+const SUN_ALTITUDE_DEG: f64 = -0.833;
+/// Axial tilt of the earth.
+const OBLIQUITY_DEG: f64 = 23.4397;
+
+/// How the day/night boundary is decided.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DaySource {
+    /// Fixed local wall-clock times.
+    Fixed {
+        sunrise: NaiveTime,
+        sunset: NaiveTime,
+    },
+    /// Sunrise and sunset computed for a location (degrees, east/north positive).
+    Solar { latitude: f64, longitude: f64 },
+}
+
+/// Sunrise and sunset for a given day and place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunTimes {
+    Normal {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    /// The sun never sets (polar summer).
+    PolarDay,
+    /// The sun never rises (polar winter).
+    PolarNight,
+}
+
+/// Parse a wall-clock time in `HH:MM` form.
+pub fn parse_time_of_day(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").ok()
+}
+
+/// Compute sunrise and sunset with the standard sunrise equation.
+///
+/// Accurate to a couple of minutes, which is plenty for switching themes.
+pub fn sun_times(date: NaiveDate, latitude: f64, longitude: f64) -> SunTimes {
+    let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default();
+    #[allow(clippy::cast_precision_loss)]
+    let days = (date - j2000).num_days() as f64;
+
+    let mean_solar_noon = days - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_solar_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let l = ecliptic.to_radians();
+    let transit = JULIAN_2000 + mean_solar_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * l).sin();
+
+    let declination = (l.sin() * OBLIQUITY_DEG.to_radians().sin()).asin();
+    let phi = latitude.to_radians();
+    let cos_hour_angle = (SUN_ALTITUDE_DEG.to_radians().sin() - phi.sin() * declination.sin())
+        / (phi.cos() * declination.cos());
+
+    if cos_hour_angle < -1.0 {
+        return SunTimes::PolarDay;
+    }
+    if cos_hour_angle > 1.0 {
+        return SunTimes::PolarNight;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    SunTimes::Normal {
+        sunrise: julian_to_utc(transit - hour_angle / 360.0),
+        sunset: julian_to_utc(transit + hour_angle / 360.0),
+    }
+}
+
+fn julian_to_utc(julian: f64) -> DateTime<Utc> {
+    #[allow(clippy::cast_possible_truncation)]
+    let secs = ((julian - JULIAN_UNIX_EPOCH) * 86_400.0).round() as i64;
+    Utc.timestamp_opt(secs, 0).single().unwrap_or_default()
+}
+
+/// Whether `now` falls in the daytime window described by `source`.
+pub fn is_daytime(source: DaySource, now: DateTime<Local>) -> bool {
+    match source {
+        DaySource::Fixed { sunrise, sunset } => {
+            let time = now.time();
+            if sunrise <= sunset {
+                sunrise <= time && time < sunset
+            } else {
+                time >= sunrise || time < sunset
+            }
+        }
+        DaySource::Solar {
+            latitude,
+            longitude,
+        } => match sun_times(now.date_naive(), latitude, longitude) {
+            SunTimes::Normal { sunrise, sunset } => {
+                let now = now.with_timezone(&Utc);
+                sunrise <= now && now < sunset
+            }
+            SunTimes::PolarDay => true,
+            SunTimes::PolarNight => false,
+        },
+    }
+}
+
+/// Days a `[schedule.<days>]` table covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "lowercase")]
+pub enum ScheduleDays {
+    Daily,
+    Weekdays,
+    Weekends,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl ScheduleDays {
+    fn includes(self, day: Weekday) -> bool {
+        let weekend = matches!(day, Weekday::Sat | Weekday::Sun);
+        match self {
+            ScheduleDays::Daily => true,
+            ScheduleDays::Weekdays => !weekend,
+            ScheduleDays::Weekends => weekend,
+            ScheduleDays::Monday => day == Weekday::Mon,
+            ScheduleDays::Tuesday => day == Weekday::Tue,
+            ScheduleDays::Wednesday => day == Weekday::Wed,
+            ScheduleDays::Thursday => day == Weekday::Thu,
+            ScheduleDays::Friday => day == Weekday::Fri,
+            ScheduleDays::Saturday => day == Weekday::Sat,
+            ScheduleDays::Sunday => day == Weekday::Sun,
+        }
+    }
+
+    /// Which table wins when two set the same time on the same day.
+    fn specificity(self) -> u8 {
+        match self {
+            ScheduleDays::Daily => 0,
+            ScheduleDays::Weekdays | ScheduleDays::Weekends => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// What to apply from which local time on, per day of the week.
+///
+/// Every table covering a day contributes to it, so a `daily` evening entry
+/// combines with a `monday` morning one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeeklySchedule {
+    tables: Vec<(ScheduleDays, BTreeMap<NaiveTime, String>)>,
+}
+
+impl WeeklySchedule {
+    pub fn new(tables: Vec<(ScheduleDays, BTreeMap<NaiveTime, String>)>) -> Self {
+        Self { tables }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.iter().all(|(_, table)| table.is_empty())
+    }
+
+    /// `day`'s entries in time order.
+    pub fn day(&self, day: Weekday) -> Vec<(NaiveTime, &str)> {
+        let mut entries: BTreeMap<NaiveTime, (u8, &str)> = BTreeMap::new();
+        for (days, table) in self.tables.iter().filter(|(days, _)| days.includes(day)) {
+            let specificity = days.specificity();
+            for (&time, target) in table {
+                if entries.get(&time).is_none_or(|&(s, _)| s < specificity) {
+                    entries.insert(time, (specificity, target));
+                }
+            }
+        }
+        entries
+            .into_iter()
+            .map(|(time, (_, target))| (time, target))
+            .collect()
+    }
+
+    /// The entry in force at `now` and when it started: the latest one
+    /// today, or else the last one of the closest earlier day that has any.
+    pub fn active(&self, now: NaiveDateTime) -> Option<(NaiveDateTime, &str)> {
+        let today = self.day(now.weekday());
+        if let Some(&(time, target)) = today.iter().rev().find(|(time, _)| *time <= now.time()) {
+            return Some((now.date().and_time(time), target));
+        }
+        (1..=7).find_map(|back| {
+            let date = now.date() - Days::new(back);
+            let &(time, target) = self.day(date.weekday()).last()?;
+            Some((date.and_time(time), target))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn london_midsummer() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let SunTimes::Normal { sunrise, sunset } = sun_times(date, 51.5074, -0.1278) else {
+            panic!("expected a normal day");
+        };
+        // Published times: 03:43 and 20:21 UTC.
+        assert!((sunrise - utc("2024-06-21T03:43:00Z")).num_minutes().abs() <= 3);
+        assert!((sunset - utc("2024-06-21T20:21:00Z")).num_minutes().abs() <= 3);
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let june = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let december = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(sun_times(june, 78.2, 15.6), SunTimes::PolarDay);
+        assert_eq!(sun_times(december, 78.2, 15.6), SunTimes::PolarNight);
+    }
+
+    #[test]
+    fn fixed_window_wraps_midnight() {
+        let at = |h, m| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(2024, 1, 1)
+                        .unwrap()
+                        .and_hms_opt(h, m, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+        };
+        let normal = DaySource::Fixed {
+            sunrise: parse_time_of_day("07:00").unwrap(),
+            sunset: parse_time_of_day("19:30").unwrap(),
+        };
+        assert!(is_daytime(normal, at(12, 0)));
+        assert!(!is_daytime(normal, at(19, 30)));
+        assert!(!is_daytime(normal, at(3, 0)));
+
+        let inverted = DaySource::Fixed {
+            sunrise: parse_time_of_day("22:00").unwrap(),
+            sunset: parse_time_of_day("06:00").unwrap(),
+        };
+        assert!(is_daytime(inverted, at(23, 0)));
+        assert!(!is_daytime(inverted, at(12, 0)));
+    }
+
+    fn week() -> WeeklySchedule {
+        let table = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|&(time, target)| (parse_time_of_day(time).unwrap(), target.to_owned()))
+                .collect()
+        };
+        WeeklySchedule::new(vec![
+            (ScheduleDays::Daily, table(&[("22:00", "night.profile")])),
+            (
+                ScheduleDays::Weekdays,
+                table(&[("09:00", "work.toml"), ("18:00", "evening.toml")]),
+            ),
+            (ScheduleDays::Friday, table(&[("18:00", "theme:dracula")])),
+        ])
+    }
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn tables_combine_and_specific_days_win() {
+        let week = week();
+        let times = |day| {
+            week.day(day)
+                .into_iter()
+                .map(|(time, target)| format!("{} {target}", time.format("%H:%M")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            times(Weekday::Mon),
+            [
+                "09:00 work.toml",
+                "18:00 evening.toml",
+                "22:00 night.profile"
+            ]
+        );
+        assert_eq!(
+            times(Weekday::Fri),
+            [
+                "09:00 work.toml",
+                "18:00 theme:dracula",
+                "22:00 night.profile"
+            ]
+        );
+        assert_eq!(times(Weekday::Sun), ["22:00 night.profile"]);
+        assert_eq!("Weekends".parse(), Ok(ScheduleDays::Weekends));
+    }
+
+    #[test]
+    fn active_entry_carries_over_midnight() {
+        let week = week();
+        // 2024-06-03 is a Monday.
+        assert_eq!(
+            week.active(datetime("2024-06-03 12:30")),
+            Some((datetime("2024-06-03 09:00"), "work.toml"))
+        );
+        assert_eq!(
+            week.active(datetime("2024-06-04 07:00")),
+            Some((datetime("2024-06-03 22:00"), "night.profile"))
+        );
+        assert_eq!(
+            WeeklySchedule::default().active(datetime("2024-06-03 12:30")),
+            None
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Stopping foreground animations cleanly on Ctrl-C or SIGTERM.
+//!
+//! [`catch`] replaces the default of dying mid-frame with a flag that
+//! animation loops poll through [`requested`], so they can put the lighting
+//! back and close the keyboard. A second Ctrl-C still ends the process at
+//! once.
+
+use anyhow::{Result, anyhow};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Exit status of a process ended by a second Ctrl-C, as shells report SIGINT.
+const INTERRUPTED: i32 = 130;
+
+static STOP: LazyLock<Arc<AtomicBool>> = LazyLock::new(Arc::default);
+
+/// Catch Ctrl-C and SIGTERM from now on; later calls do nothing.
+pub fn catch() -> Result<()> {
+    static CAUGHT: Mutex<bool> = Mutex::new(false);
+    let mut caught = CAUGHT
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if *caught {
+        return Ok(());
+    }
+    let register = || -> std::io::Result<()> {
+        // Registered first, so it only fires once the flag is already set.
+        signal_hook::flag::register_conditional_shutdown(SIGINT, INTERRUPTED, Arc::clone(&STOP))?;
+        signal_hook::flag::register(SIGINT, Arc::clone(&STOP))?;
+        signal_hook::flag::register(SIGTERM, Arc::clone(&STOP))?;
+        Ok(())
+    };
+    register().map_err(|e| anyhow!("could not catch Ctrl-C: {e}"))?;
+    *caught = true;
+    Ok(())
+}
+
+/// Whether Ctrl-C or SIGTERM arrived since [`catch`].
+pub fn requested() -> bool {
+    STOP.load(Ordering::Relaxed)
+}
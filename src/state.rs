@@ -0,0 +1,222 @@
+//! The lighting and modes a series of calls leaves the keyboard in.
+//!
+//! [`KeyboardState`] is a [`KeyboardApi`] that sends nothing and only keeps
+//! what it was told, overridden the way the keyboard overrides it. Replaying
+//! a history [`Snapshot`](crate::history::Snapshot) onto one gives the state
+//! behind `save-profile`.
+
+use anyhow::Result;
+use core::time::Duration;
+
+use crate::keyboard::{
+    Color, Key, KeyGroup, KeyRef, KeyRefValue, KeyValue, KeyboardModel, NativeEffect,
+    NativeEffectPart, NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+
+/// A firmware effect as passed to [`KeyboardApi::set_fx`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FxState {
+    pub effect: NativeEffect,
+    pub part: NativeEffectPart,
+    pub period: Duration,
+    pub color: Color,
+    pub storage: NativeEffectStorage,
+}
+
+/// Everything applied so far, latest setting winning.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyboardState {
+    pub all: Option<Color>,
+    pub groups: Vec<(KeyGroup, Color)>,
+    pub keys: Vec<KeyRefValue>,
+    pub regions: Vec<(u8, Color)>,
+    /// Live effects and effects stored in on-board memory alike.
+    pub effects: Vec<FxState>,
+    pub mr: Option<u8>,
+    pub mn: Option<u8>,
+    pub gkeys_mode: Option<u8>,
+    pub startup_mode: Option<StartupMode>,
+    pub on_board_mode: Option<OnBoardMode>,
+}
+
+impl KeyboardState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether nothing has been applied.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The color `key` was left in, from its own setting, its group's or the
+    /// whole board's; `None` if nothing lit it.
+    pub fn key_color(&self, key: Key) -> Option<Color> {
+        let own = self
+            .keys
+            .iter()
+            .rev()
+            .find(|kv| kv.key == KeyRef::from(key));
+        let group = self
+            .groups
+            .iter()
+            .rev()
+            .find(|(g, _)| *g == key.key_group());
+        own.map(|kv| kv.color)
+            .or(group.map(|&(_, color)| color))
+            .or(self.all)
+    }
+
+    /// Forget the lighting a color for every key paints over.
+    fn clear_lighting(&mut self) {
+        self.all = None;
+        self.groups.clear();
+        self.keys.clear();
+        self.regions.clear();
+        self.effects
+            .retain(|fx| fx.storage != NativeEffectStorage::None);
+    }
+}
+
+impl KeyboardApi for KeyboardState {
+    fn model(&self) -> KeyboardModel {
+        KeyboardModel::Unknown
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.clear_lighting();
+        self.all = Some(color);
+        Ok(())
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.groups.retain(|&(old, _)| old != group);
+        self.keys
+            .retain(|kv| !matches!(kv.key, KeyRef::Known(key) if key.key_group() == group));
+        self.groups.push((group, color));
+        Ok(())
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        let keys: Vec<KeyRefValue> = keys.iter().map(|&kv| kv.into()).collect();
+        self.set_key_refs(&keys)
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        for kv in keys {
+            self.keys.retain(|old| old.key != kv.key);
+            self.keys.push(*kv);
+        }
+        Ok(())
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.regions.retain(|&(old, _)| old != region);
+        self.regions.push((region, color));
+        Ok(())
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.mr = Some(value);
+        Ok(())
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.mn = Some(value);
+        Ok(())
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.gkeys_mode = Some(value);
+        Ok(())
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        self.startup_mode = Some(mode);
+        Ok(())
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.on_board_mode = Some(mode);
+        Ok(())
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        if part == NativeEffectPart::All && storage == NativeEffectStorage::None {
+            self.clear_lighting();
+        }
+        self.effects
+            .retain(|fx| (fx.part, fx.storage) != (part, storage));
+        self.effects.push(FxState {
+            effect,
+            part,
+            period,
+            color,
+            storage,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_settings_override_earlier_ones() {
+        let red = Color::new(0xff, 0, 0);
+        let blue = Color::new(0, 0, 0xff);
+        let kv = |key, color| KeyValue { key, color };
+        let mut state = KeyboardState::new();
+        state.set_keys(&[kv(Key::Esc, red)]).unwrap();
+        state.set_keys(&[kv(Key::ArrowTop, red)]).unwrap();
+        state.set_group_keys(KeyGroup::Arrows, blue).unwrap();
+        assert_eq!(state.keys, [kv(Key::Esc, red).into()]);
+        assert_eq!(state.groups, [(KeyGroup::Arrows, blue)]);
+
+        state
+            .set_fx(
+                NativeEffect::Breathing,
+                NativeEffectPart::All,
+                Duration::from_secs(5),
+                red,
+                NativeEffectStorage::User,
+            )
+            .unwrap();
+        state.set_all_keys(blue).unwrap();
+        assert_eq!(state.keys, []);
+        assert_eq!(state.groups, []);
+        assert_eq!(state.all, Some(blue));
+        assert_eq!(state.effects.len(), 1, "stored effects outlive repaints");
+    }
+
+    #[test]
+    fn key_color_falls_back_to_the_group_and_the_board() {
+        let red = Color::new(0xff, 0, 0);
+        let blue = Color::new(0, 0, 0xff);
+        let mut state = KeyboardState::new();
+        assert_eq!(state.key_color(Key::M), None);
+        state.set_all_keys(blue).unwrap();
+        state.set_group_keys(KeyGroup::Arrows, red).unwrap();
+        state
+            .set_keys(&[KeyValue {
+                key: Key::M,
+                color: red,
+            }])
+            .unwrap();
+        assert_eq!(state.key_color(Key::M), Some(red));
+        assert_eq!(state.key_color(Key::ArrowTop), Some(red));
+        assert_eq!(state.key_color(Key::Esc), Some(blue));
+    }
+}
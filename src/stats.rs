@@ -0,0 +1,167 @@
+//! Per-key press counts kept by `logi-led stats record`.
+//!
+//! Counts are stored by the key names profiles use, in
+//! `key-stats.toml` under the state directory, so they survive restarts and
+//! read back on boards whose key set differs.
+
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::parse_toml;
+use crate::history::key_name;
+use crate::keyboard::{Color, Key, KeyValue, colors::mix, parser::parse_key};
+use crate::paths;
+
+/// Heatmap color of keys pressed least.
+pub const COLD: Color = Color::new(0, 0, 0xff);
+
+/// Heatmap color of the most pressed key.
+pub const HOT: Color = Color::new(0xff, 0, 0);
+
+/// How often each key was pressed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeyCounts {
+    counts: BTreeMap<String, u64>,
+}
+
+impl KeyCounts {
+    /// `key-stats.toml` in the state directory.
+    pub fn default_path() -> Option<PathBuf> {
+        paths::state_dir().map(|dir| dir.join("key-stats.toml"))
+    }
+
+    /// Read the counts saved at `path`; none if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self {
+                counts: parse_toml(&text, &path.display().to_string())?,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow!("{}: {e}", path.display())),
+        }
+    }
+
+    /// Write the counts to `path`, creating its directory.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| anyhow!("{}: {e}", dir.display()))?;
+        }
+        let text = toml::to_string(&self.counts)?;
+        fs::write(path, text).map_err(|e| anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Count one press of `key`. Keys without a profile name are not counted.
+    pub fn press(&mut self, key: Key) {
+        if let Some(name) = key_name(key) {
+            *self.counts.entry(name.to_owned()).or_default() += 1;
+        }
+    }
+
+    /// Presses of all keys together.
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Keys and their counts, most pressed first, ties by name.
+    pub fn ranked(&self) -> Vec<(&str, u64)> {
+        let mut ranked: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(name, &count)| (name.as_str(), count))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+
+    /// `key,presses` rows under a header, most pressed first.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("key,presses\n");
+        for (name, count) in self.ranked() {
+            let _ = writeln!(out, "{name},{count}");
+        }
+        out
+    }
+
+    /// A JSON object of key names to counts, most pressed first.
+    pub fn to_json(&self) -> String {
+        // Key names are plain ASCII words, so they need no escaping.
+        let fields: Vec<_> = self
+            .ranked()
+            .into_iter()
+            .map(|(name, count)| format!("  \"{name}\": {count}"))
+            .collect();
+        if fields.is_empty() {
+            "{}\n".to_owned()
+        } else {
+            format!("{{\n{}\n}}\n", fields.join(",\n"))
+        }
+    }
+
+    /// Counted keys colored from [`COLD`] to [`HOT`] by their share of the
+    /// most pressed key's count.
+    pub fn heatmap(&self) -> Vec<KeyValue> {
+        let Some(&most) = self.counts.values().max() else {
+            return Vec::new();
+        };
+        self.counts
+            .iter()
+            .filter_map(|(name, &count)| {
+                // Counts stay far below 2^52, so the conversion is exact.
+                #[allow(clippy::cast_precision_loss)]
+                let t = count as f64 / most as f64;
+                Some(KeyValue {
+                    key: parse_key(name).ok()?,
+                    color: mix(COLD, HOT, t),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts() -> KeyCounts {
+        let mut counts = KeyCounts::default();
+        for key in [Key::E, Key::Space, Key::E, Key::A, Key::E, Key::Space] {
+            counts.press(key);
+        }
+        counts
+    }
+
+    #[test]
+    fn presses_are_ranked_and_exported() {
+        let counts = counts();
+        assert_eq!(counts.total(), 6);
+        assert_eq!(counts.ranked(), [("e", 3), ("space", 2), ("a", 1)]);
+        assert_eq!(counts.to_csv(), "key,presses\ne,3\nspace,2\na,1\n");
+        assert_eq!(
+            counts.to_json(),
+            "{\n  \"e\": 3,\n  \"space\": 2,\n  \"a\": 1\n}\n"
+        );
+        assert_eq!(KeyCounts::default().to_json(), "{}\n");
+    }
+
+    #[test]
+    fn counts_survive_a_save() {
+        let path = std::env::temp_dir().join("logi_led_key_stats_test/key-stats.toml");
+        let _ = fs::remove_file(&path);
+        assert_eq!(KeyCounts::load(&path).unwrap(), KeyCounts::default());
+        let counts = counts();
+        counts.save(&path).unwrap();
+        assert_eq!(KeyCounts::load(&path).unwrap(), counts);
+    }
+
+    #[test]
+    fn the_most_pressed_key_is_hottest() {
+        let heatmap = counts().heatmap();
+        let color = |key| heatmap.iter().find(|kv| kv.key == key).unwrap().color;
+        assert_eq!(color(Key::E), HOT);
+        assert_eq!(color(Key::A), mix(COLD, HOT, 1.0 / 3.0));
+        assert_eq!(KeyCounts::default().heatmap(), Vec::new());
+    }
+}
@@ -0,0 +1,104 @@
+//! A recording keyboard for tests anywhere in the crate.
+
+use anyhow::Result;
+use core::time::Duration;
+
+use crate::keyboard::{
+    Color, KeyGroup, KeyRefValue, KeyValue, KeyboardModel, NativeEffect, NativeEffectPart,
+    NativeEffectStorage, OnBoardMode, StartupMode, api::KeyboardApi,
+};
+
+/// Every call a command made, in order, as a readable string.
+pub struct MockKeyboard {
+    pub model: KeyboardModel,
+    pub calls: Vec<String>,
+}
+
+impl MockKeyboard {
+    pub fn new(model: KeyboardModel) -> Self {
+        Self {
+            model,
+            calls: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardApi for MockKeyboard {
+    fn model(&self) -> KeyboardModel {
+        self.model
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.calls.push("commit".into());
+        Ok(())
+    }
+
+    fn set_all_keys(&mut self, color: Color) -> Result<()> {
+        self.calls.push(format!("all {color}"));
+        Ok(())
+    }
+
+    fn set_group_keys(&mut self, group: KeyGroup, color: Color) -> Result<()> {
+        self.calls.push(format!("group {group} {color}"));
+        Ok(())
+    }
+
+    fn set_keys(&mut self, keys: &[KeyValue]) -> Result<()> {
+        for kv in keys {
+            self.calls.push(format!("key {:?} {}", kv.key, kv.color));
+        }
+        Ok(())
+    }
+
+    fn set_key_refs(&mut self, keys: &[KeyRefValue]) -> Result<()> {
+        for kv in keys {
+            self.calls.push(format!("key {} {}", kv.key, kv.color));
+        }
+        Ok(())
+    }
+
+    fn set_region(&mut self, region: u8, color: Color) -> Result<()> {
+        self.calls.push(format!("region {region} {color}"));
+        Ok(())
+    }
+
+    fn set_mr_key(&mut self, value: u8) -> Result<()> {
+        self.calls.push(format!("mr {value}"));
+        Ok(())
+    }
+
+    fn set_mn_key(&mut self, value: u8) -> Result<()> {
+        self.calls.push(format!("mn {value}"));
+        Ok(())
+    }
+
+    fn set_gkeys_mode(&mut self, value: u8) -> Result<()> {
+        self.calls.push(format!("gkm {value}"));
+        Ok(())
+    }
+
+    fn set_startup_mode(&mut self, mode: StartupMode) -> Result<()> {
+        self.calls.push(format!("startup {mode}"));
+        Ok(())
+    }
+
+    fn set_on_board_mode(&mut self, mode: OnBoardMode) -> Result<()> {
+        self.calls.push(format!("on-board {mode}"));
+        Ok(())
+    }
+
+    fn set_fx(
+        &mut self,
+        effect: NativeEffect,
+        part: NativeEffectPart,
+        period: Duration,
+        color: Color,
+        storage: NativeEffectStorage,
+    ) -> Result<()> {
+        self.calls.push(format!(
+            "fx {effect} {part} {}ms {color} {storage:?}",
+            period.as_millis()
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use serde::Deserialize;
+use std::{path::Path, str::FromStr};
+use strum_macros::{Display, EnumString};
+
+use anyhow::{Result, anyhow};
+
+use crate::diagnostics::parse_toml;
+use crate::keyboard::parser::parse_color;
+use crate::keyboard::{Color, KeyGroup, KeyboardModel, api::KeyboardApi};
+
+/// Themes shipped with the binary, looked up by name before the filesystem.
+const EMBEDDED_THEMES: &[(&str, &str)] = &[
+    ("dracula", include_str!("../themes/dracula.toml")),
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+    ("nord", include_str!("../themes/nord.toml")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+pub enum ThemeVariant {
+    Dark,
+    Light,
+}
+
+/// A theme reference on the command line: `name` or `name:variant`.
+///
+/// Theme file paths may contain colons (`C:\themes\x.toml`), so an existing
+/// file is always taken whole, and only a trailing `:dark` or `:light` is
+/// split off.
+#[derive(Debug, Clone)]
+pub struct ThemeSpec {
+    pub name: String,
+    pub variant: Option<ThemeVariant>,
+}
+
+impl FromStr for ThemeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let whole = Self {
+            name: s.to_owned(),
+            variant: None,
+        };
+        if Path::new(s).exists() {
+            return Ok(whole);
+        }
+        match s.rsplit_once(':') {
+            Some((name, variant)) if !name.is_empty() => match variant.parse() {
+                Ok(variant) => Ok(Self {
+                    name: name.to_owned(),
+                    variant: Some(variant),
+                }),
+                Err(_) if is_embedded_theme(name) => {
+                    Err(format!("invalid theme variant: {variant}"))
+                }
+                Err(_) => Ok(whole),
+            },
+            _ => Ok(whole),
+        }
+    }
+}
+
+impl ThemeSpec {
+    /// Load the referenced theme and pick its variant, or `fallback` if none was given.
+    pub fn resolve(&self, fallback: ThemeVariant) -> Result<Palette> {
+        Theme::load(&self.name)?.palette(self.variant.unwrap_or(fallback))
+    }
+}
+
+/// Semantic color slots a theme fills in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Base,
+    Accent,
+    Modifiers,
+    Alerts,
+}
+
+/// Which key groups each role paints on per-key boards.
+const ROLE_GROUPS: &[(KeyGroup, Role)] = &[
+    (KeyGroup::Keys, Role::Base),
+    (KeyGroup::Numeric, Role::Base),
+    (KeyGroup::Logo, Role::Accent),
+    (KeyGroup::FKeys, Role::Accent),
+    (KeyGroup::Multimedia, Role::Accent),
+    (KeyGroup::GKeys, Role::Accent),
+    (KeyGroup::Modifiers, Role::Modifiers),
+    (KeyGroup::Functions, Role::Modifiers),
+    (KeyGroup::Arrows, Role::Modifiers),
+    (KeyGroup::Indicators, Role::Alerts),
+];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    dark: Option<RawPalette>,
+    light: Option<RawPalette>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPalette {
+    base: String,
+    accent: String,
+    modifiers: String,
+    alerts: String,
+}
+
+/// Resolved colors for one theme variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub base: Color,
+    pub accent: Color,
+    pub modifiers: Color,
+    pub alerts: Color,
+}
+
+impl Palette {
+    fn color(&self, role: Role) -> Color {
+        match role {
+            Role::Base => self.base,
+            Role::Accent => self.accent,
+            Role::Modifiers => self.modifiers,
+            Role::Alerts => self.alerts,
+        }
+    }
+}
+
+impl TryFrom<RawPalette> for Palette {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawPalette) -> Result<Self> {
+        let color = |role: &str, value: &str| {
+            parse_color(value).ok_or_else(|| anyhow!("invalid color for role {role}: {value}"))
+        };
+        Ok(Self {
+            base: color("base", &raw.base)?,
+            accent: color("accent", &raw.accent)?,
+            modifiers: color("modifiers", &raw.modifiers)?,
+            alerts: color("alerts", &raw.alerts)?,
+        })
+    }
+}
+
+/// A named theme with its light and dark palettes.
+pub struct Theme {
+    pub name: String,
+    dark: Option<Palette>,
+    light: Option<Palette>,
+}
+
+impl Theme {
+    /// Parse a theme from its TOML text; `origin` names it in error messages.
+    pub fn from_toml(text: &str, origin: &str) -> Result<Self> {
+        let file: ThemeFile = parse_toml(text, origin)?;
+        Ok(Self {
+            name: file.name,
+            dark: file.dark.map(Palette::try_from).transpose()?,
+            light: file.light.map(Palette::try_from).transpose()?,
+        })
+    }
+
+    /// Resolve a theme by embedded name, falling back to a file path.
+    pub fn load(name_or_path: &str) -> Result<Self> {
+        if let Some(&(name, text)) = EMBEDDED_THEMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(name_or_path))
+        {
+            return Self::from_toml(text, name);
+        }
+
+        let path = Path::new(name_or_path);
+        if !path.exists() {
+            return Err(anyhow!("unknown theme: {name_or_path}"));
+        }
+        Self::from_toml(&std::fs::read_to_string(path)?, name_or_path)
+    }
+
+    /// Palette for the requested variant, if the theme defines it.
+    pub fn palette(&self, variant: ThemeVariant) -> Result<Palette> {
+        match variant {
+            ThemeVariant::Dark => self.dark,
+            ThemeVariant::Light => self.light,
+        }
+        .ok_or_else(|| anyhow!("theme {} has no {variant} variant", self.name))
+    }
+}
+
+/// Which of the 16 terminal color slots fill base, accent, modifiers and alerts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalSlots(pub [usize; 4]);
+
+impl Default for WalSlots {
+    /// Blue, magenta, cyan and red, the roles Dracula and Nord give them.
+    fn default() -> Self {
+        Self([4, 5, 6, 1])
+    }
+}
+
+impl FromStr for WalSlots {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let slots: Vec<usize> = s
+            .split(',')
+            .map(|slot| slot.trim().parse().ok().filter(|&n| n < 16))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("invalid color slot in {s:?}; slots are 0-15"))?;
+        slots
+            .try_into()
+            .map(Self)
+            .map_err(|_| format!("expected four slots (base,accent,modifiers,alerts): {s}"))
+    }
+}
+
+/// Build a palette from a terminal color scheme such as pywal's output.
+///
+/// Any file naming its colors `color0` to `color15` works: pywal's
+/// `colors.json`, the `--color4: #81a2be;` variables of `colors.css`, or
+/// `*.color4: #81a2be` in Xresources.
+pub fn palette_from_colors(text: &str, slots: WalSlots) -> Result<Palette> {
+    let colors = color_slots(text);
+    let [base, accent, modifiers, alerts] = slots.0.map(|slot| {
+        colors[slot].ok_or_else(|| anyhow!("color{slot} is missing from the colors file"))
+    });
+    Ok(Palette {
+        base: base?,
+        accent: accent?,
+        modifiers: modifiers?,
+        alerts: alerts?,
+    })
+}
+
+/// Every `colorN` followed by a `#rrggbb` value, whatever the syntax between.
+fn color_slots(text: &str) -> [Option<Color>; 16] {
+    let mut colors = [None; 16];
+    for (start, _) in text.match_indices("color") {
+        let rest = &text[start + "color".len()..];
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let Some(slot) = rest[..digits].parse::<usize>().ok().filter(|&n| n < 16) else {
+            continue;
+        };
+        let value = rest[digits..].trim_start_matches(|c: char| {
+            c == '"' || c == '\'' || c == ':' || c == '=' || c.is_whitespace()
+        });
+        if let Some(hex) = value.strip_prefix('#').and_then(|hex| hex.get(..6)) {
+            colors[slot] = colors[slot].or(parse_color(hex));
+        }
+    }
+    colors
+}
+
+/// Names of the themes built into the binary.
+pub fn embedded_theme_names() -> impl Iterator<Item = &'static str> {
+    EMBEDDED_THEMES.iter().map(|&(name, _)| name)
+}
+
+/// Whether `name` is a built-in theme, as [`Theme::load`] matches it.
+fn is_embedded_theme(name: &str) -> bool {
+    embedded_theme_names().any(|embedded| embedded.eq_ignore_ascii_case(name))
+}
+
+/// Paint a palette onto the keyboard, resolving roles to the model's groups.
+pub fn apply_palette<K>(kbd: &mut K, model: KeyboardModel, palette: &Palette) -> Result<()>
+where
+    K: KeyboardApi + ?Sized,
+{
+    match model {
+        // Single-zone boards only take the base color.
+        KeyboardModel::G213 | KeyboardModel::G413 => kbd.set_all_keys(palette.base)?,
+        _ => {
+            for &(group, role) in ROLE_GROUPS {
+                kbd.set_group_keys(group, palette.color(role))?;
+            }
+        }
+    }
+
+    kbd.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockKeyboard;
+
+    #[test]
+    fn embedded_themes_parse() {
+        for name in embedded_theme_names() {
+            let theme = Theme::load(name).unwrap();
+            assert_eq!(theme.name, name);
+            theme.palette(ThemeVariant::Dark).unwrap();
+            theme.palette(ThemeVariant::Light).unwrap();
+        }
+    }
+
+    #[test]
+    fn apply_resolves_roles_to_groups() {
+        let palette = Theme::load("nord")
+            .unwrap()
+            .palette(ThemeVariant::Dark)
+            .unwrap();
+        let mut mock = MockKeyboard::new(KeyboardModel::G810);
+        apply_palette(&mut mock, KeyboardModel::G810, &palette).unwrap();
+
+        assert_eq!(mock.calls.len(), ROLE_GROUPS.len() + 1);
+        assert_eq!(mock.calls.last().unwrap(), "commit");
+        let group = |group: KeyGroup, color: Color| format!("group {group} {color}");
+        assert!(
+            mock.calls
+                .contains(&group(KeyGroup::Modifiers, palette.modifiers))
+        );
+        assert!(
+            mock.calls
+                .contains(&group(KeyGroup::Indicators, palette.alerts))
+        );
+    }
+
+    #[test]
+    fn apply_single_zone_uses_base() {
+        let palette = Theme::load("gruvbox")
+            .unwrap()
+            .palette(ThemeVariant::Light)
+            .unwrap();
+        let mut mock = MockKeyboard::new(KeyboardModel::G213);
+        apply_palette(&mut mock, KeyboardModel::G213, &palette).unwrap();
+
+        assert_eq!(
+            mock.calls,
+            vec![format!("all {}", palette.base), "commit".into()]
+        );
+    }
+
+    #[test]
+    fn theme_spec_parses_variant() {
+        let spec: ThemeSpec = "nord:light".parse().unwrap();
+        assert_eq!(spec.name, "nord");
+        assert_eq!(spec.variant, Some(ThemeVariant::Light));
+
+        let spec: ThemeSpec = "gruvbox".parse().unwrap();
+        assert_eq!(spec.variant, None);
+
+        assert!("nord:dim".parse::<ThemeSpec>().is_err());
+    }
+
+    #[test]
+    fn theme_spec_keeps_colons_in_paths() {
+        let spec: ThemeSpec = "dir:with:colons/theme".parse().unwrap();
+        assert_eq!(spec.name, "dir:with:colons/theme");
+        assert_eq!(spec.variant, None);
+
+        let spec: ThemeSpec = r"C:\themes\x.toml:light".parse().unwrap();
+        assert_eq!(spec.name, r"C:\themes\x.toml");
+        assert_eq!(spec.variant, Some(ThemeVariant::Light));
+
+        let path = std::env::temp_dir().join("logi_led_theme_spec:dark");
+        std::fs::write(&path, "").unwrap();
+        let spec: ThemeSpec = path.to_str().unwrap().parse().unwrap();
+        assert_eq!(spec.name, path.to_str().unwrap());
+        assert_eq!(spec.variant, None);
+    }
+
+    #[test]
+    fn pywal_json_and_css_map_slots_to_roles() {
+        let json = r##"{"special": {"background": "#1d1f21"},
+            "colors": {"color0": "#1d1f21", "color1": "#cc6666", "color4": "#81a2be",
+            "color5": "#b294bb", "color6": "#8abeb7", "color14": "#8abeb7"}}"##;
+        let palette = palette_from_colors(json, WalSlots::default()).unwrap();
+        assert_eq!(palette.base, Color::new(0x81, 0xa2, 0xbe));
+        assert_eq!(palette.alerts, Color::new(0xcc, 0x66, 0x66));
+
+        let css = ":root {\n  --color1: #cc6666;\n  --color4: #81a2be;\n  --color5: #b294bb;\n  --color6: #8abeb7;\n}";
+        assert_eq!(
+            palette_from_colors(css, WalSlots::default()).unwrap(),
+            palette
+        );
+
+        let slots: WalSlots = "0,1,1,1".parse().unwrap();
+        assert_eq!(
+            palette_from_colors(json, slots).unwrap().base,
+            Color::new(0x1d, 0x1f, 0x21)
+        );
+        assert!(palette_from_colors(css, slots).is_err());
+        assert!("4,5,6".parse::<WalSlots>().is_err());
+        assert!("4,5,6,16".parse::<WalSlots>().is_err());
+    }
+
+    #[test]
+    fn missing_variant_errors() {
+        let theme = Theme::from_toml(
+            "name = \"half\"\n[dark]\nbase = \"red\"\naccent = \"red\"\nmodifiers = \"red\"\nalerts = \"red\"\n",
+            "half.toml",
+        )
+        .unwrap();
+        assert!(theme.palette(ThemeVariant::Light).is_err());
+    }
+}
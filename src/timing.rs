@@ -0,0 +1,173 @@
+//! Per-phase timing for `--timing`.
+//!
+//! The backends and the keyboard API time their work with [`start`] or
+//! [`time`]; while timing is off that costs one atomic load. Phases do not
+//! nest: anything timed inside another phase counts towards the outer one, so
+//! the report adds up to the wall-clock time.
+
+use std::cell::Cell;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Where a command spends its time talking to the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Listing USB/HID devices and reading their descriptors.
+    Enumerate,
+    /// Opening the chosen device and claiming its interface.
+    Open,
+    /// Turning colors and effects into packets.
+    Build,
+    /// Sending packets, including ack reads and model pacing.
+    Write,
+    /// Sending the commit packet.
+    Commit,
+}
+
+impl Phase {
+    const ALL: [Phase; 5] = [
+        Phase::Enumerate,
+        Phase::Open,
+        Phase::Build,
+        Phase::Write,
+        Phase::Commit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Enumerate => "enumerate",
+            Phase::Open => "open",
+            Phase::Build => "build",
+            Phase::Write => "write",
+            Phase::Commit => "commit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Total {
+    elapsed: Duration,
+    count: u32,
+}
+
+const NONE: Total = Total {
+    elapsed: Duration::ZERO,
+    count: 0,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TOTALS: Mutex<[Total; Phase::ALL.len()]> = Mutex::new([NONE; Phase::ALL.len()]);
+
+thread_local! {
+    static TIMING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Start collecting; call once after parsing arguments.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Times `phase` until dropped.
+pub struct Timer(Option<(Phase, Instant)>);
+
+/// Time `phase` until the returned timer is dropped.
+pub fn start(phase: Phase) -> Timer {
+    if !ENABLED.load(Ordering::Relaxed) || TIMING.get() {
+        return Timer(None);
+    }
+    TIMING.set(true);
+    Timer(Some((phase, Instant::now())))
+}
+
+/// Run `f` as part of `phase`.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let _timer = start(phase);
+    f()
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some((phase, started)) = self.0.take() {
+            TIMING.set(false);
+            let mut totals = TOTALS
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let total = &mut totals[phase as usize];
+            total.elapsed += started.elapsed();
+            total.count += 1;
+        }
+    }
+}
+
+/// The report for a run that took `wall` in total, if timing is enabled.
+pub fn report(wall: Duration) -> Option<String> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let totals = *TOTALS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    Some(format_report(&totals, wall))
+}
+
+fn format_report(totals: &[Total; Phase::ALL.len()], wall: Duration) -> String {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let mut out = format!("timing:{:>16}  calls\n", "ms");
+    for phase in Phase::ALL {
+        let total = totals[phase as usize];
+        let _ = writeln!(
+            out,
+            "  {:<10}{:>11.1}  {:>5}",
+            phase.label(),
+            ms(total.elapsed),
+            total.count
+        );
+    }
+    let timed: Duration = totals.iter().map(|t| t.elapsed).sum();
+    let _ = writeln!(
+        out,
+        "  {:<10}{:>11.1}",
+        "other",
+        ms(wall.saturating_sub(timed))
+    );
+    let _ = writeln!(out, "  {:<10}{:>11.1}", "total", ms(wall));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_every_phase_and_the_rest() {
+        let mut totals = [NONE; Phase::ALL.len()];
+        totals[Phase::Enumerate as usize] = Total {
+            elapsed: Duration::from_millis(300),
+            count: 1,
+        };
+        totals[Phase::Write as usize] = Total {
+            elapsed: Duration::from_millis(92),
+            count: 14,
+        };
+        let report = format_report(&totals, Duration::from_millis(400));
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[1], "  enumerate       300.0      1");
+        assert_eq!(lines[4], "  write            92.0     14");
+        assert_eq!(lines[6], "  other             8.0");
+        assert_eq!(lines[7], "  total           400.0");
+    }
+
+    #[test]
+    fn nested_phases_count_towards_the_outer_one() {
+        // Enabling is global, but no other unit test opens a device.
+        enable();
+        time(Phase::Commit, || time(Phase::Write, || ()));
+        let totals = *TOTALS.lock().unwrap();
+        assert_eq!(totals[Phase::Commit as usize].count, 1);
+        assert_eq!(totals[Phase::Write as usize].count, 0);
+        assert!(!TIMING.get());
+    }
+}